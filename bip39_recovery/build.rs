@@ -0,0 +1,44 @@
+use std::env;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Compiles the device kernels the enabled features embed, into OUT_DIR for
+/// src/gpu.rs, so the toolchains are only needed where the binary is built.
+///
+/// With `cuda`, kernel.cu goes to PTX: NVCC names the compiler (nvcc by
+/// default) and CUDA_ARCH the virtual architecture, e.g. compute_61; the
+/// driver JIT-compiles the PTX for any newer GPU.
+///
+/// With `vulkan`, kernel.comp goes to SPIR-V: GLSLC names the compiler
+/// (glslc, from the Vulkan SDK or shaderc, by default).
+fn main() {
+    println!("cargo:rerun-if-changed=kernel.cu");
+    println!("cargo:rerun-if-changed=kernel.comp");
+    println!("cargo:rerun-if-env-changed=NVCC");
+    println!("cargo:rerun-if-env-changed=CUDA_ARCH");
+    println!("cargo:rerun-if-env-changed=GLSLC");
+    let out = PathBuf::from(env::var_os("OUT_DIR").expect("cargo sets OUT_DIR"));
+    if env::var_os("CARGO_FEATURE_CUDA").is_some() {
+        let nvcc = env::var("NVCC").unwrap_or_else(|_| "nvcc".to_string());
+        let mut command = Command::new(&nvcc);
+        command.arg("-ptx").arg("kernel.cu").arg("-o").arg(out.join("kernel.ptx"));
+        if let Ok(arch) = env::var("CUDA_ARCH") {
+            command.arg(format!("-arch={}", arch));
+        }
+        run(command, &nvcc, "kernel.cu", "cuda");
+    }
+    if env::var_os("CARGO_FEATURE_VULKAN").is_some() {
+        let glslc = env::var("GLSLC").unwrap_or_else(|_| "glslc".to_string());
+        let mut command = Command::new(&glslc);
+        command.arg("--target-env=vulkan1.0").arg("-O").arg("kernel.comp").arg("-o").arg(out.join("kernel.spv"));
+        run(command, &glslc, "kernel.comp", "vulkan");
+    }
+}
+
+fn run(mut command: Command, compiler: &str, source: &str, feature: &str) {
+    match command.status() {
+        Ok(status) if status.success() => {}
+        Ok(status) => panic!("{} failed to compile {} ({})", compiler, source, status),
+        Err(e) => panic!("--features {} compiles {} with {}, which failed to run: {}", feature, source, compiler, e),
+    }
+}
@@ -0,0 +1,313 @@
+// kernel.cl: the OpenCL C counterpart of kernel.cu, for --accelerator opencl
+
+// SHA-256 round constants
+__constant uint K[64] = {
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2
+};
+
+uint rotr(uint x, int n) {
+    return (x >> n) | (x << (32 - n));
+}
+
+// First byte of SHA-256 over at most 55 bytes, which always fit one block.
+// BIP-39 entropy is 16 to 32 bytes, so this is all the checksum needs.
+uchar sha256_first_byte(const uchar* data, int len) {
+    uint w[64];
+    for (int i = 0; i < 16; i++) {
+        w[i] = 0;
+    }
+    for (int i = 0; i < len; i++) {
+        w[i / 4] |= (uint)data[i] << (24 - 8 * (i % 4));
+    }
+    w[len / 4] |= 0x80u << (24 - 8 * (len % 4));
+    w[15] = (uint)len * 8;
+    for (int i = 16; i < 64; i++) {
+        uint s0 = rotr(w[i - 15], 7) ^ rotr(w[i - 15], 18) ^ (w[i - 15] >> 3);
+        uint s1 = rotr(w[i - 2], 17) ^ rotr(w[i - 2], 19) ^ (w[i - 2] >> 10);
+        w[i] = w[i - 16] + s0 + w[i - 7] + s1;
+    }
+    uint a = 0x6a09e667, b = 0xbb67ae85, c = 0x3c6ef372, d = 0xa54ff53a;
+    uint e = 0x510e527f, f = 0x9b05688c, g = 0x1f83d9ab, h = 0x5be0cd19;
+    for (int i = 0; i < 64; i++) {
+        uint t1 = h + (rotr(e, 6) ^ rotr(e, 11) ^ rotr(e, 25)) + ((e & f) ^ (~e & g)) + K[i] + w[i];
+        uint t2 = (rotr(a, 2) ^ rotr(a, 13) ^ rotr(a, 22)) + ((a & b) ^ (a & c) ^ (b & c));
+        h = g; g = f; f = e; e = d + t1;
+        d = c; c = b; b = a; a = t1 + t2;
+    }
+    return (uchar)((a + 0x6a09e667) >> 24);
+}
+
+// Same rules as src/entropy.rs: 12, 15, 18, 21, or 24 words of 11 bits,
+// the last words/3 bits being the leading bits of SHA-256(entropy).
+bool bip39_checksum_ok(const ushort* indices, int words) {
+    if (words < 12 || words > 24 || words % 3 != 0) {
+        return false;
+    }
+    int cs = words / 3;
+    int len = (words * 11 - cs) / 8;
+    uchar entropy[32];
+    ulong acc = 0;
+    int bits = 0, n = 0;
+    for (int i = 0; i < words; i++) {
+        acc = (acc << 11) | (indices[i] & 0x7ff);
+        bits += 11;
+        while (bits >= 8 && n < len) {
+            bits -= 8;
+            entropy[n++] = (uchar)(acc >> bits);
+        }
+    }
+    uchar checksum = (uchar)(acc & ((1u << cs) - 1));
+    return (sha256_first_byte(entropy, len) >> (8 - cs)) == checksum;
+}
+
+// SHA-512 round constants
+__constant ulong K512[80] = {
+    0x428a2f98d728ae22UL, 0x7137449123ef65cdUL, 0xb5c0fbcfec4d3b2fUL, 0xe9b5dba58189dbbcUL,
+    0x3956c25bf348b538UL, 0x59f111f1b605d019UL, 0x923f82a4af194f9bUL, 0xab1c5ed5da6d8118UL,
+    0xd807aa98a3030242UL, 0x12835b0145706fbeUL, 0x243185be4ee4b28cUL, 0x550c7dc3d5ffb4e2UL,
+    0x72be5d74f27b896fUL, 0x80deb1fe3b1696b1UL, 0x9bdc06a725c71235UL, 0xc19bf174cf692694UL,
+    0xe49b69c19ef14ad2UL, 0xefbe4786384f25e3UL, 0x0fc19dc68b8cd5b5UL, 0x240ca1cc77ac9c65UL,
+    0x2de92c6f592b0275UL, 0x4a7484aa6ea6e483UL, 0x5cb0a9dcbd41fbd4UL, 0x76f988da831153b5UL,
+    0x983e5152ee66dfabUL, 0xa831c66d2db43210UL, 0xb00327c898fb213fUL, 0xbf597fc7beef0ee4UL,
+    0xc6e00bf33da88fc2UL, 0xd5a79147930aa725UL, 0x06ca6351e003826fUL, 0x142929670a0e6e70UL,
+    0x27b70a8546d22ffcUL, 0x2e1b21385c26c926UL, 0x4d2c6dfc5ac42aedUL, 0x53380d139d95b3dfUL,
+    0x650a73548baf63deUL, 0x766a0abb3c77b2a8UL, 0x81c2c92e47edaee6UL, 0x92722c851482353bUL,
+    0xa2bfe8a14cf10364UL, 0xa81a664bbc423001UL, 0xc24b8b70d0f89791UL, 0xc76c51a30654be30UL,
+    0xd192e819d6ef5218UL, 0xd69906245565a910UL, 0xf40e35855771202aUL, 0x106aa07032bbd1b8UL,
+    0x19a4c116b8d2d0c8UL, 0x1e376c085141ab53UL, 0x2748774cdf8eeb99UL, 0x34b0bcb5e19b48a8UL,
+    0x391c0cb3c5c95a63UL, 0x4ed8aa4ae3418acbUL, 0x5b9cca4f7763e373UL, 0x682e6ff3d6b2b8a3UL,
+    0x748f82ee5defb2fcUL, 0x78a5636f43172f60UL, 0x84c87814a1f0ab72UL, 0x8cc702081a6439ecUL,
+    0x90befffa23631e28UL, 0xa4506cebde82bde9UL, 0xbef9a3f7b2c67915UL, 0xc67178f2e372532bUL,
+    0xca273eceea26619cUL, 0xd186b8c721c0c207UL, 0xeada7dd6cde0eb1eUL, 0xf57d4f7fee6ed178UL,
+    0x06f067aa72176fbaUL, 0x0a637dc5a2c898a6UL, 0x113f9804bef90daeUL, 0x1b710b35131c471bUL,
+    0x28db77f523047d84UL, 0x32caab7b40c72493UL, 0x3c9ebe0a15c9bebcUL, 0x431d67c49c100d4cUL,
+    0x4cc5d4becb3e42b6UL, 0x597f299cfc657e2aUL, 0x5fcb6fab3ad6faecUL, 0x6c44198c4a475817UL,
+};
+
+ulong rotr64(ulong x, int n) {
+    return (x >> n) | (x << (64 - n));
+}
+
+void sha512_compress(ulong* state, const uchar* block) {
+    ulong w[80];
+    for (int i = 0; i < 16; i++) {
+        ulong v = 0;
+        for (int j = 0; j < 8; j++) {
+            v = (v << 8) | block[i * 8 + j];
+        }
+        w[i] = v;
+    }
+    for (int i = 16; i < 80; i++) {
+        ulong s0 = rotr64(w[i - 15], 1) ^ rotr64(w[i - 15], 8) ^ (w[i - 15] >> 7);
+        ulong s1 = rotr64(w[i - 2], 19) ^ rotr64(w[i - 2], 61) ^ (w[i - 2] >> 6);
+        w[i] = w[i - 16] + s0 + w[i - 7] + s1;
+    }
+    ulong a = state[0], b = state[1], c = state[2], d = state[3];
+    ulong e = state[4], f = state[5], g = state[6], h = state[7];
+    for (int i = 0; i < 80; i++) {
+        ulong t1 = h + (rotr64(e, 14) ^ rotr64(e, 18) ^ rotr64(e, 41)) + ((e & f) ^ (~e & g)) + K512[i] + w[i];
+        ulong t2 = (rotr64(a, 28) ^ rotr64(a, 34) ^ rotr64(a, 39)) + ((a & b) ^ (a & c) ^ (b & c));
+        h = g; g = f; f = e; e = d + t1;
+        d = c; c = b; b = a; a = t1 + t2;
+    }
+    state[0] += a; state[1] += b; state[2] += c; state[3] += d;
+    state[4] += e; state[5] += f; state[6] += g; state[7] += h;
+}
+
+typedef struct {
+    ulong state[8];
+    uchar buffer[128];
+    uint buffered;
+    ulong total;
+} Sha512;
+
+void sha512_init(Sha512* ctx) {
+    ctx->state[0] = 0x6a09e667f3bcc908UL; ctx->state[1] = 0xbb67ae8584caa73bUL;
+    ctx->state[2] = 0x3c6ef372fe94f82bUL; ctx->state[3] = 0xa54ff53a5f1d36f1UL;
+    ctx->state[4] = 0x510e527fade682d1UL; ctx->state[5] = 0x9b05688c2b3e6c1fUL;
+    ctx->state[6] = 0x1f83d9abfb41bd6bUL; ctx->state[7] = 0x5be0cd19137e2179UL;
+    ctx->buffered = 0;
+    ctx->total = 0;
+}
+
+void sha512_byte(Sha512* ctx, uchar byte) {
+    ctx->buffer[ctx->buffered++] = byte;
+    if (ctx->buffered == 128) {
+        sha512_compress(ctx->state, ctx->buffer);
+        ctx->buffered = 0;
+    }
+    ctx->total += 1;
+}
+
+void sha512_update(Sha512* ctx, const uchar* data, uint len) {
+    for (uint i = 0; i < len; i++) {
+        sha512_byte(ctx, data[i]);
+    }
+}
+
+// The salt stays in global memory, since a passphrase has no length limit
+void sha512_update_global(Sha512* ctx, __global const uchar* data, uint len) {
+    for (uint i = 0; i < len; i++) {
+        sha512_byte(ctx, data[i]);
+    }
+}
+
+void store64(uchar* out, const ulong* words, int count) {
+    for (int i = 0; i < count; i++) {
+        for (int j = 0; j < 8; j++) {
+            out[i * 8 + j] = (uchar)(words[i] >> (56 - 8 * j));
+        }
+    }
+}
+
+void sha512_final(Sha512* ctx, uchar* out) {
+    ulong bits = ctx->total * 8;
+    ctx->buffer[ctx->buffered++] = 0x80;
+    if (ctx->buffered > 112) {
+        while (ctx->buffered < 128) {
+            ctx->buffer[ctx->buffered++] = 0;
+        }
+        sha512_compress(ctx->state, ctx->buffer);
+        ctx->buffered = 0;
+    }
+    while (ctx->buffered < 120) {
+        ctx->buffer[ctx->buffered++] = 0;
+    }
+    store64(ctx->buffer + 120, &bits, 1);
+    sha512_compress(ctx->state, ctx->buffer);
+    store64(out, ctx->state, 8);
+}
+
+// PBKDF2-HMAC-SHA512, 2048 rounds, one 64-byte block: the BIP-39 seed of
+// `password` (the mnemonic sentence) and `salt` ("mnemonic" + passphrase).
+void bip39_seed(const uchar* password, uint password_len,
+                __global const uchar* salt, uint salt_len, uchar* seed) {
+    uchar key[128];
+    for (int i = 0; i < 128; i++) {
+        key[i] = 0;
+    }
+    if (password_len > 128) {
+        Sha512 ctx;
+        sha512_init(&ctx);
+        sha512_update(&ctx, password, password_len);
+        sha512_final(&ctx, key);
+    } else {
+        for (uint i = 0; i < password_len; i++) {
+            key[i] = password[i];
+        }
+    }
+
+    // Both pads fill a whole block, so HMAC starts from these states
+    uchar pad[128];
+    Sha512 inner, outer;
+    for (int i = 0; i < 128; i++) {
+        pad[i] = key[i] ^ 0x36;
+    }
+    sha512_init(&inner);
+    sha512_update(&inner, pad, 128);
+    for (int i = 0; i < 128; i++) {
+        pad[i] = key[i] ^ 0x5c;
+    }
+    sha512_init(&outer);
+    sha512_update(&outer, pad, 128);
+
+    const uchar block_index[4] = {0, 0, 0, 1};
+    uchar u[64];
+    Sha512 ctx = inner;
+    sha512_update_global(&ctx, salt, salt_len);
+    sha512_update(&ctx, block_index, 4);
+    sha512_final(&ctx, u);
+    ctx = outer;
+    sha512_update(&ctx, u, 64);
+    sha512_final(&ctx, u);
+    for (int i = 0; i < 64; i++) {
+        seed[i] = u[i];
+    }
+
+    // Every later round hashes 64 bytes after a pad: one padded block each,
+    // of which only the first 64 bytes change
+    uchar block[128];
+    for (int i = 0; i < 64; i++) {
+        block[i] = u[i];
+    }
+    block[64] = 0x80;
+    for (int i = 65; i < 128; i++) {
+        block[i] = 0;
+    }
+    block[126] = (uchar)((128 + 64) * 8 >> 8);
+    block[127] = (uchar)((128 + 64) * 8);
+    for (int round = 1; round < 2048; round++) {
+        ulong state[8];
+        for (int i = 0; i < 8; i++) {
+            state[i] = inner.state[i];
+        }
+        sha512_compress(state, block);
+        store64(block, state, 8);
+        for (int i = 0; i < 8; i++) {
+            state[i] = outer.state[i];
+        }
+        sha512_compress(state, block);
+        store64(block, state, 8);
+        for (int i = 0; i < 64; i++) {
+            seed[i] ^= block[i];
+        }
+    }
+}
+
+// One work item per candidate. A candidate whose checksum fails gets valid
+// 0 and no seeds; otherwise one seed per salt, in salt order. Keys and
+// addresses are derived from the seeds on the host.
+__kernel void recover_kernel(
+    __global const ushort* indices,     // count * words wordlist indices
+    uint count,
+    uint words,
+    __global const uchar* wordlist,     // 2048 words, 8 bytes each, zero-padded
+    __global const uchar* salts,        // "mnemonic" + passphrase, back to back
+    __global const uint* salt_offsets,  // salt_count + 1 offsets into salts
+    uint salt_count,
+    __global uchar* valid,              // count flags
+    __global uchar* seeds               // count * salt_count * 64 bytes
+) {
+    uint idx = get_global_id(0);
+    if (idx >= count) {
+        return;
+    }
+    valid[idx] = 0;
+    if (words > 24) {
+        return;
+    }
+    ushort own[24];
+    for (uint w = 0; w < words; w++) {
+        own[w] = indices[(ulong)idx * words + w];
+    }
+    if (!bip39_checksum_ok(own, (int)words)) {
+        return;
+    }
+    valid[idx] = 1;
+    uchar sentence[24 * 9];
+    uint len = 0;
+    for (uint w = 0; w < words; w++) {
+        if (w > 0) {
+            sentence[len++] = ' ';
+        }
+        __global const uchar* word = wordlist + (own[w] & 0x7ff) * 8;
+        for (int c = 0; c < 8 && word[c]; c++) {
+            sentence[len++] = word[c];
+        }
+    }
+    for (uint s = 0; s < salt_count; s++) {
+        uchar seed[64];
+        bip39_seed(sentence, len, salts + salt_offsets[s], salt_offsets[s + 1] - salt_offsets[s], seed);
+        __global uchar* out = seeds + ((ulong)idx * salt_count + s) * 64;
+        for (int i = 0; i < 64; i++) {
+            out[i] = seed[i];
+        }
+    }
+}
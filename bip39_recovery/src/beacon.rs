@@ -0,0 +1,122 @@
+use std::net::{SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// UDP port a work server answers discovery probes on, one above the
+/// default `--listen` port.
+pub const PORT: u16 = 8788;
+
+/// Where a worker sends its probe by default: every host of the local
+/// network.
+pub const BROADCAST: &str = "255.255.255.255:8788";
+
+/// What a worker broadcasts; anything else on the port is ignored.
+const PROBE: &[u8] = b"bip39_recovery work server?";
+
+/// How long a probe waits for answers before it's sent again.
+const ANSWER_WAIT: Duration = Duration::from_secs(2);
+
+/// A work server's answer to a probe. The server is at the address the
+/// answer came from, on `port`.
+#[derive(Serialize, Deserialize)]
+struct Answer {
+    port: u16,
+    /// Whether claims need `--token`
+    token: bool,
+}
+
+/// Answers probes on `port` with the work server's HTTP `listen` port, on a
+/// thread of its own for as long as the server runs.
+pub fn announce(port: u16, listen: SocketAddr, token: bool) -> Result<()> {
+    if listen.ip().is_loopback() {
+        return Err(anyhow::anyhow!(
+            "--announce needs a --listen address other machines can reach, e.g. 0.0.0.0:{}, not {}",
+            listen.port(),
+            listen
+        ));
+    }
+    let socket = UdpSocket::bind(("0.0.0.0", port))
+        .map_err(|e| anyhow::anyhow!("Failed to listen for workers on UDP port {}: {}", port, e))?;
+    let answer = serde_json::to_vec(&Answer { port: listen.port(), token })?;
+    std::thread::spawn(move || {
+        let mut buf = [0u8; 64];
+        loop {
+            match socket.recv_from(&mut buf) {
+                Ok((len, from)) if &buf[..len] == PROBE => {
+                    if let Err(e) = socket.send_to(&answer, from) {
+                        eprintln!("Failed to answer the worker probe from {}: {}", from, e);
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => eprintln!("Worker probe failed: {}", e),
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Sends a probe to `target` (the broadcast address, or a server's own
+/// address) and returns the first work server to answer as
+/// `http://host:port`, or `None` when none answers within `wait`.
+pub fn find(target: &str, token: bool, wait: Duration) -> Result<Option<String>> {
+    let socket = UdpSocket::bind(("0.0.0.0", 0))?;
+    socket.set_broadcast(true)?;
+    let deadline = Instant::now() + wait;
+    while Instant::now() < deadline {
+        socket
+            .send_to(PROBE, target)
+            .map_err(|e| anyhow::anyhow!("Failed to send a work server probe to {}: {}", target, e))?;
+        let answered = Instant::now() + ANSWER_WAIT.min(deadline.saturating_duration_since(Instant::now()));
+        let mut buf = [0u8; 256];
+        while let Some(timeout) = answered.checked_duration_since(Instant::now()).filter(|timeout| !timeout.is_zero()) {
+            socket.set_read_timeout(Some(timeout))?;
+            let (len, from) = match socket.recv_from(&mut buf) {
+                Ok(received) => received,
+                Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => break,
+                Err(e) => return Err(e.into()),
+            };
+            let Ok(answer) = serde_json::from_slice::<Answer>(&buf[..len]) else { continue };
+            let server = format!("http://{}", SocketAddr::new(from.ip(), answer.port));
+            if answer.token && !token {
+                println!("Work server {} wants a --token; skipping it", server);
+                continue;
+            }
+            return Ok(Some(server));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A UDP port nothing is bound to, to announce on.
+    fn free_port() -> u16 {
+        UdpSocket::bind(("127.0.0.1", 0)).unwrap().local_addr().unwrap().port()
+    }
+
+    #[test]
+    fn finds_an_announced_server() {
+        let port = free_port();
+        announce(port, "0.0.0.0:8787".parse().unwrap(), false).unwrap();
+        let found = find(&format!("127.0.0.1:{}", port), false, Duration::from_secs(10)).unwrap();
+        assert_eq!(found.as_deref(), Some("http://127.0.0.1:8787"));
+    }
+
+    #[test]
+    fn skips_a_server_that_wants_a_token() {
+        let port = free_port();
+        announce(port, "0.0.0.0:8787".parse().unwrap(), true).unwrap();
+        let target = format!("127.0.0.1:{}", port);
+        assert_eq!(find(&target, false, Duration::from_millis(500)).unwrap(), None);
+        assert!(find(&target, true, Duration::from_secs(10)).unwrap().is_some());
+    }
+
+    #[test]
+    fn rejects_a_loopback_listen_address() {
+        assert!(announce(free_port(), "127.0.0.1:8787".parse().unwrap(), false).is_err());
+    }
+}
@@ -0,0 +1,206 @@
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use bip39::Mnemonic;
+use bitcoin::bip32::{ChildNumber, DerivationPath, Xpriv};
+use bitcoin::hashes::{hash160, Hash};
+use bitcoin::Network;
+use clap::ValueEnum;
+use rayon::prelude::*;
+use secp256k1::Secp256k1;
+
+use crate::paths;
+
+/// How a derived key is compared against the targets.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum MatchMode {
+    /// Encode the full address string
+    Address,
+    /// Stop at the hash160 fingerprint inside the address
+    Hash160,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Backend {
+    Cpu,
+    Gpu,
+}
+
+/// Dimensions swept by `bench`; every combination is measured.
+pub struct BenchMatrix {
+    pub seconds: f64,
+    pub backends: Vec<Backend>,
+    pub threads: Vec<usize>,
+    pub address_types: Vec<String>,
+    pub path_counts: Vec<usize>,
+    pub gap_limits: Vec<usize>,
+    pub match_modes: Vec<MatchMode>,
+}
+
+struct Config {
+    backend: Backend,
+    threads: usize,
+    address_type: String,
+    paths: usize,
+    gap: usize,
+    match_mode: MatchMode,
+}
+
+// Candidates handed to the pool at a time between clock checks
+const CHUNK: u64 = 256;
+
+/// Deterministic valid 12-word mnemonics, so every configuration pays for
+/// the full derivation rather than stopping at the checksum.
+fn mnemonic(index: u64) -> Mnemonic {
+    let mut entropy = [0u8; 16];
+    entropy[..8].copy_from_slice(&index.to_le_bytes());
+    Mnemonic::from_entropy(&entropy).expect("16 bytes is valid BIP-39 entropy")
+}
+
+/// Seed, master key, `paths` accounts under the conventional purpose, and
+/// `gap` receive addresses per account, encoded as `match_mode` says.
+fn derive(config: &Config, secp: &Secp256k1<secp256k1::All>, index: u64) -> Result<usize> {
+    let seed = mnemonic(index).to_seed("");
+    let master = Xpriv::new_master(Network::Bitcoin, &seed)?;
+    let purpose = paths::conventional_purpose(&config.address_type).unwrap_or(44);
+    let mut derived = 0;
+    for account in 0..config.paths as u32 {
+        let path: DerivationPath = format!("m/{}'/0'/{}'/0", purpose, account).parse()?;
+        let chain = master.derive_priv(secp, &path)?;
+        for i in 0..config.gap as u32 {
+            let child = chain.derive_priv(secp, &[ChildNumber::from_normal_idx(i)?])?;
+            let pubkey = bitcoin::PublicKey::new(child.private_key.public_key(secp));
+            match config.match_mode {
+                MatchMode::Address => {
+                    let address = crate::encode_address(&pubkey, &config.address_type, Network::Bitcoin)
+                        .ok_or_else(|| anyhow::anyhow!("Unsupported address type {}", config.address_type))??;
+                    std::hint::black_box(address);
+                }
+                MatchMode::Hash160 => {
+                    let hash = hash160::Hash::hash(&pubkey.to_bytes());
+                    // Nested segwit commits to the hash of the witness program
+                    if config.address_type == "p2sh-p2wpkh" {
+                        let mut program = vec![0x00, 0x14];
+                        program.extend_from_slice(hash.as_byte_array());
+                        std::hint::black_box(hash160::Hash::hash(&program));
+                    } else {
+                        std::hint::black_box(hash);
+                    }
+                }
+            }
+            derived += 1;
+        }
+    }
+    Ok(derived)
+}
+
+/// Candidates and addresses per second for `config`, or `None` when the
+/// backend isn't available.
+fn measure(config: &Config, seconds: f64) -> Result<Option<(f64, f64)>> {
+    if config.backend == Backend::Gpu {
+        return Ok(None);
+    }
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(config.threads)
+        .build()
+        .map_err(|e| anyhow::anyhow!("Failed to build a {}-thread pool: {}", config.threads, e))?;
+    let secp = Secp256k1::new();
+    let budget = Duration::from_secs_f64(seconds.max(0.1));
+    let start = Instant::now();
+    let mut candidates = 0u64;
+    let mut addresses = 0u64;
+    while start.elapsed() < budget {
+        let chunk = CHUNK * config.threads as u64;
+        let derived = pool.install(|| {
+            (candidates..candidates + chunk)
+                .into_par_iter()
+                .map(|index| derive(config, &secp, index))
+                .sum::<Result<usize>>()
+        })?;
+        candidates += chunk;
+        addresses += derived as u64;
+    }
+    let elapsed = start.elapsed().as_secs_f64();
+    Ok(Some((candidates as f64 / elapsed, addresses as f64 / elapsed)))
+}
+
+/// `bench`: measures every combination of the matrix and prints a table
+/// ordered from the cheapest configuration per candidate.
+pub fn run(matrix: &BenchMatrix) -> Result<()> {
+    let mut configs = Vec::new();
+    for &backend in &matrix.backends {
+        for &threads in &matrix.threads {
+            for address_type in &matrix.address_types {
+                for &paths in &matrix.path_counts {
+                    for &gap in &matrix.gap_limits {
+                        for &match_mode in &matrix.match_modes {
+                            configs.push(Config {
+                                backend,
+                                threads: threads.max(1),
+                                address_type: address_type.to_lowercase(),
+                                paths: paths.max(1),
+                                gap: gap.max(1),
+                                match_mode,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+    }
+    println!(
+        "Benchmarking {} configurations for {:.1}s each (about {:.0}s)",
+        configs.len(),
+        matrix.seconds,
+        configs.len() as f64 * matrix.seconds
+    );
+
+    let mut rows = Vec::new();
+    for config in configs {
+        let result = measure(&config, matrix.seconds)?;
+        match result {
+            Some((rate, _)) => {
+                println!(
+                    "  {:?} threads={} {} paths={} gap={} {:?}: {:.0} candidates/s",
+                    config.backend, config.threads, config.address_type, config.paths, config.gap, config.match_mode, rate
+                );
+            }
+            None => println!("  {:?}: not measured (bench runs on the CPU only)", config.backend),
+        }
+        rows.push((config, result));
+    }
+
+    rows.sort_by(|a, b| {
+        let rate = |row: &(Config, Option<(f64, f64)>)| row.1.map_or(0.0, |(rate, _)| rate);
+        rate(b).total_cmp(&rate(a))
+    });
+    let best = rows.first().and_then(|row| row.1).map_or(0.0, |(rate, _)| rate);
+    println!();
+    println!(
+        "{:<8} {:>7} {:<12} {:>5} {:>5} {:<8} {:>14} {:>14} {:>9}",
+        "backend", "threads", "type", "paths", "gap", "match", "candidates/s", "addresses/s", "relative"
+    );
+    for (config, result) in &rows {
+        let backend = format!("{:?}", config.backend).to_lowercase();
+        let match_mode = format!("{:?}", config.match_mode).to_lowercase();
+        match result {
+            Some((rate, address_rate)) => println!(
+                "{:<8} {:>7} {:<12} {:>5} {:>5} {:<8} {:>14.0} {:>14.0} {:>8.0}%",
+                backend,
+                config.threads,
+                config.address_type,
+                config.paths,
+                config.gap,
+                match_mode,
+                rate,
+                address_rate,
+                if best > 0.0 { rate * 100.0 / best } else { 0.0 }
+            ),
+            None => println!(
+                "{:<8} {:>7} {:<12} {:>5} {:>5} {:<8} {:>14} {:>14} {:>9}",
+                backend, config.threads, config.address_type, config.paths, config.gap, match_mode, "n/a", "n/a", "-"
+            ),
+        }
+    }
+    Ok(())
+}
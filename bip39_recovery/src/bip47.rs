@@ -0,0 +1,64 @@
+use anyhow::Result;
+use bitcoin::base58;
+use bitcoin::bip32::{ChainCode, ChildNumber, DerivationPath, Fingerprint, Xpub};
+use bitcoin::{Address, Network};
+use secp256k1::{PublicKey, Secp256k1};
+
+/// Base58Check version byte of a serialized payment code (`PM8T...`).
+const VERSION_BYTE: u8 = 0x47;
+const PAYLOAD_LEN: usize = 80;
+
+/// A BIP-47 payment code: the public key and chain code of the wallet's
+/// `m/47'/<coin>'/<account>'` key.
+pub struct PaymentCode {
+    xpub: Xpub,
+}
+
+impl PaymentCode {
+    pub fn parse(code: &str, network: Network) -> Result<Self> {
+        let data = base58::decode_check(code.trim())
+            .map_err(|e| anyhow::anyhow!("Invalid payment code {}: {}", code, e))?;
+        let [version_byte, payload @ ..] = data.as_slice() else {
+            return Err(anyhow::anyhow!("Invalid payment code {}: empty", code));
+        };
+        if *version_byte != VERSION_BYTE || payload.len() != PAYLOAD_LEN {
+            return Err(anyhow::anyhow!("{} is not a serialized payment code", code));
+        }
+        // version, features, then the 33-byte key and 32-byte chain code
+        if !(1..=3).contains(&payload[0]) {
+            return Err(anyhow::anyhow!("Unsupported payment code version {}", payload[0]));
+        }
+        let public_key = PublicKey::from_slice(&payload[2..35])
+            .map_err(|e| anyhow::anyhow!("Invalid payment code {}: bad public key: {}", code, e))?;
+        let mut chain_code = [0u8; 32];
+        chain_code.copy_from_slice(&payload[35..67]);
+        let xpub = Xpub {
+            network,
+            depth: 3,
+            parent_fingerprint: Fingerprint::default(),
+            child_number: ChildNumber::Hardened { index: 0 },
+            public_key,
+            chain_code: ChainCode::from(chain_code),
+        };
+        Ok(Self { xpub })
+    }
+
+    /// The P2PKH address notification transactions are sent to, which is
+    /// what a candidate seed has to reproduce.
+    pub fn notification_address(&self, secp: &Secp256k1<secp256k1::All>) -> Result<String> {
+        let child = self.xpub.derive_pub(secp, &[ChildNumber::Normal { index: 0 }])?;
+        Ok(Address::p2pkh(&bitcoin::PublicKey::new(child.public_key), self.xpub.network).to_string())
+    }
+}
+
+/// Where a seed's notification key lives: account 0 under purpose 47', with
+/// coin type 0' on mainnet and 1' on test networks.
+pub fn notification_path(network: Network) -> DerivationPath {
+    let coin_type = if network == Network::Bitcoin { 0 } else { 1 };
+    DerivationPath::from(vec![
+        ChildNumber::Hardened { index: 47 },
+        ChildNumber::Hardened { index: coin_type },
+        ChildNumber::Hardened { index: 0 },
+        ChildNumber::Normal { index: 0 },
+    ])
+}
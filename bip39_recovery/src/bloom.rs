@@ -0,0 +1,259 @@
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::UNIX_EPOCH;
+
+use anyhow::Result;
+use bitcoin::hashes::{sha256, Hash};
+
+use crate::ethereum;
+
+/// Leading bytes of a saved filter.
+const MAGIC: &[u8; 8] = b"B39BLOM1";
+
+/// The most hash functions a filter uses, whatever rate is asked for.
+const MAX_HASHES: u32 = 32;
+
+/// A bloom filter over the addresses of a large list, for target sets too
+/// big to hold as strings. A hit is confirmed by scanning the list itself,
+/// so a false positive costs one pass over the file and never a false match.
+pub struct AddressBloom {
+    bits: Vec<u64>,
+    hashes: u32,
+    entries: u64,
+    source: String,
+    positives: AtomicU64,
+    false_positives: AtomicU64,
+}
+
+impl AddressBloom {
+    /// The filter of `source`, loaded from `cache` when that was saved from
+    /// the file as it is now, otherwise built for `fp_rate` and, if `cache`
+    /// is given, saved there for the next run.
+    pub fn open(source: &str, fp_rate: f64, cache: Option<&str>) -> Result<Self> {
+        if !(fp_rate > 0.0 && fp_rate < 1.0) {
+            return Err(anyhow::anyhow!("Bloom false positive rate must be between 0 and 1, got {}", fp_rate));
+        }
+        let stamp = source_stamp(source)?;
+        if let Some(cache) = cache {
+            if let Some(bloom) = Self::load(cache, source, stamp)? {
+                return Ok(bloom);
+            }
+        }
+        let bloom = Self::build(source, fp_rate)?;
+        if let Some(cache) = cache {
+            bloom.save(cache, stamp)?;
+        }
+        Ok(bloom)
+    }
+
+    fn build(source: &str, fp_rate: f64) -> Result<Self> {
+        // Sized from the line count, so the file is read twice
+        let entries = addresses(source)?.try_fold(0u64, |count, address| address.map(|_| count + 1))?;
+        let ln2 = std::f64::consts::LN_2;
+        let bit_count = ((entries.max(1) as f64 * -fp_rate.ln() / (ln2 * ln2)).ceil() as u64).max(64);
+        let hashes = ((bit_count as f64 / entries.max(1) as f64 * ln2).round() as u32).clamp(1, MAX_HASHES);
+        let mut bloom = Self::empty(source, bit_count.div_ceil(64) as usize, hashes, entries);
+        for address in addresses(source)? {
+            bloom.insert(&address?);
+        }
+        Ok(bloom)
+    }
+
+    fn empty(source: &str, words: usize, hashes: u32, entries: u64) -> Self {
+        Self {
+            bits: vec![0; words],
+            hashes,
+            entries,
+            source: source.to_string(),
+            positives: AtomicU64::new(0),
+            false_positives: AtomicU64::new(0),
+        }
+    }
+
+    /// A saved filter, or `None` when there is none or it was saved from a
+    /// different version of the list.
+    fn load(cache: &str, source: &str, stamp: (u64, u64)) -> Result<Option<Self>> {
+        let mut file = match File::open(cache) {
+            Ok(file) => BufReader::new(file),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(anyhow::anyhow!("Failed to open bloom filter {}: {}", cache, e)),
+        };
+        let mut header = [0u8; 8 + 4 + 8 * 4];
+        file.read_exact(&mut header).map_err(|e| anyhow::anyhow!("Failed to read bloom filter {}: {}", cache, e))?;
+        if &header[..8] != MAGIC {
+            return Err(anyhow::anyhow!("{} is not a bloom filter written by this tool", cache));
+        }
+        let u64_at = |offset: usize| u64::from_le_bytes(header[offset..offset + 8].try_into().expect("8 bytes"));
+        let hashes = u32::from_le_bytes(header[8..12].try_into().expect("4 bytes"));
+        let (entries, words, length, modified) = (u64_at(12), u64_at(20), u64_at(28), u64_at(36));
+        if (length, modified) != stamp {
+            return Ok(None);
+        }
+        let mut bloom = Self::empty(source, words as usize, hashes, entries);
+        let mut word = [0u8; 8];
+        for bits in bloom.bits.iter_mut() {
+            file.read_exact(&mut word).map_err(|e| anyhow::anyhow!("Bloom filter {} is truncated: {}", cache, e))?;
+            *bits = u64::from_le_bytes(word);
+        }
+        Ok(Some(bloom))
+    }
+
+    fn save(&self, cache: &str, stamp: (u64, u64)) -> Result<()> {
+        let file = File::create(cache).map_err(|e| anyhow::anyhow!("Failed to create bloom filter {}: {}", cache, e))?;
+        let mut file = BufWriter::new(file);
+        let mut header = MAGIC.to_vec();
+        header.extend_from_slice(&self.hashes.to_le_bytes());
+        for value in [self.entries, self.bits.len() as u64, stamp.0, stamp.1] {
+            header.extend_from_slice(&value.to_le_bytes());
+        }
+        file.write_all(&header)
+            .and_then(|_| self.bits.iter().try_for_each(|bits| file.write_all(&bits.to_le_bytes())))
+            .and_then(|_| file.flush())
+            .map_err(|e| anyhow::anyhow!("Failed to write bloom filter {}: {}", cache, e))
+    }
+
+    /// Bit positions of `address`, by double hashing the two halves of its
+    /// SHA-256.
+    fn positions(&self, address: &str) -> impl Iterator<Item = usize> {
+        let hash = sha256::Hash::hash(address.as_bytes()).to_byte_array();
+        let first = u64::from_le_bytes(hash[..8].try_into().expect("8 bytes"));
+        let second = u64::from_le_bytes(hash[8..16].try_into().expect("8 bytes"));
+        let bit_count = self.bits.len() as u64 * 64;
+        (0..u64::from(self.hashes)).map(move |i| (first.wrapping_add(i.wrapping_mul(second)) % bit_count) as usize)
+    }
+
+    fn insert(&mut self, address: &str) {
+        for position in self.positions(address).collect::<Vec<_>>() {
+            self.bits[position / 64] |= 1 << (position % 64);
+        }
+    }
+
+    fn might_contain(&self, address: &str) -> bool {
+        self.positions(address).all(|position| self.bits[position / 64] & (1 << (position % 64)) != 0)
+    }
+
+    /// Whether `address` is on the list: the filter first, then a scan of
+    /// the file for the filter's hits.
+    pub fn contains(&self, address: &str) -> Result<bool> {
+        if !self.might_contain(address) {
+            return Ok(false);
+        }
+        self.positives.fetch_add(1, Ordering::Relaxed);
+        for entry in addresses(&self.source)? {
+            if entry? == address {
+                return Ok(true);
+            }
+        }
+        self.false_positives.fetch_add(1, Ordering::Relaxed);
+        Ok(false)
+    }
+
+    /// Addresses the filter was built from.
+    pub fn entries(&self) -> u64 {
+        self.entries
+    }
+
+    /// Size of the filter in bytes.
+    pub fn size(&self) -> usize {
+        self.bits.len() * 8
+    }
+
+    /// Filter hits so far, and how many of them the file didn't confirm.
+    pub fn positives(&self) -> (u64, u64) {
+        (self.positives.load(Ordering::Relaxed), self.false_positives.load(Ordering::Relaxed))
+    }
+}
+
+/// Length and modification time of the list, which a saved filter must
+/// have been built from.
+fn source_stamp(source: &str) -> Result<(u64, u64)> {
+    let metadata =
+        fs::metadata(source).map_err(|e| anyhow::anyhow!("Failed to read address database file {}: {}", source, e))?;
+    let modified = metadata.modified().ok().and_then(|time| time.duration_since(UNIX_EPOCH).ok());
+    Ok((metadata.len(), modified.map_or(0, |time| time.as_secs())))
+}
+
+/// The addresses of a list file, one per line, normalized as the address
+/// database normalizes them.
+fn addresses(source: &str) -> Result<impl Iterator<Item = Result<String>>> {
+    let file =
+        File::open(source).map_err(|e| anyhow::anyhow!("Failed to open address database file {}: {}", source, e))?;
+    Ok(BufReader::new(file).lines().filter_map(|line| match line {
+        Ok(line) if line.trim().is_empty() => None,
+        Ok(line) => Some(ethereum::normalize(line.trim())),
+        Err(e) => Some(Err(anyhow::anyhow!("Failed to read address database: {}", e))),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("bip39_recovery-bloom-{}-{}", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        path.to_string_lossy().into_owned()
+    }
+
+    fn address(n: usize) -> String {
+        format!("1Address{:05}", n)
+    }
+
+    fn list(count: usize) -> String {
+        (0..count).map(|n| address(n) + "\n").collect()
+    }
+
+    #[test]
+    fn finds_every_entry_and_confirms_hits() {
+        let source = file("entries");
+        fs::write(&source, format!("\n{}  \n", list(1000))).unwrap();
+        let bloom = AddressBloom::open(&source, 0.01, None).unwrap();
+        assert_eq!(bloom.entries(), 1000);
+        assert!((0..1000).all(|n| bloom.contains(&address(n)).unwrap()));
+        assert_eq!(bloom.positives(), (1000, 0));
+
+        // Misses stay near the rate asked for, and a hit the file doesn't
+        // hold is never reported
+        assert!((1000..11000).all(|n| !bloom.contains(&address(n)).unwrap()));
+        let (positives, false_positives) = bloom.positives();
+        assert_eq!(positives - false_positives, 1000);
+        assert!(false_positives < 200, "{} false positives", false_positives);
+        let _ = fs::remove_file(&source);
+    }
+
+    #[test]
+    fn reuses_a_cache_of_the_same_list() {
+        let (source, cache) = (file("source"), file("cache"));
+        fs::write(&source, list(500)).unwrap();
+        let built = AddressBloom::open(&source, 0.001, Some(&cache)).unwrap();
+
+        // Loaded rather than built, so the rate asked for has no effect
+        let loaded = AddressBloom::open(&source, 0.5, Some(&cache)).unwrap();
+        assert_eq!((loaded.bits.len(), loaded.hashes, loaded.entries()), (built.bits.len(), built.hashes, 500));
+        assert_eq!(loaded.bits, built.bits);
+
+        // A changed list is read again
+        fs::write(&source, list(501)).unwrap();
+        let rebuilt = AddressBloom::open(&source, 0.5, Some(&cache)).unwrap();
+        assert_eq!(rebuilt.entries(), 501);
+        assert!(rebuilt.size() < built.size());
+        assert!(rebuilt.contains(&address(500)).unwrap());
+
+        fs::write(&cache, b"not a filter at all, but long enough to have a header").unwrap();
+        assert!(AddressBloom::open(&source, 0.5, Some(&cache)).is_err());
+        let _ = fs::remove_file(&source);
+        let _ = fs::remove_file(&cache);
+    }
+
+    #[test]
+    fn rejects_bad_rates_and_missing_lists() {
+        let source = file("rates");
+        assert!(AddressBloom::open(&source, 0.01, None).is_err());
+        fs::write(&source, list(10)).unwrap();
+        for rate in [0.0, 1.0, -0.5, f64::NAN] {
+            assert!(AddressBloom::open(&source, rate, None).is_err(), "rate {}", rate);
+        }
+        let _ = fs::remove_file(&source);
+    }
+}
@@ -0,0 +1,237 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+
+/// Widest `--max-displacement` enumerated below the full permutation
+/// count; the window of words a position may take grows as 2K + 1.
+pub const MAX_DISPLACEMENT: usize = 8;
+
+/// How far the true order may be from the one given.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bound {
+    /// Each word is at most this many positions from where it was given
+    Displacement(usize),
+    /// At most this many swaps of neighbouring words were made, so the
+    /// order has at most this many pairs out of place
+    Swaps(usize),
+}
+
+/// The orderings of the permutable words within a [`Bound`] of the order
+/// given, ranked lexicographically by position indices, so rank 0 is the
+/// order as given.
+#[derive(Clone)]
+pub struct BoundedOrder {
+    n: usize,
+    bound: Bound,
+    /// Displacement: completions from each position and window of used
+    /// words, bit `b` standing for word `position - K + b`
+    completions: Vec<HashMap<u64, u64>>,
+    /// Swaps: `within[m][b]`, the orderings of `m` words with at most `b`
+    /// pairs out of place
+    within: Vec<Vec<u64>>,
+    space: u64,
+}
+
+impl BoundedOrder {
+    pub fn new(n: usize, bound: Bound) -> Result<Self> {
+        let mut order = Self { n, bound, completions: Vec::new(), within: Vec::new(), space: 0 };
+        match bound {
+            Bound::Displacement(k) => {
+                // Past n - 1 the bound allows every order
+                let k = k.min(n.saturating_sub(1));
+                if k > MAX_DISPLACEMENT {
+                    return Err(anyhow::anyhow!(
+                        "--max-displacement above {} is too wide to enumerate over {} words; leave it out to try every order",
+                        MAX_DISPLACEMENT,
+                        n
+                    ));
+                }
+                order.bound = Bound::Displacement(k);
+                order.completions = vec![HashMap::new(); n + 1];
+                order.space = order.count(0, (1 << k) - 1);
+            }
+            Bound::Swaps(swaps) => {
+                let budget = swaps.min(n * n.saturating_sub(1) / 2);
+                order.bound = Bound::Swaps(budget);
+                order.within = within(n, budget);
+                order.space = order.within[n][budget];
+            }
+        }
+        Ok(order)
+    }
+
+    /// Number of orderings, the ranks of the search.
+    pub fn space(&self) -> u64 {
+        self.space
+    }
+
+    pub fn bound(&self) -> Bound {
+        self.bound
+    }
+
+    /// Window bits a word may be placed from at `position`: unused, and a
+    /// word that exists.
+    fn choices(&self, position: usize, mask: u64) -> impl Iterator<Item = usize> + '_ {
+        let Bound::Displacement(k) = self.bound else { unreachable!("only displacement bounds have a window") };
+        (0..=2 * k).filter(move |&bit| mask & (1 << bit) == 0 && position + bit < self.n + k)
+    }
+
+    /// Orderings of the remaining positions; the word leaving the window at
+    /// each step must have been placed by then.
+    fn count(&mut self, position: usize, mask: u64) -> u64 {
+        if position == self.n {
+            return 1;
+        }
+        if let Some(&count) = self.completions[position].get(&mask) {
+            return count;
+        }
+        let mut total: u64 = 0;
+        for bit in self.choices(position, mask).collect::<Vec<_>>() {
+            let next = mask | (1 << bit);
+            if next & 1 != 0 {
+                total = total.saturating_add(self.count(position + 1, next >> 1));
+            }
+        }
+        self.completions[position].insert(mask, total);
+        total
+    }
+
+    fn completions_from(&self, position: usize, mask: u64) -> u64 {
+        if position == self.n {
+            1
+        } else {
+            self.completions[position].get(&mask).copied().unwrap_or(0)
+        }
+    }
+
+    /// Position indices of the `rank`-th ordering.
+    pub fn unrank(&self, mut rank: u64) -> Vec<usize> {
+        let mut indices = Vec::with_capacity(self.n);
+        match self.bound {
+            Bound::Displacement(k) => {
+                let mut mask: u64 = (1 << k) - 1;
+                for position in 0..self.n {
+                    for bit in self.choices(position, mask) {
+                        let next = mask | (1 << bit);
+                        if next & 1 == 0 {
+                            continue;
+                        }
+                        let count = self.completions_from(position + 1, next >> 1);
+                        if rank < count {
+                            indices.push(position + bit - k);
+                            mask = next >> 1;
+                            break;
+                        }
+                        rank -= count;
+                    }
+                }
+            }
+            Bound::Swaps(mut budget) => {
+                // Taking the r-th smallest remaining word puts r pairs out
+                // of place
+                let mut remaining: Vec<usize> = (0..self.n).collect();
+                while !remaining.is_empty() {
+                    let m = remaining.len();
+                    for r in 0..m.min(budget + 1) {
+                        let count = self.within[m - 1][budget - r];
+                        if rank < count {
+                            indices.push(remaining.remove(r));
+                            budget -= r;
+                            break;
+                        }
+                        rank -= count;
+                    }
+                }
+            }
+        }
+        indices
+    }
+
+    /// The orderings of `items` with ranks in `[start, end)`.
+    pub fn into_range<T: Clone>(self, items: Vec<T>, start: u64, end: u64) -> impl Iterator<Item = Vec<T>> {
+        let end = end.min(self.space);
+        (start..end).map(move |rank| self.unrank(rank).into_iter().map(|i| items[i].clone()).collect())
+    }
+}
+
+/// `within[m][b]` for every `m` up to `n` and `b` up to `budget`: prefix
+/// sums of the Mahonian numbers, the orderings with exactly `b` inversions.
+fn within(n: usize, budget: usize) -> Vec<Vec<u64>> {
+    let mut exact = vec![0u64; budget + 1];
+    exact[0] = 1;
+    let mut within = Vec::with_capacity(n + 1);
+    for m in 0..=n {
+        if m > 1 {
+            // A new largest word inserted with r of the others after it
+            // adds r inversions
+            let previous = exact.clone();
+            for (b, count) in exact.iter_mut().enumerate() {
+                *count = (0..m.min(b + 1)).fold(0u64, |sum, r| sum.saturating_add(previous[b - r]));
+            }
+        }
+        let sums: Vec<u64> = exact
+            .iter()
+            .scan(0u64, |sum, &count| {
+                *sum = sum.saturating_add(count);
+                Some(*sum)
+            })
+            .collect();
+        within.push(sums);
+    }
+    within
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use super::*;
+
+    fn inversions(order: &[usize]) -> usize {
+        order.iter().tuple_combinations().filter(|(a, b)| a > b).count()
+    }
+
+    /// Unranking yields the permutations within the bound, in lexicographic
+    /// order, as filtering every permutation would.
+    fn check(n: usize, bound: Bound, keep: impl Fn(&[usize]) -> bool) {
+        let expected: Vec<Vec<usize>> = (0..n).permutations(n).filter(|order| keep(order)).collect();
+        let order = BoundedOrder::new(n, bound).unwrap();
+        assert_eq!(order.space(), expected.len() as u64, "{:?} over {} words", bound, n);
+        let all: Vec<Vec<usize>> = order.into_range((0..n).collect(), 0, u64::MAX).collect();
+        assert_eq!(all, expected, "{:?} over {} words", bound, n);
+    }
+
+    #[test]
+    fn matches_filtered_permutations() {
+        for n in 0..=7 {
+            for k in 0..=n {
+                check(n, Bound::Displacement(k), |order| {
+                    order.iter().enumerate().all(|(position, &word)| position.abs_diff(word) <= k)
+                });
+            }
+            for swaps in 0..=n * n.saturating_sub(1) / 2 + 1 {
+                check(n, Bound::Swaps(swaps), |order| inversions(order) <= swaps);
+            }
+        }
+    }
+
+    #[test]
+    fn counts_over_a_full_mnemonic() {
+        // Neighbours swapped or not: a Fibonacci number
+        assert_eq!(BoundedOrder::new(24, Bound::Displacement(1)).unwrap().space(), 75025);
+        let one_swap = BoundedOrder::new(24, Bound::Swaps(1)).unwrap();
+        assert_eq!(one_swap.space(), 24);
+        assert_eq!(one_swap.unrank(0), (0..24).collect::<Vec<_>>());
+        let last = one_swap.unrank(23);
+        assert_eq!((last[0], last[1], inversions(&last)), (1, 0, 1));
+    }
+
+    #[test]
+    fn clamps_wide_bounds() {
+        let order = BoundedOrder::new(5, Bound::Displacement(100)).unwrap();
+        assert_eq!((order.bound(), order.space()), (Bound::Displacement(4), 120));
+        let order = BoundedOrder::new(5, Bound::Swaps(100)).unwrap();
+        assert_eq!((order.bound(), order.space()), (Bound::Swaps(10), 120));
+        assert!(BoundedOrder::new(24, Bound::Displacement(MAX_DISPLACEMENT + 1)).is_err());
+    }
+}
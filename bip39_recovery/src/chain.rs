@@ -0,0 +1,472 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::Address;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::live_stats::unix_now;
+
+/// Tries a query gets, backing off twice as long after each failure, before
+/// its error is returned.
+const ATTEMPTS: u32 = 5;
+
+/// The first backoff; later ones double it.
+const BACKOFF: Duration = Duration::from_secs(2);
+
+/// How long a server gets to answer one request.
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Where address histories come from.
+enum Server {
+    /// An Esplora REST API, e.g. `http://localhost:3000` or `http://host/api`
+    Esplora { host: String, base: String },
+    /// An Electrum server's TCP port, e.g. `tcp://localhost:50001`
+    Electrum { host: String },
+}
+
+/// Answers already had, kept in the state directory so an interrupted scan
+/// asks only for the rest when it's run again.
+#[derive(Default, Serialize, Deserialize)]
+struct Cache {
+    /// The `--chain` the answers came from; another server starts over
+    server: String,
+    /// Confirmed and unconfirmed transactions per address
+    transactions: HashMap<String, Lookup>,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+struct Lookup {
+    count: usize,
+    /// When the server was asked, in Unix seconds
+    checked: u64,
+}
+
+impl Lookup {
+    /// An address with history keeps it, so only an unused answer can go
+    /// stale: one older than `max_age` is asked again.
+    fn is_fresh(&self, now: u64, max_age: Duration) -> bool {
+        self.count > 0 || now.saturating_sub(self.checked) < max_age.as_secs()
+    }
+}
+
+/// Shared client for address lookups against an Esplora or Electrum
+/// server. Lookups are answered from the cache where they can be, the rest
+/// go out `batch` at a time (as one JSON-RPC batch to Electrum) and no
+/// faster than `rate` addresses per second, and failures are retried with
+/// backoff, so a public server isn't hammered and a rate-limited answer
+/// doesn't end a scan. A cached "unused" is trusted for `max_age`, since the
+/// address may have been used since.
+pub struct ChainClient {
+    url: String,
+    server: Server,
+    /// Time between two lookups at `rate`, and when the next may go out
+    interval: Duration,
+    next: Mutex<Instant>,
+    batch: usize,
+    cache_path: String,
+    max_age: Duration,
+    cache: Mutex<Cache>,
+    /// The open Electrum connection, reopened after a failure
+    connection: Mutex<Option<BufReader<TcpStream>>>,
+}
+
+#[derive(Deserialize)]
+struct EsploraStats {
+    tx_count: usize,
+}
+
+#[derive(Deserialize)]
+struct EsploraAddress {
+    chain_stats: EsploraStats,
+    mempool_stats: EsploraStats,
+}
+
+#[derive(Deserialize)]
+struct RpcReply {
+    id: usize,
+    #[serde(default)]
+    result: Option<serde_json::Value>,
+    #[serde(default)]
+    error: Option<serde_json::Value>,
+}
+
+impl ChainClient {
+    /// `url` is `http://host:port[/path]` for Esplora or `tcp://host:port`
+    /// for Electrum. TLS isn't supported; reach a public `https://` or
+    /// `ssl://` endpoint through a local tunnel or proxy.
+    pub fn new(url: &str, rate: f64, batch: usize, cache_path: &str, max_age: Duration) -> Result<Self> {
+        let server = if let Some(rest) = url.strip_prefix("http://") {
+            let (host, base) = rest.split_once('/').unwrap_or((rest, ""));
+            let base = base.trim_matches('/');
+            Server::Esplora { host: host.to_string(), base: if base.is_empty() { String::new() } else { format!("/{}", base) } }
+        } else if let Some(host) = url.strip_prefix("tcp://") {
+            Server::Electrum { host: host.trim_end_matches('/').to_string() }
+        } else {
+            return Err(anyhow::anyhow!(
+                "Invalid --chain {}: expected http://host:port for Esplora or tcp://host:port for Electrum (TLS isn't supported)",
+                url
+            ));
+        };
+        if !rate.is_finite() || rate <= 0.0 {
+            return Err(anyhow::anyhow!("Invalid --chain-rate {}: must be a positive number of lookups per second", rate));
+        }
+        if batch == 0 {
+            return Err(anyhow::anyhow!("--chain-batch must be at least 1"));
+        }
+        let cache = match fs::read_to_string(cache_path) {
+            Ok(json) => match serde_json::from_str::<Cache>(&json) {
+                Ok(cache) if cache.server == url => {
+                    info!("Reusing {} cached address histories from {}", cache.transactions.len(), cache_path);
+                    cache
+                }
+                Ok(_) => Cache { server: url.to_string(), ..Cache::default() },
+                Err(e) => {
+                    warn!("Ignoring unreadable chain cache {}: {}", cache_path, e);
+                    Cache { server: url.to_string(), ..Cache::default() }
+                }
+            },
+            Err(_) => Cache { server: url.to_string(), ..Cache::default() },
+        };
+        Ok(Self {
+            url: url.to_string(),
+            server,
+            interval: Duration::from_secs_f64(1.0 / rate),
+            next: Mutex::new(Instant::now()),
+            batch,
+            cache_path: cache_path.to_string(),
+            max_age,
+            cache: Mutex::new(cache),
+            connection: Mutex::new(None),
+        })
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// How many transactions each of `addresses` has had, confirmed or not.
+    pub fn transactions(&self, addresses: &[String]) -> Result<Vec<usize>> {
+        let missing: Vec<String> = {
+            let cache = self.cache.lock().unwrap();
+            let now = unix_now();
+            let mut seen = HashSet::new();
+            addresses
+                .iter()
+                .filter(|address| {
+                    let lookup = cache.transactions.get(*address);
+                    !lookup.is_some_and(|lookup| lookup.is_fresh(now, self.max_age)) && seen.insert(*address)
+                })
+                .cloned()
+                .collect()
+        };
+        for batch in missing.chunks(self.batch) {
+            let counts = self.retrying(|| self.query(batch))?;
+            let checked = unix_now();
+            let mut cache = self.cache.lock().unwrap();
+            let lookups = counts.into_iter().map(|count| Lookup { count, checked });
+            cache.transactions.extend(batch.iter().cloned().zip(lookups));
+            self.save(&cache);
+        }
+        let cache = self.cache.lock().unwrap();
+        Ok(addresses.iter().map(|address| cache.transactions[address].count).collect())
+    }
+
+    /// Whether each of `addresses` has any history.
+    pub fn used(&self, addresses: &[String]) -> Result<Vec<bool>> {
+        Ok(self.transactions(addresses)?.into_iter().map(|count| count > 0).collect())
+    }
+
+    fn save(&self, cache: &Cache) {
+        let tmp = format!("{}.tmp", self.cache_path);
+        let saved = serde_json::to_string(cache)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| Ok(fs::write(&tmp, json)?))
+            .and_then(|_| Ok(fs::rename(&tmp, &self.cache_path)?));
+        if let Err(e) = saved {
+            warn!("Failed to save the chain cache {}: {}", self.cache_path, e);
+        }
+    }
+
+    /// Waits until the next lookup may go out.
+    fn pace(&self) {
+        let wait = {
+            let mut next = self.next.lock().unwrap();
+            let now = Instant::now();
+            let slot = (*next).max(now);
+            *next = slot + self.interval;
+            slot - now
+        };
+        std::thread::sleep(wait);
+    }
+
+    fn retrying<T>(&self, query: impl Fn() -> Result<T>) -> Result<T> {
+        let mut backoff = BACKOFF;
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            match query() {
+                Ok(answer) => return Ok(answer),
+                Err(e) if attempt < ATTEMPTS => {
+                    warn!("Chain query to {} failed ({}); retrying in {}s", self.url, e, backoff.as_secs());
+                    // A broken connection is reopened by the next try
+                    self.connection.lock().unwrap().take();
+                    std::thread::sleep(backoff);
+                    backoff *= 2;
+                }
+                Err(e) => return Err(anyhow::anyhow!("Chain query to {} failed after {} tries: {}", self.url, ATTEMPTS, e)),
+            }
+        }
+    }
+
+    fn query(&self, addresses: &[String]) -> Result<Vec<usize>> {
+        match &self.server {
+            Server::Esplora { host, base } => addresses
+                .iter()
+                .map(|address| {
+                    self.pace();
+                    let body = http_get(host, &format!("{}/address/{}", base, address))?;
+                    let stats: EsploraAddress = serde_json::from_str(&body)
+                        .map_err(|e| anyhow::anyhow!("Invalid Esplora answer for {}: {}", address, e))?;
+                    Ok(stats.chain_stats.tx_count + stats.mempool_stats.tx_count)
+                })
+                .collect(),
+            Server::Electrum { host } => {
+                let requests = addresses
+                    .iter()
+                    .enumerate()
+                    .map(|(id, address)| {
+                        self.pace();
+                        Ok(serde_json::json!({
+                            "jsonrpc": "2.0",
+                            "id": id,
+                            "method": "blockchain.scripthash.get_history",
+                            "params": [scripthash(address)?],
+                        }))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                let mut replies: Vec<RpcReply> = serde_json::from_value(self.electrum(host, &requests)?)
+                    .map_err(|e| anyhow::anyhow!("Invalid Electrum answer: {}", e))?;
+                replies.sort_by_key(|reply| reply.id);
+                if replies.len() != addresses.len() {
+                    return Err(anyhow::anyhow!("Electrum answered {} of {} requests", replies.len(), addresses.len()));
+                }
+                replies
+                    .into_iter()
+                    .zip(addresses)
+                    .map(|(reply, address)| match (reply.result, reply.error) {
+                        (Some(serde_json::Value::Array(history)), None) => Ok(history.len()),
+                        (_, Some(error)) => Err(anyhow::anyhow!("Electrum refused {}: {}", address, error)),
+                        _ => Err(anyhow::anyhow!("Invalid Electrum answer for {}", address)),
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// Sends a JSON-RPC batch over the open connection, opening it (and
+    /// introducing the client) first if there's none.
+    fn electrum(&self, host: &str, requests: &[serde_json::Value]) -> Result<serde_json::Value> {
+        let mut connection = self.connection.lock().unwrap();
+        if connection.is_none() {
+            let stream = TcpStream::connect(host)?;
+            stream.set_read_timeout(Some(TIMEOUT))?;
+            let mut reader = BufReader::new(stream);
+            let version = serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "method": "server.version",
+                "params": ["bip39_recovery", "1.4"],
+            });
+            exchange(&mut reader, &version)?;
+            *connection = Some(reader);
+        }
+        let reader = connection.as_mut().expect("connection opened above");
+        exchange(reader, &serde_json::Value::Array(requests.to_vec()))
+    }
+}
+
+/// Derives a chain's addresses from index 0 with `derive` until `gap_limit`
+/// in a row past the last used one are unused, as a wallet restoring the
+/// chain would, and returns each with whether `used` says it is. `used` is
+/// asked a window of `gap_limit` addresses at a time.
+pub fn gap_scan(
+    gap_limit: u32,
+    mut derive: impl FnMut(u32) -> Result<String>,
+    used: impl Fn(&[String]) -> Result<Vec<bool>>,
+) -> Result<Vec<(u32, String, bool)>> {
+    let gap_limit = gap_limit.max(1);
+    let mut scanned = Vec::new();
+    let mut end = gap_limit;
+    let mut index = 0;
+    while index < end {
+        let window = (index..end.min(index.saturating_add(gap_limit))).map(|i| Ok((i, derive(i)?))).collect::<Result<Vec<_>>>()?;
+        let addresses: Vec<String> = window.iter().map(|(_, address)| address.clone()).collect();
+        for ((i, address), used) in window.into_iter().zip(used(&addresses)?) {
+            if used {
+                end = end.max(i.saturating_add(1).saturating_add(gap_limit));
+            }
+            scanned.push((i, address, used));
+        }
+        index = scanned.len() as u32;
+    }
+    Ok(scanned)
+}
+
+/// Writes one line of JSON and reads the line that answers it.
+fn exchange(reader: &mut BufReader<TcpStream>, request: &serde_json::Value) -> Result<serde_json::Value> {
+    let mut line = serde_json::to_string(request)?;
+    line.push('\n');
+    reader.get_mut().write_all(line.as_bytes())?;
+    let mut answer = String::new();
+    if reader.read_line(&mut answer)? == 0 {
+        return Err(anyhow::anyhow!("Electrum server closed the connection"));
+    }
+    Ok(serde_json::from_str(&answer)?)
+}
+
+/// Electrum's key for an address: the SHA-256 of its output script, byte
+/// reversed, in hex.
+fn scripthash(address: &str) -> Result<String> {
+    let script = Address::from_str(address)
+        .map_err(|e| anyhow::anyhow!("Can't look up {} on chain: {}", address, e))?
+        .assume_checked()
+        .script_pubkey();
+    let mut hash = sha256::Hash::hash(script.as_bytes()).to_byte_array();
+    hash.reverse();
+    Ok(hash.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// A plain HTTP/1.0 GET, which keeps the answer free of chunked encoding.
+fn http_get(host: &str, path: &str) -> Result<String> {
+    let mut stream = TcpStream::connect(host)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    write!(stream, "GET {} HTTP/1.0\r\nHost: {}\r\nAccept: application/json\r\n\r\n", path, host)?;
+    stream.flush()?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    let (head, body) = response.split_once("\r\n\r\n").unwrap_or((&response, ""));
+    let status = head.lines().next().unwrap_or_default();
+    match status.split_whitespace().nth(1) {
+        Some("200") => Ok(body.to_string()),
+        _ => Err(anyhow::anyhow!("{} answered {}: {}", host, status, body.trim())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use super::*;
+
+    /// An Esplora server on a local port that says the addresses in `used`
+    /// have one transaction and others none, and counts what it's asked.
+    fn esplora(used: &'static [&'static str]) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let asked = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&asked);
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut reader = BufReader::new(stream.unwrap());
+                let mut request = String::new();
+                reader.read_line(&mut request).unwrap();
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                counter.fetch_add(1, Ordering::SeqCst);
+                let path = request.split_whitespace().nth(1).unwrap_or_default();
+                let count = used.iter().any(|address| path.ends_with(address)) as usize;
+                let body = format!(r#"{{"chain_stats":{{"tx_count":{}}},"mempool_stats":{{"tx_count":0}}}}"#, count);
+                write!(reader.get_mut(), "HTTP/1.0 200 OK\r\nContent-Type: application/json\r\n\r\n{}", body).unwrap();
+            }
+        });
+        (url, asked)
+    }
+
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    fn cache_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("bip39_recovery-{}-{}.json", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        path.to_string_lossy().into_owned()
+    }
+
+    fn addresses(list: &[&str]) -> Vec<String> {
+        list.iter().map(|address| address.to_string()).collect()
+    }
+
+    #[test]
+    fn gap_scan_stops_gap_limit_past_the_last_used() {
+        let scanned = gap_scan(20, |i| Ok(i.to_string()), |window| {
+            Ok(window.iter().map(|address| address == "3" || address == "22").collect())
+        })
+        .unwrap();
+        assert_eq!(scanned.len(), 43);
+        let used: Vec<u32> = scanned.iter().filter(|(_, _, used)| *used).map(|(i, _, _)| *i).collect();
+        assert_eq!(used, [3, 22]);
+    }
+
+    #[test]
+    fn gap_scan_of_an_unused_chain_is_one_gap() {
+        let scanned = gap_scan(5, |i| Ok(i.to_string()), |window| Ok(vec![false; window.len()])).unwrap();
+        assert_eq!(scanned.len(), 5);
+        assert_eq!(gap_scan(0, |i| Ok(i.to_string()), |window| Ok(vec![false; window.len()])).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn answers_repeated_lookups_from_the_cache() {
+        let (url, asked) = esplora(&["spent"]);
+        let path = cache_path("chain-cache");
+        let client = ChainClient::new(&url, 1000.0, 20, &path, DAY).unwrap();
+        assert_eq!(client.used(&addresses(&["spent", "idle", "spent"])).unwrap(), [true, false, true]);
+        assert_eq!(asked.load(Ordering::SeqCst), 2);
+        assert_eq!(client.used(&addresses(&["idle", "spent"])).unwrap(), [false, true]);
+        assert_eq!(asked.load(Ordering::SeqCst), 2);
+
+        // A later run reads them back from the file
+        let client = ChainClient::new(&url, 1000.0, 20, &path, DAY).unwrap();
+        assert_eq!(client.transactions(&addresses(&["spent"])).unwrap(), [1]);
+        assert_eq!(asked.load(Ordering::SeqCst), 2);
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn asks_again_once_an_unused_answer_is_stale() {
+        let (url, asked) = esplora(&["spent"]);
+        let path = cache_path("chain-cache-age");
+        let client = ChainClient::new(&url, 1000.0, 20, &path, Duration::ZERO).unwrap();
+        assert_eq!(client.used(&addresses(&["spent", "idle"])).unwrap(), [true, false]);
+        assert_eq!(client.used(&addresses(&["spent", "idle"])).unwrap(), [true, false]);
+        // A used address stays used; only the unused one is asked about again
+        assert_eq!(asked.load(Ordering::SeqCst), 3);
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn starts_over_for_another_server() {
+        let (url, asked) = esplora(&[]);
+        let path = cache_path("chain-cache-server");
+        ChainClient::new(&url, 1000.0, 20, &path, DAY).unwrap().used(&addresses(&["a"])).unwrap();
+        let (other, asked_other) = esplora(&["a"]);
+        assert_eq!(ChainClient::new(&other, 1000.0, 20, &path, DAY).unwrap().used(&addresses(&["a"])).unwrap(), [true]);
+        assert_eq!((asked.load(Ordering::SeqCst), asked_other.load(Ordering::SeqCst)), (1, 1));
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn rejects_other_schemes() {
+        assert!(ChainClient::new("https://blockstream.info/api", 5.0, 20, &cache_path("chain-scheme"), DAY).is_err());
+        assert!(ChainClient::new("http://localhost:3000", 0.0, 20, &cache_path("chain-rate"), DAY).is_err());
+    }
+}
@@ -0,0 +1,39 @@
+use std::convert::Infallible;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Ranks a thread unranks and checks before taking another chunk. Small
+/// enough that the threads finish together, large enough that unranking the
+/// start of each chunk costs nothing.
+pub const CHUNK_SIZE: u64 = 1 << 12;
+
+/// Calls `f` with `[start, end)` in contiguous `[first, last)` chunks of
+/// [`CHUNK_SIZE`] ranks, on every thread of the current rayon pool, so each
+/// builds the candidates of its own chunk instead of sharing one iterator
+/// behind a lock. A thread takes the next chunk as soon as it finishes one,
+/// so chunks go out in rank order and the ranks done ahead of the
+/// checkpoint frontier stay within about a chunk per thread.
+///
+/// The first error stops any more chunks being handed out; chunks already
+/// taken are finished.
+pub fn try_for_each<E: Send>(start: u64, end: u64, f: impl Fn(u64, u64) -> Result<(), E> + Sync) -> Result<(), E> {
+    let next = AtomicU64::new(start);
+    let results = rayon::broadcast(|_| loop {
+        let first = next.fetch_add(CHUNK_SIZE, Ordering::Relaxed);
+        if first >= end {
+            return Ok(());
+        }
+        if let Err(e) = f(first, first.saturating_add(CHUNK_SIZE).min(end)) {
+            next.store(end, Ordering::Relaxed);
+            return Err(e);
+        }
+    });
+    results.into_iter().collect()
+}
+
+/// [`try_for_each`] for work that can't fail.
+pub fn for_each(start: u64, end: u64, f: impl Fn(u64, u64) + Sync) {
+    let Ok(()) = try_for_each(start, end, |first, last| {
+        f(first, last);
+        Ok::<_, Infallible>(())
+    });
+}
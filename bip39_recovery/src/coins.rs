@@ -0,0 +1,157 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+
+use anyhow::Result;
+use bitcoin::bip32::DerivationPath;
+use serde::Deserialize;
+
+use crate::matrix::{self, Encoding, PathEntry, SearchMatrix, ADDRESS_TYPES};
+use crate::overrides::AddressOverrides;
+use crate::{paths, pathspec};
+
+/// Coins file read when `--coins-file` isn't given.
+pub const DEFAULT_FILE: &str = "coins.toml";
+
+/// The coins file shipped with the source, used when there is no
+/// `coins.toml` in the working directory.
+const BUILTIN: &str = include_str!("../coins.toml");
+
+/// `coins.toml` contents, one table per chain, e.g.
+///
+/// ```toml
+/// [coin.litecoin]
+/// aliases = ["ltc"]
+/// coin_type = 2
+/// address_types = ["p2pkh", "p2sh-p2wpkh", "p2wpkh"]
+/// bech32_hrp = "ltc"
+/// p2pkh_version = "0x30"
+/// p2sh_version = "0x32"
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CoinsFile {
+    #[serde(default)]
+    coin: BTreeMap<String, CoinFile>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CoinFile {
+    /// Other names `--coin` accepts, e.g. the ticker
+    #[serde(default)]
+    aliases: Vec<String>,
+    /// SLIP-44 coin type, used for the default paths
+    coin_type: u32,
+    #[serde(default = "default_curve")]
+    curve: String,
+    address_types: Vec<String>,
+    /// Explicit paths, each checked with every address type. Without them
+    /// every address type gets `m/<purpose>'/<coin_type>'/0'/0/0`.
+    #[serde(default)]
+    paths: Vec<String>,
+    #[serde(default = "default_network")]
+    network: String,
+    bech32_hrp: Option<String>,
+    p2pkh_version: Option<String>,
+    p2sh_version: Option<String>,
+}
+
+fn default_curve() -> String {
+    "secp256k1".to_string()
+}
+
+fn default_network() -> String {
+    "mainnet".to_string()
+}
+
+/// A chain resolved from `coins.toml`.
+pub struct Coin {
+    pub name: String,
+    pub coin_type: u32,
+    pub matrix: SearchMatrix,
+    /// Where the definition came from, for messages
+    pub source: String,
+}
+
+/// The coin definitions in `file`, or the built-in ones when `file` is the
+/// default and doesn't exist.
+pub fn read(file: &str) -> Result<(String, String)> {
+    match fs::read_to_string(file) {
+        Ok(content) => Ok((file.to_string(), content)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound && file == DEFAULT_FILE => {
+            Ok(("built-in coin definitions".to_string(), BUILTIN.to_string()))
+        }
+        Err(e) => Err(anyhow::anyhow!("Failed to read coins file {}: {}", file, e)),
+    }
+}
+
+/// Comma-separated coin names with their aliases, for error messages.
+fn names(coins: &CoinsFile) -> String {
+    coins
+        .coin
+        .iter()
+        .map(|(name, spec)| match spec.aliases.as_slice() {
+            [] => name.clone(),
+            aliases => format!("{} ({})", name, aliases.join(", ")),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Loads `name`, a coin or one of its aliases, from the coin definitions in
+/// `file` and builds the search matrix it describes.
+pub fn load(file: &str, name: &str) -> Result<Coin> {
+    let (source, content) = read(file)?;
+    let coins: CoinsFile =
+        toml::from_str(&content).map_err(|e| anyhow::anyhow!("Failed to parse coins file {}: {}", source, e))?;
+    let (name, spec) = coins
+        .coin
+        .iter()
+        .find(|(coin, spec)| {
+            coin.eq_ignore_ascii_case(name) || spec.aliases.iter().any(|alias| alias.eq_ignore_ascii_case(name))
+        })
+        .ok_or_else(|| anyhow::anyhow!("Coin {} is not defined in {} (available: {})", name, source, names(&coins)))?;
+
+    // Only secp256k1 chains share the derivation the search performs
+    if !spec.curve.eq_ignore_ascii_case("secp256k1") {
+        return Err(anyhow::anyhow!("Coin {}: unsupported curve {}, only secp256k1 is supported", name, spec.curve));
+    }
+    if spec.address_types.is_empty() {
+        return Err(anyhow::anyhow!("Coin {} needs at least one address type", name));
+    }
+    let address_types: Vec<String> = spec.address_types.iter().map(|t| t.to_lowercase()).collect();
+    for address_type in &address_types {
+        if !ADDRESS_TYPES.contains(&address_type.as_str()) {
+            return Err(anyhow::anyhow!("Coin {}: unsupported address type {}", name, address_type));
+        }
+    }
+    let network = matrix::parse_network(&spec.network)?;
+
+    let mut search_matrix = if spec.paths.is_empty() {
+        let entries = address_types
+            .iter()
+            .map(|address_type| {
+                let purpose = paths::conventional_purpose(address_type).unwrap_or(44);
+                let path: DerivationPath = format!("m/{}'/{}'/0'/0/0", purpose, spec.coin_type).parse()?;
+                Ok(PathEntry { path, encodings: vec![Encoding { address_type: address_type.clone(), network }] })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        SearchMatrix { paths: entries, overrides: AddressOverrides::default() }
+    } else {
+        let paths = spec
+            .paths
+            .iter()
+            .map(|path| pathspec::parse(path).map_err(|e| anyhow::anyhow!("Coin {}: {}", name, e)))
+            .collect::<Result<Vec<_>>>()?;
+        SearchMatrix::build(paths, &address_types, &[network])
+    };
+    search_matrix.overrides = AddressOverrides::parse(
+        spec.bech32_hrp.as_deref(),
+        spec.p2pkh_version.as_deref(),
+        spec.p2sh_version.as_deref(),
+    )
+    .map_err(|e| anyhow::anyhow!("Coin {}: {}", name, e))?;
+
+    Ok(Coin { name: name.to_lowercase(), coin_type: spec.coin_type, matrix: search_matrix, source })
+}
@@ -0,0 +1,335 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::sync::Mutex;
+
+use anyhow::Result;
+use serde::Serialize;
+
+/// Index range a run was assigned and the size of the whole space, stored
+/// alongside the processed count so shards can be merged later.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Shard {
+    pub start: u64,
+    pub end: u64,
+    pub space: u64,
+}
+
+/// Label for checkpoints written before phases were recorded.
+pub const UNNAMED_PHASE: &str = "unnamed";
+
+/// Throughput samples kept in a checkpoint, at most one per interval, so
+/// the history spans about two days of saves.
+const MAX_SAMPLES: usize = 48;
+const SAMPLE_INTERVAL: u64 = 3600;
+
+/// Prefix and version of resume tokens.
+const TOKEN_PREFIX: &str = "r1";
+
+/// Contents of a progress file: the processed count on the first line (the
+/// original format), optionally followed by `range <start> <end>`,
+/// `space <size>`, `phase <name>`, `config <hash>`, `started <unix time>`,
+/// `derived <count>`, `sample <unix time> <processed>`, and
+/// `done <start> <end>` lines.
+///
+/// `processed` counts the ranks from the start of the range that are all
+/// done; ranks finished past the first one still in flight are kept as
+/// `done` ranges of absolute ranks, so a resume redoes nothing and skips
+/// nothing however the workers interleaved. `derived` counts the processed
+/// candidates that passed the filters and were fully derived, across every
+/// run that wrote the checkpoint.
+#[derive(Clone, Debug)]
+pub struct Checkpoint {
+    pub processed: u64,
+    pub shard: Option<Shard>,
+    pub phase: Option<String>,
+    pub config: Option<String>,
+    pub started: Option<u64>,
+    pub derived: u64,
+    pub samples: Vec<(u64, u64)>,
+    pub ahead: Vec<(u64, u64)>,
+}
+
+impl Checkpoint {
+    pub fn new(shard: Shard, phase: &str, config: &str, started: u64) -> Self {
+        Self {
+            processed: 0,
+            shard: Some(shard),
+            phase: Some(phase.to_string()),
+            config: Some(config.to_string()),
+            started: Some(started),
+            derived: 0,
+            samples: Vec::new(),
+            ahead: Vec::new(),
+        }
+    }
+
+    /// Updates the processed count, sampling it when the last sample is at
+    /// least an interval old.
+    pub fn record(&mut self, at: u64, processed: u64) {
+        self.processed = processed;
+        if self.samples.last().is_some_and(|&(last, _)| at < last + SAMPLE_INTERVAL) {
+            return;
+        }
+        self.samples.push((at, processed));
+        if self.samples.len() > MAX_SAMPLES {
+            self.samples.drain(..self.samples.len() - MAX_SAMPLES);
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = format!("{}\n", self.processed);
+        if let Some(shard) = self.shard {
+            out.push_str(&format!("range {} {}\nspace {}\n", shard.start, shard.end, shard.space));
+        }
+        if let Some(phase) = &self.phase {
+            out.push_str(&format!("phase {}\n", phase));
+        }
+        if let Some(config) = &self.config {
+            out.push_str(&format!("config {}\n", config));
+        }
+        if let Some(started) = self.started {
+            out.push_str(&format!("started {}\n", started));
+        }
+        if self.derived > 0 {
+            out.push_str(&format!("derived {}\n", self.derived));
+        }
+        for (at, processed) in &self.samples {
+            out.push_str(&format!("sample {} {}\n", at, processed));
+        }
+        for (start, end) in &self.ahead {
+            out.push_str(&format!("done {} {}\n", start, end));
+        }
+        out
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut lines = content.lines().map(str::trim).filter(|line| !line.is_empty());
+        let processed = lines
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("Empty checkpoint"))?
+            .parse::<u64>()
+            .map_err(|e| anyhow::anyhow!("Invalid processed count: {}", e))?;
+        let mut range = None;
+        let mut space = None;
+        let mut phase = None;
+        let mut config = None;
+        let mut started = None;
+        let mut derived = 0;
+        let mut samples = Vec::new();
+        let mut ahead = Vec::new();
+        for line in lines {
+            if let Some(name) = line.strip_prefix("phase ") {
+                phase = Some(name.trim().to_string());
+                continue;
+            }
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields.as_slice() {
+                ["range", start, end] => range = Some((parse_index(start)?, parse_index(end)?)),
+                ["space", size] => space = Some(parse_index(size)?),
+                ["config", hash] => config = Some(hash.to_string()),
+                ["started", at] => started = Some(parse_index(at)?),
+                ["derived", count] => derived = parse_index(count)?,
+                ["sample", at, processed] => samples.push((parse_index(at)?, parse_index(processed)?)),
+                ["done", start, end] => ahead.push((parse_index(start)?, parse_index(end)?)),
+                _ => return Err(anyhow::anyhow!("Unrecognized checkpoint line: {}", line)),
+            }
+        }
+        let shard = match (range, space) {
+            (Some((start, end)), Some(space)) => Some(Shard { start, end, space }),
+            _ => None,
+        };
+        Ok(Self { processed, shard, phase, config, started, derived, samples, ahead })
+    }
+
+    pub fn load(file: &str) -> Result<Self> {
+        let content = fs::read_to_string(file)
+            .map_err(|e| anyhow::anyhow!("Failed to read checkpoint {}: {}", file, e))?;
+        Self::parse(&content).map_err(|e| anyhow::anyhow!("Failed to parse checkpoint {}: {}", file, e))
+    }
+
+    pub fn phase(&self) -> &str {
+        self.phase.as_deref().unwrap_or(UNNAMED_PHASE)
+    }
+
+    /// The slice of the space this checkpoint has finished.
+    pub fn covered(&self) -> Option<(u64, u64)> {
+        let shard = self.shard?;
+        let end = shard.start.saturating_add(self.processed).min(shard.end);
+        (end > shard.start).then_some((shard.start, end))
+    }
+
+    /// A one-line stand-in for the progress file, to carry a run to another
+    /// machine: the configuration hash, range, space, and processed count.
+    /// `None` for checkpoints without a range or configuration.
+    pub fn token(&self) -> Option<String> {
+        let shard = self.shard?;
+        let config = self.config.as_deref()?;
+        Some(format!("{}-{}-{}-{}-{}-{}", TOKEN_PREFIX, config, shard.start, shard.end, shard.space, self.processed))
+    }
+
+    /// The checkpoint a token stands for; phase, history, and ranks done
+    /// ahead aren't carried, so the latter are searched again.
+    pub fn from_token(token: &str) -> Result<Self> {
+        let fields: Vec<&str> = token.trim().split('-').collect();
+        let [prefix, config, start, end, space, processed] = fields[..] else {
+            return Err(anyhow::anyhow!("Malformed resume token {}", token));
+        };
+        if prefix != TOKEN_PREFIX {
+            return Err(anyhow::anyhow!("Unsupported resume token version {}", prefix));
+        }
+        let shard = Shard { start: parse_index(start)?, end: parse_index(end)?, space: parse_index(space)? };
+        if shard.start > shard.end || shard.end > shard.space {
+            return Err(anyhow::anyhow!(
+                "Resume token range {}..{} doesn't fit a space of {}",
+                shard.start,
+                shard.end,
+                shard.space
+            ));
+        }
+        Ok(Self {
+            processed: parse_index(processed)?,
+            shard: Some(shard),
+            phase: None,
+            config: Some(config.to_string()),
+            started: None,
+            derived: 0,
+            samples: Vec::new(),
+            ahead: Vec::new(),
+        })
+    }
+}
+
+/// Tracks which ranks of a run are done while workers finish them out of
+/// order: everything before the frontier, plus the ranks done past it.
+pub struct Frontier {
+    state: Mutex<(u64, BTreeSet<u64>)>,
+}
+
+impl Frontier {
+    /// Ranks before `next` are done, as are those in the `[start, end)`
+    /// ranges of `ahead`.
+    pub fn new(next: u64, ahead: &[(u64, u64)]) -> Self {
+        let frontier = Self { state: Mutex::new((next, BTreeSet::new())) };
+        for &(start, end) in ahead {
+            for rank in start.max(next)..end {
+                frontier.complete(rank);
+            }
+        }
+        frontier
+    }
+
+    pub fn complete(&self, rank: u64) {
+        let mut state = self.state.lock().unwrap();
+        let (next, done) = &mut *state;
+        if rank < *next {
+            return;
+        }
+        done.insert(rank);
+        while done.remove(next) {
+            *next += 1;
+        }
+    }
+
+    pub fn is_done(&self, rank: u64) -> bool {
+        let state = self.state.lock().unwrap();
+        rank < state.0 || state.1.contains(&rank)
+    }
+
+    /// Ranks done past the frontier.
+    pub fn ahead_count(&self) -> u64 {
+        self.state.lock().unwrap().1.len() as u64
+    }
+
+    /// The first rank not done, and the ranks done past it as `[start, end)`
+    /// ranges.
+    pub fn snapshot(&self) -> (u64, Vec<(u64, u64)>) {
+        let state = self.state.lock().unwrap();
+        let mut ranges: Vec<(u64, u64)> = Vec::new();
+        for &rank in &state.1 {
+            match ranges.last_mut() {
+                Some(last) if last.1 == rank => last.1 += 1,
+                _ => ranges.push((rank, rank + 1)),
+            }
+        }
+        (state.0, ranges)
+    }
+}
+
+fn parse_index(value: &str) -> Result<u64> {
+    value.parse::<u64>().map_err(|e| anyhow::anyhow!("Invalid index {}: {}", value, e))
+}
+
+/// Union of covered `[start, end)` ranges over a space of `space` indices.
+#[derive(Clone, Debug, Serialize)]
+pub struct Coverage {
+    pub space: u64,
+    pub covered: Vec<(u64, u64)>,
+}
+
+impl Coverage {
+    pub fn new(space: u64, mut ranges: Vec<(u64, u64)>) -> Self {
+        ranges.sort_unstable();
+        let mut covered: Vec<(u64, u64)> = Vec::new();
+        for (start, end) in ranges {
+            let end = end.min(space);
+            if start >= end {
+                continue;
+            }
+            match covered.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => covered.push((start, end)),
+            }
+        }
+        Self { space, covered }
+    }
+
+    pub fn covered_count(&self) -> u64 {
+        self.covered.iter().map(|(start, end)| end - start).sum()
+    }
+
+    /// Gaps between covered ranges.
+    pub fn remaining(&self) -> Vec<(u64, u64)> {
+        let mut gaps = Vec::new();
+        let mut cursor = 0;
+        for &(start, end) in &self.covered {
+            if start > cursor {
+                gaps.push((cursor, start));
+            }
+            cursor = cursor.max(end);
+        }
+        if cursor < self.space {
+            gaps.push((cursor, self.space));
+        }
+        gaps
+    }
+}
+
+/// Splits `ranges` into `parts` assignments of roughly equal size; one
+/// assignment may span several ranges.
+pub fn split_ranges(ranges: &[(u64, u64)], parts: usize) -> Vec<Vec<(u64, u64)>> {
+    let total: u64 = ranges.iter().map(|(start, end)| end - start).sum();
+    let parts = parts.max(1) as u64;
+    let mut assignments = Vec::new();
+    let mut current = Vec::new();
+    let mut filled = 0u64;
+    let mut target = total.div_ceil(parts);
+    for &(mut start, end) in ranges {
+        while start < end {
+            let take = (end - start).min(target - filled);
+            current.push((start, start + take));
+            start += take;
+            filled += take;
+            if filled == target {
+                assignments.push(std::mem::take(&mut current));
+                filled = 0;
+                let left = parts.saturating_sub(assignments.len() as u64).max(1);
+                let remaining: u64 = total - assignments.iter().flatten().map(|(s, e)| e - s).sum::<u64>();
+                target = remaining.div_ceil(left).max(1);
+            }
+        }
+    }
+    if !current.is_empty() {
+        assignments.push(current);
+    }
+    assignments
+}
@@ -0,0 +1,209 @@
+use std::collections::HashSet;
+use std::env;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::power::Throttle;
+
+pub const PID_FILE: &str = "daemon.pid";
+pub const SOCKET_FILE: &str = "control.sock";
+
+// Set in the environment of the detached child so it doesn't detach again
+const CHILD_ENV: &str = "BIP39_RECOVERY_DAEMON";
+
+/// Whether this process is the detached daemon rather than the launcher.
+pub fn is_detached() -> bool {
+    env::var_os(CHILD_ENV).is_some()
+}
+
+/// Re-runs the current command line in the background, in its own process
+/// group and without a terminal, and returns the child's pid. All output
+/// then goes to the log file in the state directory.
+#[cfg(unix)]
+pub fn detach() -> Result<u32> {
+    use std::os::unix::process::CommandExt;
+    use std::process::{Command, Stdio};
+
+    let exe = env::current_exe().map_err(|e| anyhow::anyhow!("Failed to locate executable: {}", e))?;
+    let child = Command::new(exe)
+        .args(env::args_os().skip(1))
+        .env(CHILD_ENV, "1")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .process_group(0)
+        .spawn()
+        .map_err(|e| anyhow::anyhow!("Failed to start daemon: {}", e))?;
+    Ok(child.id())
+}
+
+#[cfg(not(unix))]
+pub fn detach() -> Result<u32> {
+    Err(anyhow::anyhow!("--daemon is only supported on Unix"))
+}
+
+/// What the control socket and dashboard can see and change in a running
+/// search.
+pub struct Control {
+    pub processed: Arc<AtomicUsize>,
+    pub initial_processed: usize,
+    pub total: u64,
+    pub matches: Arc<AtomicUsize>,
+    pub throttle: Arc<Throttle>,
+    pub address_db: Arc<Option<RwLock<HashSet<String>>>>,
+    pub started: Instant,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Status {
+    pub state: &'static str,
+    pub processed: u64,
+    pub total: u64,
+    pub percent: f64,
+    pub speed: f64,
+    pub matches: u64,
+    pub elapsed_seconds: f64,
+}
+
+impl Control {
+    pub fn status(&self) -> Status {
+        let processed = self.processed.load(Ordering::Relaxed);
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let speed = if elapsed > 0.0 { processed.saturating_sub(self.initial_processed) as f64 / elapsed } else { 0.0 };
+        let state = if self.throttle.is_held() {
+            "paused"
+        } else if self.throttle.is_paused() {
+            "paused-battery"
+        } else {
+            "running"
+        };
+        Status {
+            state,
+            processed: processed as u64,
+            total: self.total,
+            percent: if self.total > 0 { processed as f64 * 100.0 / self.total as f64 } else { 100.0 },
+            speed,
+            matches: self.matches.load(Ordering::Relaxed) as u64,
+            elapsed_seconds: elapsed,
+        }
+    }
+
+    /// Runs one command line and returns the reply.
+    pub fn execute(&self, line: &str) -> String {
+        let mut fields = line.split_whitespace();
+        match (fields.next(), fields.next()) {
+            (Some("status"), None) => {
+                let status = self.status();
+                format!(
+                    "state={} processed={} total={} speed={:.0} matches={}",
+                    status.state, status.processed, status.total, status.speed, status.matches
+                )
+            }
+            (Some("pause"), None) => {
+                self.throttle.set_held(true);
+                "ok paused".to_string()
+            }
+            (Some("resume"), None) => {
+                self.throttle.set_held(false);
+                "ok resumed".to_string()
+            }
+            (Some("stop"), None) => "ok stopping".to_string(),
+            (Some("add-target"), Some(address)) if fields.next().is_none() => {
+                match (self.address_db.as_ref(), crate::ethereum::normalize(address)) {
+                    (Some(_), Err(e)) => format!("error {}", e),
+                    (Some(db), Ok(address)) => {
+                        if db.write().unwrap().insert(address) {
+                            "ok added".to_string()
+                        } else {
+                            "ok already present".to_string()
+                        }
+                    }
+                    (None, _) => "error no target set to add to".to_string(),
+                }
+            }
+            _ => format!("error unknown command: {}", line.trim()),
+        }
+    }
+}
+
+/// Listens on `socket` for line-based commands: `status`, `pause`,
+/// `resume`, `stop`, and `add-target <address>`. Each gets a one-line reply.
+#[cfg(unix)]
+pub fn serve(socket: &str, control: Arc<Control>) -> Result<()> {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixListener;
+
+    use log::{info, warn};
+
+    // The state directory lock is held, so any existing socket is stale
+    let _ = std::fs::remove_file(socket);
+    let listener =
+        UnixListener::bind(socket).map_err(|e| anyhow::anyhow!("Failed to bind control socket {}: {}", socket, e))?;
+    info!("Listening for control commands on {}", socket);
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    warn!("Control socket accept failed: {}", e);
+                    continue;
+                }
+            };
+            let reader = match stream.try_clone() {
+                Ok(reader) => BufReader::new(reader),
+                Err(e) => {
+                    warn!("Control socket error: {}", e);
+                    continue;
+                }
+            };
+            for line in reader.lines() {
+                let Ok(line) = line else { break };
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let reply = control.execute(&line);
+                info!("Control command {:?}: {}", line.trim(), reply);
+                if writeln!(stream, "{}", reply).is_err() {
+                    break;
+                }
+                if line.trim() == "stop" {
+                    crate::signals::interrupt();
+                }
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn serve(_socket: &str, _control: Arc<Control>) -> Result<()> {
+    Err(anyhow::anyhow!("The control socket is only supported on Unix"))
+}
+
+/// `control`: sends one command to a running daemon and prints the reply.
+#[cfg(unix)]
+pub fn send(socket: &str, command: &str) -> Result<()> {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixStream;
+
+    let mut stream = UnixStream::connect(socket)
+        .map_err(|e| anyhow::anyhow!("Failed to connect to control socket {}: {}", socket, e))?;
+    writeln!(stream, "{}", command)?;
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply)?;
+    let reply = reply.trim();
+    println!("{}", reply);
+    if reply.starts_with("error") {
+        return Err(anyhow::anyhow!("Daemon rejected command: {}", command));
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn send(_socket: &str, _command: &str) -> Result<()> {
+    Err(anyhow::anyhow!("The control socket is only supported on Unix"))
+}
@@ -0,0 +1,93 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+
+use anyhow::Result;
+use log::{info, warn};
+
+use crate::daemon::Control;
+
+const PAGE: &str = include_str!("dashboard.html");
+
+/// Serves the single-page dashboard on `address`: `GET /` returns the page,
+/// `GET /api/status` the current [`crate::daemon::Status`] as JSON, and
+/// `POST /api/pause`, `/api/resume`, and `/api/stop` drive the search.
+pub fn serve(address: &str, control: Arc<Control>) -> Result<()> {
+    let listener =
+        TcpListener::bind(address).map_err(|e| anyhow::anyhow!("Failed to bind dashboard to {}: {}", address, e))?;
+    info!("Dashboard listening on http://{}", address);
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    if let Err(e) = handle(stream, &control) {
+                        warn!("Dashboard request failed: {}", e);
+                    }
+                }
+                Err(e) => warn!("Dashboard accept failed: {}", e),
+            }
+        }
+    });
+    Ok(())
+}
+
+fn handle(mut stream: TcpStream, control: &Control) -> Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut host = None;
+    let mut origin = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            match name.trim().to_ascii_lowercase().as_str() {
+                "host" => host = Some(value.trim().to_string()),
+                "origin" => origin = Some(value.trim().to_string()),
+                _ => {}
+            }
+        }
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    // Another site open in the same browser must not be able to stop the run
+    let cross_origin = match (&origin, &host) {
+        (Some(origin), Some(host)) => origin.split_once("://").map(|(_, rest)| rest) != Some(host.as_str()),
+        (Some(_), None) => true,
+        _ => false,
+    };
+    match (method, path) {
+        ("GET", "/") => respond(&mut stream, "200 OK", "text/html; charset=utf-8", PAGE),
+        ("GET", "/api/status") => {
+            respond(&mut stream, "200 OK", "application/json", &serde_json::to_string(&control.status())?)
+        }
+        ("POST", _) if cross_origin => respond(&mut stream, "403 Forbidden", "text/plain", "cross-origin request"),
+        ("POST", "/api/pause" | "/api/resume" | "/api/stop") => {
+            let command = path.trim_start_matches("/api/");
+            let reply = control.execute(command);
+            info!("Dashboard command {}: {}", command, reply);
+            respond(&mut stream, "200 OK", "text/plain", &reply)?;
+            if command == "stop" {
+                crate::signals::interrupt();
+            }
+            Ok(())
+        }
+        _ => respond(&mut stream, "404 Not Found", "text/plain", "not found"),
+    }
+}
+
+fn respond(stream: &mut TcpStream, status: &str, content_type: &str, body: &str) -> Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )?;
+    stream.flush()?;
+    Ok(())
+}
@@ -0,0 +1,158 @@
+use std::fs;
+
+use anyhow::Result;
+use bip39::{Language, Mnemonic};
+use bitcoin::bip32::{ChildNumber, DerivationPath, Xpriv};
+use bitcoin::Network;
+use clap::ValueEnum;
+use secp256k1::Secp256k1;
+use serde::Serialize;
+
+use crate::matrix::{self, ADDRESS_TYPES};
+use crate::{paths, pathspec};
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum TableFormat {
+    /// Aligned columns for reading
+    Table,
+    Json,
+    Csv,
+}
+
+/// Settings of a `derive` run.
+pub struct Derive {
+    pub mnemonic: Option<String>,
+    pub mnemonic_file: Option<String>,
+    pub passphrase: String,
+    pub account_paths: Vec<String>,
+    pub accounts: String,
+    pub address_types: Vec<String>,
+    pub index_range: String,
+    pub change: bool,
+    pub network: String,
+    pub format: TableFormat,
+}
+
+#[derive(Serialize)]
+pub struct Row {
+    pub path: String,
+    pub address_type: String,
+    pub address: String,
+}
+
+/// Which addresses a seed is listed at: each address type paired with the
+/// account paths it is derived under, and the chains and indices below them.
+pub struct Layout {
+    groups: Vec<(String, Vec<DerivationPath>)>,
+    chains: Vec<u32>,
+    indices: pathspec::Range,
+    network: Network,
+}
+
+impl Layout {
+    /// Without account paths, each type gets its conventional
+    /// `m/<purpose>'/<coin>'/<account>'` for every account in `accounts`.
+    pub fn new(
+        address_types: &[String],
+        account_paths: &[String],
+        accounts: &str,
+        index_range: &str,
+        change: bool,
+        network: &str,
+    ) -> Result<Self> {
+        let network = matrix::parse_network(network)?;
+        for address_type in address_types {
+            if !ADDRESS_TYPES.contains(&address_type.as_str()) {
+                return Err(anyhow::anyhow!("Unsupported address type {}", address_type));
+            }
+        }
+        let indices = pathspec::parse_range(index_range)?;
+        let chains = if change { vec![0, 1] } else { vec![0] };
+
+        let coin_type = if network == Network::Bitcoin { 0 } else { 1 };
+        let accounts = pathspec::parse_range(accounts)?;
+        let mut groups = Vec::new();
+        for address_type in address_types {
+            let account_paths = if account_paths.is_empty() {
+                let purpose = paths::conventional_purpose(address_type).unwrap_or(44);
+                (accounts.first..=accounts.last)
+                    .map(|account| format!("m/{}'/{}'/{}'", purpose, coin_type, account).parse())
+                    .collect::<Result<Vec<DerivationPath>, _>>()?
+            } else {
+                account_paths.iter().map(|path| pathspec::parse(path)).collect::<Result<Vec<_>>>()?
+            };
+            groups.push((address_type.clone(), account_paths));
+        }
+        Ok(Self { groups, chains, indices, network })
+    }
+
+    /// The addresses of `seed`, in the order of the address types.
+    pub fn rows(&self, seed: &[u8]) -> Result<Vec<Row>> {
+        let secp = Secp256k1::new();
+        let master = Xpriv::new_master(Network::Bitcoin, seed)?;
+        let mut rows = Vec::new();
+        for (address_type, account_paths) in &self.groups {
+            for account_path in account_paths {
+                let account_key = master.derive_priv(&secp, account_path)?;
+                for &chain in &self.chains {
+                    for index in self.indices.first..=self.indices.last {
+                        let tail = [ChildNumber::from_normal_idx(chain)?, ChildNumber::from_normal_idx(index)?];
+                        let key = account_key.derive_priv(&secp, &tail)?;
+                        let pubkey = bitcoin::PublicKey::new(key.private_key.public_key(&secp));
+                        let Some(address) = crate::encode_address(&pubkey, address_type, self.network) else { continue };
+                        rows.push(Row {
+                            path: account_path.child(tail[0]).child(tail[1]).to_string(),
+                            address_type: address_type.to_string(),
+                            address: address?,
+                        });
+                    }
+                }
+            }
+        }
+        Ok(rows)
+    }
+}
+
+/// `derive`: prints the addresses a known mnemonic has under each account
+/// path, for each address type, over an index range of the receive chain
+/// (and the change chain with `change`).
+pub fn run(derive: Derive) -> Result<()> {
+    let phrase = match (&derive.mnemonic, &derive.mnemonic_file) {
+        (Some(phrase), None) => phrase.clone(),
+        (None, Some(file)) => fs::read_to_string(file)
+            .map_err(|e| anyhow::anyhow!("Failed to read mnemonic file {}: {}", file, e))?,
+        _ => return Err(anyhow::anyhow!("Give exactly one of --mnemonic or --mnemonic-file")),
+    };
+    let mnemonic = Mnemonic::parse_in_normalized(Language::English, phrase.trim())
+        .map_err(|e| anyhow::anyhow!("Invalid mnemonic: {}", e))?;
+    let layout = Layout::new(
+        &derive.address_types,
+        &derive.account_paths,
+        &derive.accounts,
+        &derive.index_range,
+        derive.change,
+        &derive.network,
+    )?;
+    print_rows(&layout.rows(&mnemonic.to_seed(&derive.passphrase))?, derive.format)
+}
+
+pub fn print_rows(rows: &[Row], format: TableFormat) -> Result<()> {
+    match format {
+        TableFormat::Table => {
+            let width = rows.iter().map(|row| row.path.len() + 2).max().unwrap_or(0);
+            println!("{:<width$}{:<13}ADDRESS", "PATH", "TYPE", width = width.max(6));
+            for row in rows {
+                println!("{:<width$}{:<13}{}", row.path, row.address_type, row.address, width = width.max(6));
+            }
+        }
+        TableFormat::Json => println!("{}", serde_json::to_string_pretty(rows)?),
+        // None of the fields can hold a comma or quote
+        TableFormat::Csv => {
+            println!("path,address_type,address");
+            for row in rows {
+                println!("{},{},{}", row.path, row.address_type, row.address);
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,159 @@
+use std::collections::HashSet;
+use std::fs;
+use std::str::FromStr;
+
+use anyhow::Result;
+use bitcoin::bip32::{ChildNumber, DerivationPath, Xpriv, Xpub};
+use secp256k1::Secp256k1;
+use serde::Deserialize;
+
+use crate::chain::{self, ChainClient};
+
+/// Bitcoin Core `listdescriptors` output; only the descriptors are used.
+#[derive(Deserialize)]
+struct ListDescriptors {
+    descriptors: Vec<DescriptorEntry>,
+}
+
+#[derive(Deserialize)]
+struct DescriptorEntry {
+    desc: String,
+}
+
+/// Target addresses expanded from a descriptor export, and what couldn't be.
+pub struct Expanded {
+    pub addresses: HashSet<String>,
+    pub descriptors: usize,
+    pub skipped: Vec<String>,
+}
+
+/// Reads `listdescriptors` JSON, or one descriptor per line, and derives
+/// `gap_limit` addresses from every ranged descriptor, or with `chain` as
+/// many as it takes to get `gap_limit` unused ones past the last used.
+/// `pkh`, `wpkh`, `sh(wpkh)`, key-path-only `tr`, and `addr` descriptors are
+/// supported; others are reported in `skipped`. Checksums are stripped, not
+/// verified.
+pub fn load(file: &str, gap_limit: u32, chain: Option<&ChainClient>) -> Result<Expanded> {
+    let content = fs::read_to_string(file)
+        .map_err(|e| anyhow::anyhow!("Failed to read descriptors file {}: {}", file, e))?;
+    let descriptors: Vec<String> = if content.trim_start().starts_with('{') {
+        let export: ListDescriptors = serde_json::from_str(&content)
+            .map_err(|e| anyhow::anyhow!("Failed to parse descriptors file {}: {}", file, e))?;
+        export.descriptors.into_iter().map(|entry| entry.desc).collect()
+    } else {
+        content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_string)
+            .collect()
+    };
+
+    let secp = Secp256k1::new();
+    let mut expanded = Expanded { addresses: HashSet::new(), descriptors: descriptors.len(), skipped: Vec::new() };
+    for descriptor in &descriptors {
+        let body = descriptor.split('#').next().unwrap_or_default().trim();
+        let (address_type, key) = if let Some(inner) = unwrap(body, "sh(wpkh(", "))") {
+            ("p2sh-p2wpkh", inner)
+        } else if let Some(inner) = unwrap(body, "wpkh(", ")") {
+            ("p2wpkh", inner)
+        } else if let Some(inner) = unwrap(body, "pkh(", ")") {
+            ("p2pkh", inner)
+        } else if let Some(inner) = unwrap(body, "tr(", ")").filter(|inner| !inner.contains(',')) {
+            // Key path only; a script tree would change the output key
+            ("p2tr", inner)
+        } else if let Some(address) = unwrap(body, "addr(", ")") {
+            expanded.addresses.insert(address.to_string());
+            continue;
+        } else {
+            expanded.skipped.push(descriptor.clone());
+            continue;
+        };
+        let addresses = expand_key(&secp, key, address_type, gap_limit, chain)
+            .map_err(|e| anyhow::anyhow!("Descriptor {}: {}", descriptor, e))?;
+        expanded.addresses.extend(addresses);
+    }
+    Ok(expanded)
+}
+
+fn unwrap<'a>(body: &'a str, open: &str, close: &str) -> Option<&'a str> {
+    body.strip_prefix(open)?.strip_suffix(close)
+}
+
+/// Addresses for `[origin]xpub/.../*`, with `<a;b>` multipath steps expanded
+/// into each branch.
+fn expand_key(
+    secp: &Secp256k1<secp256k1::All>,
+    key: &str,
+    address_type: &str,
+    gap_limit: u32,
+    chain: Option<&ChainClient>,
+) -> Result<Vec<String>> {
+    // The origin only documents where the key came from
+    let key = match key.strip_prefix('[') {
+        Some(rest) => rest.split_once(']').map(|(_, key)| key).ok_or_else(|| anyhow::anyhow!("unterminated key origin"))?,
+        None => key,
+    };
+    let mut steps = key.split('/');
+    let encoded = steps.next().unwrap_or_default();
+    let xpub = match Xpub::from_str(encoded) {
+        Ok(xpub) => xpub,
+        Err(_) => {
+            let xprv = Xpriv::from_str(encoded).map_err(|e| anyhow::anyhow!("unsupported key {}: {}", encoded, e))?;
+            Xpub::from_priv(secp, &xprv)
+        }
+    };
+
+    // Each entry is one concrete path below the key, `None` marking the
+    // wildcard position
+    let mut branches: Vec<Vec<Option<ChildNumber>>> = vec![Vec::new()];
+    for step in steps {
+        let choices: Vec<Option<ChildNumber>> = if step == "*" {
+            vec![None]
+        } else if let Some(list) = step.strip_prefix('<').and_then(|s| s.strip_suffix('>')) {
+            list.split(';').map(|index| parse_step(index).map(Some)).collect::<Result<_>>()?
+        } else {
+            vec![Some(parse_step(step)?)]
+        };
+        branches = branches
+            .into_iter()
+            .flat_map(|branch| {
+                choices.iter().map(move |choice| {
+                    let mut branch = branch.clone();
+                    branch.push(*choice);
+                    branch
+                })
+            })
+            .collect();
+    }
+
+    let mut addresses = Vec::new();
+    for branch in branches {
+        let derive = |index| -> Result<String> {
+            let path: Vec<ChildNumber> = branch
+                .iter()
+                .map(|step| step.map_or_else(|| ChildNumber::from_normal_idx(index), Ok))
+                .collect::<Result<_, _>>()?;
+            let child = xpub.derive_pub(secp, &DerivationPath::from(path))?;
+            let pubkey = bitcoin::PublicKey::new(child.public_key);
+            crate::encode_address(&pubkey, address_type, xpub.network)
+                .ok_or_else(|| anyhow::anyhow!("unsupported address type {}", address_type))?
+        };
+        match (branch.contains(&None), chain) {
+            (true, Some(chain)) => addresses.extend(
+                chain::gap_scan(gap_limit, derive, |addresses| chain.used(addresses))?.into_iter().map(|(_, address, _)| address),
+            ),
+            (true, None) => addresses.extend((0..gap_limit).map(derive).collect::<Result<Vec<_>>>()?),
+            (false, _) => addresses.push(derive(0)?),
+        }
+    }
+    Ok(addresses)
+}
+
+fn parse_step(step: &str) -> Result<ChildNumber> {
+    if step.ends_with(['h', 'H', '\'']) {
+        return Err(anyhow::anyhow!("hardened step {} below an extended public key", step));
+    }
+    let index: u32 = step.parse().map_err(|e| anyhow::anyhow!("invalid path step {}: {}", step, e))?;
+    Ok(ChildNumber::from_normal_idx(index)?)
+}
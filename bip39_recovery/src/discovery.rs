@@ -0,0 +1,118 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+use bip39::{Language, Mnemonic};
+use bitcoin::bip32::{ChildNumber, DerivationPath, Xpriv};
+use bitcoin::Network;
+use secp256k1::Secp256k1;
+
+use crate::bloom::AddressBloom;
+use crate::chain::{self, ChainClient};
+use crate::hashdb::{self, HashDb};
+use crate::overrides::AddressOverrides;
+use crate::{paths, Match};
+
+/// BIP-44 stops after this many consecutive unused accounts.
+const EMPTY_ACCOUNTS: u32 = 2;
+
+/// How discovery tells whether an address has been used.
+#[derive(Clone, Copy)]
+pub enum Usage<'a> {
+    /// It's a target: in the set, the hash database, or the bloom filter's
+    /// list. Without a chain backend this is all there is to go on, so an
+    /// account whose addresses the search wasn't given counts as unused
+    /// even if it holds coins
+    Targets(&'a HashSet<String>),
+    HashDb(&'a HashDb),
+    Bloom(&'a AddressBloom),
+    /// It has history on chain (`--chain`)
+    Chain(&'a ChainClient),
+}
+
+impl Usage<'_> {
+    fn used(&self, addresses: &[String]) -> Result<Vec<bool>> {
+        match self {
+            Usage::Targets(targets) => Ok(addresses.iter().map(|address| targets.contains(address)).collect()),
+            Usage::HashDb(db) => Ok(addresses
+                .iter()
+                .map(|address| hashdb::address_key(address).is_some_and(|key| db.contains(&key)))
+                .collect()),
+            Usage::Bloom(bloom) => addresses.iter().map(|address| bloom.contains(address)).collect(),
+            Usage::Chain(client) => client.used(addresses),
+        }
+    }
+}
+
+/// An account with at least one used address.
+pub struct Account {
+    pub coin_type: u32,
+    pub account: u32,
+    pub used: Vec<(DerivationPath, String)>,
+}
+
+/// BIP-44 account discovery for a found seed: accounts are scanned from 0
+/// until two unused ones in a row, for each coin type in `coin_types` (or
+/// the match's own when empty), with the match's purpose and address type.
+/// Each account's receive and change chains are scanned until `gap_limit`
+/// addresses in a row past the last used one are unused, `usage` telling
+/// which are.
+pub fn discover(
+    found: &Match,
+    coin_types: &[u32],
+    usage: Usage,
+    overrides: &AddressOverrides,
+    gap_limit: u32,
+    secp: &Secp256k1<secp256k1::All>,
+) -> Result<Vec<Account>> {
+    let children: Vec<ChildNumber> = found.variant.path.as_ref().to_vec();
+    let purpose = match children.first() {
+        Some(ChildNumber::Hardened { index }) if children.len() >= 3 => *index,
+        _ => return Err(anyhow::anyhow!("Path {} is not a BIP-44 style account path", found.variant.path)),
+    };
+    let coin_types = if coin_types.is_empty() {
+        vec![paths::coin_type(&found.variant.path).unwrap_or(0)]
+    } else {
+        coin_types.to_vec()
+    };
+    let mnemonic = Mnemonic::parse_in(Language::English, &found.mnemonic)
+        .map_err(|e| anyhow::anyhow!("Failed to parse the found mnemonic: {}", e))?;
+    let master = Xpriv::new_master(Network::Bitcoin, &mnemonic.to_seed_normalized(&found.passphrase))?;
+
+    let mut accounts = Vec::new();
+    for coin_type in coin_types {
+        let mut empty = 0;
+        let mut account = 0;
+        while empty < EMPTY_ACCOUNTS {
+            let account_path: DerivationPath = format!("m/{}'/{}'/{}'", purpose, coin_type, account).parse()?;
+            let account_key = master.derive_priv(secp, &account_path)?;
+            let mut used = Vec::new();
+            for branch in 0..2 {
+                let derive = |index| -> Result<String> {
+                    let tail = [ChildNumber::from_normal_idx(branch)?, ChildNumber::from_normal_idx(index)?];
+                    let key = account_key.derive_priv(secp, &tail)?;
+                    let pubkey = bitcoin::PublicKey::new(key.private_key.public_key(secp));
+                    match overrides.encode(&pubkey, &found.variant.address_type) {
+                        Some(custom) => custom,
+                        None => crate::encode_address(&pubkey, &found.variant.address_type, found.variant.network)
+                            .ok_or_else(|| anyhow::anyhow!("Unsupported address type {}", found.variant.address_type))?,
+                    }
+                };
+                for (index, address, _) in chain::gap_scan(gap_limit, derive, |addresses| usage.used(addresses))?
+                    .into_iter()
+                    .filter(|(_, _, used)| *used)
+                {
+                    let tail = [ChildNumber::from_normal_idx(branch)?, ChildNumber::from_normal_idx(index)?];
+                    used.push((account_path.extend(tail), address));
+                }
+            }
+            if used.is_empty() {
+                empty += 1;
+            } else {
+                empty = 0;
+                accounts.push(Account { coin_type, account, used });
+            }
+            account += 1;
+        }
+    }
+    Ok(accounts)
+}
@@ -0,0 +1,218 @@
+/// A field element of GF(2^255 - 19) in five 51-bit limbs, least significant first.
+#[derive(Clone, Copy)]
+struct Fe([u64; 5]);
+
+const MASK: u64 = (1 << 51) - 1;
+
+/// 2d, where d = -121665/121666 is the curve constant.
+const D2: [u8; 32] = [
+    0x59, 0xf1, 0xb2, 0x26, 0x94, 0x9b, 0xd6, 0xeb, 0x56, 0xb1, 0x83, 0x82, 0x9a, 0x14, 0xe0, 0x00, 0x30, 0xd1, 0xf3, 0xee,
+    0xf2, 0x80, 0x8e, 0x19, 0xe7, 0xfc, 0xdf, 0x56, 0xdc, 0xd9, 0x06, 0x24,
+];
+
+/// Coordinates of the base point.
+const BASE_X: [u8; 32] = [
+    0x1a, 0xd5, 0x25, 0x8f, 0x60, 0x2d, 0x56, 0xc9, 0xb2, 0xa7, 0x25, 0x95, 0x60, 0xc7, 0x2c, 0x69, 0x5c, 0xdc, 0xd6, 0xfd,
+    0x31, 0xe2, 0xa4, 0xc0, 0xfe, 0x53, 0x6e, 0xcd, 0xd3, 0x36, 0x69, 0x21,
+];
+const BASE_Y: [u8; 32] = [
+    0x58, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66,
+    0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66,
+];
+
+/// The order of the base point, in 64-bit limbs.
+const ORDER: [u64; 4] = [0x5812631a5cf5d3ed, 0x14def9dea2f79cd6, 0, 0x1000000000000000];
+
+impl Fe {
+    const ZERO: Fe = Fe([0; 5]);
+    const ONE: Fe = Fe([1, 0, 0, 0, 0]);
+
+    fn from_bytes(bytes: &[u8; 32]) -> Fe {
+        let load = |offset: usize| u64::from_le_bytes(bytes[offset..offset + 8].try_into().expect("8 bytes"));
+        Fe([
+            load(0) & MASK,
+            (load(6) >> 3) & MASK,
+            (load(12) >> 6) & MASK,
+            (load(19) >> 1) & MASK,
+            (load(24) >> 12) & MASK,
+        ])
+    }
+
+    /// Carries every limb back under 51 bits, folding the top into the
+    /// bottom as 2^255 = 19.
+    fn carry(mut self) -> Fe {
+        for _ in 0..2 {
+            for i in 0..4 {
+                self.0[i + 1] += self.0[i] >> 51;
+                self.0[i] &= MASK;
+            }
+            self.0[0] += 19 * (self.0[4] >> 51);
+            self.0[4] &= MASK;
+        }
+        self
+    }
+
+    fn to_bytes(self) -> [u8; 32] {
+        let mut limbs = self.carry().0;
+        // Subtract p once if the value is at least p
+        let mut q = (limbs[0] + 19) >> 51;
+        for limb in &limbs[1..] {
+            q = (limb + q) >> 51;
+        }
+        limbs[0] += 19 * q;
+        for i in 0..4 {
+            limbs[i + 1] += limbs[i] >> 51;
+            limbs[i] &= MASK;
+        }
+        limbs[4] &= MASK;
+        let mut bytes = [0u8; 32];
+        let mut acc: u128 = 0;
+        let mut bits = 0;
+        let mut out = 0;
+        for limb in limbs {
+            acc |= u128::from(limb) << bits;
+            bits += 51;
+            while bits >= 8 && out < 32 {
+                bytes[out] = acc as u8;
+                acc >>= 8;
+                bits -= 8;
+                out += 1;
+            }
+        }
+        if out < 32 {
+            bytes[out] = acc as u8;
+        }
+        bytes
+    }
+
+    fn add(self, other: Fe) -> Fe {
+        Fe(std::array::from_fn(|i| self.0[i] + other.0[i])).carry()
+    }
+
+    /// Adds 2p before subtracting so no limb goes negative.
+    fn sub(self, other: Fe) -> Fe {
+        const TWO_P: [u64; 5] = [0xFFFFFFFFFFFDA, 0xFFFFFFFFFFFFE, 0xFFFFFFFFFFFFE, 0xFFFFFFFFFFFFE, 0xFFFFFFFFFFFFE];
+        Fe(std::array::from_fn(|i| self.0[i] + TWO_P[i] - other.0[i])).carry()
+    }
+
+    fn mul(self, other: Fe) -> Fe {
+        let a = self.0.map(u128::from);
+        let b = other.0.map(u128::from);
+        // Products past the top limb wrap around times 19
+        let b19 = b.map(|limb| limb * 19);
+        let mut t = [0u128; 5];
+        for (i, a) in a.iter().enumerate() {
+            for j in 0..5 {
+                let k = i + j;
+                if k < 5 {
+                    t[k] += a * b[j];
+                } else {
+                    t[k - 5] += a * b19[j];
+                }
+            }
+        }
+        let mut limbs = [0u64; 5];
+        let mut carry: u128 = 0;
+        for i in 0..5 {
+            let value = t[i] + carry;
+            limbs[i] = (value as u64) & MASK;
+            carry = value >> 51;
+        }
+        limbs[0] += (carry * 19) as u64;
+        Fe(limbs).carry()
+    }
+
+    fn square(self) -> Fe {
+        self.mul(self)
+    }
+
+    /// The inverse, as the power p - 2.
+    fn invert(self) -> Fe {
+        // p - 2 = 2^255 - 21: every bit set but bits 2 and 4
+        let mut result = Fe::ONE;
+        for bit in (0..255).rev() {
+            result = result.square();
+            if bit != 2 && bit != 4 {
+                result = result.mul(self);
+            }
+        }
+        result
+    }
+}
+
+/// A point in extended coordinates (X:Y:Z:T), x = X/Z, y = Y/Z, xy = T/Z.
+#[derive(Clone, Copy)]
+struct Point {
+    x: Fe,
+    y: Fe,
+    z: Fe,
+    t: Fe,
+}
+
+impl Point {
+    const IDENTITY: Point = Point { x: Fe::ZERO, y: Fe::ONE, z: Fe::ONE, t: Fe::ZERO };
+
+    fn base() -> Point {
+        let (x, y) = (Fe::from_bytes(&BASE_X), Fe::from_bytes(&BASE_Y));
+        Point { x, y, z: Fe::ONE, t: x.mul(y) }
+    }
+
+    /// Unified addition for a = -1, which doubles as well.
+    fn add(&self, other: &Point) -> Point {
+        let d2 = Fe::from_bytes(&D2);
+        let a = self.y.sub(self.x).mul(other.y.sub(other.x));
+        let b = self.y.add(self.x).mul(other.y.add(other.x));
+        let c = self.t.mul(d2).mul(other.t);
+        let d = self.z.add(self.z).mul(other.z);
+        let (e, f, g, h) = (b.sub(a), d.sub(c), d.add(c), b.add(a));
+        Point { x: e.mul(f), y: g.mul(h), z: f.mul(g), t: e.mul(h) }
+    }
+
+    /// The y coordinate with the sign of x in the top bit.
+    fn compress(&self) -> [u8; 32] {
+        let z = self.z.invert();
+        let x = self.x.mul(z).to_bytes();
+        let mut y = self.y.mul(z).to_bytes();
+        y[31] |= (x[0] & 1) << 7;
+        y
+    }
+}
+
+/// `scalar` times the edwards25519 base point, compressed, as Monero derives
+/// public keys; `scalar` is little-endian. The keys are only compared, never
+/// used to sign, so the ladder isn't constant-time.
+pub fn public_key(scalar: &[u8; 32]) -> [u8; 32] {
+    let mut result = Point::IDENTITY;
+    let mut power = Point::base();
+    for byte in scalar {
+        for bit in 0..8 {
+            if (byte >> bit) & 1 != 0 {
+                result = result.add(&power);
+            }
+            power = power.add(&power);
+        }
+    }
+    result.compress()
+}
+
+/// `bytes` as a little-endian number, reduced modulo the group order.
+pub fn reduce(bytes: &[u8; 32]) -> [u8; 32] {
+    let mut limbs: [u64; 4] =
+        std::array::from_fn(|i| u64::from_le_bytes(bytes[i * 8..i * 8 + 8].try_into().expect("8 bytes")));
+    // The order is just over 2^252, so at most 15 subtractions are needed
+    let at_least_order = |limbs: &[u64; 4]| limbs.iter().rev().cmp(ORDER.iter().rev()) != std::cmp::Ordering::Less;
+    while at_least_order(&limbs) {
+        let mut borrow = false;
+        for (limb, order) in limbs.iter_mut().zip(ORDER) {
+            let (value, under) = limb.overflowing_sub(order);
+            let (value, under_again) = value.overflowing_sub(u64::from(borrow));
+            *limb = value;
+            borrow = under || under_again;
+        }
+    }
+    let mut reduced = [0u8; 32];
+    for (chunk, limb) in reduced.chunks_mut(8).zip(limbs) {
+        chunk.copy_from_slice(&limb.to_le_bytes());
+    }
+    reduced
+}
@@ -0,0 +1,114 @@
+use bitcoin::hashes::hmac::{Hmac, HmacEngine};
+use bitcoin::hashes::{sha512, Hash, HashEngine};
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::gpu::Seed;
+
+/// Electrum's PBKDF2 rounds, the same as BIP-39's.
+const PBKDF2_ROUNDS: u32 = 2048;
+
+/// Wallet layouts Electrum derives for its seed types: the first receive
+/// path and its address type.
+pub const LAYOUTS: [(&str, &str); 2] = [("m/0/0", "p2pkh"), ("m/0'/0/0", "p2wpkh")];
+
+/// The kind of wallet an Electrum seed was made for, which its version
+/// prefix records.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SeedType {
+    /// Version `01`, a P2PKH wallet under `m/0` and `m/1`
+    Standard,
+    /// Version `100`, a native segwit wallet under `m/0'`
+    Segwit,
+}
+
+/// Electrum's normalization of seeds and passphrases: NFKD, lower case,
+/// accents dropped, and single spaces.
+fn normalize(text: &str) -> String {
+    let folded: String = text.nfkd().flat_map(char::to_lowercase).filter(|c| !is_combining_mark(*c)).collect();
+    folded.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The seed type `mnemonic` is versioned as, or `None` when it isn't an
+/// Electrum seed (or is a two-factor one, which can't be searched here).
+/// Electrum seeds carry no checksum; the version is the leading hex digits
+/// of an HMAC-SHA512 of the phrase, so a random phrase passes about one
+/// time in 256.
+pub fn seed_type(mnemonic: &str) -> Option<SeedType> {
+    let mut engine = HmacEngine::<sha512::Hash>::new(b"Seed version");
+    engine.input(normalize(mnemonic).as_bytes());
+    let version = Hmac::<sha512::Hash>::from_engine(engine).to_byte_array();
+    match (version[0], version[1] >> 4) {
+        (0x01, _) => Some(SeedType::Standard),
+        (0x10, 0x0) => Some(SeedType::Segwit),
+        _ => None,
+    }
+}
+
+/// The wallet seed: PBKDF2-HMAC-SHA512 of the normalized phrase, salted
+/// with `electrum` and the normalized passphrase.
+pub fn to_seed(mnemonic: &str, passphrase: &str) -> Seed {
+    let password = normalize(mnemonic);
+    let mut salt = b"electrum".to_vec();
+    salt.extend_from_slice(normalize(passphrase).as_bytes());
+    salt.extend_from_slice(&1u32.to_be_bytes());
+
+    // One block is the whole 64-byte output
+    let mut engine = HmacEngine::<sha512::Hash>::new(password.as_bytes());
+    engine.input(&salt);
+    let mut block = Hmac::<sha512::Hash>::from_engine(engine).to_byte_array();
+    let mut seed = block;
+    for _ in 1..PBKDF2_ROUNDS {
+        let mut engine = HmacEngine::<sha512::Hash>::new(password.as_bytes());
+        engine.input(&block);
+        block = Hmac::<sha512::Hash>::from_engine(engine).to_byte_array();
+        for (byte, next) in seed.iter_mut().zip(block) {
+            *byte ^= next;
+        }
+    }
+    seed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Segwit seed of Electrum's own mnemonic tests.
+    const SEGWIT: &str = "wild father tree among universe such mobile favorite target dynamic credit identify";
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    #[test]
+    fn seed_types() {
+        assert_eq!(seed_type(SEGWIT), Some(SeedType::Segwit));
+        let standard = "cycle rocket west magnet parrot shuffle foot correct salt library feed song";
+        assert_eq!(seed_type(standard), Some(SeedType::Standard));
+        let segwit = "bitter grass shiver impose acquire brush forget axis eager alone wine silver";
+        assert_eq!(seed_type(segwit), Some(SeedType::Segwit));
+        // A BIP-39 phrase isn't versioned
+        assert_eq!(seed_type("legal winner thank year wave sausage worth useful legal winner thank yellow"), None);
+    }
+
+    #[test]
+    fn seeds() {
+        assert_eq!(
+            hex(&to_seed(SEGWIT, "")),
+            "aac2a6302e48577ab4b46f23dbae0774e2e62c796f797d0a1b5faeb528301e3064342dafb79069e7c4c6b8c38ae11d7a973bec0d4f\
+             70626f8cc5184a8d0b0756"
+        );
+        assert_eq!(
+            hex(&to_seed(SEGWIT, "Did you ever hear the tragedy of Darth Plagueis the Wise?")),
+            "4aa29f2aeb0127efb55138ab9e7be83b36750358751906f86c662b21a1ea1370f949e6d1a12fa56d3d93cadda93038c76ac8118\
+             597364e46f5156fde6183c82f"
+        );
+    }
+
+    #[test]
+    fn normalizes_case_spaces_and_accents() {
+        let messy = "  Wild FATHER tree among  universe such mobile favorite target dynamic credit identify ";
+        assert_eq!(seed_type(messy), Some(SeedType::Segwit));
+        assert_eq!(to_seed(messy, "Pässphrase"), to_seed(SEGWIT, "passphrase"));
+    }
+}
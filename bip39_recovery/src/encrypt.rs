@@ -0,0 +1,95 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use anyhow::Result;
+
+/// Whom `--encrypt-result` seals matches to. Encryption is left to the
+/// `age` and `gpg` tools already on the machine, so their keyrings,
+/// smartcards, and agents work as usual.
+pub enum Recipient {
+    /// An age recipient (`age1...`) or SSH public key, via `age`.
+    Age(String),
+    /// A GPG key ID, fingerprint, or user ID, via `gpg`.
+    Gpg(String),
+    /// A passphrase for GPG's symmetric encryption.
+    Passphrase(String),
+}
+
+impl Recipient {
+    /// `passphrase:<PASSPHRASE>`, `passphrase-env:<VAR>` to read it from
+    /// the environment instead of the command line, an age recipient or
+    /// SSH key, or anything else as a GPG recipient.
+    pub fn parse(value: &str) -> Result<Self> {
+        let value = value.trim();
+        if let Some(passphrase) = value.strip_prefix("passphrase:") {
+            return Self::passphrase(passphrase.to_string());
+        }
+        if let Some(var) = value.strip_prefix("passphrase-env:") {
+            let passphrase = std::env::var(var)
+                .map_err(|e| anyhow::anyhow!("Can't read the result passphrase from {}: {}", var, e))?;
+            return Self::passphrase(passphrase);
+        }
+        if value.is_empty() {
+            return Err(anyhow::anyhow!("Empty --encrypt-result recipient"));
+        }
+        if value.starts_with("age1") || value.starts_with("ssh-") {
+            Ok(Recipient::Age(value.to_string()))
+        } else {
+            Ok(Recipient::Gpg(value.to_string()))
+        }
+    }
+
+    fn passphrase(passphrase: String) -> Result<Self> {
+        // GPG reads it as the first line of its input
+        if passphrase.is_empty() || passphrase.contains('\n') {
+            return Err(anyhow::anyhow!("The result passphrase must be a single non-empty line"));
+        }
+        Ok(Recipient::Passphrase(passphrase))
+    }
+
+    /// What the encrypted blob is opened with, for the startup message.
+    pub fn describe(&self) -> String {
+        match self {
+            Recipient::Age(recipient) => format!("age recipient {}", recipient),
+            Recipient::Gpg(recipient) => format!("GPG recipient {}", recipient),
+            Recipient::Passphrase(_) => "a passphrase (gpg --symmetric)".to_string(),
+        }
+    }
+
+    /// `plaintext` encrypted and ASCII-armored.
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<String> {
+        let (program, args, mut input) = match self {
+            Recipient::Age(recipient) => ("age", vec!["--encrypt", "--armor", "--recipient", recipient.as_str()], Vec::new()),
+            Recipient::Gpg(recipient) => (
+                "gpg",
+                vec!["--batch", "--yes", "--armor", "--trust-model", "always", "--encrypt", "--recipient", recipient.as_str()],
+                Vec::new(),
+            ),
+            Recipient::Passphrase(passphrase) => (
+                "gpg",
+                vec!["--batch", "--yes", "--armor", "--symmetric", "--pinentry-mode", "loopback", "--passphrase-fd", "0"],
+                format!("{}\n", passphrase).into_bytes(),
+            ),
+        };
+        input.extend_from_slice(plaintext);
+        let mut child = Command::new(program)
+            .args(&args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| anyhow::anyhow!("Failed to run {} for --encrypt-result (is it installed?): {}", program, e))?;
+        let written = child.stdin.take().expect("piped stdin").write_all(&input);
+        let output = child.wait_with_output().map_err(|e| anyhow::anyhow!("Failed to run {}: {}", program, e))?;
+        written.map_err(|e| anyhow::anyhow!("Failed to pass the result to {}: {}", program, e))?;
+        if !output.status.success() {
+            return Err(anyhow::anyhow!(
+                "{} failed to encrypt the result ({}): {}",
+                program,
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        String::from_utf8(output.stdout).map_err(|_| anyhow::anyhow!("{} wrote a non-armored result", program))
+    }
+}
@@ -0,0 +1,342 @@
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+
+use anyhow::Result;
+use secp256k1::Secp256k1;
+
+use crate::bloom::AddressBloom;
+use crate::gpu::Seed;
+use crate::hashdb::{self, HashDb};
+use crate::matrix::SearchMatrix;
+use crate::missing::MissingWords;
+use crate::passphrase::Passphrases;
+use crate::pbkdf2::{Backend, Pbkdf2Batch};
+use crate::permutation::PermutationRange;
+use crate::pipeline::{self, Feed, Ranked};
+use crate::stages::StageStats;
+use crate::substitutes::Substitutes;
+use crate::summary::RunSummary;
+use crate::targets::AddressPattern;
+use crate::tokenlist::TokenList;
+use crate::{
+    chunks, search_space, seedlist, try_mnemonic, verify, Arrangement, Bip39Wordlist, Match, SeedFormat, Targets,
+};
+
+/// What derived addresses are compared against.
+pub enum Target {
+    /// One address, matched by the bytes it wraps as the CLI matches it,
+    /// or as text when it doesn't decode
+    Address(String),
+    /// Any address of a set, e.g. those [`crate::targets::load`] reads
+    Addresses(HashSet<String>),
+    /// Any address fitting a pattern; a hit is only as sure as the pattern
+    Pattern(AddressPattern),
+    /// Any address of a list too large to hold, behind a bloom filter
+    Bloom(AddressBloom),
+    /// Any key of a hash database, see [`crate::hashdb`]
+    HashDb(HashDb),
+}
+
+impl Target {
+    fn targets(&self) -> Targets<'_> {
+        match self {
+            Target::Address(_) => unreachable!("a single address is resolved by RecoveryEngine::new"),
+            Target::Addresses(addresses) => Targets { addresses: Some(addresses), ..Targets::default() },
+            Target::Pattern(pattern) => Targets { pattern: Some(pattern), ..Targets::default() },
+            Target::Bloom(bloom) => Targets { bloom: Some(bloom), ..Targets::default() },
+            Target::HashDb(hash_db) => Targets { hash_db: Some(hash_db), ..Targets::default() },
+        }
+    }
+}
+
+/// How candidates are built. `words` and `fixed_words` of the config only
+/// apply to permutations and missing words; tokenlists, seedlists, and
+/// substitutes give whole mnemonics.
+pub enum Candidates {
+    /// Every ordering of the words after the fixed ones
+    Permutations,
+    /// The words after the fixed ones in any order plus `count` words lost
+    /// outright, at the 1-based `positions` or anywhere when empty; see
+    /// [`MissingWords`]
+    Missing { count: usize, positions: Vec<usize> },
+    /// Every selection of a tokenlist
+    Tokenlist(TokenList),
+    /// Every line of a file of `total_words`-word mnemonics
+    Seedlist { file: String, total_words: usize },
+    /// Every combination of the words each position may hold
+    Substitutes(Substitutes),
+}
+
+/// Settings of a recovery: how candidates are built, how each is derived,
+/// and what it must derive to count as a match.
+pub struct RecoveryConfig {
+    /// Known words in mnemonic order, the first `fixed_words` of which stay
+    /// where they are
+    pub words: Vec<String>,
+    pub fixed_words: usize,
+    pub candidates: Candidates,
+    pub matrix: SearchMatrix,
+    pub passphrases: Passphrases,
+    pub target: Target,
+    /// Keep going after a match instead of stopping at the first
+    pub find_all: bool,
+    /// Ranks searched: from `start` up to `end`, or the end of the space
+    pub start: u64,
+    pub end: Option<u64>,
+}
+
+impl RecoveryConfig {
+    /// Every ordering of `words` derived under `matrix` and checked against
+    /// `target`: nothing fixed, no passphrase, the whole space, and a stop at
+    /// the first match. Change the fields for anything else.
+    pub fn new(words: Vec<String>, matrix: SearchMatrix, target: Target) -> Self {
+        Self {
+            words,
+            fixed_words: 0,
+            candidates: Candidates::Permutations,
+            matrix,
+            passphrases: Passphrases::none(),
+            target,
+            find_all: false,
+            start: 0,
+            end: None,
+        }
+    }
+
+    fn missing(&self) -> Result<Option<MissingWords>> {
+        match &self.candidates {
+            Candidates::Missing { count, positions } => {
+                MissingWords::new(self.words.len() + count, self.fixed_words, *count, positions).map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+/// How a run went.
+pub struct Report {
+    /// Candidates taken from the range, including those ruled out early
+    pub checked: u64,
+    /// Matches reported, all of them cross-verified
+    pub matches: u64,
+    /// Why candidates were rejected, or matched
+    pub summary: RunSummary,
+}
+
+/// Searches a [`RecoveryConfig`] on the rayon pool through the CLI's
+/// [`pipeline`], with seeds batched on the CPU's vector units: wordlist and
+/// checksum, then every passphrase, path, and encoding. Each hit is
+/// re-derived along an independent route ([`verify::rederive`]) before it is
+/// reported. Devices, checkpoints, and the CLI's other run controls stay in
+/// the binary.
+pub struct RecoveryEngine {
+    config: RecoveryConfig,
+    missing: Option<MissingWords>,
+    wordlist: Bip39Wordlist,
+    space: u64,
+    stop: AtomicBool,
+}
+
+impl RecoveryEngine {
+    pub fn new(mut config: RecoveryConfig) -> Result<Self> {
+        if config.fixed_words > config.words.len() {
+            return Err(anyhow::anyhow!(
+                "{} fixed words but only {} words given",
+                config.fixed_words,
+                config.words.len()
+            ));
+        }
+        if let Target::Address(address) = &config.target {
+            config.target = match hashdb::address_key(address) {
+                Some(key) => Target::HashDb(HashDb::from_keys(vec![key])),
+                None => Target::Addresses(HashSet::from([address.clone()])),
+            };
+        }
+        let missing = config.missing()?;
+        let (tokenlist, seedlist, substitutes, total_words) = match &config.candidates {
+            Candidates::Tokenlist(tokenlist) => (Some(tokenlist), None, None, 0),
+            Candidates::Seedlist { file, total_words } => (None, Some(file.as_str()), None, *total_words),
+            Candidates::Substitutes(substitutes) => (None, None, Some(substitutes), 0),
+            Candidates::Missing { count, .. } => (None, None, None, config.words.len() + count),
+            Candidates::Permutations => (None, None, None, config.words.len()),
+        };
+        let space = search_space(
+            tokenlist,
+            seedlist,
+            substitutes,
+            None,
+            missing.as_ref(),
+            Arrangement::All(total_words.saturating_sub(config.fixed_words)),
+        )?;
+        Ok(Self { config, missing, wordlist: Bip39Wordlist::english(), space, stop: AtomicBool::new(false) })
+    }
+
+    /// Candidate ranks in the whole search, whatever range is configured.
+    pub fn space(&self) -> u64 {
+        self.space
+    }
+
+    /// Asks a run in progress to stop once the candidates in flight are done.
+    /// A stopped engine stays stopped, so a later [`RecoveryEngine::run`]
+    /// returns at once.
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    /// Candidates in the configured range, in rank order, each a whole
+    /// mnemonic. `None` is a rank that yields no candidate (a tokenlist rank
+    /// ruled out by an anchor, or an unusable seedlist line).
+    pub fn candidates(&self) -> Result<Box<dyn Iterator<Item = Option<Vec<String>>> + Send + '_>> {
+        self.range(self.config.start, self.end())
+    }
+
+    /// End of the configured range.
+    fn end(&self) -> u64 {
+        self.config.end.unwrap_or(self.space).min(self.space)
+    }
+
+    /// Candidates with ranks in `[start, end)`.
+    fn range(&self, start: u64, end: u64) -> Result<Box<dyn Iterator<Item = Option<Vec<String>>> + Send + '_>> {
+        match &self.config.candidates {
+            Candidates::Seedlist { file, total_words } => {
+                Ok(Box::new(seedlist::candidates(file, *total_words, start, end)?))
+            }
+            _ => Ok(self.unrank(start, end)),
+        }
+    }
+
+    /// Candidates with ranks in `[start, end)` of a source that unranks any
+    /// range, which is every one but a seedlist.
+    fn unrank(&self, start: u64, end: u64) -> Box<dyn Iterator<Item = Option<Vec<String>>> + Send> {
+        let config = &self.config;
+        let fixed = config.words[..config.fixed_words].to_vec();
+        let permutable = config.words[config.fixed_words..].to_vec();
+        let ranks: Box<dyn Iterator<Item = Option<Vec<String>>> + Send> = match &config.candidates {
+            Candidates::Permutations => {
+                Box::new(PermutationRange::new(permutable, start, end).map(|(_, perm)| Some(perm)))
+            }
+            Candidates::Missing { .. } => match self.missing.clone() {
+                Some(missing) => Box::new(missing.into_range(permutable, start, end)),
+                None => Box::new(std::iter::empty()),
+            },
+            Candidates::Tokenlist(tokenlist) => return Box::new(tokenlist.clone().into_range(start, end)),
+            Candidates::Substitutes(substitutes) => return Box::new(substitutes.clone().into_range(start, end)),
+            // Read forward by `range` instead
+            Candidates::Seedlist { .. } => return Box::new(std::iter::empty()),
+        };
+        Box::new(ranks.map(move |perm| perm.map(|perm| [fixed.as_slice(), perm.as_slice()].concat())))
+    }
+
+    /// Searches the range, calling `on_match` with each verified match as it
+    /// is found. Stops at the first match unless `find_all` is set, or when
+    /// [`RecoveryEngine::stop`] is called.
+    pub fn run(&self, on_match: impl Fn(&Match) + Sync) -> Result<Report> {
+        let config = &self.config;
+        let secp = Secp256k1::new();
+        let summary = RunSummary::default();
+        let stages = StageStats::default();
+        let (checked, matches) = (AtomicU64::new(0), AtomicU64::new(0));
+        let targets = config.target.targets();
+        let backend = Backend::detect();
+        let simd = (backend.lanes() > 1).then(|| Pbkdf2Batch::new(backend, &config.passphrases));
+
+        let check = |(_, candidate): (u64, Option<Vec<String>>), seeds: Option<&mut dyn Iterator<Item = Seed>>| {
+            checked.fetch_add(1, Ordering::Relaxed);
+            let Some(words) = candidate else { return };
+            // As in the CLI, a candidate that fails to derive is counted in
+            // the summary and skipped
+            let found = try_mnemonic(
+                &words,
+                SeedFormat::Bip39,
+                &config.matrix,
+                &config.passphrases,
+                seeds,
+                None,
+                &targets,
+                &secp,
+                &self.wordlist,
+                &summary,
+                &stages,
+                false,
+            );
+            let Ok(Some(found)) = found else { return };
+            let confirmed = verify::rederive(&found, SeedFormat::Bip39, &config.matrix.overrides, &secp)
+                .is_ok_and(|address| address == found.address);
+            if !confirmed || (!config.find_all && self.stop.swap(true, Ordering::SeqCst)) {
+                return;
+            }
+            matches.fetch_add(1, Ordering::Relaxed);
+            on_match(&found);
+        };
+        let (start, end) = (config.start, self.end());
+        let ranked = |start: u64, candidates: Box<dyn Iterator<Item = Option<Vec<String>>> + Send>| -> Ranked {
+            Box::new((start..).zip(candidates))
+        };
+        let feed = match &config.candidates {
+            // A seedlist is only read forward, so its lines are shared out
+            // as they are read
+            Candidates::Seedlist { file, total_words } => {
+                Feed::sequential(ranked(start, Box::new(seedlist::candidates(file, *total_words, start, end)?)))
+            }
+            // Other candidates unrank any range, so each worker builds its own batch
+            _ => Feed::ranked(start, end, |start, end| ranked(start, self.unrank(start, end))),
+        };
+        let stopped = || self.stop.load(Ordering::Relaxed);
+        let cpu = |batch, seeds| pipeline::check_batch(batch, seeds, simd.as_ref(), &[], &stopped, &check);
+        pipeline::run(&feed, chunks::CHUNK_SIZE, None, &cpu, &stopped);
+        Ok(Report { checked: checked.into_inner(), matches: matches.into_inner(), summary })
+    }
+
+    /// Runs the search on a background thread and yields its matches as they
+    /// are found. Dropping the iterator stops the search.
+    pub fn into_matches(self) -> Matches {
+        // Cleared before the search starts, so the iterator's drop can't be
+        // undone by it
+        self.stop.store(false, Ordering::Relaxed);
+        let engine = Arc::new(self);
+        let (sender, receiver) = mpsc::channel();
+        let worker = Arc::clone(&engine);
+        let handle = thread::spawn(move || {
+            worker.run(|found| {
+                // The receiver only goes away once the search is told to stop
+                let _ = sender.send(found.clone());
+            })
+        });
+        Matches { engine, receiver, handle: Some(handle) }
+    }
+}
+
+/// Matches of a search running in the background; see
+/// [`RecoveryEngine::into_matches`].
+pub struct Matches {
+    engine: Arc<RecoveryEngine>,
+    receiver: mpsc::Receiver<Match>,
+    handle: Option<thread::JoinHandle<Result<Report>>>,
+}
+
+impl Matches {
+    /// Waits for the search to end, without stopping it, and returns how it
+    /// went. Matches not yet taken from the iterator are dropped.
+    pub fn finish(mut self) -> Result<Report> {
+        match self.handle.take() {
+            Some(handle) => handle.join().map_err(|_| anyhow::anyhow!("Search thread panicked"))?,
+            None => Err(anyhow::anyhow!("Search already finished")),
+        }
+    }
+}
+
+impl Iterator for Matches {
+    type Item = Match;
+
+    fn next(&mut self) -> Option<Match> {
+        self.receiver.recv().ok()
+    }
+}
+
+impl Drop for Matches {
+    fn drop(&mut self) {
+        self.engine.stop();
+    }
+}
@@ -0,0 +1,225 @@
+use bip39::Language;
+use bitcoin::hashes::{sha256, Hash};
+
+/// Mnemonic lengths BIP-39 defines: 128 to 256 bits of entropy in steps of
+/// 32, each with ENT/32 checksum bits, 11 bits per word.
+pub const WORD_COUNTS: [usize; 5] = [12, 15, 18, 21, 24];
+
+const BITS_PER_WORD: usize = 11;
+
+/// Checksum bits for a mnemonic of `words` words, or `None` for a length
+/// BIP-39 doesn't define.
+pub fn checksum_bits(words: usize) -> Option<usize> {
+    WORD_COUNTS.contains(&words).then_some(words / 3)
+}
+
+/// Entropy length in bytes for a mnemonic of `words` words.
+pub fn entropy_len(words: usize) -> Option<usize> {
+    checksum_bits(words).map(|cs| (words * BITS_PER_WORD - cs) / 8)
+}
+
+/// The checksum BIP-39 appends to `entropy`: the first ENT/32 bits of its
+/// SHA-256, right-aligned.
+pub fn checksum(entropy: &[u8]) -> u8 {
+    let cs = entropy.len() * 8 / 32;
+    sha256::Hash::hash(entropy).as_byte_array()[0] >> (8 - cs)
+}
+
+/// Splits word indices into the entropy they encode and the checksum bits
+/// they carry, or `None` for an undefined length or an index past 2047.
+pub fn split(indices: &[u16]) -> Option<(Vec<u8>, u8)> {
+    let cs = checksum_bits(indices.len())?;
+    let len = entropy_len(indices.len())?;
+    if indices.iter().any(|&index| index >= 1 << BITS_PER_WORD) {
+        return None;
+    }
+    // Every length packs into at most 264 bits, so stream through a u64
+    let mut entropy = Vec::with_capacity(len);
+    let mut acc: u64 = 0;
+    let mut bits = 0;
+    for &index in indices {
+        acc = (acc << BITS_PER_WORD) | u64::from(index);
+        bits += BITS_PER_WORD;
+        while bits >= 8 && entropy.len() < len {
+            bits -= 8;
+            entropy.push((acc >> bits) as u8);
+        }
+    }
+    // What's left after the entropy bytes is exactly the checksum
+    debug_assert_eq!(bits, cs);
+    let checksum = (acc & ((1 << cs) - 1)) as u8;
+    Some((entropy, checksum))
+}
+
+/// Positions of `words` in the English wordlist, or `None` if one isn't on it.
+pub fn indices<S: AsRef<str>>(words: &[S]) -> Option<Vec<u16>> {
+    words.iter().map(|word| Language::English.find_word(word.as_ref())).collect()
+}
+
+/// Whether `indices` form a mnemonic with a valid checksum. One SHA-256 over
+/// at most 32 bytes, with no string handling.
+pub fn is_valid(indices: &[u16]) -> bool {
+    split(indices).is_some_and(|(entropy, cs)| checksum(&entropy) == cs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Entropy and mnemonic pairs from the Trezor BIP-39 reference vectors.
+    const VECTORS: [(&str, &str); 18] = [
+        ("00000000000000000000000000000000", "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about"),
+        ("7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f", "legal winner thank year wave sausage worth useful legal winner thank yellow"),
+        ("80808080808080808080808080808080", "letter advice cage absurd amount doctor acoustic avoid letter advice cage above"),
+        ("ffffffffffffffffffffffffffffffff", "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo wrong"),
+        (
+            "000000000000000000000000000000000000000000000000",
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon agent",
+        ),
+        (
+            "7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f",
+            "legal winner thank year wave sausage worth useful legal winner thank year wave sausage worth useful legal will",
+        ),
+        (
+            "808080808080808080808080808080808080808080808080",
+            "letter advice cage absurd amount doctor acoustic avoid letter advice cage absurd amount doctor acoustic avoid letter always",
+        ),
+        ("ffffffffffffffffffffffffffffffffffffffffffffffff", "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo when"),
+        (
+            "0000000000000000000000000000000000000000000000000000000000000000",
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon art",
+        ),
+        (
+            "7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f",
+            "legal winner thank year wave sausage worth useful legal winner thank year wave sausage worth useful legal winner thank year wave sausage worth title",
+        ),
+        (
+            "8080808080808080808080808080808080808080808080808080808080808080",
+            "letter advice cage absurd amount doctor acoustic avoid letter advice cage absurd amount doctor acoustic avoid letter advice cage absurd amount doctor acoustic bless",
+        ),
+        (
+            "ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+            "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo vote",
+        ),
+        ("9e885d952ad362caeb4efe34a8e91bd2", "ozone drill grab fiber curtain grace pudding thank cruise elder eight picnic"),
+        (
+            "6610b25967cdcca9d59875f5cb50b0ea75433311869e930b",
+            "gravity machine north sort system female filter attitude volume fold club stay feature office ecology stable narrow fog",
+        ),
+        (
+            "68a79eaca2324873eacc50cb9c6eca8cc68ea5d936f98787c60c7ebc74e6ce7c",
+            "hamster diagram private dutch cause delay private meat slide toddler razor book happy fancy gospel tennis maple dilemma loan word shrug inflict delay length",
+        ),
+        ("c0ba5a8e914111210f2bd131f3d5e08d", "scheme spot photo card baby mountain device kick cradle pact join borrow"),
+        (
+            "6d9be1ee6ebd27a258115aad99b7317b9c8d28b6d76431c3",
+            "horn tenant knee talent sponsor spell gate clip pulse soap slush warm silver nephew swap uncle crack brave",
+        ),
+        (
+            "9f6a2878b2520799a44ef18bc7df394e7061a224d2c33cd015b157d746869863",
+            "panda eyebrow bullet gorilla call smoke muffin taste mesh discover soft ostrich alcohol speed nation flash devote level hobby quick inner drive ghost inside",
+        ),
+    ];
+
+    fn hex(text: &str) -> Vec<u8> {
+        (0..text.len()).step_by(2).map(|i| u8::from_str_radix(&text[i..i + 2], 16).unwrap()).collect()
+    }
+
+    fn words(phrase: &str) -> Vec<&str> {
+        phrase.split_whitespace().collect()
+    }
+
+    /// Entropy of `len` bytes that differs from vector to vector.
+    fn patterned(len: usize, seed: u8) -> Vec<u8> {
+        (0..len).map(|i| (i as u8).wrapping_mul(37).wrapping_add(seed.wrapping_mul(101))).collect()
+    }
+
+    #[test]
+    fn lengths() {
+        for (words, cs, len) in [(12, 4, 16), (15, 5, 20), (18, 6, 24), (21, 7, 28), (24, 8, 32)] {
+            assert_eq!(checksum_bits(words), Some(cs));
+            assert_eq!(entropy_len(words), Some(len));
+        }
+        for words in [0, 1, 11, 13, 14, 16, 17, 19, 20, 22, 23, 25, 27] {
+            assert_eq!(checksum_bits(words), None);
+            assert_eq!(entropy_len(words), None);
+        }
+    }
+
+    #[test]
+    fn reference_vectors() {
+        for (entropy, phrase) in VECTORS {
+            let indices = indices(&words(phrase)).expect("vector words are on the list");
+            let (own, cs) = split(&indices).expect("vector lengths are defined");
+            assert_eq!(own, hex(entropy), "{}", phrase);
+            assert_eq!(cs, checksum(&own), "{}", phrase);
+            assert!(is_valid(&indices), "{}", phrase);
+        }
+    }
+
+    /// 15 and 21 words have no Trezor vectors, so every length is also
+    /// checked against the bip39 crate's encoding of the same entropy.
+    #[test]
+    fn every_length_matches_bip39() {
+        for words in WORD_COUNTS {
+            let len = entropy_len(words).unwrap();
+            for seed in 0..32 {
+                let entropy = patterned(len, seed);
+                let mnemonic = bip39::Mnemonic::from_entropy(&entropy).unwrap();
+                let expected: Vec<u16> = mnemonic.word_indices().map(|index| index as u16).collect();
+                assert_eq!(indices(&mnemonic.words().collect::<Vec<_>>()), Some(expected.clone()));
+                assert_eq!(split(&expected), Some((entropy.clone(), mnemonic.checksum())));
+                assert!(is_valid(&expected));
+            }
+        }
+    }
+
+    /// Changing only the checksum bits of the last word must fail every
+    /// length.
+    #[test]
+    fn invalid_checksums() {
+        for words in WORD_COUNTS {
+            let cs = checksum_bits(words).unwrap();
+            for seed in 0..16 {
+                let entropy = patterned(entropy_len(words).unwrap(), seed);
+                let valid: Vec<u16> = bip39::Mnemonic::from_entropy(&entropy)
+                    .unwrap()
+                    .word_indices()
+                    .map(|index| index as u16)
+                    .collect();
+                for flip in 0..cs {
+                    let mut wrong = valid.clone();
+                    *wrong.last_mut().unwrap() ^= 1 << flip;
+                    assert!(!is_valid(&wrong), "{} words, checksum bit {} flipped", words, flip);
+                }
+                // A changed entropy bit keeps the checksum now and then, so
+                // the bip39 crate's parser decides which
+                let mut changed = valid.clone();
+                changed[0] ^= 1 << 10;
+                let list = Language::English.word_list();
+                let phrase: Vec<&str> = changed.iter().map(|&index| list[index as usize]).collect();
+                let expected = bip39::Mnemonic::parse_normalized(&phrase.join(" ")).is_ok();
+                assert_eq!(is_valid(&changed), expected, "{} words, first entropy bit flipped", words);
+            }
+        }
+        for (_, phrase) in VECTORS {
+            let mut wrong = indices(&words(phrase)).unwrap();
+            *wrong.last_mut().unwrap() ^= 1;
+            assert!(!is_valid(&wrong), "{} with the last checksum bit flipped", phrase);
+        }
+    }
+
+    #[test]
+    fn undefined_input() {
+        let twelve = indices(&words(VECTORS[0].1)).unwrap();
+        assert!(!is_valid(&twelve[..11]));
+        assert!(!is_valid(&[twelve.as_slice(), &[0]].concat()));
+        assert!(!is_valid(&[]));
+        let mut past_list = twelve.clone();
+        past_list[3] = 2048;
+        assert_eq!(split(&past_list), None);
+        assert!(!is_valid(&past_list));
+        assert_eq!(indices(&["abandon", "abandonn"]), None);
+        assert_eq!(indices(&["zoo", "wrong"]), Some(vec![2047, 2037]));
+    }
+}
@@ -0,0 +1,139 @@
+use std::time::{Duration, Instant};
+
+use bip39_recovery::summary::RunSummary;
+use rayon::prelude::*;
+
+/// Candidates a thread checks at a time between clock checks.
+const CHUNK: u64 = 256;
+
+/// Runs over this long are worth rethinking before starting.
+const IMPRACTICAL_SECONDS: f64 = 365.25 * 86400.0;
+
+/// The size of a search, as `estimate` reports it.
+pub struct SearchSize {
+    /// Candidate ranks in the range searched
+    pub candidates: u64,
+    /// Ranks in the whole space, of which the range may be a slice
+    pub space: u64,
+    /// Path, address type, and network combinations derived per candidate
+    pub combinations: usize,
+    pub passphrases: u64,
+    /// Share of candidates expected to pass the checksum and be derived
+    pub checksum_pass: f64,
+}
+
+impl SearchSize {
+    /// Addresses derived over the whole range.
+    pub fn derivations(&self) -> f64 {
+        self.candidates as f64 * self.checksum_pass * self.combinations as f64 * self.passphrases as f64
+    }
+}
+
+/// What a benchmark got through.
+pub struct Measurement {
+    pub candidates: u64,
+    /// Candidates that passed the checksum and were fully derived
+    pub derived: u64,
+    pub seconds: f64,
+    pub threads: usize,
+}
+
+impl Measurement {
+    pub fn rate(&self) -> f64 {
+        self.candidates as f64 / self.seconds.max(f64::EPSILON)
+    }
+}
+
+/// Checks candidates `sample` builds from their sample index with `check`,
+/// a chunk at a time on each thread of the current rayon pool, for about
+/// `seconds`. `check` records its outcomes in `summary`.
+pub fn benchmark(
+    seconds: f64,
+    sample: &(dyn Fn(u64) -> Vec<String> + Sync),
+    check: &(dyn Fn(&[Vec<String>]) + Sync),
+    summary: &RunSummary,
+) -> Measurement {
+    let threads = rayon::current_num_threads();
+    let budget = Duration::from_secs_f64(seconds.max(0.1));
+    let start = Instant::now();
+    let mut candidates = 0u64;
+    while start.elapsed() < budget {
+        (0..threads as u64).into_par_iter().for_each(|thread| {
+            let first = candidates + thread * CHUNK;
+            check(&(first..first + CHUNK).map(sample).collect::<Vec<_>>());
+        });
+        candidates += CHUNK * threads as u64;
+    }
+    Measurement { candidates, derived: summary.derived(), seconds: start.elapsed().as_secs_f64(), threads }
+}
+
+/// A `words`-word candidate drawn from `vocabulary`, the same for the same
+/// `index`. Drawn words pass the checksum about as often as the orderings of
+/// a real search do, so they cost about as much to check.
+pub fn drawn(vocabulary: &[String], words: usize, index: u64) -> Vec<String> {
+    (0..words as u64)
+        .map(|position| {
+            // splitmix64 of the index and position
+            let mut z = (index << 8 | position).wrapping_add(1).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^= z >> 31;
+            vocabulary[(z % vocabulary.len() as u64) as usize].clone()
+        })
+        .collect()
+}
+
+/// A duration in the largest unit that fits, e.g. `3.2 years`.
+pub fn duration(seconds: f64) -> String {
+    const UNITS: [(&str, f64); 5] =
+        [("years", 365.25 * 86400.0), ("days", 86400.0), ("hours", 3600.0), ("minutes", 60.0), ("seconds", 1.0)];
+    if !seconds.is_finite() {
+        return "forever".to_string();
+    }
+    let (unit, size) = UNITS.iter().find(|(_, size)| seconds >= *size).copied().unwrap_or(UNITS[4]);
+    match seconds / size {
+        value if value >= 1e6 => format!("{:.1e} {}", value, unit),
+        value => format!("{:.1} {}", value, unit),
+    }
+}
+
+/// Prints the size of the search, the rate measured, and how long the
+/// search would take at that rate.
+pub fn report(size: &SearchSize, measured: &Measurement) {
+    let line = |label: &str, value: String| println!("  {:<25} {}", label, value);
+    println!("Search space:");
+    if size.candidates == size.space {
+        line("Candidates:", size.candidates.to_string());
+    } else {
+        line("Candidates:", format!("{} (a range of {})", size.candidates, size.space));
+    }
+    line(
+        "Passing the checksum:",
+        format!("{:.4}% (~{:.3e})", size.checksum_pass * 100.0, size.candidates as f64 * size.checksum_pass),
+    );
+    line("Paths and address types:", format!("{} per candidate", size.combinations));
+    line("Passphrases:", format!("{} per candidate", size.passphrases));
+    line("Addresses derived:", format!("~{:.3e}", size.derivations()));
+    println!();
+    let rate = measured.rate();
+    println!(
+        "Benchmark: {} candidates ({} fully derived) in {:.1}s on {} CPU thread{}: {:.0} candidates/s",
+        measured.candidates,
+        measured.derived,
+        measured.seconds,
+        measured.threads,
+        if measured.threads == 1 { "" } else { "s" },
+        rate
+    );
+    let seconds = size.candidates as f64 / rate;
+    println!("Estimated time for the whole search: {}", duration(seconds));
+    println!("Expected time to a match, if there is one: {}", duration(seconds / 2.0));
+    if seconds > IMPRACTICAL_SECONDS {
+        println!();
+        println!(
+            "This search is impractical on this machine. Narrow it before starting: pin words in place with \
+             --fixed-words or --known-positions, limit the order with --max-displacement, drop paths, address types, \
+             or passphrases, or split it across machines with shard-plan."
+        );
+    }
+}
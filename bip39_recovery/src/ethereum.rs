@@ -0,0 +1,141 @@
+use anyhow::Result;
+use secp256k1::PublicKey;
+
+/// Matrix encoding whose "address" is an Ethereum account.
+pub const ETH: &str = "eth";
+
+/// SLIP-44 coin type of Ether, as in the `m/44'/60'/0'/0/0` wallets use.
+pub const COIN_TYPE: u32 = 60;
+
+/// The account of a public key: the last 20 bytes of the Keccak-256 of its
+/// uncompressed form, without the `04` prefix, in EIP-55 mixed case.
+pub fn address(pubkey: &PublicKey) -> String {
+    let hash = keccak256(&pubkey.serialize_uncompressed()[1..]);
+    checksummed(&hex(&hash[12..]))
+}
+
+/// Whether `address` is written as an account: `0x` and 40 hex digits.
+pub fn is_address(address: &str) -> bool {
+    address.len() == 42 && address.starts_with("0x") && address[2..].bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Normalizes a target for matching. Accounts come back in EIP-55 form; one
+/// already in mixed case must carry a valid checksum, since a typo there is
+/// otherwise a target nothing can ever match. Anything else is returned as
+/// given.
+pub fn normalize(address: &str) -> Result<String> {
+    if !is_address(address) {
+        return Ok(address.to_string());
+    }
+    let digits = &address[2..];
+    let checksummed = checksummed(&digits.to_ascii_lowercase());
+    let single_case = digits == digits.to_ascii_lowercase() || digits == digits.to_ascii_uppercase();
+    if !single_case && checksummed != address {
+        return Err(anyhow::anyhow!("Invalid EIP-55 checksum in {} (expected {})", address, checksummed));
+    }
+    Ok(checksummed)
+}
+
+/// EIP-55: a hex digit is upper case where the matching nibble of the
+/// Keccak-256 of the lower-case hex is 8 or more.
+fn checksummed(lower: &str) -> String {
+    let hash = keccak256(lower.as_bytes());
+    let digits: String = lower
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            let nibble = (hash[i / 2] >> if i % 2 == 0 { 4 } else { 0 }) & 0x0f;
+            if nibble >= 8 {
+                c.to_ascii_uppercase()
+            } else {
+                c
+            }
+        })
+        .collect();
+    format!("0x{}", digits)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+const ROUND_CONSTANTS: [u64; 24] = [
+    0x0000000000000001,
+    0x0000000000008082,
+    0x800000000000808a,
+    0x8000000080008000,
+    0x000000000000808b,
+    0x0000000080000001,
+    0x8000000080008081,
+    0x8000000000008009,
+    0x000000000000008a,
+    0x0000000000000088,
+    0x0000000080008009,
+    0x000000008000000a,
+    0x000000008000808b,
+    0x800000000000008b,
+    0x8000000000008089,
+    0x8000000000008003,
+    0x8000000000008002,
+    0x8000000000000080,
+    0x000000000000800a,
+    0x800000008000000a,
+    0x8000000080008081,
+    0x8000000000008080,
+    0x0000000080000001,
+    0x8000000080008008,
+];
+
+/// Rotation offsets and lane order of the rho and pi steps.
+const ROTATIONS: [u32; 24] = [1, 3, 6, 10, 15, 21, 28, 36, 45, 55, 2, 14, 27, 41, 56, 8, 25, 43, 62, 18, 39, 61, 20, 44];
+const LANES: [usize; 24] = [10, 7, 11, 17, 18, 3, 5, 16, 8, 21, 24, 4, 15, 23, 19, 13, 12, 2, 20, 14, 22, 9, 6, 1];
+
+fn keccak_f(state: &mut [u64; 25]) {
+    for round_constant in ROUND_CONSTANTS {
+        let mut columns = [0u64; 5];
+        for (x, column) in columns.iter_mut().enumerate() {
+            *column = state[x] ^ state[x + 5] ^ state[x + 10] ^ state[x + 15] ^ state[x + 20];
+        }
+        for x in 0..5 {
+            let d = columns[(x + 4) % 5] ^ columns[(x + 1) % 5].rotate_left(1);
+            for y in 0..5 {
+                state[x + 5 * y] ^= d;
+            }
+        }
+        let mut carried = state[1];
+        for (&lane, &rotation) in LANES.iter().zip(&ROTATIONS) {
+            let next = state[lane];
+            state[lane] = carried.rotate_left(rotation);
+            carried = next;
+        }
+        for y in 0..5 {
+            let row = [state[5 * y], state[5 * y + 1], state[5 * y + 2], state[5 * y + 3], state[5 * y + 4]];
+            for x in 0..5 {
+                state[5 * y + x] = row[x] ^ (!row[(x + 1) % 5] & row[(x + 2) % 5]);
+            }
+        }
+        state[0] ^= round_constant;
+    }
+}
+
+/// Keccak-256 as Ethereum uses it: the original `0x01` padding, not the
+/// `0x06` of the later SHA3-256 standard.
+pub fn keccak256(data: &[u8]) -> [u8; 32] {
+    const RATE: usize = 136;
+    let mut state = [0u64; 25];
+    let mut padded = data.to_vec();
+    padded.push(0x01);
+    padded.resize(padded.len().div_ceil(RATE) * RATE, 0);
+    *padded.last_mut().unwrap() |= 0x80;
+    for block in padded.chunks(RATE) {
+        for (lane, bytes) in state.iter_mut().zip(block.chunks(8)) {
+            *lane ^= u64::from_le_bytes(bytes.try_into().unwrap());
+        }
+        keccak_f(&mut state);
+    }
+    let mut out = [0u8; 32];
+    for (bytes, lane) in out.chunks_mut(8).zip(&state) {
+        bytes.copy_from_slice(&lane.to_le_bytes());
+    }
+    out
+}
@@ -0,0 +1,128 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+
+use anyhow::Result;
+use indicatif::{ProgressBar, ProgressStyle};
+use log::info;
+
+use crate::entropy;
+use crate::permutation::PermutationRange;
+
+/// Where an interrupted export stopped: the next permutation rank to
+/// generate and the length of the export file at that point.
+struct ExportOffset {
+    next_rank: u64,
+    bytes: u64,
+}
+
+fn offset_file(export_file: &str) -> String {
+    format!("{}.offset", export_file)
+}
+
+fn load_offset(export_file: &str) -> Result<Option<ExportOffset>> {
+    let file = offset_file(export_file);
+    let content = match fs::read_to_string(&file) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(anyhow::anyhow!("Failed to read export offset {}: {}", file, e)),
+    };
+    let fields: Vec<u64> = content
+        .split_whitespace()
+        .map(|field| field.parse::<u64>())
+        .collect::<Result<_, _>>()
+        .map_err(|e| anyhow::anyhow!("Failed to parse export offset {}: {}", file, e))?;
+    match fields.as_slice() {
+        [next_rank, bytes] => Ok(Some(ExportOffset { next_rank: *next_rank, bytes: *bytes })),
+        _ => Err(anyhow::anyhow!("Malformed export offset {}", file)),
+    }
+}
+
+fn save_offset(export_file: &str, offset: &ExportOffset) -> Result<()> {
+    let file = offset_file(export_file);
+    fs::write(&file, format!("{} {}\n", offset.next_rank, offset.bytes))
+        .map_err(|e| anyhow::anyhow!("Failed to write export offset {}: {}", file, e))
+}
+
+/// Writes every candidate in `[start, end)` that passes the wordlist and
+/// checksum checks to `export_file`, one mnemonic per line, instead of
+/// testing it. Duplicate arrangements of repeated words are written once.
+///
+/// Resuming truncates the file back to the last recorded offset, so an
+/// interrupted export continues without duplicated or torn lines.
+pub fn export_candidates(
+    export_file: &str,
+    fixed_words: &[String],
+    permutable_words: Vec<String>,
+    start: u64,
+    end: u64,
+    batch_size: usize,
+) -> Result<()> {
+    let resume = load_offset(export_file)?.filter(|offset| offset.next_rank > start && offset.next_rank <= end);
+    let mut file = match &resume {
+        Some(offset) => {
+            let file = OpenOptions::new()
+                .write(true)
+                .open(export_file)
+                .map_err(|e| anyhow::anyhow!("Failed to open export file {}: {}", export_file, e))?;
+            file.set_len(offset.bytes)
+                .map_err(|e| anyhow::anyhow!("Failed to truncate export file {}: {}", export_file, e))?;
+            file
+        }
+        None => File::create(export_file)
+            .map_err(|e| anyhow::anyhow!("Failed to create export file {}: {}", export_file, e))?,
+    };
+    let mut bytes = resume.as_ref().map_or(0, |offset| offset.bytes);
+    let first = resume.as_ref().map_or(start, |offset| offset.next_rank);
+    file.seek(SeekFrom::Start(bytes))?;
+    let mut writer = BufWriter::new(file);
+    if first > start {
+        println!("Resuming export at permutation {} ({} bytes already written)", first, bytes);
+    }
+
+    let pb = ProgressBar::new(end - start);
+    pb.set_style(
+        ProgressStyle::with_template("[{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} ({percent}%) | {msg}")
+            .unwrap()
+            .progress_chars("##-"),
+    );
+    pb.set_position(first - start);
+
+    let mut exported: u64 = 0;
+    let mut rejected: u64 = 0;
+    let mut since_offset = 0usize;
+    let mut next_rank = first;
+    for (rank, perm) in PermutationRange::new(permutable_words, first, end).skip_duplicates() {
+        let mut words = fixed_words.to_vec();
+        words.extend(perm);
+        // The same checksum filter the search applies, without building a Mnemonic
+        if entropy::indices(&words).is_some_and(|indices| entropy::is_valid(&indices)) {
+            let phrase = words.join(" ");
+            writeln!(writer, "{}", phrase)?;
+            bytes += phrase.len() as u64 + 1;
+            exported += 1;
+        } else {
+            rejected += 1;
+        }
+        next_rank = rank + 1;
+        since_offset += 1;
+        if since_offset >= batch_size.max(1) {
+            writer.flush()?;
+            save_offset(export_file, &ExportOffset { next_rank, bytes })?;
+            pb.set_position(next_rank - start);
+            pb.set_message(format!("Exported: {}, rejected: {}", exported, rejected));
+            since_offset = 0;
+        }
+    }
+    writer.flush()?;
+    next_rank = next_rank.max(end);
+    save_offset(export_file, &ExportOffset { next_rank, bytes })?;
+    pb.finish_with_message(format!("Exported: {}, rejected: {}", exported, rejected));
+
+    let message = format!(
+        "Exported {} candidates to {} ({} rejected by wordlist/checksum, duplicates skipped)",
+        exported, export_file, rejected
+    );
+    println!("{}", message);
+    info!("{}", message);
+    Ok(())
+}
@@ -0,0 +1,176 @@
+use anyhow::Result;
+
+use crate::permutation;
+use crate::Bip39Wordlist;
+
+/// Wordlist entries within this many single-letter edits of a word that
+/// isn't on the list are tried in its place.
+pub const MAX_DISTANCE: usize = 2;
+
+/// BIP-39 words are unique in their first four letters, so a word that
+/// starts with the same four is taken as a candidate whatever its length.
+const PREFIX_LETTERS: usize = 4;
+
+/// Known words with every word that isn't on the BIP-39 list replaced by
+/// the list entries close to it, for backups with misspelled words, or by
+/// those it is the start of, for backups of prefixes.
+///
+/// Candidates are ranked ordering by ordering of the permutable words, as
+/// in a plain search, then by the choice of replacement for each misspelled
+/// word, the last one turning fastest.
+#[derive(Clone, Debug)]
+pub struct FuzzyWords {
+    /// Per known word, the words tried in its place (just itself when it is
+    /// on the list)
+    slots: Vec<Vec<String>>,
+    /// Misspelled or shortened words as given, by known-word position
+    typos: Vec<(usize, String)>,
+    fixed_words: usize,
+    fills: u64,
+    space: u64,
+}
+
+impl FuzzyWords {
+    pub fn new(words: &[String], fixed_words: usize, wordlist: &Bip39Wordlist) -> Result<Self> {
+        Self::expand(words, fixed_words, wordlist, |position, word| {
+            let near = nearby(word, wordlist);
+            if near.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "Word {} ({}) is not a BIP39 word and none is within {} edits of it",
+                    position + 1,
+                    word,
+                    MAX_DISTANCE
+                ));
+            }
+            Ok(near)
+        })
+    }
+
+    /// Known words with every word that isn't on the list taken as the start
+    /// of one and replaced by the list entries it begins, for backups that
+    /// only recorded each word's first letters ("abou" for "about").
+    pub fn prefixes(words: &[String], fixed_words: usize, wordlist: &Bip39Wordlist) -> Result<Self> {
+        Self::expand(words, fixed_words, wordlist, |position, word| {
+            let matching = wordlist.with_prefix(word);
+            if matching.is_empty() {
+                return Err(anyhow::anyhow!("Word {} ({}) is not a BIP39 word or the start of one", position + 1, word));
+            }
+            Ok(matching)
+        })
+    }
+
+    /// Replaces each word that isn't on the list by what `replacements`
+    /// gives for its 0-based position and lowercased spelling.
+    fn expand(
+        words: &[String],
+        fixed_words: usize,
+        wordlist: &Bip39Wordlist,
+        mut replacements: impl FnMut(usize, &str) -> Result<Vec<String>>,
+    ) -> Result<Self> {
+        let mut slots = Vec::with_capacity(words.len());
+        let mut typos = Vec::new();
+        for (position, word) in words.iter().enumerate() {
+            let word = word.to_lowercase();
+            if wordlist.index(&word).is_some() {
+                slots.push(vec![word]);
+                continue;
+            }
+            slots.push(replacements(position, &word)?);
+            typos.push((position, word));
+        }
+        let fills = slots.iter().try_fold(1u64, |fills, words| fills.checked_mul(words.len() as u64));
+        let space = fills.and_then(|fills| fills.checked_mul(permutation::factorial(words.len() - fixed_words)));
+        let (fills, space) = fills
+            .zip(space)
+            .ok_or_else(|| anyhow::anyhow!("Too many corrections to rank; write out some of the words in full"))?;
+        Ok(Self { slots, typos, fixed_words, fills, space })
+    }
+
+    /// Number of candidate ranks: orderings times corrections.
+    pub fn space(&self) -> u64 {
+        self.space
+    }
+
+    /// Each misspelled or shortened word with its 1-based position and the
+    /// words tried in its place.
+    pub fn corrections(&self) -> impl Iterator<Item = (usize, &str, &[String])> {
+        self.typos.iter().map(|(position, word)| (position + 1, word.as_str(), self.slots[*position].as_slice()))
+    }
+
+    /// Candidates with ranks in `[start, end)`, each a whole mnemonic, fixed
+    /// words included, since those may be misspelled too.
+    pub fn into_range(self, start: u64, end: u64) -> FuzzyRange {
+        let end = end.min(self.space);
+        FuzzyRange { words: self, rank: start, end, outer: None, order: Vec::new() }
+    }
+}
+
+/// List entries sharing the first four letters of `word`, or within
+/// [`MAX_DISTANCE`] edits of it, nearest first.
+fn nearby(word: &str, wordlist: &Bip39Wordlist) -> Vec<String> {
+    let prefix: String = word.chars().take(PREFIX_LETTERS).collect();
+    let mut near: Vec<(usize, String)> = Vec::new();
+    if prefix.chars().count() == PREFIX_LETTERS {
+        near.extend(wordlist.with_prefix(&prefix).into_iter().map(|entry| (levenshtein(word, &entry), entry)));
+    }
+    for entry in wordlist.words() {
+        let distance = levenshtein(word, &entry);
+        if distance <= MAX_DISTANCE && !near.iter().any(|(_, known)| *known == entry) {
+            near.push((distance, entry));
+        }
+    }
+    near.sort();
+    near.into_iter().map(|(_, entry)| entry).collect()
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+pub struct FuzzyRange {
+    words: FuzzyWords,
+    rank: u64,
+    end: u64,
+    // Ordering of the last candidate, kept while only the corrections change
+    outer: Option<u64>,
+    order: Vec<usize>,
+}
+
+impl Iterator for FuzzyRange {
+    type Item = Option<Vec<String>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.rank >= self.end {
+            return None;
+        }
+        let rank = self.rank;
+        self.rank += 1;
+        let words = &self.words;
+        let (outer, mut fill) = (rank / words.fills, rank % words.fills);
+        if self.outer != Some(outer) {
+            let permutable = words.slots.len() - words.fixed_words;
+            self.order = (0..words.fixed_words)
+                .chain(permutation::unrank(permutable, outer).into_iter().map(|i| words.fixed_words + i))
+                .collect();
+            self.outer = Some(outer);
+        }
+
+        // Each slot's choice is a digit of the fill, the last slot lowest
+        let mut choices = vec![0; words.slots.len()];
+        for (choice, slot) in choices.iter_mut().zip(&words.slots).rev() {
+            *choice = (fill % slot.len() as u64) as usize;
+            fill /= slot.len() as u64;
+        }
+        Some(Some(self.order.iter().map(|&slot| words.slots[slot][choices[slot]].clone()).collect()))
+    }
+}
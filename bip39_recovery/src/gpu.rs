@@ -0,0 +1,2326 @@
+use anyhow::Result;
+use bip39::Language;
+
+use crate::passphrase::Passphrases;
+use crate::workers::WorkerStats;
+
+/// A BIP-39 seed as PBKDF2-HMAC-SHA512 produces it.
+pub type Seed = [u8; 64];
+
+/// API the GPU kernel runs through.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Accelerator {
+    /// NVIDIA devices, running kernel.cu's PTX
+    Cuda,
+    /// AMD, Intel, or NVIDIA devices of any OpenCL platform, running kernel.cl
+    Opencl,
+    /// GPUs of any vendor with a Vulkan driver, running kernel.comp's SPIR-V
+    Vulkan,
+}
+
+impl Accelerator {
+    pub fn name(self) -> &'static str {
+        match self {
+            Accelerator::Cuda => "CUDA",
+            Accelerator::Opencl => "OpenCL",
+            Accelerator::Vulkan => "Vulkan",
+        }
+    }
+}
+
+/// Passphrases a device seeds per candidate, from the start of the list. A
+/// longer list's others are seeded by the CPU threads as they reach them, a
+/// lane group at a time ([`crate::pbkdf2::SeedStream`]), so neither side
+/// holds every passphrase or a seed for each.
+pub const DEVICE_PASSPHRASES: usize = 64;
+
+/// One device as [`Gpu`] drives it: batches go out with `launch` on every
+/// device before any is waited on with `collect`. Each backend implements
+/// it over its own API.
+trait Device {
+    /// `gpu<ordinal>`, as worker stats label it.
+    fn label(&self) -> String;
+
+    fn name(&self) -> &str;
+
+    /// Relative compute, for splitting batches.
+    fn weight(&self) -> u64;
+
+    /// Starts the kernel over `indices`, `words` per candidate, without
+    /// waiting for it; [`Device::collect`] returns what it found.
+    fn launch(&mut self, indices: &[u16], words: u32) -> Result<()>;
+
+    /// Waits for the launch in flight and returns its checksum flags and
+    /// seeds.
+    fn collect(&mut self) -> Result<(Vec<u8>, Vec<u8>)>;
+}
+
+fn boxed<D: Device + 'static>(devices: Vec<D>) -> Vec<Box<dyn Device>> {
+    devices.into_iter().map(|device| Box::new(device) as Box<dyn Device>).collect()
+}
+
+/// GPU devices running `recover_kernel` (kernel.cu under CUDA, kernel.cl
+/// under OpenCL, kernel.comp under Vulkan) over batches of candidates: each
+/// checks its share's checksums and computes the seed of those that pass,
+/// once for each of the first [`DEVICE_PASSPHRASES`] passphrases. PBKDF2's
+/// 2048 rounds are most of the work per candidate; keys, addresses, and
+/// matching stay on the CPU threads, which derive from the seeds they
+/// return.
+///
+/// A batch is consecutive ranks, split into one consecutive run per device
+/// in proportion to its compute (compute units times clock; Vulkan doesn't
+/// report it, so its devices get equal runs), and launched on all of them
+/// before waiting on any.
+pub struct Gpu {
+    accelerator: Accelerator,
+    kernels: Vec<Box<dyn Device>>,
+    passphrases: usize,
+}
+
+impl Gpu {
+    /// Opens the devices with the given ordinals, or all of them when
+    /// `devices` is empty, under `accelerator`, or when it's `None` under
+    /// the first of CUDA, OpenCL, and Vulkan that opens. `kernel` is a file
+    /// to load in place of the kernel built into the binary: PTX for CUDA,
+    /// OpenCL C source, or SPIR-V for Vulkan. The first
+    /// [`DEVICE_PASSPHRASES`] of `passphrases` are tried with every
+    /// candidate, and seeds come back in their order.
+    pub fn open(
+        accelerator: Option<Accelerator>,
+        kernel: Option<&str>,
+        devices: &[u32],
+        passphrases: &Passphrases,
+    ) -> Result<Self> {
+        let mut wordlist = vec![0u8; 2048 * 8];
+        for (slot, word) in wordlist.chunks_exact_mut(8).zip(Language::English.word_list()) {
+            slot[..word.len()].copy_from_slice(word.as_bytes());
+        }
+        let mut salts = Vec::new();
+        let mut salt_offsets = vec![0u32];
+        for passphrase in passphrases.candidates().take(DEVICE_PASSPHRASES) {
+            salts.extend_from_slice(b"mnemonic");
+            salts.extend_from_slice(passphrase.as_bytes());
+            salt_offsets.push(salts.len() as u32);
+        }
+        let open = |accelerator| -> Result<Vec<Box<dyn Device>>> {
+            Ok(match accelerator {
+                Accelerator::Cuda => boxed(cuda::Kernel::open(kernel, devices, &wordlist, &salts, &salt_offsets)?),
+                Accelerator::Opencl => boxed(opencl::Kernel::open(kernel, devices, &wordlist, &salts, &salt_offsets)?),
+                Accelerator::Vulkan => boxed(vulkan::Kernel::open(kernel, devices, &wordlist, &salts, &salt_offsets)?),
+            })
+        };
+        let (accelerator, kernels) = match accelerator {
+            Some(accelerator) => (accelerator, open(accelerator)?),
+            None => {
+                let mut failures = Vec::new();
+                let mut opened = None;
+                for accelerator in [Accelerator::Cuda, Accelerator::Opencl, Accelerator::Vulkan] {
+                    match open(accelerator) {
+                        Ok(kernels) => {
+                            opened = Some((accelerator, kernels));
+                            break;
+                        }
+                        Err(e) => failures.push(format!("{}: {}", accelerator.name(), e)),
+                    }
+                }
+                opened.ok_or_else(|| anyhow::anyhow!("{}", failures.join("; ")))?
+            }
+        };
+        Ok(Self { accelerator, kernels, passphrases: salt_offsets.len() - 1 })
+    }
+
+    pub fn accelerator(&self) -> Accelerator {
+        self.accelerator
+    }
+
+    /// `gpu<ordinal> (<device name>)` for each device in use.
+    pub fn names(&self) -> Vec<String> {
+        self.kernels.iter().map(|kernel| format!("{} ({})", kernel.label(), kernel.name())).collect()
+    }
+
+    /// `gpu<ordinal>` for each device in use, as worker stats label them.
+    pub fn labels(&self) -> Vec<String> {
+        self.kernels.iter().map(|kernel| kernel.label()).collect()
+    }
+
+    /// Seeds for each candidate of `batch` (the words after `fixed`), one per
+    /// passphrase the device takes. `None` leaves a candidate to the CPU: the device ruled it
+    /// out, or it isn't one the device takes (words outside the wordlist, or
+    /// a length other than the batch's). Each device's share is counted in
+    /// `stats` under its label.
+    pub fn seeds(
+        &mut self,
+        fixed: &[String],
+        batch: &[Option<Vec<String>>],
+        stats: &WorkerStats,
+    ) -> Result<Vec<Option<Vec<Seed>>>> {
+        let words = fixed.len() + batch.iter().flatten().map(Vec::len).next().unwrap_or(0);
+        let mut indices: Vec<u16> = Vec::with_capacity(batch.len() * words);
+        let mut slots = Vec::with_capacity(batch.len());
+        for candidate in batch {
+            let candidate = candidate.as_ref().filter(|perm| fixed.len() + perm.len() == words).and_then(|perm| {
+                fixed.iter().chain(perm).map(|word| Language::English.find_word(word)).collect::<Option<Vec<u16>>>()
+            });
+            slots.push(candidate.map(|candidate| {
+                indices.extend(candidate);
+                indices.len() / words - 1
+            }));
+        }
+        if indices.is_empty() {
+            return Ok(vec![None; batch.len()]);
+        }
+
+        // Shares in proportion to compute, the rounding left over to the first
+        let count = indices.len() / words;
+        let total: u64 = self.kernels.iter().map(|kernel| kernel.weight()).sum();
+        let mut shares: Vec<usize> = self
+            .kernels
+            .iter()
+            .map(|kernel| (count as u64 * kernel.weight() / total.max(1)) as usize)
+            .collect();
+        shares[0] += count - shares.iter().sum::<usize>();
+        let mut start = 0;
+        for (kernel, &share) in self.kernels.iter_mut().zip(&shares) {
+            kernel.launch(&indices[start * words..(start + share) * words], words as u32)?;
+            start += share;
+        }
+        let (mut valid, mut seeds) = (Vec::with_capacity(count), Vec::new());
+        for (kernel, &share) in self.kernels.iter_mut().zip(&shares) {
+            let (own_valid, own_seeds) = kernel.collect()?;
+            valid.extend(own_valid);
+            seeds.extend(own_seeds);
+            stats.record_device(&kernel.label(), share as u64);
+        }
+
+        let per_candidate = self.passphrases * 64;
+        Ok(slots
+            .into_iter()
+            .map(|slot| {
+                let slot = slot.filter(|&slot| valid[slot] != 0)?;
+                let own = &seeds[slot * per_candidate..(slot + 1) * per_candidate];
+                Some(own.chunks_exact(64).map(|seed| seed.try_into().expect("64-byte chunk")).collect())
+            })
+            .collect())
+    }
+}
+
+/// The CUDA driver API as libcuda exports it, declared here rather than
+/// through a binding crate; only what [`Kernel`](cuda::Kernel) calls.
+#[cfg(feature = "cuda")]
+mod cu {
+    use std::ffi::{c_char, c_void};
+
+    pub type Handle = *mut c_void;
+    pub type DevicePtr = u64;
+
+    pub const SUCCESS: i32 = 0;
+    pub const DEVICE_ATTRIBUTE_CLOCK_RATE: i32 = 13;
+    pub const DEVICE_ATTRIBUTE_MULTIPROCESSOR_COUNT: i32 = 16;
+    pub const CTX_SCHED_AUTO: u32 = 0;
+    pub const CTX_MAP_HOST: u32 = 0x08;
+    pub const STREAM_NON_BLOCKING: u32 = 0x1;
+
+    #[cfg_attr(windows, link(name = "nvcuda"))]
+    #[cfg_attr(not(windows), link(name = "cuda"))]
+    extern "C" {
+        pub fn cuInit(flags: u32) -> i32;
+        pub fn cuGetErrorName(error: i32, name: *mut *const c_char) -> i32;
+        pub fn cuDeviceGetCount(count: *mut i32) -> i32;
+        pub fn cuDeviceGet(device: *mut i32, ordinal: i32) -> i32;
+        pub fn cuDeviceGetName(name: *mut c_char, len: i32, device: i32) -> i32;
+        pub fn cuDeviceGetAttribute(value: *mut i32, attribute: i32, device: i32) -> i32;
+        pub fn cuCtxCreate_v2(context: *mut Handle, flags: u32, device: i32) -> i32;
+        pub fn cuCtxDestroy_v2(context: Handle) -> i32;
+        pub fn cuCtxSetCurrent(context: Handle) -> i32;
+        pub fn cuModuleLoadData(module: *mut Handle, image: *const c_void) -> i32;
+        pub fn cuModuleUnload(module: Handle) -> i32;
+        pub fn cuModuleGetFunction(function: *mut Handle, module: Handle, name: *const c_char) -> i32;
+        pub fn cuStreamCreate(stream: *mut Handle, flags: u32) -> i32;
+        pub fn cuStreamDestroy_v2(stream: Handle) -> i32;
+        pub fn cuStreamSynchronize(stream: Handle) -> i32;
+        pub fn cuMemAlloc_v2(pointer: *mut DevicePtr, size: usize) -> i32;
+        pub fn cuMemFree_v2(pointer: DevicePtr) -> i32;
+        pub fn cuMemcpyHtoD_v2(device: DevicePtr, host: *const c_void, size: usize) -> i32;
+        pub fn cuMemcpyDtoH_v2(host: *mut c_void, device: DevicePtr, size: usize) -> i32;
+        pub fn cuLaunchKernel(
+            function: Handle,
+            grid_x: u32,
+            grid_y: u32,
+            grid_z: u32,
+            block_x: u32,
+            block_y: u32,
+            block_z: u32,
+            shared_bytes: u32,
+            stream: Handle,
+            params: *mut *mut c_void,
+            extra: *mut *mut c_void,
+        ) -> i32;
+    }
+}
+
+#[cfg(feature = "cuda")]
+mod cuda {
+    use std::ffi::{c_char, c_void, CStr, CString};
+    use std::fs;
+    use std::mem;
+    use std::ptr;
+
+    use anyhow::Result;
+
+    use super::cu::{self, DevicePtr, Handle};
+    use super::Device;
+
+    const THREADS_PER_BLOCK: u32 = 128;
+
+    /// kernel.cu, compiled to PTX by build.rs.
+    const PTX: &str = include_str!(concat!(env!("OUT_DIR"), "/kernel.ptx"));
+
+    fn check(code: i32, call: &str) -> Result<()> {
+        if code == cu::SUCCESS {
+            return Ok(());
+        }
+        let mut name: *const c_char = ptr::null();
+        let name = if unsafe { cu::cuGetErrorName(code, &mut name) } == cu::SUCCESS && !name.is_null() {
+            unsafe { CStr::from_ptr(name) }.to_string_lossy().into_owned()
+        } else {
+            format!("CUDA error {}", code)
+        };
+        Err(anyhow::anyhow!("{} failed with {}", call, name))
+    }
+
+    /// A context, module, or stream, released when dropped.
+    struct Object {
+        handle: Handle,
+        release: unsafe extern "C" fn(Handle) -> i32,
+    }
+
+    impl Object {
+        /// Takes the handle a `cu*Create`/`cu*Load` call filled in along
+        /// with its result.
+        fn new(handle: Handle, code: i32, release: unsafe extern "C" fn(Handle) -> i32, call: &str) -> Result<Self> {
+            check(code, call)?;
+            if handle.is_null() {
+                return Err(anyhow::anyhow!("{} returned no object", call));
+            }
+            Ok(Self { handle, release })
+        }
+    }
+
+    impl Drop for Object {
+        fn drop(&mut self) {
+            unsafe {
+                (self.release)(self.handle);
+            }
+        }
+    }
+
+    /// Device memory, freed when dropped.
+    struct Buffer {
+        pointer: DevicePtr,
+        len: usize,
+    }
+
+    impl Buffer {
+        fn alloc(len: usize) -> Result<Self> {
+            let mut pointer = 0;
+            // Zero bytes isn't an allocation the driver makes
+            check(unsafe { cu::cuMemAlloc_v2(&mut pointer, len.max(1)) }, "cuMemAlloc")?;
+            Ok(Self { pointer, len })
+        }
+
+        fn upload<T>(data: &[T]) -> Result<Self> {
+            let buffer = Self::alloc(mem::size_of_val(data))?;
+            if buffer.len > 0 {
+                check(
+                    unsafe { cu::cuMemcpyHtoD_v2(buffer.pointer, data.as_ptr() as *const c_void, buffer.len) },
+                    "cuMemcpyHtoD",
+                )?;
+            }
+            Ok(buffer)
+        }
+
+        fn download(&self) -> Result<Vec<u8>> {
+            let mut host = vec![0u8; self.len];
+            if self.len > 0 {
+                check(
+                    unsafe { cu::cuMemcpyDtoH_v2(host.as_mut_ptr() as *mut c_void, self.pointer, self.len) },
+                    "cuMemcpyDtoH",
+                )?;
+            }
+            Ok(host)
+        }
+    }
+
+    impl Drop for Buffer {
+        fn drop(&mut self) {
+            unsafe {
+                cu::cuMemFree_v2(self.pointer);
+            }
+        }
+    }
+
+    /// The PTX in the file at `path`, or the built-in kernel's.
+    fn load_ptx(path: Option<&str>) -> Result<CString> {
+        let text = match path {
+            Some(path) => fs::read_to_string(path).map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path, e))?,
+            None => PTX.to_string(),
+        };
+        CString::new(text)
+            .map_err(|e| anyhow::anyhow!("Invalid PTX in {}: {}", path.unwrap_or("the built-in kernel"), e))
+    }
+
+    fn attribute(device: i32, attribute: i32) -> Result<i32> {
+        let mut value = 0;
+        check(unsafe { cu::cuDeviceGetAttribute(&mut value, attribute, device) }, "cuDeviceGetAttribute")?;
+        Ok(value)
+    }
+
+    pub struct Kernel {
+        ordinal: u32,
+        name: String,
+        weight: u64,
+        function: Handle,
+        wordlist: Buffer,
+        salts: Buffer,
+        salt_offsets: Buffer,
+        salt_count: u32,
+        // Buffers of the launch in flight, until it's collected
+        pending: Option<Pending>,
+        stream: Object,
+        _module: Object,
+        // Dropped last, after everything allocated in it
+        context: Object,
+    }
+
+    struct Pending {
+        _input: Buffer,
+        valid: Buffer,
+        seeds: Buffer,
+    }
+
+    impl Kernel {
+        /// One kernel per device in `devices`, or per device present when
+        /// it's empty, loaded from the PTX file `ptx`, or from the built-in
+        /// kernel.
+        pub fn open(
+            ptx: Option<&str>,
+            devices: &[u32],
+            wordlist: &[u8],
+            salts: &[u8],
+            salt_offsets: &[u32],
+        ) -> Result<Vec<Self>> {
+            let ptx = load_ptx(ptx)?;
+            check(unsafe { cu::cuInit(0) }, "cuInit")?;
+            let mut present = 0;
+            check(unsafe { cu::cuDeviceGetCount(&mut present) }, "cuDeviceGetCount")?;
+            if present <= 0 {
+                return Err(anyhow::anyhow!("no CUDA device found"));
+            }
+            let present = present as u32;
+            let mut ordinals: Vec<u32> = if devices.is_empty() { (0..present).collect() } else { devices.to_vec() };
+            ordinals.sort_unstable();
+            ordinals.dedup();
+            let entry = CString::new("recover_kernel").expect("no NUL");
+            let mut kernels = Vec::with_capacity(ordinals.len());
+            for ordinal in ordinals {
+                if ordinal >= present {
+                    return Err(anyhow::anyhow!("no CUDA device {} ({} present, numbered from 0)", ordinal, present));
+                }
+                let mut device = 0;
+                check(unsafe { cu::cuDeviceGet(&mut device, ordinal as i32) }, "cuDeviceGet")?;
+                let mut name = [0 as c_char; 256];
+                check(unsafe { cu::cuDeviceGetName(name.as_mut_ptr(), name.len() as i32, device) }, "cuDeviceGetName")?;
+                let name = unsafe { CStr::from_ptr(name.as_ptr()) }.to_string_lossy().trim().to_string();
+                // Creating a context makes it current on this thread, so what
+                // follows is loaded and allocated in it
+                let mut handle = ptr::null_mut();
+                let code = unsafe { cu::cuCtxCreate_v2(&mut handle, cu::CTX_MAP_HOST | cu::CTX_SCHED_AUTO, device) };
+                let context = Object::new(handle, code, cu::cuCtxDestroy_v2, "cuCtxCreate")?;
+                let mut handle = ptr::null_mut();
+                let code = unsafe { cu::cuModuleLoadData(&mut handle, ptx.as_ptr() as *const c_void) };
+                let module = Object::new(handle, code, cu::cuModuleUnload, "cuModuleLoadData")?;
+                let mut function = ptr::null_mut();
+                check(
+                    unsafe { cu::cuModuleGetFunction(&mut function, module.handle, entry.as_ptr()) },
+                    "cuModuleGetFunction",
+                )?;
+                let mut handle = ptr::null_mut();
+                let code = unsafe { cu::cuStreamCreate(&mut handle, cu::STREAM_NON_BLOCKING) };
+                let stream = Object::new(handle, code, cu::cuStreamDestroy_v2, "cuStreamCreate")?;
+                let multiprocessors = attribute(device, cu::DEVICE_ATTRIBUTE_MULTIPROCESSOR_COUNT)?;
+                let clock = attribute(device, cu::DEVICE_ATTRIBUTE_CLOCK_RATE)?;
+                kernels.push(Self {
+                    ordinal,
+                    name,
+                    weight: (multiprocessors.max(1) as u64) * (clock.max(1) as u64),
+                    function,
+                    wordlist: Buffer::upload(wordlist)?,
+                    salts: Buffer::upload(salts)?,
+                    salt_offsets: Buffer::upload(salt_offsets)?,
+                    salt_count: (salt_offsets.len() - 1) as u32,
+                    pending: None,
+                    stream,
+                    _module: module,
+                    context,
+                });
+            }
+            Ok(kernels)
+        }
+
+        fn make_current(&self) -> Result<()> {
+            check(unsafe { cu::cuCtxSetCurrent(self.context.handle) }, "cuCtxSetCurrent")
+        }
+    }
+
+    impl Drop for Kernel {
+        // The buffers, stream, and module are freed in the device's context
+        fn drop(&mut self) {
+            let _ = self.make_current();
+        }
+    }
+
+    impl Device for Kernel {
+        fn label(&self) -> String {
+            format!("gpu{}", self.ordinal)
+        }
+
+        fn name(&self) -> &str {
+            &self.name
+        }
+
+        fn weight(&self) -> u64 {
+            self.weight
+        }
+
+        fn launch(&mut self, indices: &[u16], words: u32) -> Result<()> {
+            let count = indices.len() / words as usize;
+            let seeds_len = count * self.salt_count as usize * 64;
+            if count == 0 {
+                self.pending = None;
+                return Ok(());
+            }
+            self.make_current()?;
+            let input = Buffer::upload(indices)?;
+            let valid = Buffer::alloc(count)?;
+            let seeds = Buffer::alloc(seeds_len)?;
+            let blocks = (count as u32).div_ceil(THREADS_PER_BLOCK);
+            // The kernel's arguments, in order, each passed by its address
+            let count = count as u32;
+            let mut params: [*mut c_void; 9] = [
+                &input.pointer as *const DevicePtr as *mut c_void,
+                &count as *const u32 as *mut c_void,
+                &words as *const u32 as *mut c_void,
+                &self.wordlist.pointer as *const DevicePtr as *mut c_void,
+                &self.salts.pointer as *const DevicePtr as *mut c_void,
+                &self.salt_offsets.pointer as *const DevicePtr as *mut c_void,
+                &self.salt_count as *const u32 as *mut c_void,
+                &valid.pointer as *const DevicePtr as *mut c_void,
+                &seeds.pointer as *const DevicePtr as *mut c_void,
+            ];
+            let code = unsafe {
+                cu::cuLaunchKernel(
+                    self.function,
+                    blocks,
+                    1,
+                    1,
+                    THREADS_PER_BLOCK,
+                    1,
+                    1,
+                    0,
+                    self.stream.handle,
+                    params.as_mut_ptr(),
+                    ptr::null_mut(),
+                )
+            };
+            check(code, "cuLaunchKernel")?;
+            self.pending = Some(Pending { _input: input, valid, seeds });
+            Ok(())
+        }
+
+        fn collect(&mut self) -> Result<(Vec<u8>, Vec<u8>)> {
+            let Some(pending) = self.pending.take() else {
+                return Ok((Vec::new(), Vec::new()));
+            };
+            self.make_current()?;
+            check(unsafe { cu::cuStreamSynchronize(self.stream.handle) }, "cuStreamSynchronize")?;
+            Ok((pending.valid.download()?, pending.seeds.download()?))
+        }
+    }
+}
+
+#[cfg(not(feature = "cuda"))]
+mod cuda {
+    use anyhow::Result;
+
+    use super::Device;
+
+    /// Stands in for the device in builds without CUDA; it can't be opened.
+    pub enum Kernel {}
+
+    impl Kernel {
+        pub fn open(
+            _ptx: Option<&str>,
+            _devices: &[u32],
+            _wordlist: &[u8],
+            _salts: &[u8],
+            _salt_offsets: &[u32],
+        ) -> Result<Vec<Self>> {
+            Err(anyhow::anyhow!("this build has no CUDA support; rebuild with --features cuda"))
+        }
+    }
+
+    impl Device for Kernel {
+        fn label(&self) -> String {
+            match *self {}
+        }
+
+        fn name(&self) -> &str {
+            match *self {}
+        }
+
+        fn weight(&self) -> u64 {
+            match *self {}
+        }
+
+        fn launch(&mut self, _indices: &[u16], _words: u32) -> Result<()> {
+            match *self {}
+        }
+
+        fn collect(&mut self) -> Result<(Vec<u8>, Vec<u8>)> {
+            match *self {}
+        }
+    }
+}
+
+/// The OpenCL API as the ICD loader exports it, declared here rather than
+/// through a binding crate; only what [`Kernel`](opencl::Kernel) calls.
+#[cfg(feature = "opencl")]
+#[allow(non_snake_case)]
+mod cl {
+    use std::ffi::{c_char, c_void};
+
+    pub type Handle = *mut c_void;
+    pub type Notify = Option<unsafe extern "C" fn(*const c_char, *const c_void, usize, *mut c_void)>;
+
+    pub const SUCCESS: i32 = 0;
+    pub const DEVICE_NOT_FOUND: i32 = -1;
+    pub const BUILD_PROGRAM_FAILURE: i32 = -11;
+    pub const DEVICE_TYPE_GPU: u64 = 1 << 2;
+    pub const DEVICE_TYPE_ACCELERATOR: u64 = 1 << 3;
+    pub const DEVICE_MAX_COMPUTE_UNITS: u32 = 0x1002;
+    pub const DEVICE_MAX_CLOCK_FREQUENCY: u32 = 0x100C;
+    pub const DEVICE_NAME: u32 = 0x102B;
+    pub const PROGRAM_BUILD_LOG: u32 = 0x1183;
+    pub const MEM_WRITE_ONLY: u64 = 1 << 1;
+    pub const MEM_READ_ONLY: u64 = 1 << 2;
+    pub const MEM_COPY_HOST_PTR: u64 = 1 << 5;
+    pub const TRUE: u32 = 1;
+
+    #[cfg_attr(target_os = "macos", link(name = "OpenCL", kind = "framework"))]
+    #[cfg_attr(not(target_os = "macos"), link(name = "OpenCL"))]
+    extern "C" {
+        pub fn clGetPlatformIDs(entries: u32, platforms: *mut Handle, count: *mut u32) -> i32;
+        pub fn clGetDeviceIDs(platform: Handle, kind: u64, entries: u32, devices: *mut Handle, count: *mut u32) -> i32;
+        pub fn clGetDeviceInfo(device: Handle, param: u32, size: usize, value: *mut c_void, size_ret: *mut usize) -> i32;
+        pub fn clCreateContext(
+            properties: *const isize,
+            count: u32,
+            devices: *const Handle,
+            notify: Notify,
+            user_data: *mut c_void,
+            error: *mut i32,
+        ) -> Handle;
+        pub fn clCreateCommandQueue(context: Handle, device: Handle, properties: u64, error: *mut i32) -> Handle;
+        pub fn clCreateProgramWithSource(
+            context: Handle,
+            count: u32,
+            strings: *const *const c_char,
+            lengths: *const usize,
+            error: *mut i32,
+        ) -> Handle;
+        pub fn clBuildProgram(
+            program: Handle,
+            count: u32,
+            devices: *const Handle,
+            options: *const c_char,
+            notify: Option<unsafe extern "C" fn(Handle, *mut c_void)>,
+            user_data: *mut c_void,
+        ) -> i32;
+        pub fn clGetProgramBuildInfo(
+            program: Handle,
+            device: Handle,
+            param: u32,
+            size: usize,
+            value: *mut c_void,
+            size_ret: *mut usize,
+        ) -> i32;
+        pub fn clCreateKernel(program: Handle, name: *const c_char, error: *mut i32) -> Handle;
+        pub fn clCreateBuffer(context: Handle, flags: u64, size: usize, host: *mut c_void, error: *mut i32) -> Handle;
+        pub fn clSetKernelArg(kernel: Handle, index: u32, size: usize, value: *const c_void) -> i32;
+        pub fn clEnqueueNDRangeKernel(
+            queue: Handle,
+            kernel: Handle,
+            dimensions: u32,
+            offset: *const usize,
+            global: *const usize,
+            local: *const usize,
+            waits: u32,
+            wait_list: *const Handle,
+            event: *mut Handle,
+        ) -> i32;
+        pub fn clEnqueueReadBuffer(
+            queue: Handle,
+            buffer: Handle,
+            blocking: u32,
+            offset: usize,
+            size: usize,
+            host: *mut c_void,
+            waits: u32,
+            wait_list: *const Handle,
+            event: *mut Handle,
+        ) -> i32;
+        pub fn clFlush(queue: Handle) -> i32;
+        pub fn clFinish(queue: Handle) -> i32;
+        pub fn clReleaseMemObject(buffer: Handle) -> i32;
+        pub fn clReleaseKernel(kernel: Handle) -> i32;
+        pub fn clReleaseProgram(program: Handle) -> i32;
+        pub fn clReleaseCommandQueue(queue: Handle) -> i32;
+        pub fn clReleaseContext(context: Handle) -> i32;
+    }
+}
+
+#[cfg(feature = "opencl")]
+mod opencl {
+    use std::ffi::{c_void, CString};
+    use std::fs;
+    use std::mem;
+    use std::ptr;
+
+    use anyhow::Result;
+
+    use super::cl::{self, Handle};
+    use super::Device;
+
+    /// kernel.cl, built into the binary so OpenCL needs nothing beside it;
+    /// the driver compiles it for each device when it's opened.
+    const SOURCE: &str = include_str!("../kernel.cl");
+
+    fn check(code: i32, call: &str) -> Result<()> {
+        if code == cl::SUCCESS {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("{} failed with OpenCL error {}", call, code))
+        }
+    }
+
+    /// A context, queue, program, kernel, or buffer, released when dropped.
+    struct Object {
+        handle: Handle,
+        release: unsafe extern "C" fn(Handle) -> i32,
+    }
+
+    impl Object {
+        /// Takes `handle` as returned by a `clCreate*` call along with its
+        /// error code.
+        fn new(handle: Handle, error: i32, release: unsafe extern "C" fn(Handle) -> i32, call: &str) -> Result<Self> {
+            check(error, call)?;
+            if handle.is_null() {
+                return Err(anyhow::anyhow!("{} returned no object", call));
+            }
+            Ok(Self { handle, release })
+        }
+    }
+
+    impl Drop for Object {
+        fn drop(&mut self) {
+            unsafe {
+                (self.release)(self.handle);
+            }
+        }
+    }
+
+    /// A read-only device buffer holding a copy of `data`.
+    fn upload<T>(context: &Object, data: &[T]) -> Result<Object> {
+        let mut error = 0;
+        let buffer = unsafe {
+            cl::clCreateBuffer(
+                context.handle,
+                cl::MEM_READ_ONLY | cl::MEM_COPY_HOST_PTR,
+                mem::size_of_val(data),
+                data.as_ptr() as *mut c_void,
+                &mut error,
+            )
+        };
+        Object::new(buffer, error, cl::clReleaseMemObject, "clCreateBuffer")
+    }
+
+    fn output(context: &Object, size: usize) -> Result<Object> {
+        let mut error = 0;
+        let buffer = unsafe { cl::clCreateBuffer(context.handle, cl::MEM_WRITE_ONLY, size, ptr::null_mut(), &mut error) };
+        Object::new(buffer, error, cl::clReleaseMemObject, "clCreateBuffer")
+    }
+
+    fn read(queue: &Object, buffer: &Object, into: &mut [u8]) -> Result<()> {
+        let code = unsafe {
+            cl::clEnqueueReadBuffer(
+                queue.handle,
+                buffer.handle,
+                cl::TRUE,
+                0,
+                into.len(),
+                into.as_mut_ptr() as *mut c_void,
+                0,
+                ptr::null(),
+                ptr::null_mut(),
+            )
+        };
+        check(code, "clEnqueueReadBuffer")
+    }
+
+    fn device_info(device: Handle, param: u32) -> Result<Vec<u8>> {
+        let mut size = 0;
+        check(
+            unsafe { cl::clGetDeviceInfo(device, param, 0, ptr::null_mut(), &mut size) },
+            "clGetDeviceInfo",
+        )?;
+        let mut value = vec![0u8; size];
+        check(
+            unsafe { cl::clGetDeviceInfo(device, param, size, value.as_mut_ptr() as *mut c_void, ptr::null_mut()) },
+            "clGetDeviceInfo",
+        )?;
+        Ok(value)
+    }
+
+    fn device_u32(device: Handle, param: u32) -> Result<u32> {
+        let value = device_info(device, param)?;
+        let bytes = value.get(..4).ok_or_else(|| anyhow::anyhow!("clGetDeviceInfo returned {} bytes", value.len()))?;
+        Ok(u32::from_ne_bytes(bytes.try_into().expect("4 bytes")))
+    }
+
+    /// GPUs and accelerators of every platform, numbered from 0 in
+    /// platform order.
+    fn present() -> Result<Vec<Handle>> {
+        let mut count = 0;
+        check(unsafe { cl::clGetPlatformIDs(0, ptr::null_mut(), &mut count) }, "clGetPlatformIDs")?;
+        let mut platforms = vec![ptr::null_mut(); count as usize];
+        check(
+            unsafe { cl::clGetPlatformIDs(count, platforms.as_mut_ptr(), ptr::null_mut()) },
+            "clGetPlatformIDs",
+        )?;
+        let kind = cl::DEVICE_TYPE_GPU | cl::DEVICE_TYPE_ACCELERATOR;
+        let mut devices = Vec::new();
+        for platform in platforms {
+            let mut count = 0;
+            match unsafe { cl::clGetDeviceIDs(platform, kind, 0, ptr::null_mut(), &mut count) } {
+                cl::DEVICE_NOT_FOUND => continue,
+                code => check(code, "clGetDeviceIDs")?,
+            }
+            let mut own = vec![ptr::null_mut(); count as usize];
+            check(
+                unsafe { cl::clGetDeviceIDs(platform, kind, count, own.as_mut_ptr(), ptr::null_mut()) },
+                "clGetDeviceIDs",
+            )?;
+            devices.extend(own);
+        }
+        Ok(devices)
+    }
+
+    /// The compiler's output for `device`, for a build that failed.
+    fn build_log(program: &Object, device: Handle) -> String {
+        let mut size = 0;
+        let mut log = Vec::new();
+        unsafe {
+            if cl::clGetProgramBuildInfo(program.handle, device, cl::PROGRAM_BUILD_LOG, 0, ptr::null_mut(), &mut size)
+                == cl::SUCCESS
+            {
+                log.resize(size, 0u8);
+                cl::clGetProgramBuildInfo(
+                    program.handle,
+                    device,
+                    cl::PROGRAM_BUILD_LOG,
+                    size,
+                    log.as_mut_ptr() as *mut c_void,
+                    ptr::null_mut(),
+                );
+            }
+        }
+        String::from_utf8_lossy(&log).trim_end_matches('\0').trim().to_string()
+    }
+
+    pub struct Kernel {
+        ordinal: u32,
+        name: String,
+        weight: u64,
+        kernel: Object,
+        wordlist: Object,
+        salts: Object,
+        salt_offsets: Object,
+        salt_count: u32,
+        // Buffers of the launch in flight, until it's collected
+        pending: Option<Pending>,
+        queue: Object,
+        _program: Object,
+        context: Object,
+    }
+
+    struct Pending {
+        count: usize,
+        _input: Object,
+        valid: Object,
+        seeds: Object,
+        seeds_len: usize,
+    }
+
+    impl Kernel {
+        /// One kernel per device in `devices`, or per device present when
+        /// it's empty, built from the OpenCL C in `source` (a path), or from
+        /// the built-in kernel.cl.
+        pub fn open(
+            source: Option<&str>,
+            devices: &[u32],
+            wordlist: &[u8],
+            salts: &[u8],
+            salt_offsets: &[u32],
+        ) -> Result<Vec<Self>> {
+            let source = match source {
+                Some(path) => fs::read_to_string(path).map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path, e))?,
+                None => SOURCE.to_string(),
+            };
+            let present = present()?;
+            if present.is_empty() {
+                return Err(anyhow::anyhow!("no OpenCL GPU found"));
+            }
+            let mut ordinals: Vec<u32> =
+                if devices.is_empty() { (0..present.len() as u32).collect() } else { devices.to_vec() };
+            ordinals.sort_unstable();
+            ordinals.dedup();
+            let entry = CString::new("recover_kernel").expect("no NUL");
+            let mut kernels = Vec::with_capacity(ordinals.len());
+            for ordinal in ordinals {
+                let Some(&device) = present.get(ordinal as usize) else {
+                    return Err(anyhow::anyhow!(
+                        "no OpenCL device {} ({} present, numbered from 0)",
+                        ordinal,
+                        present.len()
+                    ));
+                };
+                let name = String::from_utf8_lossy(&device_info(device, cl::DEVICE_NAME)?)
+                    .trim_end_matches('\0')
+                    .trim()
+                    .to_string();
+                let mut error = 0;
+                let context = unsafe {
+                    cl::clCreateContext(ptr::null(), 1, &device, None, ptr::null_mut(), &mut error)
+                };
+                let context = Object::new(context, error, cl::clReleaseContext, "clCreateContext")?;
+                let queue = unsafe { cl::clCreateCommandQueue(context.handle, device, 0, &mut error) };
+                let queue = Object::new(queue, error, cl::clReleaseCommandQueue, "clCreateCommandQueue")?;
+                let (text, len) = (source.as_ptr() as *const _, source.len());
+                let program = unsafe { cl::clCreateProgramWithSource(context.handle, 1, &text, &len, &mut error) };
+                let program = Object::new(program, error, cl::clReleaseProgram, "clCreateProgramWithSource")?;
+                let code =
+                    unsafe { cl::clBuildProgram(program.handle, 1, &device, ptr::null(), None, ptr::null_mut()) };
+                if code == cl::BUILD_PROGRAM_FAILURE {
+                    return Err(anyhow::anyhow!(
+                        "failed to build the kernel for {}: {}",
+                        name,
+                        build_log(&program, device)
+                    ));
+                }
+                check(code, "clBuildProgram")?;
+                let kernel = unsafe { cl::clCreateKernel(program.handle, entry.as_ptr(), &mut error) };
+                let kernel = Object::new(kernel, error, cl::clReleaseKernel, "clCreateKernel")?;
+                let units = device_u32(device, cl::DEVICE_MAX_COMPUTE_UNITS)?;
+                let clock = device_u32(device, cl::DEVICE_MAX_CLOCK_FREQUENCY)?;
+                kernels.push(Self {
+                    ordinal,
+                    name,
+                    weight: u64::from(units.max(1)) * u64::from(clock.max(1)),
+                    kernel,
+                    wordlist: upload(&context, wordlist)?,
+                    salts: upload(&context, salts)?,
+                    salt_offsets: upload(&context, salt_offsets)?,
+                    salt_count: (salt_offsets.len() - 1) as u32,
+                    pending: None,
+                    queue,
+                    _program: program,
+                    context,
+                });
+            }
+            Ok(kernels)
+        }
+
+        fn set_arg<T>(&self, index: u32, value: &T) -> Result<()> {
+            let code = unsafe {
+                cl::clSetKernelArg(self.kernel.handle, index, mem::size_of::<T>(), value as *const T as *const c_void)
+            };
+            check(code, "clSetKernelArg")
+        }
+    }
+
+    impl Device for Kernel {
+        fn label(&self) -> String {
+            format!("gpu{}", self.ordinal)
+        }
+
+        fn name(&self) -> &str {
+            &self.name
+        }
+
+        fn weight(&self) -> u64 {
+            self.weight
+        }
+
+        fn launch(&mut self, indices: &[u16], words: u32) -> Result<()> {
+            let count = indices.len() / words as usize;
+            let seeds_len = count * self.salt_count as usize * 64;
+            if count == 0 {
+                self.pending = None;
+                return Ok(());
+            }
+            let input = upload(&self.context, indices)?;
+            let valid = output(&self.context, count)?;
+            let seeds = output(&self.context, seeds_len.max(1))?;
+            self.set_arg(0, &input.handle)?;
+            self.set_arg(1, &(count as u32))?;
+            self.set_arg(2, &words)?;
+            self.set_arg(3, &self.wordlist.handle)?;
+            self.set_arg(4, &self.salts.handle)?;
+            self.set_arg(5, &self.salt_offsets.handle)?;
+            self.set_arg(6, &self.salt_count)?;
+            self.set_arg(7, &valid.handle)?;
+            self.set_arg(8, &seeds.handle)?;
+            let global = count;
+            let code = unsafe {
+                cl::clEnqueueNDRangeKernel(
+                    self.queue.handle,
+                    self.kernel.handle,
+                    1,
+                    ptr::null(),
+                    &global,
+                    ptr::null(),
+                    0,
+                    ptr::null(),
+                    ptr::null_mut(),
+                )
+            };
+            check(code, "clEnqueueNDRangeKernel")?;
+            check(unsafe { cl::clFlush(self.queue.handle) }, "clFlush")?;
+            self.pending = Some(Pending { count, _input: input, valid, seeds, seeds_len });
+            Ok(())
+        }
+
+        fn collect(&mut self) -> Result<(Vec<u8>, Vec<u8>)> {
+            let Some(pending) = self.pending.take() else {
+                return Ok((Vec::new(), Vec::new()));
+            };
+            check(unsafe { cl::clFinish(self.queue.handle) }, "clFinish")?;
+            let mut valid = vec![0u8; pending.count];
+            let mut seeds = vec![0u8; pending.seeds_len];
+            read(&self.queue, &pending.valid, &mut valid)?;
+            if !seeds.is_empty() {
+                read(&self.queue, &pending.seeds, &mut seeds)?;
+            }
+            Ok((valid, seeds))
+        }
+    }
+}
+
+#[cfg(not(feature = "opencl"))]
+mod opencl {
+    use anyhow::Result;
+
+    use super::Device;
+
+    /// Stands in for the device in builds without OpenCL; it can't be opened.
+    pub enum Kernel {}
+
+    impl Kernel {
+        pub fn open(
+            _source: Option<&str>,
+            _devices: &[u32],
+            _wordlist: &[u8],
+            _salts: &[u8],
+            _salt_offsets: &[u32],
+        ) -> Result<Vec<Self>> {
+            Err(anyhow::anyhow!("this build has no OpenCL support; rebuild with --features opencl"))
+        }
+    }
+
+    impl Device for Kernel {
+        fn label(&self) -> String {
+            match *self {}
+        }
+
+        fn name(&self) -> &str {
+            match *self {}
+        }
+
+        fn weight(&self) -> u64 {
+            match *self {}
+        }
+
+        fn launch(&mut self, _indices: &[u16], _words: u32) -> Result<()> {
+            match *self {}
+        }
+
+        fn collect(&mut self) -> Result<(Vec<u8>, Vec<u8>)> {
+            match *self {}
+        }
+    }
+}
+
+/// The Vulkan API as the loader exports it, declared here rather than
+/// through a binding crate; only what [`Kernel`](vulkan::Kernel) calls, and
+/// of each structure only the fields it sets or reads.
+#[cfg(feature = "vulkan")]
+#[allow(non_snake_case)]
+mod vk {
+    use std::ffi::{c_char, c_void, CStr};
+
+    /// Instance, physical device, device, queue, or command buffer.
+    pub type Handle = *mut c_void;
+    /// Any other object; these are 64-bit on every platform.
+    pub type Object = u64;
+    type Allocator = *const c_void;
+
+    pub const SUCCESS: i32 = 0;
+    pub const INCOMPLETE: i32 = 5;
+    pub const API_VERSION_1_0: u32 = 1 << 22;
+    pub const INSTANCE_CREATE_ENUMERATE_PORTABILITY: u32 = 0x1;
+    /// Lists portability drivers such as MoltenVK, which a loader hides
+    /// from instances that don't ask for them
+    pub const KHR_PORTABILITY_ENUMERATION: &CStr = c"VK_KHR_portability_enumeration";
+    /// Must be enabled on a device that offers it
+    pub const KHR_PORTABILITY_SUBSET: &CStr = c"VK_KHR_portability_subset";
+    pub const PHYSICAL_DEVICE_TYPE_CPU: u32 = 4;
+    pub const QUEUE_COMPUTE: u32 = 0x2;
+    pub const MEMORY_PROPERTY_HOST_VISIBLE: u32 = 0x2;
+    pub const MEMORY_PROPERTY_HOST_COHERENT: u32 = 0x4;
+    pub const BUFFER_USAGE_STORAGE_BUFFER: u32 = 0x20;
+    pub const SHADER_STAGE_COMPUTE: u32 = 0x20;
+    pub const DESCRIPTOR_TYPE_STORAGE_BUFFER: u32 = 7;
+    pub const PIPELINE_BIND_POINT_COMPUTE: u32 = 1;
+    pub const COMMAND_POOL_CREATE_RESET_COMMAND_BUFFER: u32 = 0x2;
+    pub const COMMAND_BUFFER_USAGE_ONE_TIME_SUBMIT: u32 = 0x1;
+    pub const PIPELINE_STAGE_COMPUTE_SHADER: u32 = 0x800;
+    pub const PIPELINE_STAGE_HOST: u32 = 0x4000;
+    pub const ACCESS_SHADER_WRITE: u32 = 0x40;
+    pub const ACCESS_HOST_READ: u32 = 0x2000;
+    pub const WHOLE_SIZE: u64 = !0;
+    /// Index of `shaderInt64` among `VkPhysicalDeviceFeatures`' flags
+    pub const FEATURE_SHADER_INT64: usize = 40;
+
+    pub const STRUCTURE_TYPE_APPLICATION_INFO: u32 = 0;
+    pub const STRUCTURE_TYPE_INSTANCE_CREATE_INFO: u32 = 1;
+    pub const STRUCTURE_TYPE_DEVICE_QUEUE_CREATE_INFO: u32 = 2;
+    pub const STRUCTURE_TYPE_DEVICE_CREATE_INFO: u32 = 3;
+    pub const STRUCTURE_TYPE_SUBMIT_INFO: u32 = 4;
+    pub const STRUCTURE_TYPE_MEMORY_ALLOCATE_INFO: u32 = 5;
+    pub const STRUCTURE_TYPE_FENCE_CREATE_INFO: u32 = 8;
+    pub const STRUCTURE_TYPE_BUFFER_CREATE_INFO: u32 = 12;
+    pub const STRUCTURE_TYPE_SHADER_MODULE_CREATE_INFO: u32 = 16;
+    pub const STRUCTURE_TYPE_PIPELINE_SHADER_STAGE_CREATE_INFO: u32 = 18;
+    pub const STRUCTURE_TYPE_COMPUTE_PIPELINE_CREATE_INFO: u32 = 29;
+    pub const STRUCTURE_TYPE_PIPELINE_LAYOUT_CREATE_INFO: u32 = 30;
+    pub const STRUCTURE_TYPE_DESCRIPTOR_SET_LAYOUT_CREATE_INFO: u32 = 32;
+    pub const STRUCTURE_TYPE_DESCRIPTOR_POOL_CREATE_INFO: u32 = 33;
+    pub const STRUCTURE_TYPE_DESCRIPTOR_SET_ALLOCATE_INFO: u32 = 34;
+    pub const STRUCTURE_TYPE_WRITE_DESCRIPTOR_SET: u32 = 35;
+    pub const STRUCTURE_TYPE_COMMAND_POOL_CREATE_INFO: u32 = 39;
+    pub const STRUCTURE_TYPE_COMMAND_BUFFER_ALLOCATE_INFO: u32 = 40;
+    pub const STRUCTURE_TYPE_COMMAND_BUFFER_BEGIN_INFO: u32 = 42;
+    pub const STRUCTURE_TYPE_MEMORY_BARRIER: u32 = 46;
+
+    #[repr(C)]
+    pub struct ApplicationInfo {
+        pub s_type: u32,
+        pub next: *const c_void,
+        pub application_name: *const c_char,
+        pub application_version: u32,
+        pub engine_name: *const c_char,
+        pub engine_version: u32,
+        pub api_version: u32,
+    }
+
+    #[repr(C)]
+    pub struct InstanceCreateInfo {
+        pub s_type: u32,
+        pub next: *const c_void,
+        pub flags: u32,
+        pub application_info: *const ApplicationInfo,
+        pub layer_count: u32,
+        pub layers: *const *const c_char,
+        pub extension_count: u32,
+        pub extensions: *const *const c_char,
+    }
+
+    #[repr(C)]
+    pub struct ExtensionProperties {
+        pub extension_name: [c_char; 256],
+        pub spec_version: u32,
+    }
+
+    /// `VkPhysicalDeviceProperties`: the leading fields, then room for the
+    /// limits and sparse properties that follow them.
+    #[repr(C)]
+    pub struct PhysicalDeviceProperties {
+        pub api_version: u32,
+        pub driver_version: u32,
+        pub vendor_id: u32,
+        pub device_id: u32,
+        pub device_type: u32,
+        pub device_name: [c_char; 256],
+        pub pipeline_cache_uuid: [u8; 16],
+        pub rest: [u64; 128],
+    }
+
+    /// `VkPhysicalDeviceFeatures`: 55 `VkBool32` flags.
+    pub type PhysicalDeviceFeatures = [u32; 55];
+
+    #[repr(C)]
+    #[derive(Clone, Copy, Default)]
+    pub struct QueueFamilyProperties {
+        pub queue_flags: u32,
+        pub queue_count: u32,
+        pub timestamp_valid_bits: u32,
+        pub min_image_transfer_granularity: [u32; 3],
+    }
+
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    pub struct MemoryType {
+        pub property_flags: u32,
+        pub heap_index: u32,
+    }
+
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    pub struct MemoryHeap {
+        pub size: u64,
+        pub flags: u32,
+    }
+
+    #[repr(C)]
+    pub struct PhysicalDeviceMemoryProperties {
+        pub memory_type_count: u32,
+        pub memory_types: [MemoryType; 32],
+        pub memory_heap_count: u32,
+        pub memory_heaps: [MemoryHeap; 16],
+    }
+
+    #[repr(C)]
+    pub struct DeviceQueueCreateInfo {
+        pub s_type: u32,
+        pub next: *const c_void,
+        pub flags: u32,
+        pub queue_family_index: u32,
+        pub queue_count: u32,
+        pub queue_priorities: *const f32,
+    }
+
+    #[repr(C)]
+    pub struct DeviceCreateInfo {
+        pub s_type: u32,
+        pub next: *const c_void,
+        pub flags: u32,
+        pub queue_create_info_count: u32,
+        pub queue_create_infos: *const DeviceQueueCreateInfo,
+        pub layer_count: u32,
+        pub layers: *const *const c_char,
+        pub extension_count: u32,
+        pub extensions: *const *const c_char,
+        pub enabled_features: *const PhysicalDeviceFeatures,
+    }
+
+    #[repr(C)]
+    pub struct BufferCreateInfo {
+        pub s_type: u32,
+        pub next: *const c_void,
+        pub flags: u32,
+        pub size: u64,
+        pub usage: u32,
+        pub sharing_mode: u32,
+        pub queue_family_index_count: u32,
+        pub queue_family_indices: *const u32,
+    }
+
+    #[repr(C)]
+    #[derive(Default)]
+    pub struct MemoryRequirements {
+        pub size: u64,
+        pub alignment: u64,
+        pub memory_type_bits: u32,
+    }
+
+    #[repr(C)]
+    pub struct MemoryAllocateInfo {
+        pub s_type: u32,
+        pub next: *const c_void,
+        pub allocation_size: u64,
+        pub memory_type_index: u32,
+    }
+
+    #[repr(C)]
+    pub struct ShaderModuleCreateInfo {
+        pub s_type: u32,
+        pub next: *const c_void,
+        pub flags: u32,
+        pub code_size: usize,
+        pub code: *const u32,
+    }
+
+    #[repr(C)]
+    pub struct DescriptorSetLayoutBinding {
+        pub binding: u32,
+        pub descriptor_type: u32,
+        pub descriptor_count: u32,
+        pub stage_flags: u32,
+        pub immutable_samplers: *const Object,
+    }
+
+    #[repr(C)]
+    pub struct DescriptorSetLayoutCreateInfo {
+        pub s_type: u32,
+        pub next: *const c_void,
+        pub flags: u32,
+        pub binding_count: u32,
+        pub bindings: *const DescriptorSetLayoutBinding,
+    }
+
+    #[repr(C)]
+    pub struct PushConstantRange {
+        pub stage_flags: u32,
+        pub offset: u32,
+        pub size: u32,
+    }
+
+    #[repr(C)]
+    pub struct PipelineLayoutCreateInfo {
+        pub s_type: u32,
+        pub next: *const c_void,
+        pub flags: u32,
+        pub set_layout_count: u32,
+        pub set_layouts: *const Object,
+        pub push_constant_range_count: u32,
+        pub push_constant_ranges: *const PushConstantRange,
+    }
+
+    #[repr(C)]
+    pub struct PipelineShaderStageCreateInfo {
+        pub s_type: u32,
+        pub next: *const c_void,
+        pub flags: u32,
+        pub stage: u32,
+        pub module: Object,
+        pub name: *const c_char,
+        pub specialization_info: *const c_void,
+    }
+
+    #[repr(C)]
+    pub struct ComputePipelineCreateInfo {
+        pub s_type: u32,
+        pub next: *const c_void,
+        pub flags: u32,
+        pub stage: PipelineShaderStageCreateInfo,
+        pub layout: Object,
+        pub base_pipeline_handle: Object,
+        pub base_pipeline_index: i32,
+    }
+
+    #[repr(C)]
+    pub struct DescriptorPoolSize {
+        pub descriptor_type: u32,
+        pub descriptor_count: u32,
+    }
+
+    #[repr(C)]
+    pub struct DescriptorPoolCreateInfo {
+        pub s_type: u32,
+        pub next: *const c_void,
+        pub flags: u32,
+        pub max_sets: u32,
+        pub pool_size_count: u32,
+        pub pool_sizes: *const DescriptorPoolSize,
+    }
+
+    #[repr(C)]
+    pub struct DescriptorSetAllocateInfo {
+        pub s_type: u32,
+        pub next: *const c_void,
+        pub descriptor_pool: Object,
+        pub descriptor_set_count: u32,
+        pub set_layouts: *const Object,
+    }
+
+    #[repr(C)]
+    pub struct DescriptorBufferInfo {
+        pub buffer: Object,
+        pub offset: u64,
+        pub range: u64,
+    }
+
+    #[repr(C)]
+    pub struct WriteDescriptorSet {
+        pub s_type: u32,
+        pub next: *const c_void,
+        pub dst_set: Object,
+        pub dst_binding: u32,
+        pub dst_array_element: u32,
+        pub descriptor_count: u32,
+        pub descriptor_type: u32,
+        pub image_info: *const c_void,
+        pub buffer_info: *const DescriptorBufferInfo,
+        pub texel_buffer_view: *const Object,
+    }
+
+    #[repr(C)]
+    pub struct CommandPoolCreateInfo {
+        pub s_type: u32,
+        pub next: *const c_void,
+        pub flags: u32,
+        pub queue_family_index: u32,
+    }
+
+    #[repr(C)]
+    pub struct CommandBufferAllocateInfo {
+        pub s_type: u32,
+        pub next: *const c_void,
+        pub command_pool: Object,
+        pub level: u32,
+        pub command_buffer_count: u32,
+    }
+
+    #[repr(C)]
+    pub struct CommandBufferBeginInfo {
+        pub s_type: u32,
+        pub next: *const c_void,
+        pub flags: u32,
+        pub inheritance_info: *const c_void,
+    }
+
+    #[repr(C)]
+    pub struct MemoryBarrier {
+        pub s_type: u32,
+        pub next: *const c_void,
+        pub src_access_mask: u32,
+        pub dst_access_mask: u32,
+    }
+
+    #[repr(C)]
+    pub struct SubmitInfo {
+        pub s_type: u32,
+        pub next: *const c_void,
+        pub wait_semaphore_count: u32,
+        pub wait_semaphores: *const Object,
+        pub wait_dst_stage_mask: *const u32,
+        pub command_buffer_count: u32,
+        pub command_buffers: *const Handle,
+        pub signal_semaphore_count: u32,
+        pub signal_semaphores: *const Object,
+    }
+
+    #[repr(C)]
+    pub struct FenceCreateInfo {
+        pub s_type: u32,
+        pub next: *const c_void,
+        pub flags: u32,
+    }
+
+    #[cfg_attr(windows, link(name = "vulkan-1"))]
+    #[cfg_attr(not(windows), link(name = "vulkan"))]
+    extern "C" {
+        pub fn vkCreateInstance(info: *const InstanceCreateInfo, allocator: Allocator, instance: *mut Handle) -> i32;
+        pub fn vkDestroyInstance(instance: Handle, allocator: Allocator);
+        pub fn vkEnumerateInstanceExtensionProperties(
+            layer: *const c_char,
+            count: *mut u32,
+            properties: *mut ExtensionProperties,
+        ) -> i32;
+        pub fn vkEnumerateDeviceExtensionProperties(
+            physical: Handle,
+            layer: *const c_char,
+            count: *mut u32,
+            properties: *mut ExtensionProperties,
+        ) -> i32;
+        pub fn vkEnumeratePhysicalDevices(instance: Handle, count: *mut u32, devices: *mut Handle) -> i32;
+        pub fn vkGetPhysicalDeviceProperties(device: Handle, properties: *mut PhysicalDeviceProperties);
+        pub fn vkGetPhysicalDeviceFeatures(device: Handle, features: *mut PhysicalDeviceFeatures);
+        pub fn vkGetPhysicalDeviceQueueFamilyProperties(
+            device: Handle,
+            count: *mut u32,
+            properties: *mut QueueFamilyProperties,
+        );
+        pub fn vkGetPhysicalDeviceMemoryProperties(device: Handle, properties: *mut PhysicalDeviceMemoryProperties);
+        pub fn vkCreateDevice(
+            physical: Handle,
+            info: *const DeviceCreateInfo,
+            allocator: Allocator,
+            device: *mut Handle,
+        ) -> i32;
+        pub fn vkDestroyDevice(device: Handle, allocator: Allocator);
+        pub fn vkDeviceWaitIdle(device: Handle) -> i32;
+        pub fn vkGetDeviceQueue(device: Handle, family: u32, index: u32, queue: *mut Handle);
+        pub fn vkCreateBuffer(
+            device: Handle,
+            info: *const BufferCreateInfo,
+            allocator: Allocator,
+            buffer: *mut Object,
+        ) -> i32;
+        pub fn vkDestroyBuffer(device: Handle, buffer: Object, allocator: Allocator);
+        pub fn vkGetBufferMemoryRequirements(device: Handle, buffer: Object, requirements: *mut MemoryRequirements);
+        pub fn vkAllocateMemory(
+            device: Handle,
+            info: *const MemoryAllocateInfo,
+            allocator: Allocator,
+            memory: *mut Object,
+        ) -> i32;
+        pub fn vkFreeMemory(device: Handle, memory: Object, allocator: Allocator);
+        pub fn vkBindBufferMemory(device: Handle, buffer: Object, memory: Object, offset: u64) -> i32;
+        pub fn vkMapMemory(
+            device: Handle,
+            memory: Object,
+            offset: u64,
+            size: u64,
+            flags: u32,
+            data: *mut *mut c_void,
+        ) -> i32;
+        pub fn vkCreateShaderModule(
+            device: Handle,
+            info: *const ShaderModuleCreateInfo,
+            allocator: Allocator,
+            module: *mut Object,
+        ) -> i32;
+        pub fn vkDestroyShaderModule(device: Handle, module: Object, allocator: Allocator);
+        pub fn vkCreateDescriptorSetLayout(
+            device: Handle,
+            info: *const DescriptorSetLayoutCreateInfo,
+            allocator: Allocator,
+            layout: *mut Object,
+        ) -> i32;
+        pub fn vkDestroyDescriptorSetLayout(device: Handle, layout: Object, allocator: Allocator);
+        pub fn vkCreatePipelineLayout(
+            device: Handle,
+            info: *const PipelineLayoutCreateInfo,
+            allocator: Allocator,
+            layout: *mut Object,
+        ) -> i32;
+        pub fn vkDestroyPipelineLayout(device: Handle, layout: Object, allocator: Allocator);
+        pub fn vkCreateComputePipelines(
+            device: Handle,
+            cache: Object,
+            count: u32,
+            infos: *const ComputePipelineCreateInfo,
+            allocator: Allocator,
+            pipelines: *mut Object,
+        ) -> i32;
+        pub fn vkDestroyPipeline(device: Handle, pipeline: Object, allocator: Allocator);
+        pub fn vkCreateDescriptorPool(
+            device: Handle,
+            info: *const DescriptorPoolCreateInfo,
+            allocator: Allocator,
+            pool: *mut Object,
+        ) -> i32;
+        pub fn vkDestroyDescriptorPool(device: Handle, pool: Object, allocator: Allocator);
+        pub fn vkAllocateDescriptorSets(
+            device: Handle,
+            info: *const DescriptorSetAllocateInfo,
+            sets: *mut Object,
+        ) -> i32;
+        pub fn vkUpdateDescriptorSets(
+            device: Handle,
+            write_count: u32,
+            writes: *const WriteDescriptorSet,
+            copy_count: u32,
+            copies: *const c_void,
+        );
+        pub fn vkCreateCommandPool(
+            device: Handle,
+            info: *const CommandPoolCreateInfo,
+            allocator: Allocator,
+            pool: *mut Object,
+        ) -> i32;
+        pub fn vkDestroyCommandPool(device: Handle, pool: Object, allocator: Allocator);
+        pub fn vkAllocateCommandBuffers(
+            device: Handle,
+            info: *const CommandBufferAllocateInfo,
+            buffers: *mut Handle,
+        ) -> i32;
+        pub fn vkBeginCommandBuffer(buffer: Handle, info: *const CommandBufferBeginInfo) -> i32;
+        pub fn vkEndCommandBuffer(buffer: Handle) -> i32;
+        pub fn vkCmdBindPipeline(buffer: Handle, bind_point: u32, pipeline: Object);
+        pub fn vkCmdBindDescriptorSets(
+            buffer: Handle,
+            bind_point: u32,
+            layout: Object,
+            first_set: u32,
+            set_count: u32,
+            sets: *const Object,
+            dynamic_offset_count: u32,
+            dynamic_offsets: *const u32,
+        );
+        pub fn vkCmdPushConstants(
+            buffer: Handle,
+            layout: Object,
+            stages: u32,
+            offset: u32,
+            size: u32,
+            values: *const c_void,
+        );
+        pub fn vkCmdDispatch(buffer: Handle, x: u32, y: u32, z: u32);
+        pub fn vkCmdPipelineBarrier(
+            buffer: Handle,
+            src_stages: u32,
+            dst_stages: u32,
+            dependencies: u32,
+            memory_barrier_count: u32,
+            memory_barriers: *const MemoryBarrier,
+            buffer_barrier_count: u32,
+            buffer_barriers: *const c_void,
+            image_barrier_count: u32,
+            image_barriers: *const c_void,
+        );
+        pub fn vkCreateFence(device: Handle, info: *const FenceCreateInfo, allocator: Allocator, fence: *mut Object)
+            -> i32;
+        pub fn vkDestroyFence(device: Handle, fence: Object, allocator: Allocator);
+        pub fn vkWaitForFences(device: Handle, count: u32, fences: *const Object, wait_all: u32, timeout: u64) -> i32;
+        pub fn vkResetFences(device: Handle, count: u32, fences: *const Object) -> i32;
+        pub fn vkQueueSubmit(queue: Handle, count: u32, submits: *const SubmitInfo, fence: Object) -> i32;
+    }
+}
+
+#[cfg(feature = "vulkan")]
+mod vulkan {
+    use std::ffi::{c_void, CStr, CString};
+    use std::fs;
+    use std::mem;
+    use std::ptr;
+    use std::rc::Rc;
+
+    use anyhow::Result;
+
+    use super::vk::{self, Handle};
+    use super::Device;
+
+    /// kernel.comp, compiled to SPIR-V by build.rs.
+    const SPIRV: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/kernel.spv"));
+
+    /// The first word of every SPIR-V module, in the byte order it was
+    /// written in.
+    const SPIRV_MAGIC: u32 = 0x0723_0203;
+
+    /// kernel.comp's `local_size_x`.
+    const WORKGROUP_SIZE: u32 = 64;
+
+    /// Workgroups per dispatch dimension that every device takes; larger
+    /// batches are dispatched as rows of them.
+    const MAX_WORKGROUPS: u32 = 65535;
+
+    /// Storage buffers the kernel binds, in binding order: indices,
+    /// wordlist, salts, salt offsets, checksum flags, and seeds.
+    const BINDINGS: u32 = 6;
+
+    fn check(code: i32, call: &str) -> Result<()> {
+        if code == vk::SUCCESS {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("{} failed with Vulkan error {}", call, code))
+        }
+    }
+
+    /// The SPIR-V in the file at `path`, or the built-in kernel's, as the
+    /// words a shader module is made of.
+    fn load_spirv(path: Option<&str>) -> Result<Vec<u32>> {
+        let bytes = match path {
+            Some(path) => fs::read(path).map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path, e))?,
+            None => SPIRV.to_vec(),
+        };
+        let code: Vec<u32> =
+            bytes.chunks_exact(4).map(|word| u32::from_ne_bytes(word.try_into().expect("4 bytes"))).collect();
+        if bytes.len() % 4 != 0 || code.first() != Some(&SPIRV_MAGIC) {
+            return Err(anyhow::anyhow!("{} isn't SPIR-V", path.unwrap_or("the built-in kernel")));
+        }
+        Ok(code)
+    }
+
+    /// The instance every device is opened from, destroyed after the last
+    /// of them.
+    struct Instance(Handle);
+
+    impl Instance {
+        fn new() -> Result<Self> {
+            let name = CString::new("bip39_recovery").expect("no NUL");
+            let application = vk::ApplicationInfo {
+                s_type: vk::STRUCTURE_TYPE_APPLICATION_INFO,
+                next: ptr::null(),
+                application_name: name.as_ptr(),
+                application_version: 0,
+                engine_name: ptr::null(),
+                engine_version: 0,
+                api_version: vk::API_VERSION_1_0,
+            };
+            // macOS has Vulkan only through MoltenVK, a portability driver
+            let portability = extensions(|count, properties| unsafe {
+                vk::vkEnumerateInstanceExtensionProperties(ptr::null(), count, properties)
+            })
+            .iter()
+            .any(|name| name.as_c_str() == vk::KHR_PORTABILITY_ENUMERATION);
+            let enabled = [vk::KHR_PORTABILITY_ENUMERATION.as_ptr()];
+            let info = vk::InstanceCreateInfo {
+                s_type: vk::STRUCTURE_TYPE_INSTANCE_CREATE_INFO,
+                next: ptr::null(),
+                flags: if portability { vk::INSTANCE_CREATE_ENUMERATE_PORTABILITY } else { 0 },
+                application_info: &application,
+                layer_count: 0,
+                layers: ptr::null(),
+                extension_count: portability as u32,
+                extensions: enabled.as_ptr(),
+            };
+            let mut instance = ptr::null_mut();
+            check(unsafe { vk::vkCreateInstance(&info, ptr::null(), &mut instance) }, "vkCreateInstance")?;
+            Ok(Self(instance))
+        }
+
+        /// Every device other than a CPU implementation, numbered from 0 in
+        /// the loader's order.
+        fn present(&self) -> Result<Vec<Handle>> {
+            let mut count = 0;
+            check(
+                unsafe { vk::vkEnumeratePhysicalDevices(self.0, &mut count, ptr::null_mut()) },
+                "vkEnumeratePhysicalDevices",
+            )?;
+            let mut devices = vec![ptr::null_mut(); count as usize];
+            check(
+                unsafe { vk::vkEnumeratePhysicalDevices(self.0, &mut count, devices.as_mut_ptr()) },
+                "vkEnumeratePhysicalDevices",
+            )?;
+            devices.truncate(count as usize);
+            devices.retain(|&device| properties(device).device_type != vk::PHYSICAL_DEVICE_TYPE_CPU);
+            Ok(devices)
+        }
+    }
+
+    impl Drop for Instance {
+        fn drop(&mut self) {
+            unsafe {
+                vk::vkDestroyInstance(self.0, ptr::null());
+            }
+        }
+    }
+
+    /// Names of the extensions `list` reports, asked for the count first and
+    /// then the list as Vulkan's enumerations are; none if it fails.
+    fn extensions(list: impl Fn(*mut u32, *mut vk::ExtensionProperties) -> i32) -> Vec<CString> {
+        let mut count = 0;
+        if list(&mut count, ptr::null_mut()) != vk::SUCCESS {
+            return Vec::new();
+        }
+        // All zeroes is a valid value of a plain C structure
+        let mut properties: Vec<vk::ExtensionProperties> = (0..count).map(|_| unsafe { mem::zeroed() }).collect();
+        if !matches!(list(&mut count, properties.as_mut_ptr()), vk::SUCCESS | vk::INCOMPLETE) {
+            return Vec::new();
+        }
+        properties.truncate(count as usize);
+        properties
+            .iter()
+            .map(|property| unsafe { CStr::from_ptr(property.extension_name.as_ptr()) }.to_owned())
+            .collect()
+    }
+
+    fn properties(device: Handle) -> vk::PhysicalDeviceProperties {
+        // All zeroes is a valid value of a plain C structure
+        let mut properties: vk::PhysicalDeviceProperties = unsafe { mem::zeroed() };
+        unsafe { vk::vkGetPhysicalDeviceProperties(device, &mut properties) };
+        properties
+    }
+
+    /// The first queue family of `device` that runs compute work.
+    fn compute_family(device: Handle) -> Option<u32> {
+        let mut count = 0;
+        unsafe { vk::vkGetPhysicalDeviceQueueFamilyProperties(device, &mut count, ptr::null_mut()) };
+        let mut families = vec![vk::QueueFamilyProperties::default(); count as usize];
+        unsafe { vk::vkGetPhysicalDeviceQueueFamilyProperties(device, &mut count, families.as_mut_ptr()) };
+        families
+            .iter()
+            .take(count as usize)
+            .position(|family| family.queue_flags & vk::QUEUE_COMPUTE != 0 && family.queue_count > 0)
+            .map(|family| family as u32)
+    }
+
+    /// A logical device and the pipeline, descriptor set, command buffer,
+    /// and fence one kernel runs with. Each is destroyed when dropped; any
+    /// not yet created is still null, which destroying ignores.
+    struct Context {
+        device: Handle,
+        queue: Handle,
+        memory: vk::PhysicalDeviceMemoryProperties,
+        shader: vk::Object,
+        set_layout: vk::Object,
+        pipeline_layout: vk::Object,
+        pipeline: vk::Object,
+        descriptor_pool: vk::Object,
+        descriptor_set: vk::Object,
+        command_pool: vk::Object,
+        command_buffer: Handle,
+        fence: vk::Object,
+    }
+
+    impl Context {
+        fn new(physical: Handle, family: u32, spirv: &[u32]) -> Result<Self> {
+            let priority = 1.0f32;
+            let queue_info = vk::DeviceQueueCreateInfo {
+                s_type: vk::STRUCTURE_TYPE_DEVICE_QUEUE_CREATE_INFO,
+                next: ptr::null(),
+                flags: 0,
+                queue_family_index: family,
+                queue_count: 1,
+                queue_priorities: &priority,
+            };
+            // kernel.comp's SHA-512 needs 64-bit integers, and nothing else
+            let mut features: vk::PhysicalDeviceFeatures = [0; 55];
+            features[vk::FEATURE_SHADER_INT64] = 1;
+            let subset = extensions(|count, properties| unsafe {
+                vk::vkEnumerateDeviceExtensionProperties(physical, ptr::null(), count, properties)
+            })
+            .iter()
+            .any(|name| name.as_c_str() == vk::KHR_PORTABILITY_SUBSET);
+            let enabled = [vk::KHR_PORTABILITY_SUBSET.as_ptr()];
+            let info = vk::DeviceCreateInfo {
+                s_type: vk::STRUCTURE_TYPE_DEVICE_CREATE_INFO,
+                next: ptr::null(),
+                flags: 0,
+                queue_create_info_count: 1,
+                queue_create_infos: &queue_info,
+                layer_count: 0,
+                layers: ptr::null(),
+                extension_count: subset as u32,
+                extensions: enabled.as_ptr(),
+                enabled_features: &features,
+            };
+            let mut device = ptr::null_mut();
+            check(unsafe { vk::vkCreateDevice(physical, &info, ptr::null(), &mut device) }, "vkCreateDevice")?;
+            let mut memory: vk::PhysicalDeviceMemoryProperties = unsafe { mem::zeroed() };
+            unsafe { vk::vkGetPhysicalDeviceMemoryProperties(physical, &mut memory) };
+            let mut context = Self {
+                device,
+                queue: ptr::null_mut(),
+                memory,
+                shader: 0,
+                set_layout: 0,
+                pipeline_layout: 0,
+                pipeline: 0,
+                descriptor_pool: 0,
+                descriptor_set: 0,
+                command_pool: 0,
+                command_buffer: ptr::null_mut(),
+                fence: 0,
+            };
+            unsafe { vk::vkGetDeviceQueue(device, family, 0, &mut context.queue) };
+
+            let info = vk::ShaderModuleCreateInfo {
+                s_type: vk::STRUCTURE_TYPE_SHADER_MODULE_CREATE_INFO,
+                next: ptr::null(),
+                flags: 0,
+                code_size: mem::size_of_val(spirv),
+                code: spirv.as_ptr(),
+            };
+            check(
+                unsafe { vk::vkCreateShaderModule(device, &info, ptr::null(), &mut context.shader) },
+                "vkCreateShaderModule",
+            )?;
+            let bindings: Vec<vk::DescriptorSetLayoutBinding> = (0..BINDINGS)
+                .map(|binding| vk::DescriptorSetLayoutBinding {
+                    binding,
+                    descriptor_type: vk::DESCRIPTOR_TYPE_STORAGE_BUFFER,
+                    descriptor_count: 1,
+                    stage_flags: vk::SHADER_STAGE_COMPUTE,
+                    immutable_samplers: ptr::null(),
+                })
+                .collect();
+            let info = vk::DescriptorSetLayoutCreateInfo {
+                s_type: vk::STRUCTURE_TYPE_DESCRIPTOR_SET_LAYOUT_CREATE_INFO,
+                next: ptr::null(),
+                flags: 0,
+                binding_count: BINDINGS,
+                bindings: bindings.as_ptr(),
+            };
+            check(
+                unsafe { vk::vkCreateDescriptorSetLayout(device, &info, ptr::null(), &mut context.set_layout) },
+                "vkCreateDescriptorSetLayout",
+            )?;
+            // count, words, and salt_count
+            let push_constants =
+                vk::PushConstantRange { stage_flags: vk::SHADER_STAGE_COMPUTE, offset: 0, size: 3 * 4 };
+            let info = vk::PipelineLayoutCreateInfo {
+                s_type: vk::STRUCTURE_TYPE_PIPELINE_LAYOUT_CREATE_INFO,
+                next: ptr::null(),
+                flags: 0,
+                set_layout_count: 1,
+                set_layouts: &context.set_layout,
+                push_constant_range_count: 1,
+                push_constant_ranges: &push_constants,
+            };
+            check(
+                unsafe { vk::vkCreatePipelineLayout(device, &info, ptr::null(), &mut context.pipeline_layout) },
+                "vkCreatePipelineLayout",
+            )?;
+            let entry = CString::new("main").expect("no NUL");
+            let info = vk::ComputePipelineCreateInfo {
+                s_type: vk::STRUCTURE_TYPE_COMPUTE_PIPELINE_CREATE_INFO,
+                next: ptr::null(),
+                flags: 0,
+                stage: vk::PipelineShaderStageCreateInfo {
+                    s_type: vk::STRUCTURE_TYPE_PIPELINE_SHADER_STAGE_CREATE_INFO,
+                    next: ptr::null(),
+                    flags: 0,
+                    stage: vk::SHADER_STAGE_COMPUTE,
+                    module: context.shader,
+                    name: entry.as_ptr(),
+                    specialization_info: ptr::null(),
+                },
+                layout: context.pipeline_layout,
+                base_pipeline_handle: 0,
+                base_pipeline_index: -1,
+            };
+            check(
+                unsafe { vk::vkCreateComputePipelines(device, 0, 1, &info, ptr::null(), &mut context.pipeline) },
+                "vkCreateComputePipelines",
+            )?;
+            let pool_size = vk::DescriptorPoolSize {
+                descriptor_type: vk::DESCRIPTOR_TYPE_STORAGE_BUFFER,
+                descriptor_count: BINDINGS,
+            };
+            let info = vk::DescriptorPoolCreateInfo {
+                s_type: vk::STRUCTURE_TYPE_DESCRIPTOR_POOL_CREATE_INFO,
+                next: ptr::null(),
+                flags: 0,
+                max_sets: 1,
+                pool_size_count: 1,
+                pool_sizes: &pool_size,
+            };
+            check(
+                unsafe { vk::vkCreateDescriptorPool(device, &info, ptr::null(), &mut context.descriptor_pool) },
+                "vkCreateDescriptorPool",
+            )?;
+            let info = vk::DescriptorSetAllocateInfo {
+                s_type: vk::STRUCTURE_TYPE_DESCRIPTOR_SET_ALLOCATE_INFO,
+                next: ptr::null(),
+                descriptor_pool: context.descriptor_pool,
+                descriptor_set_count: 1,
+                set_layouts: &context.set_layout,
+            };
+            check(
+                unsafe { vk::vkAllocateDescriptorSets(device, &info, &mut context.descriptor_set) },
+                "vkAllocateDescriptorSets",
+            )?;
+            let info = vk::CommandPoolCreateInfo {
+                s_type: vk::STRUCTURE_TYPE_COMMAND_POOL_CREATE_INFO,
+                next: ptr::null(),
+                flags: vk::COMMAND_POOL_CREATE_RESET_COMMAND_BUFFER,
+                queue_family_index: family,
+            };
+            check(
+                unsafe { vk::vkCreateCommandPool(device, &info, ptr::null(), &mut context.command_pool) },
+                "vkCreateCommandPool",
+            )?;
+            let info = vk::CommandBufferAllocateInfo {
+                s_type: vk::STRUCTURE_TYPE_COMMAND_BUFFER_ALLOCATE_INFO,
+                next: ptr::null(),
+                command_pool: context.command_pool,
+                level: 0,
+                command_buffer_count: 1,
+            };
+            check(
+                unsafe { vk::vkAllocateCommandBuffers(device, &info, &mut context.command_buffer) },
+                "vkAllocateCommandBuffers",
+            )?;
+            let info =
+                vk::FenceCreateInfo { s_type: vk::STRUCTURE_TYPE_FENCE_CREATE_INFO, next: ptr::null(), flags: 0 };
+            check(unsafe { vk::vkCreateFence(device, &info, ptr::null(), &mut context.fence) }, "vkCreateFence")?;
+            Ok(context)
+        }
+
+        /// The first memory type `allowed` names that the host can map and
+        /// sees the device's writes in without flushing.
+        fn memory_type(&self, allowed: u32) -> Result<u32> {
+            let wanted = vk::MEMORY_PROPERTY_HOST_VISIBLE | vk::MEMORY_PROPERTY_HOST_COHERENT;
+            (0..self.memory.memory_type_count.min(32))
+                .find(|&index| {
+                    allowed & (1 << index) != 0
+                        && self.memory.memory_types[index as usize].property_flags & wanted == wanted
+                })
+                .ok_or_else(|| anyhow::anyhow!("no host-visible, coherent memory for a buffer"))
+        }
+
+        /// Waits for the submitted launch and readies the fence for the next.
+        fn finish(&self) -> Result<()> {
+            check(unsafe { vk::vkWaitForFences(self.device, 1, &self.fence, 1, u64::MAX) }, "vkWaitForFences")?;
+            check(unsafe { vk::vkResetFences(self.device, 1, &self.fence) }, "vkResetFences")
+        }
+    }
+
+    impl Drop for Context {
+        fn drop(&mut self) {
+            unsafe {
+                vk::vkDestroyFence(self.device, self.fence, ptr::null());
+                // Frees the command buffer with it, as the descriptor pool
+                // does the set
+                vk::vkDestroyCommandPool(self.device, self.command_pool, ptr::null());
+                vk::vkDestroyDescriptorPool(self.device, self.descriptor_pool, ptr::null());
+                vk::vkDestroyPipeline(self.device, self.pipeline, ptr::null());
+                vk::vkDestroyPipelineLayout(self.device, self.pipeline_layout, ptr::null());
+                vk::vkDestroyDescriptorSetLayout(self.device, self.set_layout, ptr::null());
+                vk::vkDestroyShaderModule(self.device, self.shader, ptr::null());
+                vk::vkDestroyDevice(self.device, ptr::null());
+            }
+        }
+    }
+
+    /// A storage buffer in host-visible memory, mapped for as long as it
+    /// lives; freed when dropped. The shader reads and writes 32-bit words,
+    /// so its size is rounded up to a whole number of them.
+    struct Buffer {
+        device: Handle,
+        buffer: vk::Object,
+        memory: vk::Object,
+        mapped: *mut u8,
+        len: usize,
+    }
+
+    impl Buffer {
+        fn new(context: &Context, len: usize) -> Result<Self> {
+            let size = len.max(1).next_multiple_of(4) as u64;
+            let info = vk::BufferCreateInfo {
+                s_type: vk::STRUCTURE_TYPE_BUFFER_CREATE_INFO,
+                next: ptr::null(),
+                flags: 0,
+                size,
+                usage: vk::BUFFER_USAGE_STORAGE_BUFFER,
+                sharing_mode: 0,
+                queue_family_index_count: 0,
+                queue_family_indices: ptr::null(),
+            };
+            let mut buffer = Self { device: context.device, buffer: 0, memory: 0, mapped: ptr::null_mut(), len };
+            check(
+                unsafe { vk::vkCreateBuffer(context.device, &info, ptr::null(), &mut buffer.buffer) },
+                "vkCreateBuffer",
+            )?;
+            let mut requirements = vk::MemoryRequirements::default();
+            unsafe { vk::vkGetBufferMemoryRequirements(context.device, buffer.buffer, &mut requirements) };
+            let info = vk::MemoryAllocateInfo {
+                s_type: vk::STRUCTURE_TYPE_MEMORY_ALLOCATE_INFO,
+                next: ptr::null(),
+                allocation_size: requirements.size,
+                memory_type_index: context.memory_type(requirements.memory_type_bits)?,
+            };
+            check(
+                unsafe { vk::vkAllocateMemory(context.device, &info, ptr::null(), &mut buffer.memory) },
+                "vkAllocateMemory",
+            )?;
+            check(
+                unsafe { vk::vkBindBufferMemory(context.device, buffer.buffer, buffer.memory, 0) },
+                "vkBindBufferMemory",
+            )?;
+            let mut mapped = ptr::null_mut();
+            check(
+                unsafe { vk::vkMapMemory(context.device, buffer.memory, 0, vk::WHOLE_SIZE, 0, &mut mapped) },
+                "vkMapMemory",
+            )?;
+            buffer.mapped = mapped as *mut u8;
+            Ok(buffer)
+        }
+
+        fn upload<T>(context: &Context, data: &[T]) -> Result<Self> {
+            let buffer = Self::new(context, mem::size_of_val(data))?;
+            unsafe { ptr::copy_nonoverlapping(data.as_ptr() as *const u8, buffer.mapped, buffer.len) };
+            Ok(buffer)
+        }
+
+        fn download(&self) -> Vec<u8> {
+            let mut host = vec![0u8; self.len];
+            unsafe { ptr::copy_nonoverlapping(self.mapped, host.as_mut_ptr(), self.len) };
+            host
+        }
+    }
+
+    impl Drop for Buffer {
+        // Freeing the memory unmaps it
+        fn drop(&mut self) {
+            unsafe {
+                vk::vkDestroyBuffer(self.device, self.buffer, ptr::null());
+                vk::vkFreeMemory(self.device, self.memory, ptr::null());
+            }
+        }
+    }
+
+    pub struct Kernel {
+        ordinal: u32,
+        name: String,
+        // Buffers of the launch in flight, until it's collected
+        pending: Option<Pending>,
+        wordlist: Buffer,
+        salts: Buffer,
+        salt_offsets: Buffer,
+        salt_count: u32,
+        // Dropped after the buffers allocated from it
+        context: Context,
+        _instance: Rc<Instance>,
+    }
+
+    struct Pending {
+        count: usize,
+        _input: Buffer,
+        valid: Buffer,
+        seeds: Buffer,
+    }
+
+    impl Drop for Kernel {
+        // A launch still running keeps its buffers until it's done
+        fn drop(&mut self) {
+            unsafe {
+                vk::vkDeviceWaitIdle(self.context.device);
+            }
+        }
+    }
+
+    impl Kernel {
+        /// One kernel per device in `devices`, or per device present when
+        /// it's empty, loaded from the SPIR-V file `spirv`, or from the
+        /// built-in kernel.
+        pub fn open(
+            spirv: Option<&str>,
+            devices: &[u32],
+            wordlist: &[u8],
+            salts: &[u8],
+            salt_offsets: &[u32],
+        ) -> Result<Vec<Self>> {
+            let spirv = load_spirv(spirv)?;
+            let instance = Rc::new(Instance::new()?);
+            let present = instance.present()?;
+            if present.is_empty() {
+                return Err(anyhow::anyhow!("no Vulkan GPU found"));
+            }
+            let mut ordinals: Vec<u32> =
+                if devices.is_empty() { (0..present.len() as u32).collect() } else { devices.to_vec() };
+            ordinals.sort_unstable();
+            ordinals.dedup();
+            let mut kernels = Vec::with_capacity(ordinals.len());
+            for ordinal in ordinals {
+                let Some(&physical) = present.get(ordinal as usize) else {
+                    return Err(anyhow::anyhow!(
+                        "no Vulkan device {} ({} present, numbered from 0)",
+                        ordinal,
+                        present.len()
+                    ));
+                };
+                let properties = properties(physical);
+                let name =
+                    unsafe { CStr::from_ptr(properties.device_name.as_ptr()) }.to_string_lossy().trim().to_string();
+                let mut features: vk::PhysicalDeviceFeatures = [0; 55];
+                unsafe { vk::vkGetPhysicalDeviceFeatures(physical, &mut features) };
+                if features[vk::FEATURE_SHADER_INT64] == 0 {
+                    return Err(anyhow::anyhow!("{} has no 64-bit integers in shaders, which the kernel needs", name));
+                }
+                let family = compute_family(physical)
+                    .ok_or_else(|| anyhow::anyhow!("{} has no queue that runs compute work", name))?;
+                let context = Context::new(physical, family, &spirv)?;
+                kernels.push(Self {
+                    ordinal,
+                    name,
+                    pending: None,
+                    wordlist: Buffer::upload(&context, wordlist)?,
+                    salts: Buffer::upload(&context, salts)?,
+                    salt_offsets: Buffer::upload(&context, salt_offsets)?,
+                    salt_count: (salt_offsets.len() - 1) as u32,
+                    context,
+                    _instance: Rc::clone(&instance),
+                });
+            }
+            Ok(kernels)
+        }
+
+        /// Points the descriptor set at this launch's buffers and submits
+        /// one dispatch over `count` candidates.
+        fn submit(&self, input: &Buffer, valid: &Buffer, seeds: &Buffer, count: u32, words: u32) -> Result<()> {
+            let context = &self.context;
+            let buffers = [input, &self.wordlist, &self.salts, &self.salt_offsets, valid, seeds];
+            let infos = buffers.map(|buffer| vk::DescriptorBufferInfo {
+                buffer: buffer.buffer,
+                offset: 0,
+                range: vk::WHOLE_SIZE,
+            });
+            let writes: Vec<vk::WriteDescriptorSet> = infos
+                .iter()
+                .zip(0..)
+                .map(|(info, binding)| vk::WriteDescriptorSet {
+                    s_type: vk::STRUCTURE_TYPE_WRITE_DESCRIPTOR_SET,
+                    next: ptr::null(),
+                    dst_set: context.descriptor_set,
+                    dst_binding: binding,
+                    dst_array_element: 0,
+                    descriptor_count: 1,
+                    descriptor_type: vk::DESCRIPTOR_TYPE_STORAGE_BUFFER,
+                    image_info: ptr::null(),
+                    buffer_info: info,
+                    texel_buffer_view: ptr::null(),
+                })
+                .collect();
+            unsafe { vk::vkUpdateDescriptorSets(context.device, BINDINGS, writes.as_ptr(), 0, ptr::null()) };
+
+            let command = context.command_buffer;
+            let begin = vk::CommandBufferBeginInfo {
+                s_type: vk::STRUCTURE_TYPE_COMMAND_BUFFER_BEGIN_INFO,
+                next: ptr::null(),
+                flags: vk::COMMAND_BUFFER_USAGE_ONE_TIME_SUBMIT,
+                inheritance_info: ptr::null(),
+            };
+            check(unsafe { vk::vkBeginCommandBuffer(command, &begin) }, "vkBeginCommandBuffer")?;
+            let push_constants = [count, words, self.salt_count];
+            let groups = count.div_ceil(WORKGROUP_SIZE);
+            let columns = groups.min(MAX_WORKGROUPS);
+            // The seeds are read on the host once the fence signals
+            let barrier = vk::MemoryBarrier {
+                s_type: vk::STRUCTURE_TYPE_MEMORY_BARRIER,
+                next: ptr::null(),
+                src_access_mask: vk::ACCESS_SHADER_WRITE,
+                dst_access_mask: vk::ACCESS_HOST_READ,
+            };
+            unsafe {
+                vk::vkCmdBindPipeline(command, vk::PIPELINE_BIND_POINT_COMPUTE, context.pipeline);
+                vk::vkCmdBindDescriptorSets(
+                    command,
+                    vk::PIPELINE_BIND_POINT_COMPUTE,
+                    context.pipeline_layout,
+                    0,
+                    1,
+                    &context.descriptor_set,
+                    0,
+                    ptr::null(),
+                );
+                vk::vkCmdPushConstants(
+                    command,
+                    context.pipeline_layout,
+                    vk::SHADER_STAGE_COMPUTE,
+                    0,
+                    mem::size_of_val(&push_constants) as u32,
+                    push_constants.as_ptr() as *const c_void,
+                );
+                vk::vkCmdDispatch(command, columns, groups.div_ceil(columns), 1);
+                vk::vkCmdPipelineBarrier(
+                    command,
+                    vk::PIPELINE_STAGE_COMPUTE_SHADER,
+                    vk::PIPELINE_STAGE_HOST,
+                    0,
+                    1,
+                    &barrier,
+                    0,
+                    ptr::null(),
+                    0,
+                    ptr::null(),
+                );
+            }
+            check(unsafe { vk::vkEndCommandBuffer(command) }, "vkEndCommandBuffer")?;
+            let submit = vk::SubmitInfo {
+                s_type: vk::STRUCTURE_TYPE_SUBMIT_INFO,
+                next: ptr::null(),
+                wait_semaphore_count: 0,
+                wait_semaphores: ptr::null(),
+                wait_dst_stage_mask: ptr::null(),
+                command_buffer_count: 1,
+                command_buffers: &command,
+                signal_semaphore_count: 0,
+                signal_semaphores: ptr::null(),
+            };
+            check(unsafe { vk::vkQueueSubmit(context.queue, 1, &submit, context.fence) }, "vkQueueSubmit")
+        }
+    }
+
+    impl Device for Kernel {
+        fn label(&self) -> String {
+            format!("gpu{}", self.ordinal)
+        }
+
+        fn name(&self) -> &str {
+            &self.name
+        }
+
+        /// Vulkan reports no compute units or clock, so devices share
+        /// batches equally.
+        fn weight(&self) -> u64 {
+            1
+        }
+
+        fn launch(&mut self, indices: &[u16], words: u32) -> Result<()> {
+            // A launch never collected (another device failed first) still
+            // owns the descriptor set and command buffer until it's done
+            if let Some(stale) = self.pending.take() {
+                self.context.finish()?;
+                drop(stale);
+            }
+            let count = indices.len() / words as usize;
+            if count == 0 {
+                return Ok(());
+            }
+            let input = Buffer::upload(&self.context, indices)?;
+            let valid = Buffer::new(&self.context, count * 4)?;
+            let seeds = Buffer::new(&self.context, count * self.salt_count as usize * 64)?;
+            self.submit(&input, &valid, &seeds, count as u32, words)?;
+            self.pending = Some(Pending { count, _input: input, valid, seeds });
+            Ok(())
+        }
+
+        fn collect(&mut self) -> Result<(Vec<u8>, Vec<u8>)> {
+            let Some(pending) = self.pending.take() else {
+                return Ok((Vec::new(), Vec::new()));
+            };
+            self.context.finish()?;
+            // A flag per candidate, a little-endian 32-bit word each
+            let valid = pending.valid.download().chunks_exact(4).take(pending.count).map(|flag| flag[0]).collect();
+            Ok((valid, pending.seeds.download()))
+        }
+    }
+}
+
+#[cfg(not(feature = "vulkan"))]
+mod vulkan {
+    use anyhow::Result;
+
+    use super::Device;
+
+    /// Stands in for the device in builds without Vulkan; it can't be opened.
+    pub enum Kernel {}
+
+    impl Kernel {
+        pub fn open(
+            _spirv: Option<&str>,
+            _devices: &[u32],
+            _wordlist: &[u8],
+            _salts: &[u8],
+            _salt_offsets: &[u32],
+        ) -> Result<Vec<Self>> {
+            Err(anyhow::anyhow!("this build has no Vulkan support; rebuild with --features vulkan"))
+        }
+    }
+
+    impl Device for Kernel {
+        fn label(&self) -> String {
+            match *self {}
+        }
+
+        fn name(&self) -> &str {
+            match *self {}
+        }
+
+        fn weight(&self) -> u64 {
+            match *self {}
+        }
+
+        fn launch(&mut self, _indices: &[u16], _words: u32) -> Result<()> {
+            match *self {}
+        }
+
+        fn collect(&mut self) -> Result<(Vec<u8>, Vec<u8>)> {
+            match *self {}
+        }
+    }
+}
@@ -0,0 +1,347 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+
+use anyhow::Result;
+use bitcoin::base58;
+use bitcoin::hashes::{hash160, Hash};
+use memmap2::Mmap;
+
+use crate::{ethereum, taproot};
+
+/// What a database entry holds for an address: the 20 bytes its encoding
+/// wraps (key hash, script hash, witness program, or account), or the
+/// hash160 of a longer witness program such as a taproot output key.
+pub type Key = [u8; KEY_LEN];
+
+const KEY_LEN: usize = 20;
+
+/// Leading bytes of a database, before the entry count.
+const MAGIC: &[u8; 8] = b"B39H160\x01";
+
+const HEADER_LEN: usize = MAGIC.len() + 8;
+
+/// First bytes of a zstd frame, which is how a compressed database starts.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+fn key_of(payload: &[u8]) -> Key {
+    match payload.try_into() {
+        Ok(key) => key,
+        Err(_) => hash160::Hash::hash(payload).to_byte_array(),
+    }
+}
+
+/// The key of an address as written: base58 with any version byte, bech32
+/// with any human-readable part, or an Ethereum account.
+pub fn address_key(address: &str) -> Option<Key> {
+    let address = address.trim();
+    if ethereum::is_address(address) {
+        let digits = &address[2..];
+        let bytes: Option<Vec<u8>> =
+            (0..digits.len()).step_by(2).map(|i| u8::from_str_radix(&digits[i..i + 2], 16).ok()).collect();
+        return bytes.map(|bytes| key_of(&bytes));
+    }
+    if let Ok((_, _, program)) = bech32::segwit::decode(address) {
+        return Some(key_of(&program));
+    }
+    // One version byte for Bitcoin and most forks, two for a few (Zcash)
+    match base58::decode_check(address) {
+        Ok(data) if matches!(data.len(), 21 | 22) => Some(key_of(&data[data.len() - KEY_LEN..])),
+        _ => None,
+    }
+}
+
+/// The key the `address_type` address of `pubkey` has, computed without
+/// encoding the address; `None` for types that aren't addresses.
+pub fn pubkey_key(pubkey: &bitcoin::PublicKey, address_type: &str) -> Option<Key> {
+    let key_hash = || hash160::Hash::hash(&pubkey.to_bytes());
+    match address_type {
+        "p2pkh" | "p2wpkh" => Some(key_hash().to_byte_array()),
+        "p2sh-p2wpkh" => {
+            let mut redeem_script = vec![0x00, 0x14];
+            redeem_script.extend_from_slice(key_hash().as_byte_array());
+            Some(hash160::Hash::hash(&redeem_script).to_byte_array())
+        }
+        "p2tr" => Some(key_of(&taproot::output_key(pubkey).serialize())),
+        ethereum::ETH => {
+            let hash = ethereum::keccak256(&pubkey.inner.serialize_uncompressed()[1..]);
+            Some(key_of(&hash[12..]))
+        }
+        _ => None,
+    }
+}
+
+/// Counts from [`build`].
+pub struct BuildStats {
+    pub entries: usize,
+    pub duplicates: usize,
+    pub unparsed: usize,
+    pub bytes: u64,
+}
+
+/// Converts a list of addresses, one per line, into a database of their
+/// sorted keys at `output`, compressed with zstd if `compress`.
+pub fn build(input: &str, output: &str, compress: bool) -> Result<BuildStats> {
+    let file = File::open(input).map_err(|e| anyhow::anyhow!("Failed to open address list {}: {}", input, e))?;
+    let mut keys = Vec::new();
+    let mut unparsed = 0;
+    for line in BufReader::new(file).lines() {
+        let line = line.map_err(|e| anyhow::anyhow!("Failed to read address list {}: {}", input, e))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match address_key(&line) {
+            Some(key) => keys.push(key),
+            None => unparsed += 1,
+        }
+    }
+    keys.sort_unstable();
+    let before = keys.len();
+    keys.dedup();
+    let mut data = encode(&keys);
+    if compress {
+        data = compress_data(&data)?;
+    }
+    let file = File::create(output).map_err(|e| anyhow::anyhow!("Failed to create hash database {}: {}", output, e))?;
+    let mut file = BufWriter::new(file);
+    file.write_all(&data)
+        .and_then(|_| file.flush())
+        .map_err(|e| anyhow::anyhow!("Failed to write hash database {}: {}", output, e))?;
+    Ok(BuildStats { entries: keys.len(), duplicates: before - keys.len(), unparsed, bytes: data.len() as u64 })
+}
+
+/// The file form of sorted, distinct `keys`.
+fn encode(keys: &[Key]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(HEADER_LEN + keys.len() * KEY_LEN);
+    data.extend_from_slice(MAGIC);
+    data.extend_from_slice(&(keys.len() as u64).to_le_bytes());
+    for key in keys {
+        data.extend_from_slice(key);
+    }
+    data
+}
+
+#[cfg(feature = "zstd")]
+fn compress_data(data: &[u8]) -> Result<Vec<u8>> {
+    zstd::bulk::compress(data, 19).map_err(|e| anyhow::anyhow!("Failed to compress hash database: {}", e))
+}
+
+#[cfg(not(feature = "zstd"))]
+fn compress_data(_data: &[u8]) -> Result<Vec<u8>> {
+    Err(anyhow::anyhow!("this build has no zstd support; rebuild with --features zstd"))
+}
+
+#[cfg(feature = "zstd")]
+fn decompress_data(file: File) -> Result<Vec<u8>> {
+    zstd::stream::decode_all(file).map_err(|e| anyhow::anyhow!("Failed to decompress hash database: {}", e))
+}
+
+#[cfg(not(feature = "zstd"))]
+fn decompress_data(_file: File) -> Result<Vec<u8>> {
+    Err(anyhow::anyhow!("the hash database is compressed and this build has no zstd support; rebuild with --features zstd"))
+}
+
+enum Storage {
+    /// An uncompressed database, paged in by the OS as lookups touch it
+    Mapped(Mmap),
+    /// A compressed database inflated into memory, or one built there
+    Loaded(Vec<u8>),
+}
+
+/// A database written by [`build`]: sorted keys, looked up by binary search.
+pub struct HashDb {
+    storage: Storage,
+    count: usize,
+}
+
+impl HashDb {
+    pub fn open(path: &str) -> Result<Self> {
+        let mut file = File::open(path).map_err(|e| anyhow::anyhow!("Failed to open hash database {}: {}", path, e))?;
+        let mut magic = [0u8; 4];
+        let compressed = file.read_exact(&mut magic).is_ok() && magic == ZSTD_MAGIC;
+        let file = File::open(path).map_err(|e| anyhow::anyhow!("Failed to open hash database {}: {}", path, e))?;
+        let storage = if compressed {
+            Storage::Loaded(decompress_data(file)?)
+        } else {
+            // The file is only read, and a database is replaced by writing a
+            // new one rather than changed in place
+            let map = unsafe { Mmap::map(&file) }
+                .map_err(|e| anyhow::anyhow!("Failed to map hash database {}: {}", path, e))?;
+            Storage::Mapped(map)
+        };
+        let bytes = match &storage {
+            Storage::Mapped(map) => &map[..],
+            Storage::Loaded(data) => &data[..],
+        };
+        if bytes.len() < HEADER_LEN || &bytes[..MAGIC.len()] != MAGIC {
+            return Err(anyhow::anyhow!("{} is not a hash database written by build-db", path));
+        }
+        let count = u64::from_le_bytes(bytes[MAGIC.len()..HEADER_LEN].try_into().expect("8 bytes")) as usize;
+        if bytes.len() != HEADER_LEN + count * KEY_LEN {
+            return Err(anyhow::anyhow!("Hash database {} is truncated or damaged", path));
+        }
+        Ok(Self { storage, count })
+    }
+
+    /// A database of `keys` held in memory, for targets known up front.
+    pub fn from_keys(mut keys: Vec<Key>) -> Self {
+        keys.sort_unstable();
+        keys.dedup();
+        Self { storage: Storage::Loaded(encode(&keys)), count: keys.len() }
+    }
+
+    fn entries(&self) -> &[u8] {
+        match &self.storage {
+            Storage::Mapped(map) => &map[HEADER_LEN..],
+            Storage::Loaded(data) => &data[HEADER_LEN..],
+        }
+    }
+
+    pub fn contains(&self, key: &Key) -> bool {
+        let entries = self.entries();
+        let (mut low, mut high) = (0, self.count);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            match entries[mid * KEY_LEN..(mid + 1) * KEY_LEN].cmp(key.as_slice()) {
+                std::cmp::Ordering::Less => low = mid + 1,
+                std::cmp::Ordering::Greater => high = mid,
+                std::cmp::Ordering::Equal => return true,
+            }
+        }
+        false
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Whether the database is mapped from disk rather than held in memory.
+    pub fn is_mapped(&self) -> bool {
+        matches!(self.storage, Storage::Mapped(_))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use bitcoin::Network;
+    use secp256k1::{Secp256k1, SecretKey};
+
+    use super::*;
+
+    const ADDRESS_TYPES: [&str; 5] = ["p2pkh", "p2wpkh", "p2sh-p2wpkh", "p2tr", ethereum::ETH];
+
+    /// The public key of secret key `n`.
+    fn pubkey(n: u8) -> bitcoin::PublicKey {
+        let mut secret = [0u8; 32];
+        secret[31] = n;
+        bitcoin::PublicKey::new(SecretKey::from_slice(&secret).unwrap().public_key(&Secp256k1::new()))
+    }
+
+    fn file(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("bip39_recovery-hashdb-{}-{}", name, std::process::id()));
+        path.to_string_lossy().into_owned()
+    }
+
+    fn hex(key: &Key) -> String {
+        key.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    #[test]
+    fn keys_of_known_addresses() {
+        let hash160 = "751e76e8199196d454941c45d1b3a323f1433bd6";
+        for address in [
+            "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH",
+            "mrCDrCybB6J1vRfbwM5hemdJz73FwDBC8r",
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+            " tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx ",
+        ] {
+            assert_eq!(address_key(address).map(|key| hex(&key)).as_deref(), Some(hash160), "{}", address);
+        }
+        let account = address_key("0x7E5F4552091A69125d5DfCb7b8C2659029395Bdf").unwrap();
+        assert_eq!(hex(&account), "7e5f4552091a69125d5dfcb7b8c2659029395bdf");
+        assert_eq!(address_key("not an address"), None);
+        assert_eq!(address_key("1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMh"), None);
+    }
+
+    /// A key computed from the public key is the key of its encoded address.
+    #[test]
+    fn pubkey_keys_match_addresses() {
+        for n in 1..=3 {
+            let pubkey = pubkey(n);
+            for network in [Network::Bitcoin, Network::Testnet] {
+                for address_type in ADDRESS_TYPES {
+                    let address = crate::encode_address(&pubkey, address_type, network).unwrap().unwrap();
+                    assert_eq!(pubkey_key(&pubkey, address_type), address_key(&address), "{}", address);
+                }
+            }
+        }
+        assert_eq!(pubkey_key(&pubkey(1), "xpub"), None);
+    }
+
+    #[test]
+    fn builds_and_opens() {
+        let (list, db) = (file("list"), file("db"));
+        let addresses: Vec<String> = (1..=50)
+            .map(|n| {
+                let address_type = ADDRESS_TYPES[n as usize % ADDRESS_TYPES.len()];
+                crate::encode_address(&pubkey(n), address_type, Network::Bitcoin).unwrap().unwrap()
+            })
+            .collect();
+        let content = format!("{}\n\n{}\nnot an address\n", addresses.join("\n"), addresses[7]);
+        fs::write(&list, content).unwrap();
+
+        let stats = build(&list, &db, false).unwrap();
+        assert_eq!((stats.entries, stats.duplicates, stats.unparsed), (50, 1, 1));
+        assert_eq!(stats.bytes, (HEADER_LEN + 50 * KEY_LEN) as u64);
+
+        let hash_db = HashDb::open(&db).unwrap();
+        assert!(hash_db.is_mapped());
+        assert_eq!(hash_db.len(), 50);
+        for address in &addresses {
+            assert!(hash_db.contains(&address_key(address).unwrap()), "{}", address);
+        }
+        for n in 51..=60 {
+            assert!(!hash_db.contains(&pubkey_key(&pubkey(n), "p2pkh").unwrap()));
+        }
+
+        let keys: Vec<Key> = addresses.iter().map(|address| address_key(address).unwrap()).collect();
+        let in_memory = HashDb::from_keys(keys.iter().chain(&keys).copied().collect());
+        assert!(!in_memory.is_mapped());
+        assert_eq!(in_memory.len(), 50);
+        assert!(keys.iter().all(|key| in_memory.contains(key)));
+        assert!(HashDb::from_keys(Vec::new()).is_empty());
+
+        #[cfg(feature = "zstd")]
+        {
+            // Hashes barely compress, so only the framing shows
+            build(&list, &db, true).unwrap();
+            assert_eq!(fs::read(&db).unwrap()[..4], ZSTD_MAGIC);
+            let compressed = HashDb::open(&db).unwrap();
+            assert!(!compressed.is_mapped());
+            assert!(keys.iter().all(|key| compressed.contains(key)));
+        }
+        #[cfg(not(feature = "zstd"))]
+        assert!(build(&list, &db, true).is_err());
+
+        let _ = fs::remove_file(&list);
+        let _ = fs::remove_file(&db);
+    }
+
+    #[test]
+    fn rejects_damaged_databases() {
+        let db = file("damaged");
+        let mut data = encode(&[[1; KEY_LEN], [2; KEY_LEN]]);
+        data.pop();
+        fs::write(&db, &data).unwrap();
+        assert!(HashDb::open(&db).is_err());
+        fs::write(&db, b"1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH\n").unwrap();
+        assert!(HashDb::open(&db).is_err());
+        let _ = fs::remove_file(&db);
+        assert!(HashDb::open(&db).is_err());
+    }
+}
@@ -0,0 +1,134 @@
+use std::ffi::OsString;
+use std::fs;
+
+use clap::error::ErrorKind;
+use clap::parser::ValueSource;
+use clap::{Arg, ArgAction, ArgMatches, Command, CommandFactory, FromArgMatches};
+
+/// The option naming a job file.
+const CONFIG: &str = "config";
+
+/// `argv` parsed as `P`, with the options of its `--config` job file filled
+/// in wherever the command line leaves them out. A job file is a TOML table
+/// of the same options by their long names (`total-words = 12`, or
+/// `total_words`), so it takes everything the command line does and is
+/// checked the same way. An option on the command line replaces the file's
+/// value, along with any file option it conflicts with, so `--address-file`
+/// overrides a file's `address`.
+pub fn parse_from<P: CommandFactory + FromArgMatches>(argv: Vec<OsString>) -> Result<(P, ArgMatches), clap::Error> {
+    let mut command = P::command();
+    // A first, lenient pass finds the file and what the command line sets;
+    // options the file provides may still be missing here
+    let given = P::command().ignore_errors(true).try_get_matches_from(&argv)?;
+    let matches = match given.get_one::<String>(CONFIG) {
+        Some(file) => {
+            let tokens = load(&command, file, &given).map_err(|e| command.error(ErrorKind::InvalidValue, e))?;
+            let mut merged = argv[..1].to_vec();
+            merged.extend(tokens.into_iter().map(OsString::from));
+            merged.extend(argv[1..].iter().cloned());
+            command.try_get_matches_from_mut(merged)?
+        }
+        None => command.try_get_matches_from_mut(argv)?,
+    };
+    Ok((P::from_arg_matches(&matches)?, matches))
+}
+
+/// The options of a job file as command-line tokens, skipping those `given`
+/// on the command line sets or overrides.
+fn load(command: &Command, file: &str, given: &ArgMatches) -> Result<Vec<String>, String> {
+    let text = fs::read_to_string(file).map_err(|e| format!("Failed to read job file {}: {}", file, e))?;
+    let table: toml::Table = text.parse().map_err(|e| format!("Invalid job file {}: {}", file, e))?;
+    let on_command_line = |arg: &Arg| given.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine);
+    let mut tokens = Vec::new();
+    for (key, value) in &table {
+        let arg = command
+            .get_arguments()
+            .find(|arg| !arg.is_positional() && (arg.get_id() == key.as_str() || arg.get_long() == Some(key.as_str())))
+            .ok_or_else(|| format!("Unknown option {:?} in job file {}", key, file))?;
+        if arg.get_id() == CONFIG {
+            return Err(format!("Job file {} names another job file; they don't nest", file));
+        }
+        let overridden = command
+            .get_arguments()
+            .filter(|other| on_command_line(other))
+            .any(|other| other.get_id() == arg.get_id() || conflicting(command, arg, other));
+        if !overridden {
+            tokens.extend(option_tokens(arg, key, value).map_err(|e| format!("Job file {}: {}", file, e))?);
+        }
+    }
+    Ok(tokens)
+}
+
+fn conflicting(command: &Command, a: &Arg, b: &Arg) -> bool {
+    command.get_arg_conflicts_with(a).iter().any(|arg| arg.get_id() == b.get_id())
+        || command.get_arg_conflicts_with(b).iter().any(|arg| arg.get_id() == a.get_id())
+}
+
+/// One option of a job file as the command line would give it: a flag when
+/// it's `true`, an array as the option repeated.
+fn option_tokens(arg: &Arg, key: &str, value: &toml::Value) -> Result<Vec<String>, String> {
+    let long = arg.get_long().expect("options have a long name");
+    match (arg.get_action(), value) {
+        (ArgAction::SetTrue, toml::Value::Boolean(set)) => Ok(set.then(|| format!("--{}", long)).into_iter().collect()),
+        (ArgAction::SetTrue, _) => Err(format!("{} is a flag; set it to true or false", key)),
+        (ArgAction::Append, toml::Value::Array(values)) if values.is_empty() => Ok(vec![format!("--{}", long)]),
+        (ArgAction::Append, toml::Value::Array(values)) => {
+            values.iter().map(|value| Ok(format!("--{}={}", long, scalar(key, value)?))).collect()
+        }
+        (_, toml::Value::Array(_)) => Err(format!("{} takes a single value", key)),
+        (_, value) => Ok(vec![format!("--{}={}", long, scalar(key, value)?)]),
+    }
+}
+
+fn scalar(key: &str, value: &toml::Value) -> Result<String, String> {
+    match value {
+        toml::Value::String(value) => Ok(value.clone()),
+        toml::Value::Integer(value) => Ok(value.to_string()),
+        toml::Value::Float(value) => Ok(value.to_string()),
+        toml::Value::Boolean(value) => Ok(value.to_string()),
+        _ => Err(format!("{} must be a string, number, or boolean", key)),
+    }
+}
+
+/// The options `matches` sets, from its job file or the command line, as a
+/// job file that `--config` loads back. Defaults are left out, so a later
+/// release's defaults still apply.
+pub fn render(command: &Command, matches: &ArgMatches) -> String {
+    let mut out = String::new();
+    for arg in command.get_arguments() {
+        let id = arg.get_id().as_str();
+        let (Some(long), false) = (arg.get_long(), id == CONFIG) else {
+            continue;
+        };
+        if !matches!(matches.value_source(id), Some(ValueSource::CommandLine | ValueSource::EnvVariable)) {
+            continue;
+        }
+        let raw: Vec<String> =
+            matches.get_raw(id).into_iter().flatten().map(|value| value.to_string_lossy().into_owned()).collect();
+        let value = match arg.get_action() {
+            ArgAction::SetTrue => toml::Value::Boolean(true),
+            ArgAction::Append => toml::Value::Array(raw.iter().map(|value| typed(value)).collect()),
+            _ => match raw.first() {
+                Some(value) => typed(value),
+                None => continue,
+            },
+        };
+        out.push_str(&format!("{} = {}\n", long, value));
+    }
+    out
+}
+
+/// A raw value as the TOML type that reads back to the same text.
+fn typed(raw: &str) -> toml::Value {
+    if let Some(value) = raw.parse::<i64>().ok().filter(|value| value.to_string() == raw) {
+        return toml::Value::Integer(value);
+    }
+    if let Some(value) = raw.parse::<f64>().ok().filter(|value| value.is_finite() && value.to_string() == raw) {
+        return toml::Value::Float(value);
+    }
+    match raw {
+        "true" => toml::Value::Boolean(true),
+        "false" => toml::Value::Boolean(false),
+        _ => toml::Value::String(raw.to_string()),
+    }
+}
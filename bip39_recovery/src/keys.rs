@@ -0,0 +1,100 @@
+use std::io::{self, IsTerminal, Read};
+#[cfg(unix)]
+use std::sync::OnceLock;
+
+use anyhow::Result;
+
+/// Foreground controls, one key each.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Key {
+    /// `p`: pause, or resume a paused search
+    Pause,
+    /// `c`: write a checkpoint now
+    Checkpoint,
+    /// `s`: print outcome counts and stage timings
+    Stats,
+    /// `+`: let one more worker thread search
+    MoreThreads,
+    /// `-`: park one worker thread
+    FewerThreads,
+    /// `q`: checkpoint and exit, like Ctrl+C
+    Quit,
+    /// `h` or `?`: list the keys
+    Help,
+}
+
+pub const HELP: &str = "Keys: p pause/resume, c checkpoint, s stats, +/- threads, q checkpoint and quit, h help";
+
+impl Key {
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte.to_ascii_lowercase() {
+            b'p' => Some(Self::Pause),
+            b'c' => Some(Self::Checkpoint),
+            b's' => Some(Self::Stats),
+            b'+' | b'=' => Some(Self::MoreThreads),
+            b'-' | b'_' => Some(Self::FewerThreads),
+            b'q' => Some(Self::Quit),
+            b'h' | b'?' => Some(Self::Help),
+            _ => None,
+        }
+    }
+}
+
+// Terminal settings from before `listen` changed them, as `stty -g` prints them
+#[cfg(unix)]
+static SAVED_MODE: OnceLock<String> = OnceLock::new();
+
+/// Reads keys from stdin on a background thread and passes each to `on_key`.
+/// On Unix the terminal is switched out of line mode (without echo) so keys
+/// act immediately; elsewhere they take effect on Enter.
+pub fn listen(on_key: impl Fn(Key) + Send + 'static) -> Result<()> {
+    if !io::stdin().is_terminal() {
+        return Err(anyhow::anyhow!("--interactive needs a terminal on stdin"));
+    }
+    #[cfg(unix)]
+    unbuffer()?;
+    std::thread::spawn(move || {
+        let mut stdin = io::stdin().lock();
+        let mut byte = [0u8; 1];
+        while let Ok(1) = stdin.read(&mut byte) {
+            if let Some(key) = Key::from_byte(byte[0]) {
+                on_key(key);
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(unix)]
+fn unbuffer() -> Result<()> {
+    use std::process::{Command, Stdio};
+
+    let saved = Command::new("stty")
+        .arg("-g")
+        .stdin(Stdio::inherit())
+        .output()
+        .map_err(|e| anyhow::anyhow!("Failed to read terminal settings: {}", e))?;
+    if !saved.status.success() {
+        return Err(anyhow::anyhow!("Failed to read terminal settings: stty exited with {}", saved.status));
+    }
+    let _ = SAVED_MODE.set(String::from_utf8_lossy(&saved.stdout).trim().to_string());
+    // Signals stay enabled, so Ctrl+C still checkpoints and exits
+    let status = Command::new("stty")
+        .args(["-icanon", "-echo", "min", "1"])
+        .stdin(Stdio::inherit())
+        .status()
+        .map_err(|e| anyhow::anyhow!("Failed to change terminal settings: {}", e))?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("Failed to change terminal settings: stty exited with {}", status));
+    }
+    Ok(())
+}
+
+/// Puts the terminal back the way `listen` found it. Does nothing if it
+/// never changed it, so every exit path can call it.
+pub fn restore() {
+    #[cfg(unix)]
+    if let Some(mode) = SAVED_MODE.get() {
+        let _ = std::process::Command::new("stty").arg(mode).stdin(std::process::Stdio::inherit()).status();
+    }
+}
@@ -0,0 +1,480 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io::{BufRead, BufReader};
+
+use anyhow::Result;
+use bip39::{Language, Mnemonic};
+use bitcoin::bip32::{ChildNumber, DerivationPath, Xpriv};
+use bitcoin::{Address, Network};
+use log::{debug, error};
+use patricia_tree::PatriciaMap;
+use secp256k1::Secp256k1;
+
+pub mod bloom;
+pub mod bounded;
+pub mod chunks;
+pub mod coins;
+pub mod ed25519;
+pub mod electrum;
+pub mod engine;
+pub mod entropy;
+pub mod ethereum;
+pub mod fuzzy;
+pub mod gpu;
+pub mod hashdb;
+pub mod lightning;
+pub mod matrix;
+pub mod missing;
+pub mod monero;
+pub mod overrides;
+pub mod passphrase;
+pub mod paths;
+pub mod pathspec;
+pub mod pbkdf2;
+pub mod permutation;
+pub mod pipeline;
+pub mod pool;
+pub mod positions;
+pub mod redact;
+pub mod seedlist;
+pub mod slip39;
+pub mod stages;
+pub mod substitutes;
+pub mod summary;
+pub mod taproot;
+pub mod targets;
+pub mod tested;
+pub mod tokenlist;
+pub mod verify;
+pub mod weighted;
+pub mod wipe;
+pub mod wordindex;
+pub mod workers;
+pub mod xpub;
+
+pub use engine::{Candidates, Matches, RecoveryConfig, RecoveryEngine, Report, Target};
+
+use bloom::AddressBloom;
+use bounded::BoundedOrder;
+use fuzzy::FuzzyWords;
+use gpu::Seed;
+use hashdb::HashDb;
+use matrix::{SearchMatrix, Variant};
+use missing::MissingWords;
+use passphrase::Passphrases;
+use pool::WordPool;
+use stages::{Stage, StageStats};
+use substitutes::Substitutes;
+use summary::{Outcome, RunSummary};
+use targets::AddressPattern;
+use tested::TestedSet;
+use tokenlist::TokenList;
+use wipe::{Wipe, Wiped};
+
+/// The BIP-39 English wordlist, for looking up a word's index.
+pub struct Bip39Wordlist {
+    wordlist: PatriciaMap<u16>,
+}
+
+impl Bip39Wordlist {
+    /// Loads the list from a file, one word per line in index order.
+    pub fn new(wordlist_path: &str) -> Result<Self> {
+        let file = fs::File::open(wordlist_path)
+            .map_err(|e| anyhow::anyhow!("Failed to open wordlist file {}: {}", wordlist_path, e))?;
+        let reader = BufReader::new(file);
+        let mut wordlist = PatriciaMap::new();
+        for (index, line) in reader.lines().enumerate() {
+            let line = line.map_err(|e| anyhow::anyhow!("Failed to read wordlist file: {}", e))?;
+            wordlist.insert(line.trim(), index as u16);
+        }
+        Ok(Self { wordlist })
+    }
+
+    /// The list built into the `bip39` crate, for callers without the file.
+    pub fn english() -> Self {
+        let mut wordlist = PatriciaMap::new();
+        for (index, word) in Language::English.word_list().iter().enumerate() {
+            wordlist.insert(word, index as u16);
+        }
+        Self { wordlist }
+    }
+
+    /// Position of `word` in the list, which is its BIP-39 index as long as
+    /// the file is the standard English list in order.
+    pub fn index(&self, word: &str) -> Option<u16> {
+        self.wordlist.get(word).copied()
+    }
+
+    /// Words of the list that start with `prefix`.
+    pub fn with_prefix(&self, prefix: &str) -> Vec<String> {
+        self.wordlist
+            .iter_prefix(prefix.as_bytes())
+            .map(|(word, _)| String::from_utf8_lossy(&word).into_owned())
+            .collect()
+    }
+
+    /// Every word of the list, in alphabetical order.
+    pub fn words(&self) -> Vec<String> {
+        self.wordlist.keys().map(|word| String::from_utf8_lossy(&word).into_owned()).collect()
+    }
+}
+
+/// How a phrase becomes a wallet seed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum SeedFormat {
+    /// BIP-39: the checksum in the last word, PBKDF2 salted with `mnemonic`
+    Bip39,
+    /// Electrum 2.0+: a version prefix in an HMAC of the phrase, PBKDF2
+    /// salted with `electrum`
+    Electrum,
+    /// Monero's 25 words, chosen with `--coin xmr`: a checksum word, and
+    /// the spend key in the words themselves
+    #[value(skip)]
+    Monero,
+}
+
+/// A candidate that derived a target address: the mnemonic, the passphrase
+/// it was tried with, the address, and the path and encoding that gave it.
+#[derive(Clone, Debug)]
+pub struct Match {
+    pub mnemonic: String,
+    pub passphrase: String,
+    pub address: String,
+    pub variant: Variant,
+}
+
+/// What derived addresses are compared against: a set, a pattern, a bloom
+/// filter over a list, or a hash database, which is also how a single
+/// address is matched ([`HashDb::from_keys`]). Only one is set.
+#[derive(Clone, Copy, Default)]
+pub struct Targets<'a> {
+    pub addresses: Option<&'a HashSet<String>>,
+    pub pattern: Option<&'a AddressPattern>,
+    pub bloom: Option<&'a AddressBloom>,
+    pub hash_db: Option<&'a HashDb>,
+}
+
+impl Targets<'_> {
+    /// Whether `address` is a target. A hash database has already filtered
+    /// what gets this far, so with nothing else to compare it's a hit.
+    fn matches(&self, address: &str) -> Result<bool> {
+        Ok(match (self.addresses, self.pattern, self.bloom, self.hash_db) {
+            (Some(db), None, None, None) => db.contains(address),
+            (None, Some(pattern), None, None) => pattern.matches(address),
+            (None, None, Some(bloom), None) => bloom.contains(address)?,
+            (None, None, None, Some(_)) => true,
+            _ => false,
+        })
+    }
+}
+
+/// `pubkey` as an address of `address_type`, or `None` for a type this
+/// doesn't encode.
+pub fn encode_address(pubkey: &bitcoin::PublicKey, address_type: &str, network: Network) -> Option<Result<String>> {
+    let addr = match address_type {
+        "p2wpkh" => Address::p2wpkh(pubkey, network),
+        "p2pkh" => Ok(Address::p2pkh(pubkey, network)),
+        "p2sh-p2wpkh" => Address::p2shwpkh(pubkey, network),
+        "p2tr" => Ok(taproot::address(pubkey, network)),
+        // Ethereum has no network variants that change the account
+        ethereum::ETH => return Some(Ok(ethereum::address(&pubkey.inner))),
+        _ => return None,
+    };
+    Some(addr.map(|addr| addr.to_string()).map_err(|e| anyhow::anyhow!("Failed to create address: {}", e)))
+}
+
+/// Tests one candidate: the wordlist and checksum (or Electrum version),
+/// then every passphrase, matrix path, and encoding against the `targets`.
+/// `seeds`, when a device or vector batch computes them, stand in for
+/// PBKDF2, one per passphrase in order; passphrases past the end of them are
+/// run here. Outcomes are counted in `summary` and stage
+/// timings in `stages`.
+#[allow(clippy::too_many_arguments)]
+pub fn try_mnemonic(
+    mnemonic_words: &[String],
+    seed_format: SeedFormat,
+    matrix: &SearchMatrix,
+    passphrases: &Passphrases,
+    mut seeds: Option<&mut dyn Iterator<Item = Seed>>,
+    tested: Option<&TestedSet>,
+    targets: &Targets,
+    secp: &Secp256k1<secp256k1::All>,
+    bip39_wordlist: &Bip39Wordlist,
+    summary: &RunSummary,
+    stages: &StageStats,
+    debug: bool,
+) -> Result<Option<Match>> {
+    if seed_format == SeedFormat::Monero {
+        return try_monero(mnemonic_words, targets, summary, stages, debug);
+    }
+    let mut sample = stages.sample();
+    let mut indices = Vec::with_capacity(mnemonic_words.len());
+    for word in mnemonic_words {
+        match bip39_wordlist.index(word) {
+            Some(index) => indices.push(index),
+            None => {
+                summary.record(Outcome::WordlistRejected);
+                sample.lap(Stage::Checksum);
+                if debug {
+                    error!("Invalid BIP-39 word: {}", redact::word(word));
+                }
+                return Ok(None);
+            }
+        }
+    }
+    // Most candidates fail here, before any string is built
+    if seed_format == SeedFormat::Bip39 && entropy::checksum_bits(indices.len()).is_some() && !entropy::is_valid(&indices) {
+        summary.record(Outcome::ChecksumRejected);
+        sample.lap(Stage::Checksum);
+        if debug {
+            error!("Checksum mismatch for '{}'", redact::phrase(mnemonic_words, &indices));
+        }
+        return Ok(None);
+    }
+
+    // The phrase, seeds, and keys are wiped once this candidate is done; the
+    // log only gets the phrase as a hash unless redaction is off
+    let mut mnemonic_str = Wiped(mnemonic_words.join(" "));
+    let shown = || redact::phrase(mnemonic_words, &indices);
+    if debug {
+        debug!("Testing mnemonic: {}", shown());
+    }
+
+    // An Electrum seed has no BIP-39 form, and only its version to check
+    let mnemonic = match seed_format {
+        SeedFormat::Bip39 => match Mnemonic::parse_in_normalized(Language::English, &mnemonic_str) {
+            Ok(m) => Some(m),
+            Err(e) => {
+                summary.record(Outcome::ChecksumRejected);
+                sample.lap(Stage::Checksum);
+                if debug {
+                    error!("Mnemonic validation failed for '{}': {}", shown(), e);
+                }
+                return Ok(None);
+            }
+        },
+        SeedFormat::Electrum if electrum::seed_type(&mnemonic_str).is_none() => {
+            summary.record(Outcome::ChecksumRejected);
+            sample.lap(Stage::Checksum);
+            if debug {
+                error!("Not an Electrum seed: '{}'", shown());
+            }
+            return Ok(None);
+        }
+        SeedFormat::Electrum | SeedFormat::Monero => None,
+    };
+
+    sample.lap(Stage::Checksum);
+
+    if tested.zip(mnemonic.as_ref()).is_some_and(|(tested, mnemonic)| tested.contains(mnemonic)) {
+        summary.record(Outcome::AlreadyTested);
+        return Ok(None);
+    }
+
+    for passphrase in passphrases.candidates() {
+        // A device or vector batch runs PBKDF2 for the passphrases in order,
+        // and any it leaves are run here
+        let seed = Wiped(seeds.as_deref_mut().and_then(Iterator::next).unwrap_or_else(|| match &mnemonic {
+            Some(mnemonic) => mnemonic.to_seed_normalized(&passphrase),
+            None => electrum::to_seed(&mnemonic_str, &passphrase),
+        }));
+        sample.lap(Stage::Pbkdf2);
+        // The network only affects how extended keys serialize, so one master
+        // key serves every network in the matrix
+        let xprv = Wiped(Xpriv::new_master(Network::Bitcoin, &*seed).map_err(|e| {
+            summary.record(Outcome::DerivationError);
+            if debug {
+                error!("Failed to derive master key for {}: {}", shown(), e);
+            }
+            anyhow::anyhow!("Failed to derive master key: {}", e)
+        })?);
+
+        // The keys along the previous path, so the next one only derives the
+        // levels below where the two branch (an address window derives a
+        // single step per path, and sibling accounts from their coin type)
+        let mut chain: Wiped<Vec<(ChildNumber, Xpriv)>> = Wiped(Vec::new());
+        for entry in &matrix.paths {
+            let children = entry.path.as_ref();
+            let shared = chain.iter().zip(children).take_while(|((cached, _), child)| cached == *child).count();
+            chain.drain(shared..).for_each(|mut level| level.wipe());
+            let derived = children[shared..]
+                .iter()
+                .try_for_each(|child| {
+                    let key = chain.last().map_or(*xprv, |(_, key)| *key).derive_priv(secp, &[*child])?;
+                    chain.push((*child, key));
+                    Ok::<_, bitcoin::bip32::Error>(())
+                })
+                .map(|_| chain.last().map_or(*xprv, |(_, key)| *key));
+            let child_xprv = Wiped(derived.map_err(|e| {
+                summary.record(Outcome::DerivationError);
+                if debug {
+                    error!("Failed to derive child key for {} at {}: {}", shown(), entry.path, e);
+                }
+                anyhow::anyhow!("Failed to derive child key: {}", e)
+            })?);
+
+            sample.lap(Stage::Bip32);
+
+            let pubkey = bitcoin::PublicKey::new(child_xprv.private_key.public_key(secp));
+            for encoding in &entry.encodings {
+                // A hash database is checked before anything is encoded, so
+                // only its hits are formatted, for the report
+                if let Some(db) = targets.hash_db {
+                    let hit = hashdb::pubkey_key(&pubkey, &encoding.address_type).is_some_and(|key| db.contains(&key));
+                    sample.lap(Stage::Matching);
+                    if !hit {
+                        continue;
+                    }
+                }
+                // A Core Lightning node key comes from the seed, not the path
+                let addr_str = if encoding.address_type == lightning::CLN_NODE_ID {
+                    lightning::cln_node_id(&*seed, secp)?
+                } else if encoding.address_type == xpub::ACCOUNT_XPUB {
+                    // The path is the account's, and its public key is compared whole
+                    xpub::encode(&child_xprv, secp)
+                } else if encoding.address_type == xpub::MASTER_FINGERPRINT {
+                    xpub::fingerprint(&child_xprv, secp)
+                } else {
+                    let addr = match encode_address(&pubkey, &encoding.address_type, encoding.network) {
+                        Some(addr) => addr,
+                        None => {
+                            summary.record(Outcome::UnsupportedType);
+                            if debug {
+                                error!("Unsupported address type: {}", encoding.address_type);
+                            }
+                            return Ok(None);
+                        }
+                    };
+                    let addr = addr.inspect_err(|e| {
+                        summary.record(Outcome::EncodingError);
+                        if debug {
+                            error!("Failed to create address for {}: {}", shown(), e);
+                        }
+                    })?;
+
+                    match matrix.overrides.encode(&pubkey, &encoding.address_type) {
+                        Some(custom) => custom.inspect_err(|_| summary.record(Outcome::EncodingError))?,
+                        None => addr,
+                    }
+                };
+                sample.lap(Stage::Hashing);
+                if debug {
+                    debug!("Derived address for '{}' at {} ({}): {}", shown(), entry.path, encoding.address_type, addr_str);
+                }
+
+                let is_match = targets.matches(&addr_str)?;
+                sample.lap(Stage::Matching);
+
+                if is_match {
+                    summary.record(Outcome::Matched);
+                    let variant = Variant {
+                        path: entry.path.clone(),
+                        address_type: encoding.address_type.clone(),
+                        network: encoding.network,
+                    };
+                    let mnemonic = std::mem::take(&mut *mnemonic_str);
+                    return Ok(Some(Match { mnemonic, passphrase, address: addr_str, variant }));
+                }
+            }
+        }
+    }
+
+    summary.record(Outcome::NoMatch);
+    if let Some((tested, mnemonic)) = tested.zip(mnemonic.as_ref()) {
+        tested.insert(mnemonic)?;
+    }
+    Ok(None)
+}
+
+/// [`try_mnemonic`] for a Monero seed, which has no passphrase or path: the
+/// words and checksum word, then the one standard address the spend key in
+/// them gives.
+fn try_monero(
+    mnemonic_words: &[String],
+    targets: &Targets,
+    summary: &RunSummary,
+    stages: &StageStats,
+    debug: bool,
+) -> Result<Option<Match>> {
+    let mut sample = stages.sample();
+    if let Some(word) = mnemonic_words.iter().find(|word| monero::index(word).is_none()) {
+        summary.record(Outcome::WordlistRejected);
+        sample.lap(Stage::Checksum);
+        if debug {
+            error!("Invalid Monero word: {}", redact::word(word));
+        }
+        return Ok(None);
+    }
+    let Some(addr_str) = monero::seed_address(mnemonic_words) else {
+        summary.record(Outcome::ChecksumRejected);
+        sample.lap(Stage::Checksum);
+        if debug {
+            let indices: Vec<u32> = mnemonic_words.iter().filter_map(|word| monero::index(word)).collect();
+            error!("Checksum word mismatch for '{}'", redact::phrase(mnemonic_words, &indices));
+        }
+        return Ok(None);
+    };
+    sample.lap(Stage::Hashing);
+    // A hash database holds no Monero keys
+    let is_match = targets.hash_db.is_none() && targets.matches(&addr_str)?;
+    sample.lap(Stage::Matching);
+    if !is_match {
+        summary.record(Outcome::NoMatch);
+        return Ok(None);
+    }
+    summary.record(Outcome::Matched);
+    let variant = Variant { path: DerivationPath::master(), address_type: monero::XMR.to_string(), network: Network::Bitcoin };
+    Ok(Some(Match { mnemonic: mnemonic_words.join(" "), passphrase: String::new(), address: addr_str, variant }))
+}
+
+/// How the known words fill the permutable positions: in every order, only
+/// in orders near the one given, or as every choice from a larger pool.
+#[derive(Clone, Copy)]
+pub enum Arrangement<'a> {
+    /// Every order of this many words
+    All(usize),
+    Bounded(&'a BoundedOrder),
+    Pool(&'a WordPool),
+}
+
+impl<'a> Arrangement<'a> {
+    /// The arrangement a search asked for, every order of the `permutable`
+    /// words unless bounded or drawn from a pool.
+    pub fn of(bounded: Option<&'a BoundedOrder>, pool: Option<&'a WordPool>, permutable: usize) -> Self {
+        match (bounded, pool) {
+            (Some(bounded), _) => Self::Bounded(bounded),
+            (None, Some(pool)) => Self::Pool(pool),
+            (None, None) => Self::All(permutable),
+        }
+    }
+
+    pub fn space(&self) -> u64 {
+        match self {
+            Self::All(permutable) => permutation::factorial(*permutable),
+            Self::Bounded(bounded) => bounded.space(),
+            Self::Pool(pool) => pool.space(),
+        }
+    }
+}
+
+/// Number of candidate ranks in the search: tokenlist selections, seedlist
+/// lines, combinations of per-position substitutes, arrangements of the
+/// known words (times the corrections of misspelled ones), or orderings
+/// combined with every fill of the missing words.
+pub fn search_space(
+    tokenlist: Option<&TokenList>,
+    seedlist: Option<&str>,
+    substitutes: Option<&Substitutes>,
+    fuzzy: Option<&FuzzyWords>,
+    missing: Option<&MissingWords>,
+    arrangement: Arrangement,
+) -> Result<u64> {
+    match (tokenlist, seedlist, substitutes, fuzzy, missing) {
+        (Some(tokenlist), _, _, _, _) => Ok(tokenlist.space()),
+        (None, Some(file), _, _, _) => seedlist::count(file),
+        (None, None, Some(substitutes), _, _) => Ok(substitutes.space()),
+        (None, None, None, Some(fuzzy), _) => Ok(fuzzy.space()),
+        (None, None, None, None, Some(missing)) => Ok(missing.space()),
+        (None, None, None, None, None) => Ok(arrangement.space()),
+    }
+}
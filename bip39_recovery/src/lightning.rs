@@ -0,0 +1,43 @@
+use std::str::FromStr;
+
+use anyhow::Result;
+use bitcoin::hashes::hmac::{Hmac, HmacEngine};
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use secp256k1::{PublicKey, Secp256k1, SecretKey};
+
+/// Matrix encoding whose "address" is a Core Lightning node ID.
+pub const CLN_NODE_ID: &str = "cln-node-id";
+
+/// Normalizes a node ID (a compressed public key in hex) for matching.
+pub fn parse_node_id(node_id: &str) -> Result<String> {
+    let public_key = PublicKey::from_str(node_id.trim())
+        .map_err(|e| anyhow::anyhow!("Invalid node ID {}: {}", node_id, e))?;
+    Ok(public_key.to_string())
+}
+
+/// The node ID Core Lightning derives from a BIP-39 seed. Its `hsm_secret`
+/// is the first 32 bytes of the seed (as `lightning-hsmtool generatehsm`
+/// writes it), and the node key is HKDF-SHA256 of that secret with info
+/// `nodeid` and a little-endian u32 salt counted up from 0 until the output
+/// is a valid key.
+pub fn cln_node_id(seed: &[u8], secp: &Secp256k1<secp256k1::All>) -> Result<String> {
+    let hsm_secret = seed.get(..32).ok_or_else(|| anyhow::anyhow!("Seed is shorter than an hsm_secret"))?;
+    for salt in 0u32.. {
+        let okm = hkdf_sha256(&salt.to_le_bytes(), hsm_secret, b"nodeid");
+        if let Ok(secret_key) = SecretKey::from_slice(&okm) {
+            return Ok(PublicKey::from_secret_key(secp, &secret_key).to_string());
+        }
+    }
+    Err(anyhow::anyhow!("No valid node key for this seed"))
+}
+
+/// RFC 5869 HKDF with SHA-256, for a single 32-byte block of output.
+fn hkdf_sha256(salt: &[u8], ikm: &[u8], info: &[u8]) -> [u8; 32] {
+    let mut engine = HmacEngine::<sha256::Hash>::new(salt);
+    engine.input(ikm);
+    let prk = Hmac::<sha256::Hash>::from_engine(engine);
+    let mut engine = HmacEngine::<sha256::Hash>::new(prk.as_byte_array());
+    engine.input(info);
+    engine.input(&[1]);
+    Hmac::<sha256::Hash>::from_engine(engine).to_byte_array()
+}
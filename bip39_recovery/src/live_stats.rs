@@ -0,0 +1,70 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use serde::Serialize;
+
+/// Snapshot of run health polled by external watchdogs and dashboards.
+#[derive(Clone, Debug, Serialize)]
+pub struct LiveStats {
+    pub phase: &'static str,
+    pub processed: u64,
+    pub total: u64,
+    pub percent: f64,
+    pub speed: f64,
+    pub eta_seconds: Option<f64>,
+    pub matches: u64,
+    pub elapsed_seconds: f64,
+    pub last_checkpoint: Option<u64>,
+    pub updated: u64,
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// Writes `stats.json` atomically (temp file + rename) so readers never see
+/// a half-written file.
+pub struct LiveStatsWriter {
+    path: PathBuf,
+    last: Mutex<Option<LiveStats>>,
+}
+
+impl LiveStatsWriter {
+    pub fn new(path: &str) -> Self {
+        Self { path: PathBuf::from(path), last: Mutex::new(None) }
+    }
+
+    pub fn write(&self, stats: LiveStats) -> Result<()> {
+        let json = serde_json::to_string_pretty(&stats)?;
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        fs::write(&tmp, json)
+            .map_err(|e| anyhow::anyhow!("Failed to write stats file {:?}: {}", tmp, e))?;
+        fs::rename(&tmp, &self.path)
+            .map_err(|e| anyhow::anyhow!("Failed to replace stats file {}: {}", self.path.display(), e))?;
+        *self.last.lock().unwrap() = Some(stats);
+        Ok(())
+    }
+
+    /// Rewrites the most recent snapshot with a terminal phase such as
+    /// `finished` or `interrupted` and the final processed count.
+    pub fn finish(&self, phase: &'static str, processed: u64, last_checkpoint: Option<u64>) -> Result<()> {
+        let last = self.last.lock().unwrap().clone();
+        match last {
+            Some(mut stats) => {
+                stats.phase = phase;
+                stats.processed = processed;
+                if stats.total > 0 {
+                    stats.percent = processed as f64 * 100.0 / stats.total as f64;
+                }
+                stats.last_checkpoint = last_checkpoint.or(stats.last_checkpoint);
+                stats.updated = unix_now();
+                self.write(stats)
+            }
+            None => Ok(()),
+        }
+    }
+}
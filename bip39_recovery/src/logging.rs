@@ -0,0 +1,178 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use clap::ValueEnum;
+
+/// Log file writer that rotates `recovery.log` to `recovery.log.1`,
+/// `recovery.log.2`, ... once it exceeds a size or age limit, keeping at most
+/// `keep` old files so `--debug` runs can't fill the disk.
+pub struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    written: u64,
+    opened: Instant,
+    max_size: Option<u64>,
+    max_age: Option<Duration>,
+    keep: usize,
+    at_line_start: bool,
+}
+
+impl RotatingFile {
+    pub fn create(path: &str, max_size: Option<u64>, max_age: Option<Duration>, keep: usize) -> io::Result<Self> {
+        let path = PathBuf::from(path);
+        let file = File::create(&path)?;
+        Ok(Self { path, file, written: 0, opened: Instant::now(), max_size, max_age, keep, at_line_start: true })
+    }
+
+    fn rotated_path(&self, generation: usize) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{}", generation));
+        PathBuf::from(name)
+    }
+
+    fn needs_rotation(&self) -> bool {
+        self.max_size.is_some_and(|max| self.written >= max)
+            || self.max_age.is_some_and(|max| self.opened.elapsed() >= max)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        if self.keep == 0 {
+            // Nothing to retain, just start the current file over
+            self.file = OpenOptions::new().write(true).truncate(true).open(&self.path)?;
+        } else {
+            let _ = fs::remove_file(self.rotated_path(self.keep));
+            for generation in (1..self.keep).rev() {
+                let from = self.rotated_path(generation);
+                if from.exists() {
+                    fs::rename(&from, self.rotated_path(generation + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.rotated_path(1))?;
+            self.file = File::create(&self.path)?;
+        }
+        self.written = 0;
+        self.opened = Instant::now();
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // Only rotate between records so no line is split across files
+        if self.at_line_start && self.needs_rotation() {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.written += written as u64;
+        if written > 0 {
+            self.at_line_start = buf[written - 1] == b'\n';
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// What a full log queue does with the next line.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum Overflow {
+    /// Wait for the writer thread to catch up
+    Block,
+    /// Discard the line and count it
+    Drop,
+}
+
+enum Message {
+    Line(Vec<u8>),
+    Flush(SyncSender<()>),
+}
+
+/// Hands complete log lines to a writer thread over a bounded queue, so
+/// worker threads logging (e.g. with `--debug`) never wait on the disk
+/// unless the queue fills up. Dropped lines are reported in the log once
+/// the writer gets to the next one.
+pub struct AsyncWriter {
+    sender: SyncSender<Message>,
+    pending: Vec<u8>,
+    overflow: Overflow,
+    dropped: Arc<AtomicU64>,
+}
+
+impl AsyncWriter {
+    pub fn spawn<W: Write + Send + 'static>(inner: W, capacity: usize, overflow: Overflow) -> io::Result<Self> {
+        let (sender, receiver) = mpsc::sync_channel(capacity.max(1));
+        let dropped = Arc::new(AtomicU64::new(0));
+        let writer_dropped = Arc::clone(&dropped);
+        thread::Builder::new()
+            .name("log-writer".to_string())
+            .spawn(move || write_lines(inner, receiver, &writer_dropped))?;
+        Ok(Self { sender, pending: Vec::new(), overflow, dropped })
+    }
+
+    fn send(&self, line: Vec<u8>) -> io::Result<()> {
+        let closed = || io::Error::new(io::ErrorKind::BrokenPipe, "log writer thread has stopped");
+        match self.overflow {
+            Overflow::Block => self.sender.send(Message::Line(line)).map_err(|_| closed()),
+            Overflow::Drop => match self.sender.try_send(Message::Line(line)) {
+                Ok(()) => Ok(()),
+                Err(TrySendError::Full(_)) => {
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                    Ok(())
+                }
+                Err(TrySendError::Disconnected(_)) => Err(closed()),
+            },
+        }
+    }
+}
+
+fn write_lines<W: Write>(mut inner: W, receiver: Receiver<Message>, dropped: &AtomicU64) {
+    for message in receiver {
+        let lost = dropped.swap(0, Ordering::Relaxed);
+        if lost > 0 {
+            let _ = writeln!(inner, "[log queue full, {} line(s) dropped]", lost);
+        }
+        match message {
+            Message::Line(line) => {
+                let _ = inner.write_all(&line);
+            }
+            Message::Flush(done) => {
+                let _ = inner.flush();
+                let _ = done.send(());
+            }
+        }
+    }
+}
+
+impl Write for AsyncWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // Records arrive in pieces; only whole lines are queued so the drop
+        // policy never cuts one in half
+        self.pending.extend_from_slice(buf);
+        while let Some(end) = self.pending.iter().position(|&byte| byte == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=end).collect();
+            self.send(line)?;
+        }
+        Ok(buf.len())
+    }
+
+    /// Waits until everything queued so far is on disk.
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.pending.is_empty() {
+            let line = std::mem::take(&mut self.pending);
+            self.send(line)?;
+        }
+        let (done, finished) = mpsc::sync_channel(1);
+        let closed = || io::Error::new(io::ErrorKind::BrokenPipe, "log writer thread has stopped");
+        self.sender.send(Message::Flush(done)).map_err(|_| closed())?;
+        finished.recv().map_err(|_| closed())
+    }
+}
@@ -1,235 +1,1712 @@
-use std::fs::{self, File};
+use std::fs;
 use std::io::{self, BufRead, BufReader, Write};
-use bitcoin::{Address, Network};
-use bitcoin::bip32::{DerivationPath, Xpriv};
-use bip39::{Language, Mnemonic};
-use clap::Parser;
+use bitcoin::bip32::DerivationPath;
+use bitcoin::hashes::{sha256, Hash};
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
 use anyhow::Result;
-use rayon::prelude::*;
-use patricia_tree::PatriciaMap;
-use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
-use std::time::Instant;
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
 use indicatif::{ProgressBar, ProgressStyle};
-use std::process;
+use std::process::{self, ExitCode};
 use std::collections::HashSet;
-use log::{info, error, debug};
+use log::{info, warn, error};
 use simplelog::{CombinedLogger, TermLogger, WriteLogger, LevelFilter, Config};
-use itertools::Itertools;
-use ctrlc;
 use secp256k1::Secp256k1;
 
+use bip39_recovery::{
+    bloom, bounded, chunks, coins, electrum, entropy, ethereum, fuzzy, gpu, hashdb, lightning, matrix, missing, monero, overrides, passphrase, paths,
+    pathspec, pbkdf2, permutation, pipeline, pool, positions, redact, seedlist, slip39, stages, substitutes, summary, targets, tested, tokenlist, verify, weighted,
+    wipe, wordindex, workers, xpub,
+};
+use bip39_recovery::{encode_address, search_space, try_mnemonic, Arrangement, Bip39Wordlist, Match, SeedFormat, Targets};
+
+mod beacon;
+mod bench;
+mod bip47;
+mod chain;
+mod coverage;
+mod daemon;
+mod derive;
+mod descriptors;
+mod discovery;
+mod dashboard;
+mod encrypt;
+mod estimate;
+mod jobfile;
+mod keys;
+mod export;
+mod live_stats;
+mod logging;
+mod output;
+mod pathsearch;
+mod power;
+mod progress;
+mod ratelimit;
+mod resume;
+mod selftest;
+mod shamir;
+mod shardplan;
+mod signals;
+mod state;
+mod stop;
+mod systemd;
+mod worker;
+mod workserver;
+
+use bloom::AddressBloom;
+use bounded::{Bound, BoundedOrder};
+use coverage::{Checkpoint, Frontier, Shard};
+use fuzzy::FuzzyWords;
+use gpu::{Accelerator, Gpu, Seed};
+use hashdb::HashDb;
+use live_stats::{LiveStats, LiveStatsWriter};
+use encrypt::Recipient;
+use logging::{AsyncWriter, RotatingFile};
+use output::{Completion, MatchRecord, ResultsWriter};
+use matrix::SearchMatrix;
+use missing::MissingWords;
+use overrides::AddressOverrides;
+use passphrase::Passphrases;
+use pbkdf2::{Pbkdf2Batch, SeedStream};
+use pool::WordPool;
+use positions::KnownPositions;
+use targets::AddressPattern;
+use permutation::PermutationRange;
+use pipeline::{Batch, Feed, Ranked};
+use power::{PowerSource, Throttle};
+use progress::{filtered_percent, EtaModel};
+use ratelimit::RateLimiter;
+use signals::Request;
+use stages::StageStats;
+use state::StateDir;
+use substitutes::Substitutes;
+use summary::RunSummary;
+use stop::{SpeedWindow, StopConditions, StopReason};
+use tested::TestedSet;
+use tokenlist::TokenList;
+use weighted::{PositionWeights, WeightedRange};
+use wipe::Wiped;
+use wordindex::{IndexBase, Resolver};
+use workers::{WorkerRates, WorkerStats};
+
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
+#[command(subcommand_negates_reqs = true, args_conflicts_with_subcommands = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// TOML job file setting any of these options by their long names;
+    /// options given on the command line override it
+    #[arg(long, value_name = "FILE")]
+    config: Option<String>,
     #[arg(long, conflicts_with_all = ["address_file", "address_db_file"])]
     address: Option<String>,
     #[arg(long, conflicts_with_all = ["address", "address_db_file"])]
     address_file: Option<String>,
     #[arg(long, conflicts_with_all = ["address", "address_file"])]
     address_db_file: Option<String>,
+    #[arg(long, conflicts_with_all = ["address", "address_file", "address_db_file"])]
+    descriptors: Option<String>,
+    /// Address list matched through a bloom filter instead of loaded as a
+    /// set, for lists too large to hold in memory
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "address", "address_file", "address_db_file", "descriptors", "payment_code", "node_id", "xpub", "fingerprint",
+            "address_pattern", "daemon"
+        ]
+    )]
+    address_bloom: Option<String>,
+    /// False positive rate the bloom filter is sized for; each false positive
+    /// costs a scan of the address list
+    #[arg(long, default_value = "0.000001", requires = "address_bloom")]
+    bloom_fp_rate: f64,
+    /// Prebuilt bloom filter, loaded when it matches the address list and
+    /// otherwise (re)built and saved here
+    #[arg(long, requires = "address_bloom")]
+    bloom_cache: Option<String>,
+    /// Sorted hash160 database written by build-db, matched without
+    /// encoding any address
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "address", "address_file", "address_db_file", "descriptors", "payment_code", "node_id", "xpub", "fingerprint",
+            "address_pattern", "address_bloom", "daemon"
+        ]
+    )]
+    hash_db: Option<String>,
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "address", "address_file", "address_db_file", "descriptors", "matrix", "coin", "path", "path_ranges", "address_type",
+            "address_index_range", "change"
+        ]
+    )]
+    payment_code: Option<String>,
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "address", "address_file", "address_db_file", "descriptors", "payment_code", "matrix", "coin", "path",
+            "path_ranges", "address_type", "coin_type_list", "legacy_paths", "toggle_hardening", "address_index_range",
+            "change"
+        ]
+    )]
+    node_id: Option<String>,
+    /// Account-level extended public key (xpub, ypub, zpub, or a testnet
+    /// form) to match instead of an address
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "address", "address_file", "address_db_file", "descriptors", "payment_code", "node_id", "address_pattern",
+            "matrix", "coin", "address_type", "legacy_paths", "address_index_range", "change"
+        ]
+    )]
+    xpub: Option<String>,
+    /// BIP32 master key fingerprint (8 hex digits, as in descriptors and
+    /// PSBTs) to match instead of an address
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "address", "address_file", "address_db_file", "descriptors", "payment_code", "node_id", "xpub",
+            "address_pattern", "matrix", "coin", "path", "path_ranges", "address_type", "coin_type_list", "legacy_paths",
+            "toggle_hardening", "address_index_range", "change"
+        ]
+    )]
+    fingerprint: Option<String>,
+    #[arg(
+        long,
+        conflicts_with_all = ["address", "address_file", "address_db_file", "descriptors", "payment_code", "node_id"]
+    )]
+    address_pattern: Option<String>,
+    #[arg(long, default_value = "20")]
+    gap_limit: u32,
     #[arg(long)]
-    total_words: usize,
+    discover_accounts: bool,
+    /// Esplora (`http://host:port[/api]`) or Electrum (`tcp://host:port`)
+    /// server to look addresses up on: --discover-accounts then checks
+    /// account usage on chain, and --descriptors derives each chain until
+    /// --gap-limit unused addresses past its last used one
     #[arg(long)]
-    fixed_words: usize,
+    chain: Option<String>,
+    /// Addresses looked up per second
+    #[arg(long, default_value = "5", requires = "chain")]
+    chain_rate: f64,
+    /// Addresses per request; Electrum gets each as one JSON-RPC batch
+    #[arg(long, default_value = "20", requires = "chain")]
+    chain_batch: usize,
+    /// Cache of the lookups in the state directory, so an interrupted scan
+    /// asks only for the rest
+    #[arg(long, default_value = "chain-cache.json", requires = "chain")]
+    chain_cache: String,
+    /// Seconds a cached "unused" answer is trusted before the address is
+    /// looked up again; used addresses are never looked up twice
+    #[arg(long, default_value = "86400", requires = "chain")]
+    chain_cache_age: u64,
+    #[arg(long, required = true)]
+    total_words: Option<usize>,
+    #[arg(long, required_unless_present_any = ["tokenlist", "seedlist", "candidates_file", "known_positions"])]
+    fixed_words: Option<usize>,
     #[arg(long, value_delimiter = ',', conflicts_with = "seed_words_file")]
     known_words: Vec<String>,
+    /// Words remembered at particular positions, e.g. `1:abandon,5:ocean`;
+    /// the known words are then the others, searched over the positions left
+    #[arg(
+        long,
+        value_delimiter = ',',
+        conflicts_with_all = ["fixed_words", "tokenlist", "seedlist", "candidates_file", "position_weights", "export_candidates"]
+    )]
+    known_positions: Vec<String>,
     #[arg(long)]
     seed_words_file: Option<String>,
-    #[arg(long, default_value = "m/44'/0'/0'/0/0")]
+    #[arg(long, value_enum, default_value = "auto")]
+    word_index_base: IndexBase,
+    /// How a phrase becomes a seed: BIP39's checksum and PBKDF2, or
+    /// Electrum's version prefix and seed, with Electrum's wallet paths by
+    /// default
+    #[arg(long, value_enum, default_value = "bip39", conflicts_with_all = ["gpu", "tested_set", "discover_accounts"])]
+    seed_format: SeedFormat,
+    #[arg(long, conflicts_with_all = ["tokenlist", "seedlist", "position_weights", "export_candidates"])]
+    missing_words: Option<usize>,
+    #[arg(long, value_delimiter = ',', requires = "missing_words")]
+    missing_positions: Vec<usize>,
+    #[arg(long, default_value = DEFAULT_PATH)]
     path: String,
+    #[arg(long, value_delimiter = ',')]
+    path_ranges: Vec<String>,
+    /// Wallet layouts to check in one pass (bip44, bip49, bip84, bip86), each
+    /// at its first receive address and as the address type it implies
+    #[arg(
+        long,
+        value_delimiter = ',',
+        conflicts_with_all = [
+            "path", "path_ranges", "address_type", "matrix", "coin", "payment_code", "node_id", "xpub", "fingerprint"
+        ]
+    )]
+    path_preset: Vec<String>,
+    #[arg(long)]
+    address_index_range: Option<String>,
+    #[arg(long, value_delimiter = ',')]
+    change: Vec<u32>,
     #[arg(long, default_value = "10000")]
     batch_size: usize,
+    /// CPU threads to search with; defaults to the physical cores
+    #[arg(long)]
+    threads: Option<usize>,
+    /// Searches with fewer candidates than this run on a single thread
+    #[arg(long, default_value = "1000")]
+    parallel_threshold: u64,
+    /// Compute seeds one candidate at a time instead of batching them on
+    /// the CPU's vector units (AVX-512, AVX2, or NEON)
+    #[arg(long)]
+    no_simd: bool,
     #[arg(long)]
     gpu: bool,
+    /// API the GPU runs through; by default CUDA, falling back to OpenCL
+    #[arg(long, value_enum, requires = "gpu")]
+    accelerator: Option<Accelerator>,
+    /// GPU kernel to load instead of the one built into the binary: PTX
+    /// under CUDA, or OpenCL C source under OpenCL
+    #[arg(long, alias = "gpu-kernel", requires = "gpu")]
+    kernel_path: Option<String>,
+    /// Device ordinals to use, e.g. 0,2; all devices by default. OpenCL
+    /// numbers the GPUs of every platform together
+    #[arg(long, value_delimiter = ',', requires = "gpu")]
+    gpu_devices: Vec<u32>,
     #[arg(long, default_value = "mainnet")]
     network: String,
-    #[arg(long, default_value = "p2wpkh")]
-    address_type: String,
+    /// Address types checked for each candidate, e.g. p2pkh,p2wpkh, or `all`
+    /// for every Bitcoin type
+    #[arg(long, value_delimiter = ',', default_value = "p2wpkh")]
+    address_type: Vec<String>,
     #[arg(long)]
     debug: bool,
+    /// Log mnemonics only as hashes of their word indices, and leave the
+    /// known words and passphrases out of the logged arguments; pass
+    /// `--redact false` to log them in full
+    #[arg(long, default_value_t = true, action = clap::ArgAction::Set)]
+    redact: bool,
     #[arg(long, default_value = "recovery.log")]
     log_file: String,
     #[arg(long, default_value = "progress.txt")]
     progress_file: String,
+    #[arg(long, default_value = "results.txt")]
+    results_file: String,
+    #[arg(long)]
+    state_dir: Option<String>,
+    #[arg(long)]
+    phase: Option<String>,
+    #[arg(long)]
+    tested_set: Option<String>,
+    #[arg(long)]
+    find_all: bool,
+    #[arg(long)]
+    stop_after_matches: Option<usize>,
+    #[arg(long)]
+    stop_at_coverage: Option<f64>,
+    #[arg(long)]
+    stop_at_index: Option<u64>,
+    #[arg(long)]
+    max_runtime: Option<u64>,
+    #[arg(long)]
+    min_speed: Option<f64>,
+    #[arg(long, default_value = "60")]
+    min_speed_window: u64,
+    #[arg(long, conflicts_with_all = ["start_index", "end_index"])]
+    resume: Option<String>,
+    #[arg(long, default_value = "0")]
+    start_index: u64,
+    #[arg(long)]
+    end_index: Option<u64>,
+    #[arg(long)]
+    systemd: bool,
+    #[arg(long)]
+    auto_path: bool,
+    #[arg(long, default_value = "0")]
+    log_max_size_mb: u64,
+    #[arg(long, default_value = "0")]
+    log_rotate_hours: u64,
+    #[arg(long, default_value = "5")]
+    log_keep: usize,
+    #[arg(long, default_value = "8192")]
+    log_queue: usize,
+    #[arg(long, value_enum, default_value = "block")]
+    log_overflow: logging::Overflow,
+    #[arg(long)]
+    stage_stats: bool,
+    #[arg(long)]
+    worker_stats_interval: Option<u64>,
+    #[arg(long)]
+    stats_file: Option<String>,
+    #[arg(long, default_value = "5")]
+    stats_interval: u64,
+    #[arg(long)]
+    matrix: Option<String>,
+    #[arg(long, conflicts_with = "matrix")]
+    coin: Option<String>,
+    #[arg(long, value_delimiter = ',')]
+    coin_type_list: Vec<u32>,
+    #[arg(long, num_args = 0.., value_delimiter = ',')]
+    legacy_paths: Option<Vec<String>>,
+    #[arg(long, value_delimiter = ',')]
+    toggle_hardening: Vec<usize>,
+    #[arg(long, default_value = coins::DEFAULT_FILE)]
+    coins_file: String,
+    /// Write the permutations that pass the checksum to this file instead of
+    /// testing them; an order that only swaps two copies of a repeated word
+    /// is written once
+    #[arg(long)]
+    export_candidates: Option<String>,
+    #[arg(long, conflicts_with_all = ["tokenlist", "seedlist", "export_candidates"])]
+    position_weights: Option<String>,
+    #[arg(long, conflicts_with_all = ["fixed_words", "known_words", "seed_words_file", "export_candidates"])]
+    tokenlist: Option<String>,
+    #[arg(
+        long,
+        visible_alias = "passwordlist",
+        conflicts_with_all = ["fixed_words", "known_words", "seed_words_file", "export_candidates", "tokenlist"]
+    )]
+    seedlist: Option<String>,
+    /// File listing the possible words of each position, one line per
+    /// position; every combination is tried
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "fixed_words", "known_words", "seed_words_file", "export_candidates", "tokenlist", "seedlist", "missing_words",
+            "position_weights"
+        ]
+    )]
+    candidates_file: Option<String>,
+    /// Try known words that aren't on the BIP39 list as the list words close
+    /// to them (same first four letters, or at most two edits away)
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "tokenlist", "seedlist", "candidates_file", "missing_words", "position_weights", "export_candidates"
+        ]
+    )]
+    fuzzy_words: bool,
+    /// Take known words that aren't on the BIP39 list as the start of one
+    /// (e.g. "abou" for "about") and try every list word they begin
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "tokenlist", "seedlist", "candidates_file", "missing_words", "position_weights", "export_candidates",
+            "fuzzy_words"
+        ]
+    )]
+    prefix_mode: bool,
+    /// Only try orders where each permutable word is at most this many
+    /// positions from where it was given
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "tokenlist", "seedlist", "candidates_file", "missing_words", "position_weights", "export_candidates",
+            "fuzzy_words", "prefix_mode", "swaps_only"
+        ]
+    )]
+    max_displacement: Option<usize>,
+    /// Only try orders at most this many swaps of neighbouring words away
+    /// from the one given
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "tokenlist", "seedlist", "candidates_file", "missing_words", "position_weights", "export_candidates",
+            "fuzzy_words", "prefix_mode"
+        ]
+    )]
+    swaps_only: Option<usize>,
+    /// Number of known words given when there are more candidates than
+    /// positions; every choice of the total words from them is tried
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "tokenlist", "seedlist", "candidates_file", "missing_words", "position_weights", "export_candidates",
+            "fuzzy_words", "prefix_mode", "max_displacement", "swaps_only", "known_positions"
+        ]
+    )]
+    pool_size: Option<usize>,
+    /// The pool words are in their true order, so only which to drop is
+    /// searched
+    #[arg(long, requires = "pool_size")]
+    ordered_pool: bool,
+    #[arg(long, value_enum, default_value = "plain")]
+    results_format: ResultsFormat,
+    /// Also record matches and, at the end, the run's statistics as JSON
+    #[arg(long, value_name = "FILE")]
+    output: Option<String>,
+    /// Write matches to the results and output files only encrypted: to an
+    /// age recipient or SSH key, a GPG recipient, or a passphrase given as
+    /// passphrase:<PASSPHRASE> or passphrase-env:<VAR>
+    #[arg(long, value_name = "RECIPIENT")]
+    encrypt_result: Option<String>,
+    /// Keep recovered mnemonics and passphrases off the screen as well
+    #[arg(long, requires = "encrypt_result")]
+    hide_result: bool,
+    #[arg(long, visible_alias = "passphrase-list")]
+    passphrase_file: Option<String>,
+    /// A BIP-39 passphrase to try; repeat for several. Tried before those of
+    /// --passphrase-file
+    #[arg(long = "passphrase", value_name = "PASSPHRASE")]
+    passphrases: Vec<String>,
+    #[arg(long)]
+    passphrase_rules: Vec<String>,
+    #[arg(long)]
+    passphrase_unicode_variants: bool,
+    #[arg(long)]
+    battery_threads: Option<usize>,
+    #[arg(long)]
+    battery_pause_percent: Option<u8>,
+    #[arg(long)]
+    max_rate: Option<f64>,
+    #[arg(long, value_delimiter = ',')]
+    backend_max_rate: Vec<String>,
+    #[arg(long, requires = "state_dir")]
+    daemon: bool,
+    #[arg(long, conflicts_with = "daemon")]
+    interactive: bool,
+    #[arg(long)]
+    dashboard: Option<String>,
+    #[arg(long)]
+    skip_self_check: bool,
+    #[arg(long)]
+    bech32_hrp: Option<String>,
+    #[arg(long)]
+    p2pkh_version: Option<String>,
+    #[arg(long)]
+    p2sh_version: Option<String>,
 }
 
-struct Bip39Wordlist {
-    wordlist: PatriciaMap<()>,
+const DEFAULT_PATH: &str = "m/44'/0'/0'/0/0";
+
+/// First BIP-86 receive address, the default for `--address-type p2tr`.
+const TAPROOT_PATH: &str = "m/86'/0'/0'/0/0";
+
+/// First Ethereum account, the default for `--address-type eth`.
+const ETHEREUM_PATH: &str = "m/44'/60'/0'/0/0";
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum ResultsFormat {
+    /// `<address> <mnemonic> [<path> <type> <network>]`
+    Plain,
+    /// `Seed found: <mnemonic>`, as printed by btcrecover
+    Btcrecover,
 }
 
-impl Bip39Wordlist {
-    fn new(wordlist_path: &str) -> Result<Self> {
-        let file = fs::File::open(wordlist_path)
-            .map_err(|e| anyhow::anyhow!("Failed to open wordlist file {}: {}", wordlist_path, e))?;
-        let reader = BufReader::new(file);
-        let mut wordlist = PatriciaMap::new();
-        for line in reader.lines() {
-            let line = line.map_err(|e| anyhow::anyhow!("Failed to read wordlist file: {}", e))?;
-            wordlist.insert(line.trim(), ());
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Inspect and combine checkpoints
+    #[command(subcommand)]
+    Resume(ResumeCommand),
+    /// Send a command (status, pause, resume, stop, add-target <address>) to a daemon
+    Control {
+        /// State directory the daemon was started with
+        #[arg(long)]
+        state_dir: String,
+        #[arg(required = true)]
+        command: Vec<String>,
+    },
+    /// Measure throughput across backends, threads, address types, path counts, and gap limits
+    Bench {
+        /// Seconds spent on each configuration
+        #[arg(long, default_value = "2")]
+        seconds: f64,
+        #[arg(long, value_enum, value_delimiter = ',', default_value = "cpu")]
+        backends: Vec<bench::Backend>,
+        /// Thread counts to try; defaults to all available cores
+        #[arg(long, value_delimiter = ',')]
+        threads: Vec<usize>,
+        #[arg(long, value_delimiter = ',', default_value = "p2pkh,p2sh-p2wpkh,p2wpkh")]
+        address_types: Vec<String>,
+        /// Accounts derived per candidate
+        #[arg(long, value_delimiter = ',', default_value = "1")]
+        path_counts: Vec<usize>,
+        /// Receive addresses checked per account
+        #[arg(long, value_delimiter = ',', default_value = "1")]
+        gap_limits: Vec<usize>,
+        #[arg(long, value_enum, value_delimiter = ',', default_value = "address,hash160")]
+        match_modes: Vec<bench::MatchMode>,
+    },
+    /// Split a search across machines in proportion to their measured throughput
+    ShardPlan {
+        /// Lines of `<name> <candidates per second>`, e.g. rates from `bench`
+        #[arg(long)]
+        machines: String,
+        /// Write a `<name>.sh` work unit per machine into this directory
+        #[arg(long)]
+        output_dir: Option<String>,
+        /// The search to split, as it would be run on a single machine
+        #[arg(last = true, required = true)]
+        search: Vec<String>,
+    },
+    /// Print the effective configuration of a search given after `--`, with
+    /// its --config job file merged in, as a job file
+    PrintConfig {
+        #[arg(last = true, required = true)]
+        search: Vec<String>,
+    },
+    /// Size up a search given after `--` and time a sample of it on this
+    /// machine, without running it
+    Estimate {
+        /// Seconds spent timing the sample
+        #[arg(long, default_value = "5")]
+        seconds: f64,
+        #[arg(last = true, required = true)]
+        search: Vec<String>,
+    },
+    /// Own a search given after `--` and hand out ranges of it to workers
+    /// over HTTP, keeping its progress until a match or the end
+    Serve {
+        /// Address to accept workers on
+        #[arg(long, default_value = "127.0.0.1:8787")]
+        listen: String,
+        /// Candidates per unit of work
+        #[arg(long, default_value = "10000000")]
+        unit_size: u64,
+        /// Seconds a worker may go without reporting before its unit is
+        /// handed to another
+        #[arg(long, default_value = "120")]
+        lease_seconds: u64,
+        /// Token workers must present
+        #[arg(long)]
+        token: Option<String>,
+        /// Directory for the server's progress, `recovery-state` by default
+        #[arg(long)]
+        state_dir: Option<String>,
+        /// Answer workers started without --server that look for one on the
+        /// local network; needs a --listen address they can reach
+        #[arg(long)]
+        announce: bool,
+        /// UDP port to answer them on
+        #[arg(long, default_value_t = beacon::PORT, requires = "announce")]
+        announce_port: u16,
+        #[arg(last = true, required = true)]
+        search: Vec<String>,
+    },
+    /// Claim ranges of a search from a work server and run them here, with
+    /// options for this machine given after `--`, e.g. `-- --gpu`
+    Worker {
+        /// The server, as `http://host:port`; without it the worker looks for
+        /// one started with --announce on the local network
+        #[arg(long)]
+        server: Option<String>,
+        /// Where to look for the server: the broadcast address, or the
+        /// server's own host and announce port
+        #[arg(long, default_value = beacon::BROADCAST, conflicts_with = "server")]
+        discover: String,
+        /// Name the server reports this worker by; the host name by default
+        #[arg(long)]
+        name: Option<String>,
+        #[arg(long)]
+        token: Option<String>,
+        /// Directory for the units in progress and their results
+        #[arg(long, default_value = "worker")]
+        state_dir: String,
+        #[arg(last = true)]
+        local: Vec<String>,
+    },
+    /// Find the derivation path of a known mnemonic by enumerating paths
+    PathSearch {
+        /// File holding the mnemonic
+        #[arg(long)]
+        mnemonic_file: String,
+        /// Passphrases to try, one per line
+        #[arg(long)]
+        passphrase_file: Option<String>,
+        #[arg(long, conflicts_with = "address_file")]
+        address: Option<String>,
+        #[arg(long)]
+        address_file: Option<String>,
+        /// Fixed levels the enumerated ones go below
+        #[arg(long, default_value = "m")]
+        prefix: String,
+        /// Most levels enumerated below the prefix
+        #[arg(long, default_value = "4")]
+        max_depth: usize,
+        /// Indices tried at each level, e.g. `0-9`
+        #[arg(long, default_value = "0-9")]
+        index_range: String,
+        #[arg(long, value_enum, default_value = "both")]
+        hardening: pathsearch::Hardening,
+        #[arg(long, value_delimiter = ',', default_value = "p2pkh,p2sh-p2wpkh,p2wpkh")]
+        address_types: Vec<String>,
+        #[arg(long, default_value = "mainnet")]
+        network: String,
+        #[arg(long, default_value = "path_progress.txt")]
+        progress_file: String,
+        #[arg(long, default_value = "results.txt")]
+        results_file: String,
+    },
+    /// Convert a list of addresses into a sorted hash160 database for --hash-db
+    BuildDb {
+        /// Addresses, one per line, in any mix of encodings and coins
+        #[arg(long)]
+        input: String,
+        #[arg(long)]
+        output: String,
+        /// Compress with zstd; the database is then inflated into memory
+        /// instead of mapped
+        #[arg(long)]
+        compress: bool,
+    },
+    /// Print the addresses of a known mnemonic across paths, address types, and indices
+    Derive {
+        /// The mnemonic itself; prefer --mnemonic-file to keep it out of shell history
+        #[arg(long, conflicts_with = "mnemonic_file", required_unless_present = "mnemonic_file")]
+        mnemonic: Option<String>,
+        #[arg(long)]
+        mnemonic_file: Option<String>,
+        #[arg(long, default_value = "")]
+        passphrase: String,
+        /// Account-level paths, e.g. `m/84'/0'/0'`; defaults to each type's conventional one
+        #[arg(long, value_delimiter = ',')]
+        account_paths: Vec<String>,
+        /// Accounts covered by the conventional paths, e.g. `0-2`
+        #[arg(long, default_value = "0")]
+        accounts: String,
+        #[arg(long, value_delimiter = ',', default_value = "p2pkh,p2sh-p2wpkh,p2wpkh")]
+        address_types: Vec<String>,
+        /// Address indices on each chain, e.g. `0-19`
+        #[arg(long, default_value = "0-19")]
+        index_range: String,
+        /// Include the change chain as well as the receive chain
+        #[arg(long)]
+        change: bool,
+        #[arg(long, default_value = "mainnet")]
+        network: String,
+        #[arg(long, value_enum, default_value = "table")]
+        format: derive::TableFormat,
+    },
+    /// Recover a SLIP-39 (Shamir) backup from shares with scrambled or lost words
+    Slip39 {
+        /// One share's words, `?` for a lost word; repeat for each share
+        #[arg(long)]
+        share: Vec<String>,
+        /// Shares one per line, in the same form
+        #[arg(long)]
+        shares_file: Option<String>,
+        /// Leading words of every share known to be in place, the rest tried
+        /// in every order; all of them by default
+        #[arg(long)]
+        fixed_words: Option<usize>,
+        #[arg(long, default_value = "")]
+        passphrase: String,
+        /// Address the wallet is known to hold, to pick out the right secret
+        #[arg(long)]
+        address: Option<String>,
+        #[arg(long, value_delimiter = ',', default_value = "p2pkh,p2sh-p2wpkh,p2wpkh")]
+        address_types: Vec<String>,
+        /// Account-level paths, e.g. `m/84'/0'/0'`; defaults to each type's conventional one
+        #[arg(long, value_delimiter = ',')]
+        account_paths: Vec<String>,
+        #[arg(long, default_value = "0")]
+        accounts: String,
+        /// Receive addresses listed and checked against --address, e.g. `0-19`
+        #[arg(long, default_value = "0-4")]
+        index_range: String,
+        #[arg(long, default_value = "mainnet")]
+        network: String,
+        #[arg(long, default_value = "results.txt")]
+        results_file: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ResumeCommand {
+    /// Merge shard checkpoints into a coverage map and plan the remaining work
+    Merge {
+        /// Progress files written by the shards
+        #[arg(required = true)]
+        files: Vec<String>,
+        #[arg(long, default_value = "1")]
+        shards: usize,
+        #[arg(long)]
+        output: Option<String>,
+    },
+    /// Report coverage per phase and per shard, optionally as JSON and SVG
+    Coverage {
+        /// Progress files from any number of phases and shards
+        #[arg(required = true)]
+        files: Vec<String>,
+        #[arg(long)]
+        output: Option<String>,
+        #[arg(long)]
+        svg: Option<String>,
+    },
+    /// Print a checkpoint's range, configuration, timing, and throughput, and
+    /// check it against a search given after `--`
+    Info {
+        file: String,
+        #[arg(last = true)]
+        search: Vec<String>,
+    },
+}
+
+/// A search given after `--` to a subcommand, parsed as the main command
+/// line is, job file included.
+fn parse_search(search: &[String]) -> Result<(Args, clap::ArgMatches)> {
+    let argv = std::iter::once("bip39_recovery".into()).chain(search.iter().map(Into::into)).collect();
+    jobfile::parse_from(argv).map_err(|e| anyhow::anyhow!("Invalid search definition: {}", e))
+}
+
+fn run_command(command: Command) -> Result<()> {
+    match command {
+        Command::Resume(ResumeCommand::Merge { files, shards, output }) => {
+            resume::merge(&files, shards, output.as_deref())
+        }
+        Command::ShardPlan { machines, output_dir, search } => shardplan::run(&machines, output_dir.as_deref(), &search),
+        Command::Estimate { .. } => unreachable!("main sets up estimates as runs"),
+        Command::Serve { listen, unit_size, lease_seconds, token, state_dir, announce, announce_port, search } => {
+            let announce = announce.then_some(announce_port);
+            workserver::run(workserver::ServeOptions { listen, unit_size, lease_seconds, token, state_dir, announce }, &search)
+        }
+        Command::Worker { server, discover, name, token, state_dir, local } => {
+            let name = name
+                .or_else(|| std::env::var("HOSTNAME").ok())
+                .or_else(|| std::env::var("COMPUTERNAME").ok())
+                .unwrap_or_else(|| format!("worker-{}", std::process::id()));
+            worker::run(worker::WorkerOptions { server, discover, name, token, state_dir, local })
+        }
+        Command::PrintConfig { search } => {
+            let (_, matches) = parse_search(&search)?;
+            print!("{}", jobfile::render(&Args::command(), &matches));
+            Ok(())
+        }
+        Command::PathSearch {
+            mnemonic_file,
+            passphrase_file,
+            address,
+            address_file,
+            prefix,
+            max_depth,
+            index_range,
+            hardening,
+            address_types,
+            network,
+            progress_file,
+            results_file,
+        } => pathsearch::run(pathsearch::PathSearch {
+            mnemonic_file,
+            passphrase_file,
+            address,
+            address_file,
+            prefix,
+            max_depth,
+            index_range,
+            hardening,
+            address_types: address_types.iter().map(|address_type| address_type.to_lowercase()).collect(),
+            network,
+            progress_file,
+            results_file,
+        }),
+        Command::Derive {
+            mnemonic,
+            mnemonic_file,
+            passphrase,
+            account_paths,
+            accounts,
+            address_types,
+            index_range,
+            change,
+            network,
+            format,
+        } => derive::run(derive::Derive {
+            mnemonic,
+            mnemonic_file,
+            passphrase,
+            account_paths,
+            accounts,
+            address_types: address_types.iter().map(|address_type| address_type.to_lowercase()).collect(),
+            index_range,
+            change,
+            network,
+            format,
+        }),
+        Command::Slip39 {
+            share,
+            shares_file,
+            fixed_words,
+            passphrase,
+            address,
+            address_types,
+            account_paths,
+            accounts,
+            index_range,
+            network,
+            results_file,
+        } => shamir::run(shamir::Slip39 {
+            shares: share,
+            shares_file,
+            fixed_words,
+            passphrase,
+            address,
+            address_types: address_types.iter().map(|address_type| address_type.to_lowercase()).collect(),
+            account_paths,
+            accounts,
+            index_range,
+            network,
+            results_file,
+        }),
+        Command::Resume(ResumeCommand::Coverage { files, output, svg }) => {
+            resume::coverage_map(&files, output.as_deref(), svg.as_deref())
+        }
+        Command::Resume(ResumeCommand::Info { file, search }) => resume::info(&file, &search),
+        Command::Bench { seconds, backends, threads, address_types, path_counts, gap_limits, match_modes } => {
+            for address_type in &address_types {
+                if !matrix::ADDRESS_TYPES.contains(&address_type.to_lowercase().as_str()) {
+                    return Err(anyhow::anyhow!("Unsupported address type {}", address_type));
+                }
+            }
+            let threads = if threads.is_empty() {
+                vec![std::thread::available_parallelism().map_or(1, |n| n.get())]
+            } else {
+                threads
+            };
+            bench::run(&bench::BenchMatrix {
+                seconds,
+                backends,
+                threads,
+                address_types,
+                path_counts,
+                gap_limits,
+                match_modes,
+            })
+        }
+        Command::BuildDb { input, output, compress } => {
+            let stats = hashdb::build(&input, &output, compress)?;
+            println!(
+                "Wrote {} entries to {} ({} bytes); {} duplicates dropped, {} lines not recognized as addresses",
+                stats.entries, output, stats.bytes, stats.duplicates, stats.unparsed
+            );
+            Ok(())
+        }
+        Command::Control { state_dir, command } => {
+            let socket = StateDir::open_unlocked(&state_dir).resolve(daemon::SOCKET_FILE);
+            daemon::send(&socket, &command.join(" "))
         }
-        Ok(Self { wordlist })
     }
-    fn contains(&self, word: &str) -> bool {
-        self.wordlist.contains_key(word)
+}
+
+/// One line per matrix path listing the encodings checked there.
+fn print_matrix(pb: &ProgressBar, search_matrix: &SearchMatrix) {
+    for entry in &search_matrix.paths {
+        let encodings: Vec<String> = entry
+            .encodings
+            .iter()
+            .map(|encoding| format!("{}/{}", encoding.address_type, matrix::network_name(encoding.network)))
+            .collect();
+        pb.println(format!("  {}: {}", entry.path, encodings.join(", ")));
     }
 }
 
-fn try_mnemonic(
-    mnemonic_words: &[String],
-    network: Network,
-    derivation_path: &DerivationPath,
-    target_address: Option<&str>,
-    address_db: Option<&HashSet<String>>,
-    secp: &Secp256k1<secp256k1::All>,
-    bip39_wordlist: &Bip39Wordlist,
-    address_type: &str,
-    debug: bool,
-) -> Result<Option<(String, String)>> {
-    for word in mnemonic_words {
-        if !bip39_wordlist.contains(word) {
-            if debug {
-                error!("Invalid BIP-39 word: {}", word);
-            }
-            return Ok(None);
-        }
+// Path levels --toggle-hardening may flip, each doubling the paths derived
+const MAX_TOGGLED_LEVELS: usize = 6;
+
+// How often the monitor thread checks the power source when battery options are set
+const POWER_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+// Unix time of the last successful checkpoint, 0 until the first one
+static LAST_CHECKPOINT: AtomicU64 = AtomicU64::new(0);
+
+fn last_checkpoint() -> Option<u64> {
+    Some(LAST_CHECKPOINT.load(Ordering::Relaxed)).filter(|&t| t > 0)
+}
+
+// Checkpoint written by save_progress, set once progress has been loaded
+static CHECKPOINT: OnceLock<Mutex<Checkpoint>> = OnceLock::new();
+
+/// Candidates derived by the runs before this one, and this run's summary,
+/// so checkpoints carry the derived count across resumes.
+static DERIVED: OnceLock<(u64, Arc<RunSummary>)> = OnceLock::new();
+
+fn derived_total() -> Option<u64> {
+    DERIVED.get().map(|(before, summary)| before + summary.derived())
+}
+
+/// Ranks of this run that are done, for checkpoints that hold whatever order
+/// the workers finish in.
+static FRONTIER: OnceLock<Frontier> = OnceLock::new();
+
+/// Candidates of consecutive ranks; `None` is a rank that yields no
+/// candidate but still counts.
+type Candidates = Box<dyn Iterator<Item = Option<Vec<String>>> + Send>;
+
+/// Builds the candidates of the ranks `[start, end)`.
+type CandidateSource = Arc<dyn Fn(u64, u64) -> Candidates + Send + Sync>;
+
+/// Ends the search early for `reason`: workers drain on `draining`, and the
+/// final checkpoint is written on the way out as usual.
+fn stop_search(stop: &StopConditions, reason: StopReason, draining: &AtomicBool, pb: &ProgressBar) {
+    if stop.trigger(reason) {
+        draining.store(true, Ordering::SeqCst);
+        pb.println(format!("Stopping: {}, saving progress", reason.describe()));
+        info!("Stopping: {}", reason.describe());
     }
+}
 
-    let mnemonic_str = mnemonic_words.join(" ");
-    if debug {
-        debug!("Testing mnemonic: {}", mnemonic_str);
+/// Prints the token that continues this run elsewhere with `--resume`.
+fn print_resume_token(pb: &ProgressBar) {
+    let token = CHECKPOINT.get().and_then(|checkpoint| checkpoint.lock().unwrap().token());
+    if let Some(token) = token {
+        pb.println(format!("Resume token: {}", token));
+        info!("Resume token: {}", token);
     }
+}
 
-    let mnemonic = match Mnemonic::parse_in_normalized(Language::English, &mnemonic_str) {
-        Ok(m) => m,
-        Err(e) => {
-            if debug {
-                error!("Mnemonic validation failed for '{}': {}", mnemonic_str, e);
+fn save_progress(processed: &Arc<AtomicUsize>, progress_file: &str) -> Result<()> {
+    let checkpoint = CHECKPOINT.get().ok_or_else(|| anyhow::anyhow!("Progress has not been loaded yet"))?;
+    // Written and renamed into place under the lock, so concurrent saves
+    // can't interleave and a crash never leaves a truncated file
+    let count = {
+        let mut checkpoint = checkpoint.lock().unwrap();
+        // Only the ranks finished so far in rank order are recorded as
+        // processed; those done past a candidate still in flight are listed
+        let count = match (FRONTIER.get(), checkpoint.shard) {
+            (Some(frontier), Some(shard)) => {
+                let (next, ahead) = frontier.snapshot();
+                checkpoint.ahead = ahead;
+                next.saturating_sub(shard.start)
             }
-            return Ok(None);
+            _ => processed.load(Ordering::Relaxed) as u64,
+        };
+        checkpoint.record(live_stats::unix_now(), count);
+        if let Some(derived) = derived_total() {
+            checkpoint.derived = derived;
         }
+        let tmp = format!("{}.tmp", progress_file);
+        fs::write(&tmp, checkpoint.render())
+            .map_err(|e| anyhow::anyhow!("Failed to write progress file {}: {}", tmp, e))?;
+        fs::rename(&tmp, progress_file)
+            .map_err(|e| anyhow::anyhow!("Failed to replace progress file {}: {}", progress_file, e))?;
+        count
     };
+    LAST_CHECKPOINT.store(live_stats::unix_now(), Ordering::Relaxed);
+    info!("Saved progress: {} permutations processed", count);
+    Ok(())
+}
+
+/// The `--output` record of how a run ended, with this run's speed and the
+/// derived count across resumes.
+fn completion(status: &'static str, processed: usize, initial: usize, total: u64, matches: usize, elapsed: f64) -> Completion {
+    let filtered = DERIVED.get().and_then(|(_, summary)| filtered_percent(summary.rejected(), summary.derived()));
+    let done = processed.saturating_sub(initial) as f64;
+    Completion {
+        status,
+        stop_reason: None,
+        processed: processed as u64,
+        total,
+        derived: derived_total(),
+        filtered_percent: filtered,
+        matches: matches as u64,
+        elapsed_seconds: elapsed,
+        speed: if elapsed > 0.0 { done / elapsed } else { 0.0 },
+        finished_at: live_stats::unix_now(),
+    }
+}
 
-    let seed = mnemonic.to_seed("");
-    let xprv = Xpriv::new_master(network, &seed)
+fn load_address_db(db_file: &str) -> Result<HashSet<String>> {
+    let file = fs::File::open(db_file)
         .map_err(|e| {
-            if debug {
-                error!("Failed to derive master key for {}: {}", mnemonic_str, e);
-            }
-            anyhow::anyhow!("Failed to derive master key: {}", e)
+            error!("Failed to open address database file {}: {}", db_file, e);
+            anyhow::anyhow!("Failed to open address database file: {}", e)
         })?;
+    let reader = BufReader::new(file);
+    let db = reader
+        .lines()
+        .map(|line| line.map_err(|e| {
+            error!("Failed to read address database: {}", e);
+            anyhow::anyhow!("Failed to read address database: {}", e)
+        }))
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .map(|s| ethereum::normalize(&s))
+        .collect::<Result<_>>()?;
+    Ok(db)
+}
 
-    let child_xprv = xprv.derive_priv(secp, derivation_path)
-        .map_err(|e| {
-            if debug {
-                error!("Failed to derive child key for {} at {}: {}", mnemonic_str, derivation_path, e);
+/// The known words from `--seed-words-file` or `--known-words`, with word
+/// numbers resolved; there must be `known_count` of them.
+fn load_known_words(args: &Args, known_count: usize) -> Result<Vec<String>> {
+    if let Some(seed_words_file) = &args.seed_words_file {
+        let file = fs::File::open(seed_words_file)
+            .map_err(|e| {
+                error!("Failed to open seed words file {}: {}", seed_words_file, e);
+                anyhow::anyhow!("Failed to open seed words file: {}", e)
+            })?;
+        let reader = BufReader::new(file);
+        let words = reader
+            .lines()
+            .collect::<Result<Vec<String>, io::Error>>()
+            .map_err(|e| {
+                error!("Failed to read seed words file {}: {}", seed_words_file, e);
+                anyhow::anyhow!("Failed to read seed words file: {}", e)
+            })?
+            .into_iter()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<String>>();
+        if words.len() != known_count {
+            error!("Seed words file contains {} words, expected {}", words.len(), known_count);
+            return Err(anyhow::anyhow!("Invalid number of seed words in file"));
+        }
+        Resolver::new(args.word_index_base, words.iter().map(String::as_str), seed_words_file)
+            .and_then(|resolver| resolver.words(&words))
+            .map_err(|e| {
+                error!("{}", e);
+                e
+            })
+    } else {
+        if args.known_words.len() != known_count {
+            error!("Provided {} known words, expected {}", args.known_words.len(), known_count);
+            return Err(anyhow::anyhow!("Invalid number of known words"));
+        }
+        Resolver::new(args.word_index_base, args.known_words.iter().map(String::as_str), "--known-words")
+            .and_then(|resolver| resolver.words(&args.known_words))
+            .map_err(|e| {
+                error!("{}", e);
+                e
+            })
+    }
+}
+
+/// Physical cores, as the distinct core ids /proc/cpuinfo lists, capped by
+/// the CPUs this process may run on. A hyperthread sibling shares its
+/// core's hashing units, so it adds little to PBKDF2 throughput. Where
+/// cpuinfo isn't available, the logical CPUs.
+fn default_threads() -> usize {
+    let logical = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let Ok(cpuinfo) = fs::read_to_string("/proc/cpuinfo") else {
+        return logical;
+    };
+    let mut cores = HashSet::new();
+    let mut package = "";
+    for line in cpuinfo.lines() {
+        let Some((key, value)) = line.split_once(':') else { continue };
+        match key.trim() {
+            "physical id" => package = value.trim(),
+            "core id" => {
+                cores.insert((package, value.trim()));
             }
-            anyhow::anyhow!("Failed to derive child key: {}", e)
-        })?;
+            _ => {}
+        }
+    }
+    match cores.len() {
+        0 => logical,
+        physical => physical.min(logical),
+    }
+}
 
-    let pubkey = bitcoin::PublicKey::new(child_xprv.private_key.public_key(secp));
-    let addr = match address_type.to_lowercase().as_str() {
-        "p2wpkh" => Address::p2wpkh(&pubkey, network),
-        "p2pkh" => Ok(Address::p2pkh(&pubkey, network)),
-        "p2sh-p2wpkh" => Address::p2shwpkh(&pubkey, network),
-        _ => {
-            if debug {
-                error!("Unsupported address type: {}", address_type);
+/// `--address-type` values, lowercased and with `all` expanded to every
+/// Bitcoin type. Ethereum is left out of `all`, since it lives under a coin
+/// type of its own.
+fn address_types(requested: &[String]) -> Result<Vec<String>> {
+    let mut address_types: Vec<String> = Vec::new();
+    for address_type in requested.iter().map(|address_type| address_type.trim().to_lowercase()) {
+        let expanded: Vec<String> = if address_type == "all" {
+            matrix::ADDRESS_TYPES.iter().filter(|&&known| known != ethereum::ETH).map(|known| known.to_string()).collect()
+        } else if matrix::ADDRESS_TYPES.contains(&address_type.as_str()) {
+            vec![address_type]
+        } else {
+            return Err(anyhow::anyhow!(
+                "Unsupported address type {} (expected {} or all)",
+                address_type,
+                matrix::ADDRESS_TYPES.join(", ")
+            ));
+        };
+        for address_type in expanded {
+            if !address_types.contains(&address_type) {
+                address_types.push(address_type);
             }
-            return Ok(None);
         }
+    }
+    Ok(address_types)
+}
+
+/// The expansion of `known_words` asked for by `--fuzzy-words` or
+/// `--prefix-mode`, if either.
+fn expand_known_words(args: &Args, known_words: &[String], fixed_words: usize) -> Result<Option<FuzzyWords>> {
+    let wordlist = Bip39Wordlist::english();
+    if args.fuzzy_words {
+        FuzzyWords::new(known_words, fixed_words, &wordlist).map(Some)
+    } else if args.prefix_mode {
+        FuzzyWords::prefixes(known_words, fixed_words, &wordlist).map(Some)
+    } else {
+        Ok(None)
+    }
+}
+
+/// The positions `--known-positions` pins, if any.
+fn known_positions(args: &Args, total_words: usize) -> Result<Option<KnownPositions>> {
+    if args.known_positions.is_empty() {
+        return Ok(None);
+    }
+    KnownPositions::parse(&args.known_positions, total_words, args.word_index_base).map(Some)
+}
+
+/// The `--missing-words` layout over the `free_words` positions that
+/// aren't pinned; `--missing-positions` count over the whole mnemonic.
+fn missing_words(
+    args: &Args,
+    free_words: usize,
+    fixed_words: usize,
+    pinned: Option<&KnownPositions>,
+) -> Result<Option<MissingWords>> {
+    let Some(count) = args.missing_words else {
+        return Ok(None);
     };
-    let addr = addr.map_err(|e| {
-        if debug {
-            error!("Failed to create address for {}: {}", mnemonic_str, e);
-        }
-        anyhow::anyhow!("Failed to create address: {}", e)
-    })?;
+    let positions = match pinned {
+        Some(pinned) => args.missing_positions.iter().map(|&position| pinned.free_position(position)).collect::<Result<Vec<_>>>()?,
+        None => args.missing_positions.clone(),
+    };
+    MissingWords::new(free_words, fixed_words, count, &positions).map(Some)
+}
+
+/// The orders near the one given asked for by `--max-displacement` or
+/// `--swaps-only`, if either.
+fn bounded_order(args: &Args, total_words: usize, fixed_words: usize) -> Result<Option<BoundedOrder>> {
+    let bound = match (args.max_displacement, args.swaps_only) {
+        (Some(displacement), _) => Bound::Displacement(displacement),
+        (None, Some(swaps)) => Bound::Swaps(swaps),
+        (None, None) => return Ok(None),
+    };
+    BoundedOrder::new(total_words.saturating_sub(fixed_words), bound).map(Some)
+}
 
-    let addr_str = addr.to_string();
-    if debug {
-        debug!("Derived address for '{}': {}", mnemonic_str, addr_str);
+/// The choices from a larger pool of words asked for by `--pool-size`, if
+/// any: the pool after the fixed words, over the positions after them.
+fn word_pool(args: &Args, total_words: usize, fixed_words: usize) -> Result<Option<WordPool>> {
+    match args.pool_size {
+        Some(pool_size) => WordPool::new(
+            pool_size.saturating_sub(fixed_words),
+            total_words.saturating_sub(fixed_words),
+            args.ordered_pool,
+        )
+        .map(Some),
+        None => Ok(None),
     }
+}
 
-    let is_match = match (target_address, address_db) {
-        (Some(target), None) => addr_str == target,
-        (None, Some(db)) => db.contains(&addr_str),
-        _ => false,
+/// Logs the arguments without a result passphrase given on the command line
+/// and, under `--redact`, without the known words (positioned or not) and
+/// passphrases. A known position keeps its number.
+fn log_args(args: &mut Args) {
+    let encrypt_result = args.encrypt_result.take();
+    args.encrypt_result = encrypt_result.as_ref().map(|value| {
+        if value.starts_with("passphrase:") { "passphrase:<hidden>".to_string() } else { value.clone() }
+    });
+    let hide = |values: &mut Vec<String>, hidden: fn(&String) -> String| {
+        let hidden = values.iter().map(hidden).collect();
+        std::mem::replace(values, hidden)
     };
+    let redacted = |_: &String| "<redacted>".to_string();
+    let positioned = |entry: &String| match entry.split_once(':') {
+        Some((position, _)) => format!("{}:<redacted>", position),
+        None => "<redacted>".to_string(),
+    };
+    let known_words = args.redact.then(|| hide(&mut args.known_words, redacted));
+    let known_positions = args.redact.then(|| hide(&mut args.known_positions, positioned));
+    let passphrases = args.redact.then(|| hide(&mut args.passphrases, redacted));
+    info!("Command-line arguments: {:?}", args);
+    args.encrypt_result = encrypt_result;
+    if let Some(known_words) = known_words {
+        args.known_words = known_words;
+    }
+    if let Some(known_positions) = known_positions {
+        args.known_positions = known_positions;
+    }
+    if let Some(passphrases) = passphrases {
+        args.passphrases = passphrases;
+    }
+}
 
-    if is_match {
-        Ok(Some((mnemonic_str, addr_str)))
-    } else {
-        Ok(None)
+/// A match as the results file records it.
+fn result_text(found: &Match, format: ResultsFormat) -> String {
+    match (format, found.passphrase.is_empty()) {
+        (ResultsFormat::Plain, true) => format!("{} {} [{}]\n", found.address, found.mnemonic, found.variant),
+        (ResultsFormat::Plain, false) => format!(
+            "{} {} [{}] passphrase: {}\n",
+            found.address, found.mnemonic, found.variant, found.passphrase
+        ),
+        (ResultsFormat::Btcrecover, true) => format!("Seed found: {}\n", found.mnemonic),
+        (ResultsFormat::Btcrecover, false) => {
+            format!("Seed found: {}\nPassword found: '{}'\n", found.mnemonic, found.passphrase)
+        }
     }
 }
 
-fn save_progress(processed: &Arc<AtomicUsize>, progress_file: &str) -> Result<()> {
-    let count = processed.load(Ordering::Relaxed);
-    let mut file = File::create(progress_file)
-        .map_err(|e| anyhow::anyhow!("Failed to create progress file {}: {}", progress_file, e))?;
-    writeln!(file, "{}", count)
-        .map_err(|e| anyhow::anyhow!("Failed to write to progress file {}: {}", progress_file, e))?;
-    info!("Saved progress: {} permutations processed", count);
+/// Appends `found` to the results file, or in its place the armored
+/// `sealed` text of an encrypted result.
+fn save_result(results_file: &str, found: &Match, format: ResultsFormat, sealed: Option<&str>) -> Result<()> {
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(results_file)
+        .map_err(|e| anyhow::anyhow!("Failed to open results file {}: {}", results_file, e))?;
+    let text = match sealed {
+        Some(sealed) => sealed.to_string(),
+        None => result_text(found, format),
+    };
+    file.write_all(text.as_bytes())
+        .map_err(|e| anyhow::anyhow!("Failed to write to results file {}: {}", results_file, e))?;
+    info!("Saved match for address {} to {}", found.address, results_file);
     Ok(())
 }
 
-fn load_progress(progress_file: &str) -> Result<usize> {
+/// The checkpoint this run continues: the one in `progress_file` if there is
+/// one, carried over to this run's range, phase, and configuration.
+fn load_progress(progress_file: &str, shard: Shard, phase: &str, config: &str) -> Result<Checkpoint> {
+    let mut current = Checkpoint::new(shard, phase, config, live_stats::unix_now());
     match fs::read_to_string(progress_file) {
         Ok(content) => {
-            let count = content.trim().parse::<usize>()
+            let checkpoint = Checkpoint::parse(&content)
                 .map_err(|e| anyhow::anyhow!("Failed to parse progress file {}: {}", progress_file, e))?;
-            info!("Loaded progress: {} permutations processed", count);
-            Ok(count)
+            // The processed count is an offset from the start of the range,
+            // so it only means the same candidates under the same start,
+            // space, and configuration; a different end is fine
+            if let Some(saved) = checkpoint.shard.filter(|saved| saved.start != shard.start || saved.space != shard.space) {
+                return Err(anyhow::anyhow!(
+                    "Progress file {} was written for range {}..{} of {}, this run is range {}..{} of {}; \
+                     move it aside to start over",
+                    progress_file, saved.start, saved.end, saved.space, shard.start, shard.end, shard.space
+                ));
+            }
+            if let Some(saved) = checkpoint.config.as_deref().filter(|saved| *saved != config) {
+                return Err(anyhow::anyhow!(
+                    "Progress file {} was written under configuration {}, this run is {}; see 'resume info', \
+                     or move it aside to start over",
+                    progress_file, saved, config
+                ));
+            }
+            if let Some(saved) = checkpoint.shard.filter(|saved| saved.end != shard.end) {
+                warn!("Progress file {} was written for range {}..{}, resuming up to {}", progress_file, saved.start, saved.end, shard.end);
+            }
+            info!("Loaded progress: {} permutations processed", checkpoint.processed);
+            current.processed = checkpoint.processed;
+            current.started = checkpoint.started.or(current.started);
+            current.derived = checkpoint.derived;
+            current.samples = checkpoint.samples;
+            current.ahead = checkpoint.ahead;
+            Ok(current)
         }
         Err(e) if e.kind() == io::ErrorKind::NotFound => {
             info!("No progress file found, starting from 0");
-            Ok(0)
+            Ok(current)
         }
         Err(e) => Err(anyhow::anyhow!("Failed to read progress file {}: {}", progress_file, e)),
     }
 }
 
-fn main() -> Result<()> {
-    let args = Args::parse();
+/// Phase a run's checkpoints belong to: `--phase`, or a name for the
+/// candidate strategy, since coverage is only comparable within one.
+fn phase_name(args: &Args) -> String {
+    args.phase.clone().unwrap_or_else(|| match (&args.tokenlist, &args.seedlist, &args.position_weights) {
+        (Some(file), _, _) => format!("tokenlist {}", file),
+        (_, Some(file), _) => format!("seedlist {}", file),
+        _ if args.candidates_file.is_some() => format!("candidates {}", args.candidates_file.as_deref().unwrap_or_default()),
+        (_, _, Some(file)) => format!("weighted {}", file),
+        _ if args.missing_words.is_some() => "missing words".to_string(),
+        _ if args.max_displacement.is_some() => format!("displacement {}", args.max_displacement.unwrap_or_default()),
+        _ if args.swaps_only.is_some() => format!("swaps {}", args.swaps_only.unwrap_or_default()),
+        _ if args.pool_size.is_some() => format!("pool {}", args.pool_size.unwrap_or_default()),
+        _ => "permutations".to_string(),
+    })
+}
 
-    // Initialize logger (only to file, suppress console logs)
-    CombinedLogger::init(vec![
-        TermLogger::new(
-            LevelFilter::Off, // Suppress console logs
-            Config::default(),
-            simplelog::TerminalMode::Mixed,
-            simplelog::ColorChoice::Auto,
+/// Hash of everything that decides which candidate an index means and how
+/// it is checked: the candidate source, the derivation matrix, passphrases,
+/// and targets. Input files are hashed by content, except the address
+/// database, which may be edited while a search runs. Checkpoints record it
+/// so a resume under different settings can be spotted.
+fn config_hash(args: &Args) -> Result<String> {
+    let content = |file: &Option<String>| -> Result<String> {
+        match file {
+            Some(file) => {
+                let bytes = fs::read(file).map_err(|e| anyhow::anyhow!("Failed to read {}: {}", file, e))?;
+                Ok(sha256::Hash::hash(&bytes).to_string())
+            }
+            None => Ok("-".to_string()),
+        }
+    };
+    // The built-in definitions are hashed like a file, so either may be used
+    let coins = match &args.coin {
+        Some(_) => sha256::Hash::hash(coins::read(&args.coins_file)?.1.as_bytes()).to_string(),
+        None => "-".to_string(),
+    };
+    let fields = [
+        format!("total_words {:?}", args.total_words),
+        format!("fixed_words {:?}", args.fixed_words),
+        format!("known_positions {:?}", args.known_positions),
+        format!(
+            "known_words {:?} {:?} {} {}",
+            args.known_words, args.word_index_base, args.fuzzy_words, args.prefix_mode
         ),
-        WriteLogger::new(
-            if args.debug { LevelFilter::Debug } else { LevelFilter::Info },
-            Config::default(),
-            File::create(&args.log_file)
-                .map_err(|e| {
-                    error!("Failed to create log file {}: {}", args.log_file, e);
-                    anyhow::anyhow!("Failed to create log file {}: {}", args.log_file, e)
-                })?,
+        format!("missing_words {:?} {:?}", args.missing_words, args.missing_positions),
+        format!("bounded_order {:?} {:?}", args.max_displacement, args.swaps_only),
+        format!("pool {:?} {}", args.pool_size, args.ordered_pool),
+        format!("seed_format {:?}", args.seed_format),
+        format!("seed_words_file {}", content(&args.seed_words_file)?),
+        format!("tokenlist {}", content(&args.tokenlist)?),
+        format!("seedlist {}", content(&args.seedlist)?),
+        format!("candidates_file {}", content(&args.candidates_file)?),
+        format!("position_weights {}", content(&args.position_weights)?),
+        format!(
+            "path {} {:?} {:?} {:?} {} {}",
+            args.path, args.path_ranges, args.path_preset, args.address_type, args.network, args.auto_path
         ),
-    ])
-    .map_err(|e| {
-        error!("Failed to initialize logger: {}", e);
-        anyhow::anyhow!("Failed to initialize logger: {}", e)
-    })?;
+        format!("matrix {}", content(&args.matrix)?),
+        format!("coin {:?} {}", args.coin, coins),
+        format!("coin_types {:?}", args.coin_type_list),
+        format!("legacy_paths {:?}", args.legacy_paths),
+        format!("toggle_hardening {:?}", args.toggle_hardening),
+        format!("address_window {:?} {:?}", args.address_index_range, args.change),
+        format!("overrides {:?} {:?} {:?}", args.bech32_hrp, args.p2pkh_version, args.p2sh_version),
+        format!(
+            "passphrases {:?} {} {:?} {}",
+            args.passphrases,
+            content(&args.passphrase_file)?,
+            args.passphrase_rules,
+            args.passphrase_unicode_variants
+        ),
+        format!("address {:?}", args.address),
+        format!("address_file {}", content(&args.address_file)?),
+        format!("address_db_file {:?}", args.address_db_file),
+        format!("address_bloom {:?}", args.address_bloom),
+        format!("hash_db {:?}", args.hash_db),
+        format!("descriptors {} {}", content(&args.descriptors)?, args.gap_limit),
+        format!(
+            "payment_code {:?} node_id {:?} xpub {:?} fingerprint {:?}",
+            args.payment_code, args.node_id, args.xpub, args.fingerprint
+        ),
+        format!("address_pattern {:?}", args.address_pattern),
+    ];
+    let hash = sha256::Hash::hash(fields.join("\n").as_bytes()).to_string();
+    Ok(hash[..16].to_string())
+}
 
-    info!("Program started");
-    info!("Command-line arguments: {:?}", args);
+/// Share of candidates expected to pass the checksum and be derived.
+fn checksum_pass(args: &Args, total_words: usize) -> f64 {
+    match (&args.seedlist, entropy::checksum_bits(total_words)) {
+        // One version prefix of 8 bits and one of 12
+        _ if args.seed_format == SeedFormat::Electrum => 2f64.powi(-8) + 2f64.powi(-12),
+        // The checksum word repeats one of the 24 before it
+        _ if args.seed_format == SeedFormat::Monero => 1.0 / 24.0,
+        (None, Some(bits)) => 2f64.powi(-(bits as i32)),
+        _ => 1.0,
+    }
+}
+
+fn format_eta(seconds: f64) -> String {
+    if !seconds.is_finite() {
+        return "unknown".to_string();
+    }
+    let seconds = seconds.round() as u64;
+    format!("{:02}:{:02}:{:02}", seconds / 3600, (seconds / 60) % 60, seconds % 60)
+}
 
-    let total_permutations = {
-        let n = args.total_words - args.fixed_words;
-        let mut result: u64 = 1;
-        for i in 1..=n {
-            result = result.saturating_mul(i as u64);
+fn main() -> Result<ExitCode> {
+    let (mut args, _) = jobfile::parse_from::<Args>(std::env::args_os().collect()).unwrap_or_else(|e| e.exit());
+    // `estimate` sets up the search it's given as a run would, then times a
+    // sample of it instead of running it
+    let mut estimate = None;
+    if let Some(Command::Estimate { seconds, search }) = &args.command {
+        estimate = Some(*seconds);
+        args = parse_search(search)?.0;
+        if args.command.is_some() {
+            return Err(anyhow::anyhow!("estimate takes a search, not another command"));
         }
-        result
+        args.daemon = false;
+    }
+    if let Some(command) = args.command.take() {
+        return run_command(command).map(|()| ExitCode::SUCCESS);
+    }
+    args.address_type = address_types(&args.address_type)?;
+    // Monero has its own wordlist, checksum word, and curve rather than a
+    // coins.toml entry, and a seed is its key, with no paths or passphrase
+    if args.coin.as_deref().is_some_and(monero::is_coin) {
+        let unsupported = [
+            ("--gpu", args.gpu),
+            ("--seed-format", args.seed_format != SeedFormat::Bip39),
+            ("--tested-set", args.tested_set.is_some()),
+            ("--discover-accounts", args.discover_accounts),
+            ("--hash-db", args.hash_db.is_some()),
+            ("--passphrase or --passphrase-file", !args.passphrases.is_empty() || args.passphrase_file.is_some()),
+            ("--path options", args.path != DEFAULT_PATH || !args.path_preset.is_empty() || !args.path_ranges.is_empty()),
+            (
+                "--coin-type-list, --legacy-paths, or --toggle-hardening",
+                !args.coin_type_list.is_empty() || args.legacy_paths.is_some() || !args.toggle_hardening.is_empty(),
+            ),
+            ("--change or --address-index-range", !args.change.is_empty() || args.address_index_range.is_some()),
+            (
+                "--fuzzy-words, --prefix-mode, --missing-words, or --known-positions",
+                args.fuzzy_words || args.prefix_mode || args.missing_words.is_some() || !args.known_positions.is_empty(),
+            ),
+            (
+                "a key or descriptor target",
+                args.payment_code.is_some()
+                    || args.node_id.is_some()
+                    || args.xpub.is_some()
+                    || args.fingerprint.is_some()
+                    || args.descriptors.is_some(),
+            ),
+        ];
+        if let Some((option, _)) = unsupported.iter().find(|(_, given)| *given) {
+            return Err(anyhow::anyhow!("--coin xmr can't be combined with {}", option));
+        }
+        if args.total_words != Some(monero::MNEMONIC_WORDS) {
+            return Err(anyhow::anyhow!("Monero seeds have {} words; pass --total-words {}", monero::MNEMONIC_WORDS, monero::MNEMONIC_WORDS));
+        }
+        args.seed_format = SeedFormat::Monero;
+        args.address_type = vec![monero::XMR.to_string()];
+    }
+    // Without a chain backend an account is used when its addresses are
+    // targets, which takes the wallet's own addresses to tell
+    if args.discover_accounts && args.chain.is_none() {
+        let unsupported = [
+            ("--address-pattern", args.address_pattern.is_some()),
+            ("--xpub", args.xpub.is_some()),
+            ("--fingerprint", args.fingerprint.is_some()),
+            ("--payment-code", args.payment_code.is_some()),
+            ("--node-id", args.node_id.is_some()),
+        ];
+        if let Some((option, _)) = unsupported.iter().find(|(_, given)| *given) {
+            return Err(anyhow::anyhow!(
+                "--discover-accounts can't tell used accounts from {}; give --chain to check them on chain, or the wallet's addresses as targets",
+                option
+            ));
+        }
+    }
+    // Notification addresses are always P2PKH
+    if args.payment_code.is_some() {
+        args.address_type = vec!["p2pkh".to_string()];
+    }
+    // The node key comes from the seed, so the master key is the only "path"
+    if args.node_id.is_some() {
+        args.path = "m".to_string();
+        args.address_type = vec![lightning::CLN_NODE_ID.to_string()];
+    }
+    // An account key is matched whole at the account path its version and
+    // child number imply, unless --path says otherwise
+    if let Some(key) = &args.xpub {
+        let account = xpub::parse(key)?;
+        if args.path == DEFAULT_PATH {
+            let path = account.path.ok_or_else(|| {
+                anyhow::anyhow!("{} is not an account-level key; pass --path with the path it was derived at", key)
+            })?;
+            args.path = path.to_string();
+        }
+        args.address_type = vec![xpub::ACCOUNT_XPUB.to_string()];
+        args.xpub = Some(account.key);
+    }
+    // The master fingerprint needs no derivation beyond the master key
+    if let Some(fingerprint) = &args.fingerprint {
+        args.fingerprint = Some(xpub::parse_fingerprint(fingerprint)?);
+        args.path = "m".to_string();
+        args.address_type = vec![xpub::MASTER_FINGERPRINT.to_string()];
+    }
+    // Taproot wallets use BIP-86 paths, so the BIP-44 default doesn't fit
+    if args.address_type == ["p2tr"] && args.path == DEFAULT_PATH {
+        args.path = TAPROOT_PATH.to_string();
+    }
+    if args.address_type == [ethereum::ETH] && args.path == DEFAULT_PATH {
+        args.path = ETHEREUM_PATH.to_string();
+    }
+    // Accounts are matched in EIP-55 form, however they were typed
+    if let Some(address) = &args.address {
+        args.address = Some(ethereum::normalize(address)?);
+    }
+    // A match limit only makes sense if matches don't end the search
+    args.find_all |= args.stop_after_matches.is_some();
+    // A pattern hit may be some other address that shares the known ends
+    args.find_all |= args.address_pattern.is_some();
+    // Four bytes leave room for chance matches in a large search
+    args.find_all |= args.fingerprint.is_some();
+    // Required by clap whenever no subcommand is given, except that a
+    // tokenlist or seedlist replaces the fixed words
+    let total_words = args.total_words.unwrap_or_default();
+    let fixed_words = args.fixed_words.unwrap_or_default();
+
+    // The launcher only starts the background copy; the lock is taken there
+    if args.daemon && !daemon::is_detached() {
+        let pid = daemon::detach()?;
+        println!(
+            "Started daemon with pid {}, logging to the state directory. Control it with: control --state-dir {} status",
+            pid,
+            args.state_dir.as_deref().unwrap_or_default()
+        );
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    // Place all run artifacts in the state directory and lock it for this
+    // run. An estimate leaves the directory and its log to the runs
+    let state_dir = Arc::new(match (&args.state_dir, estimate) {
+        (Some(dir), Some(_)) => StateDir::open_unlocked(dir),
+        (dir, _) => StateDir::open(dir.as_deref())?,
+    });
+    if args.daemon {
+        let pid_file = state_dir.resolve(daemon::PID_FILE);
+        fs::write(&pid_file, format!("{}\n", process::id()))
+            .map_err(|e| anyhow::anyhow!("Failed to write pid file {}: {}", pid_file, e))?;
+        state_dir.remove_on_release(&pid_file);
+    }
+    args.log_file = state_dir.resolve(&args.log_file);
+    args.progress_file = state_dir.resolve(&args.progress_file);
+    args.results_file = state_dir.resolve(&args.results_file);
+    args.stats_file = args.stats_file.as_deref().map(|file| state_dir.resolve(file));
+    args.output = args.output.as_deref().map(|file| state_dir.resolve(file));
+    args.tested_set = args.tested_set.as_deref().map(|file| state_dir.resolve(file));
+    args.chain_cache = state_dir.resolve(&args.chain_cache);
+
+    // Initialize logger (only to file, suppress console logs); an estimate
+    // logs nothing
+    if estimate.is_none() {
+        CombinedLogger::init(vec![
+            TermLogger::new(
+                LevelFilter::Off, // Suppress console logs
+                Config::default(),
+                simplelog::TerminalMode::Mixed,
+                simplelog::ColorChoice::Auto,
+            ),
+            WriteLogger::new(
+                if args.debug { LevelFilter::Debug } else { LevelFilter::Info },
+                Config::default(),
+                RotatingFile::create(
+                    &args.log_file,
+                    (args.log_max_size_mb > 0).then(|| args.log_max_size_mb * 1024 * 1024),
+                    (args.log_rotate_hours > 0).then(|| Duration::from_secs(args.log_rotate_hours * 3600)),
+                    args.log_keep,
+                )
+                    .and_then(|file| AsyncWriter::spawn(file, args.log_queue, args.log_overflow))
+                    .map_err(|e| {
+                        error!("Failed to create log file {}: {}", args.log_file, e);
+                        anyhow::anyhow!("Failed to create log file {}: {}", args.log_file, e)
+                    })?,
+            ),
+        ])
+        .map_err(|e| {
+            error!("Failed to initialize logger: {}", e);
+            anyhow::anyhow!("Failed to initialize logger: {}", e)
+        })?;
+    }
+
+    redact::set_enabled(args.redact);
+    info!("Program started");
+    log_args(&mut args);
+
+    if fixed_words > total_words {
+        error!(
+            "Fixed words ({}) must not exceed total words ({})",
+            fixed_words, total_words
+        );
+        return Err(anyhow::anyhow!("Invalid fixed words count"));
+    }
+
+    let tokenlist = match &args.tokenlist {
+        Some(file) => Some(TokenList::load(file, total_words, args.word_index_base).map_err(|e| {
+            error!("{}", e);
+            e
+        })?),
+        None => None,
     };
+    let substitutes = match &args.candidates_file {
+        Some(file) => Some(Substitutes::load(file, total_words, args.word_index_base).map_err(|e| {
+            error!("{}", e);
+            e
+        })?),
+        None => None,
+    };
+    let pinned = known_positions(&args, total_words).map_err(|e| {
+        error!("{}", e);
+        e
+    })?;
+    // The search runs over the positions left once pinned words are out
+    let free_words = total_words - pinned.as_ref().map_or(0, KnownPositions::len);
+    let missing = missing_words(&args, free_words, fixed_words, pinned.as_ref()).map_err(|e| {
+        error!("{}", e);
+        e
+    })?;
+    // Tokenlists, seedlists, and candidates files supply whole candidates
+    // instead of known words
+    let listed_candidates = tokenlist.is_some() || args.seedlist.is_some() || substitutes.is_some();
+    // Missing words are left out of the known ones
+    let known_count = match args.pool_size {
+        Some(pool_size) => pool_size,
+        None => free_words - missing.as_ref().map_or(0, MissingWords::count),
+    };
+    let known_words = if listed_candidates { Vec::new() } else { load_known_words(&args, known_count)? };
+    if args.seed_format == SeedFormat::Monero {
+        if let Some(word) = known_words.iter().find(|word| monero::index(word).is_none()) {
+            error!("{} is not a Monero word", word);
+            return Err(anyhow::anyhow!("Invalid Monero word {}", word));
+        }
+    }
+    let fuzzy = expand_known_words(&args, &known_words, fixed_words).map_err(|e| {
+        error!("{}", e);
+        e
+    })?;
+    let bounded = bounded_order(&args, free_words, fixed_words).map_err(|e| {
+        error!("{}", e);
+        e
+    })?;
+    let pool = word_pool(&args, free_words, fixed_words).map_err(|e| {
+        error!("{}", e);
+        e
+    })?;
+    let space_size = search_space(
+        tokenlist.as_ref(),
+        args.seedlist.as_deref(),
+        substitutes.as_ref(),
+        fuzzy.as_ref(),
+        missing.as_ref(),
+        Arrangement::of(bounded.as_ref(), pool.as_ref(), free_words.saturating_sub(fixed_words)),
+    )
+    .map_err(|e| {
+        error!("{}", e);
+        e
+    })?;
+    // A resume token carries the range of the run it was printed by
+    let resume_token = args.resume.as_deref().map(Checkpoint::from_token).transpose()?;
+    if let Some(shard) = resume_token.as_ref().and_then(|token| token.shard) {
+        if shard.space != space_size {
+            return Err(anyhow::anyhow!(
+                "Resume token is for a space of {} candidates, this search has {}",
+                shard.space,
+                space_size
+            ));
+        }
+        args.start_index = shard.start;
+        args.end_index = Some(shard.end);
+    }
+    let end_index = args.end_index.unwrap_or(space_size).min(space_size);
+    if args.start_index > end_index {
+        error!("Start index {} is past end index {}", args.start_index, end_index);
+        return Err(anyhow::anyhow!("Invalid index range"));
+    }
+    let total_permutations = end_index - args.start_index;
+    let shard = Shard { start: args.start_index, end: end_index, space: space_size };
+    let phase = phase_name(&args);
+    let config = config_hash(&args).map_err(|e| {
+        error!("{}", e);
+        e
+    })?;
+    if let Some(saved) = resume_token.as_ref().and_then(|token| token.config.as_deref()) {
+        if saved != config {
+            return Err(anyhow::anyhow!(
+                "Resume token was printed under configuration {}, this search is {}; \
+                 the candidate source, matrix, passphrases, and targets must match",
+                saved,
+                config
+            ));
+        }
+    }
 
-    let use_parallel = total_permutations >= 1000;
-    let num_threads = if use_parallel { 12 } else { 1 };
+    if args.threads == Some(0) {
+        return Err(anyhow::anyhow!("--threads must be at least 1"));
+    }
+    let threads = args.threads.unwrap_or_else(default_threads);
+    let use_parallel = threads > 1 && total_permutations >= args.parallel_threshold;
+    let num_threads = if use_parallel { threads } else { 1 };
     info!("Requested {} threads for {} permutations", num_threads, total_permutations);
 
     rayon::ThreadPoolBuilder::new()
@@ -241,111 +1718,204 @@ fn main() -> Result<()> {
         })?;
     info!("Thread pool initialized with {} threads", num_threads);
 
-    let (target_address, address_db) = match (&args.address, &args.address_file, &args.address_db_file) {
+    // Address types detected in --address-file beyond --address-type
+    let mut address_file_types: Vec<&'static str> = Vec::new();
+    let address_pattern = args.address_pattern.as_deref().map(AddressPattern::parse).transpose().map_err(|e| {
+        error!("{}", e);
+        e
+    })?;
+    let chain_cache_age = Duration::from_secs(args.chain_cache_age);
+    let chain = args
+        .chain
+        .as_deref()
+        .map(|url| chain::ChainClient::new(url, args.chain_rate, args.chain_batch, &args.chain_cache, chain_cache_age))
+        .transpose()?;
+    if let Some(chain) = &chain {
+        info!("Looking addresses up on {} at up to {} per second, cached in {}", chain.url(), args.chain_rate, args.chain_cache);
+    }
+    let (mut target_address, mut address_db) = match (&args.address, &args.address_file, &args.address_db_file) {
         (Some(addr), None, None) => (Some(addr.as_str()), None),
         (None, Some(file), None) => {
-            let addr = fs::read_to_string(file)
-                .map_err(|e| {
-                    error!("Failed to read address file {}: {}", file, e);
-                    anyhow::anyhow!("Failed to read address file: {}", e)
-                })?
-                .trim()
-                .to_string();
-            (Some(&*Box::leak(addr.into_boxed_str())), None)
+            let targets = targets::load(file).map_err(|e| {
+                error!("{}", e);
+                e
+            })?;
+            info!("Loaded {} addresses from {}", targets.addresses.len(), file);
+            for address in &targets.undetected {
+                warn!("Can't tell the address type of {}; it is matched only as the search derives it", address);
+            }
+            address_file_types = targets.address_types;
+            (None, Some(RwLock::new(targets.addresses)))
         }
         (None, None, Some(db_file)) => {
-            let file = fs::File::open(db_file)
+            let db = load_address_db(db_file)?;
+            info!("Loaded {} addresses from database", db.len());
+            (None, Some(RwLock::new(db)))
+        }
+        (None, None, None) if args.descriptors.is_some() => {
+            let file = args.descriptors.as_deref().unwrap_or_default();
+            let expanded = descriptors::load(file, args.gap_limit, chain.as_ref()).map_err(|e| {
+                error!("{}", e);
+                e
+            })?;
+            for skipped in &expanded.skipped {
+                warn!("Skipping unsupported descriptor {}", skipped);
+            }
+            println!(
+                "Expanded {} descriptors from {} into {} target addresses (gap limit {}, {} unsupported skipped)",
+                expanded.descriptors,
+                file,
+                expanded.addresses.len(),
+                args.gap_limit,
+                expanded.skipped.len()
+            );
+            info!("Loaded {} addresses from descriptors in {}", expanded.addresses.len(), file);
+            (None, Some(RwLock::new(expanded.addresses)))
+        }
+        // The seed's notification address stands in for its payment code
+        (None, None, None) if args.payment_code.is_some() => {
+            let code = args.payment_code.as_deref().unwrap_or_default();
+            let network = matrix::parse_network(&args.network)?;
+            let address = bip47::PaymentCode::parse(code, network)
+                .and_then(|payment_code| payment_code.notification_address(&Secp256k1::new()))
                 .map_err(|e| {
-                    error!("Failed to open address database file {}: {}", db_file, e);
-                    anyhow::anyhow!("Failed to open address database file: {}", e)
+                    error!("{}", e);
+                    e
                 })?;
-            let reader = BufReader::new(file);
-            let db: HashSet<String> = reader
-                .lines()
-                .map(|line| line.map_err(|e| {
-                    error!("Failed to read address database: {}", e);
-                    anyhow::anyhow!("Failed to read address database: {}", e)
-                }))
-                .collect::<Result<Vec<_>, _>>()?
-                .into_iter()
-                .map(|s| s.trim().to_string())
-                .filter(|s| !s.is_empty())
-                .collect();
-            info!("Loaded {} addresses from database", db.len());
-            (None, Some(db))
+            println!(
+                "Payment code {} has notification address {} at {}",
+                code,
+                address,
+                bip47::notification_path(network)
+            );
+            info!("Targeting payment code {} via notification address {}", code, address);
+            (None, Some(RwLock::new(HashSet::from([address]))))
+        }
+        (None, None, None) if args.node_id.is_some() => {
+            let node_id = lightning::parse_node_id(args.node_id.as_deref().unwrap_or_default()).map_err(|e| {
+                error!("{}", e);
+                e
+            })?;
+            (None, Some(RwLock::new(HashSet::from([node_id]))))
         }
+        (None, None, None) if args.xpub.is_some() => (args.xpub.as_deref(), None),
+        (None, None, None) if args.fingerprint.is_some() => (args.fingerprint.as_deref(), None),
+        (None, None, None) if address_pattern.is_some() => (None, None),
+        (None, None, None) if args.address_bloom.is_some() => (None, None),
+        (None, None, None) if args.hash_db.is_some() => (None, None),
+        // Exporting candidates doesn't test them against anything
+        (None, None, None) if args.export_candidates.is_some() => (None, None),
         _ => {
-            error!("Must specify exactly one of --address, --address-file, or --address-db-file");
-            return Err(anyhow::anyhow!("Must specify exactly one of --address, --address-file, or --address-db-file"));
+            error!("Must specify exactly one of --address, --address-file, --address-db-file, --descriptors, --payment-code, --node-id, --xpub, --fingerprint, --address-pattern, --address-bloom, or --hash-db");
+            return Err(anyhow::anyhow!(
+                "Must specify exactly one of --address, --address-file, --address-db-file, --descriptors, --payment-code, --node-id, --xpub, --fingerprint, --address-pattern, --address-bloom, or --hash-db"
+            ));
         }
     };
 
-    let known_words = if let Some(seed_words_file) = &args.seed_words_file {
-        let file = fs::File::open(seed_words_file)
-            .map_err(|e| {
-                error!("Failed to open seed words file {}: {}", seed_words_file, e);
-                anyhow::anyhow!("Failed to open seed words file: {}", e)
+    let address_bloom = match &args.address_bloom {
+        Some(file) => {
+            let cache = args.bloom_cache.as_deref().map(|cache| state_dir.resolve(cache));
+            let bloom = AddressBloom::open(file, args.bloom_fp_rate, cache.as_deref()).map_err(|e| {
+                error!("{}", e);
+                e
             })?;
-        let reader = BufReader::new(file);
-        let words = reader
-            .lines()
-            .collect::<Result<Vec<String>, io::Error>>()
-            .map_err(|e| {
-                error!("Failed to read seed words file {}: {}", seed_words_file, e);
-                anyhow::anyhow!("Failed to read seed words file: {}", e)
-            })?
-            .into_iter()
-            .map(|s| s.trim().to_string())
-            .filter(|s| !s.is_empty())
-            .collect::<Vec<String>>();
-        if words.len() != args.total_words {
-            error!("Seed words file contains {} words, expected {}", words.len(), args.total_words);
-            return Err(anyhow::anyhow!("Invalid number of seed words in file"));
-        }
-        words
-    } else {
-        if args.known_words.len() != args.total_words {
-            error!("Provided {} known words, expected {}", args.known_words.len(), args.total_words);
-            return Err(anyhow::anyhow!("Invalid number of known words"));
+            info!("Bloom filter over {} addresses from {}: {} bytes", bloom.entries(), file, bloom.size());
+            Some(bloom)
         }
-        args.known_words
+        None => None,
     };
 
-    let network = match args.network.to_lowercase().as_str() {
-        "mainnet" => Network::Bitcoin,
-        "testnet" => Network::Testnet,
-        _ => {
-            error!("Invalid network: {}. Use 'mainnet' or 'testnet'.", args.network);
-            return Err(anyhow::anyhow!("Invalid network"));
+    let hash_db = args.hash_db.as_deref().map(HashDb::open).transpose().map_err(|e| {
+        error!("{}", e);
+        e
+    })?;
+
+    // Targets added over the control socket need the set-based matcher
+    if args.daemon {
+        if let Some(address) = target_address.take() {
+            address_db = Some(RwLock::new(HashSet::from([address.to_string()])));
         }
-    };
+    }
 
-    let derivation_path = args.path.parse::<DerivationPath>().map_err(|e| {
-        error!("Invalid derivation path: {}", e);
-        anyhow::anyhow!("Invalid derivation path: {}", e)
+    let network = matrix::parse_network(&args.network).map_err(|e| {
+        error!("{}", e);
+        anyhow::anyhow!("Invalid network")
     })?;
 
-    if known_words.len() != args.total_words {
+    let path_ranges = args.path_ranges.iter().map(|range| pathspec::parse_range(range)).collect::<Result<Vec<_>>>()?;
+    let mut derivation_paths = match &args.payment_code {
+        Some(_) => vec![bip47::notification_path(network)],
+        None if !args.path_preset.is_empty() => paths::preset_paths(&args.path_preset, network)
+            .map_err(|e| {
+                error!("{}", e);
+                e
+            })?,
+        None => pathspec::expand_list(&args.path, &path_ranges).map_err(|e| {
+            error!("{}", e);
+            e
+        })?,
+    };
+
+    // Several paths listed in --path or picked with --path-preset are each
+    // checked as the address type their purpose implies
+    let listed_paths = !args.path_preset.is_empty() || args.path.contains(',');
+    // Electrum seeds default to the wallets Electrum makes for them rather
+    // than BIP-44
+    let electrum_paths = args.seed_format == SeedFormat::Electrum
+        && args.path == DEFAULT_PATH
+        && !listed_paths
+        && args.matrix.is_none()
+        && args.coin.is_none();
+    // Several types without --path are each checked at their own
+    // conventional purpose
+    let per_type_paths = args.address_type.len() > 1 && args.path == DEFAULT_PATH && !listed_paths && !electrum_paths;
+    // A purpose that doesn't fit the address type can never produce a match
+    let path_mismatch = match (&args.matrix, &args.coin, derivation_paths.first(), args.address_type.as_slice()) {
+        (None, None, Some(path), [address_type]) if args.payment_code.is_none() && !listed_paths && !electrum_paths => {
+            paths::purpose_mismatch(path, address_type)
+        }
+        _ => None,
+    };
+    if let Some((actual, expected)) = path_mismatch {
+        if args.auto_path {
+            derivation_paths = derivation_paths.iter().map(|path| paths::with_purpose(path, expected)).collect();
+            info!("Switched derivation path purpose from {}' to {}' for {}", actual, expected, args.address_type[0]);
+        } else {
+            warn!(
+                "Derivation path {} uses purpose {}' but {} wallets use {}'",
+                args.path, actual, args.address_type[0], expected
+            );
+        }
+    }
+
+    if !listed_candidates && known_words.len() != known_count {
         error!(
             "Expected {} words, got {}",
-            args.total_words,
+            known_count,
             known_words.len()
         );
         return Err(anyhow::anyhow!("Invalid number of known words"));
     }
 
-    if args.fixed_words > args.total_words {
-        error!(
-            "Fixed words ({}) must not exceed total words ({})",
-            args.fixed_words, args.total_words
-        );
-        return Err(anyhow::anyhow!("Invalid fixed words count"));
+    if let Some(export_file) = &args.export_candidates {
+        let export_file = state_dir.resolve(export_file);
+        export::export_candidates(
+            &export_file,
+            &known_words[..fixed_words],
+            known_words[fixed_words..].to_vec(),
+            args.start_index,
+            end_index,
+            args.batch_size,
+        )?;
+        state_dir.release();
+        return Ok(ExitCode::SUCCESS);
     }
 
     let pb = ProgressBar::new(total_permutations);
     pb.set_style(
         ProgressStyle::with_template(
-            "[{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} ({percent}%) | ETA: {eta_precise} | {msg}"
+            "[{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} ({percent}%) | {msg}"
         )
         .unwrap()
         .progress_chars("##-")
@@ -353,29 +1923,581 @@ fn main() -> Result<()> {
     pb.enable_steady_tick(std::time::Duration::from_millis(3));
     let pb = Arc::new(pb);
 
-    pb.println(format!("Provided words ({}): {:?}", known_words.len(), known_words));
-    if args.fixed_words > 0 {
-        pb.println(format!("Fixed words ({}): {:?}", args.fixed_words, &known_words[..args.fixed_words]));
+    match (&tokenlist, &args.tokenlist, &args.seedlist) {
+        (Some(tokenlist), Some(file), _) => pb.println(format!(
+            "Tokenlist {}: {} lines, {} token selections",
+            file,
+            tokenlist.line_count(),
+            tokenlist.selection_count()
+        )),
+        (_, _, Some(file)) => pb.println(format!("Seedlist {}: {} lines", file, space_size)),
+        _ if substitutes.is_some() => pb.println(format!(
+            "Candidates file {}: {:?} words per position, {} combinations",
+            args.candidates_file.as_deref().unwrap_or_default(),
+            substitutes.as_ref().map(Substitutes::choices).unwrap_or_default(),
+            space_size
+        )),
+        _ => pb.println(format!("Provided words ({}): {:?}", known_words.len(), known_words)),
+    }
+    if fixed_words > 0 {
+        pb.println(format!("Fixed words ({}): {:?}", fixed_words, &known_words[..fixed_words]));
+    }
+    if let Some(pinned) = &pinned {
+        let words: Vec<String> = pinned.words().map(|(position, word)| format!("{}: {}", position, word)).collect();
+        pb.println(format!("Known positions ({}): {}", pinned.len(), words.join(", ")));
+    }
+    for (position, word, near) in fuzzy.iter().flat_map(FuzzyWords::corrections) {
+        let reason = if args.prefix_mode { "is the start of" } else { "is not a BIP39 word, trying" };
+        pb.println(format!("Word {} ({}) {}: {}", position, word, reason, near.join(", ")));
+        info!("Word {} ({}) expanded to {} candidates", position, word, near.len());
+    }
+    if let Some(missing) = &missing {
+        let positions = if args.missing_positions.is_empty() {
+            format!("anywhere after position {}", fixed_words)
+        } else {
+            format!("at positions {:?}", args.missing_positions)
+        };
+        pb.println(format!("Missing words: {}, each any of the 2048, {}", missing.count(), positions));
+    }
+    match bounded.as_ref().map(BoundedOrder::bound) {
+        Some(Bound::Displacement(k)) => pb.println(format!(
+            "Orders with each word at most {} position(s) from where it was given: {} of {}",
+            k,
+            space_size,
+            permutation::factorial(free_words.saturating_sub(fixed_words))
+        )),
+        Some(Bound::Swaps(swaps)) => pb.println(format!(
+            "Orders at most {} swap(s) of neighbouring words from the one given: {} of {}",
+            swaps,
+            space_size,
+            permutation::factorial(free_words.saturating_sub(fixed_words))
+        )),
+        None => {}
+    }
+    if let Some(pool) = &pool {
+        let orders = if args.ordered_pool { "in the order given" } else { "in every order" };
+        pb.println(format!(
+            "Word pool: {} choices of {} words from {}, {}",
+            pool.selections(),
+            free_words.saturating_sub(fixed_words),
+            known_words.len().saturating_sub(fixed_words),
+            orders
+        ));
     }
-    if let Some(target) = target_address {
+    if let Some(key) = target_address.filter(|_| args.xpub.is_some()) {
+        pb.println(format!("Target account key: {}", key));
+    } else if let Some(fingerprint) = target_address.filter(|_| args.fingerprint.is_some()) {
+        pb.println(format!("Target master fingerprint: {}", fingerprint));
+    } else if let Some(target) = target_address {
         pb.println(format!("Target address: {}", target));
+    } else if let (Some(file), Some(db)) = (&args.address_file, &address_db) {
+        pb.println(format!("Checking against {} addresses from {}", db.read().unwrap().len(), file));
+    } else if let (Some(file), Some(bloom)) = (&args.address_bloom, &address_bloom) {
+        pb.println(format!(
+            "Checking against {} addresses from {} through a {:.1} MB bloom filter (false positive rate {})",
+            bloom.entries(),
+            file,
+            bloom.size() as f64 / 1e6,
+            args.bloom_fp_rate
+        ));
+    } else if let (Some(file), Some(db)) = (&args.hash_db, &hash_db) {
+        pb.println(format!(
+            "Checking against {} hash160 entries from {} ({})",
+            db.len(),
+            file,
+            if db.is_mapped() { "memory-mapped" } else { "decompressed into memory" }
+        ));
     } else {
-        pb.println("Checking against address database".to_string());
+        pb.println("Checking against address database");
+    }
+    match path_mismatch {
+        Some((actual, expected)) if args.auto_path => {
+            pb.println(format!(
+                "Derivation path purpose changed from {}' to {}' to match address type {} (--auto-path)",
+                actual, expected, args.address_type[0]
+            ));
+        }
+        Some((actual, expected)) => {
+            pb.println("*".repeat(78));
+            pb.println(format!(
+                "WARNING: derivation path {} uses purpose {}', but {} wallets derive under {}'.",
+                args.path, actual, args.address_type[0], expected
+            ));
+            pb.println("This combination almost never matches a real wallet. Pass --auto-path to switch");
+            pb.println(format!("to the conventional path, or set --path explicitly (e.g. m/{}'/0'/0'/0/0).", expected));
+            pb.println("*".repeat(78));
+        }
+        None => {}
+    }
+    let mut search_matrix = match (&args.matrix, &args.coin) {
+        (Some(file), _) => {
+            let search_matrix = SearchMatrix::from_file(file).map_err(|e| {
+                error!("{}", e);
+                e
+            })?;
+            pb.println(format!("Matrix search: {} combinations per candidate from {}", search_matrix.combinations(), file));
+            print_matrix(&pb, &search_matrix);
+            search_matrix
+        }
+        (None, Some(_)) if args.seed_format == SeedFormat::Monero => {
+            pb.println("Coin Monero: the standard address of each candidate's spend key");
+            SearchMatrix::single(DerivationPath::master(), monero::XMR, network)
+        }
+        (None, Some(name)) => {
+            let coin = coins::load(&args.coins_file, name).map_err(|e| {
+                error!("{}", e);
+                e
+            })?;
+            pb.println(format!(
+                "Coin {} (SLIP-44 type {}): {} combinations per candidate from {}",
+                coin.name,
+                coin.coin_type,
+                coin.matrix.combinations(),
+                coin.source
+            ));
+            print_matrix(&pb, &coin.matrix);
+            coin.matrix
+        }
+        (None, None) => {
+            match derivation_paths.as_slice() {
+                _ if listed_paths || electrum_paths => {}
+                [path] => pb.println(format!("Derivation path: {}", path)),
+                paths => pb.println(format!("Derivation paths: {} expanded from {}", paths.len(), args.path)),
+            }
+            // The paths moved to an address type's conventional purpose
+            let conventional_paths = |address_type: &str| -> Vec<DerivationPath> {
+                derivation_paths
+                    .iter()
+                    .map(|path| match paths::conventional_purpose(address_type) {
+                        Some(purpose) => paths::with_purpose(path, purpose),
+                        None => path.clone(),
+                    })
+                    // Accounts live under their own coin type as well
+                    .map(|path| match address_type {
+                        ethereum::ETH => paths::with_coin_type(&path, ethereum::COIN_TYPE).unwrap_or(path),
+                        _ => path,
+                    })
+                    .collect()
+            };
+            let mut search_matrix = if listed_paths {
+                let empty = SearchMatrix::build(Vec::new(), &[], &[network]);
+                derivation_paths.iter().fold(empty, |matrix, path| {
+                    // Paths without a BIP-43 purpose fall back to --address-type
+                    let address_types = match paths::default_address_type(path) {
+                        Some(address_type) => vec![address_type.to_string()],
+                        None => args.address_type.clone(),
+                    };
+                    pb.println(format!("Derivation path: {} ({})", path, address_types.join(", ")));
+                    address_types.iter().fold(matrix, |matrix, address_type| {
+                        matrix.with_paths(vec![path.clone()], address_type, network)
+                    })
+                })
+            } else if electrum_paths {
+                let empty = SearchMatrix::build(Vec::new(), &[], &[network]);
+                electrum::LAYOUTS.iter().fold(empty, |matrix, &(path, address_type)| {
+                    pb.println(format!("Electrum derivation path: {} ({})", path, address_type));
+                    let path = path.parse::<DerivationPath>().expect("Electrum layout paths are valid");
+                    matrix.with_paths(vec![path], address_type, network)
+                })
+            } else if per_type_paths {
+                let empty = SearchMatrix::build(Vec::new(), &[], &[network]);
+                let matrix = args.address_type.iter().fold(empty, |matrix, address_type| {
+                    matrix.with_paths(conventional_paths(address_type), address_type, network)
+                });
+                pb.println(format!(
+                    "Address types {} at their conventional paths: {} combinations per candidate",
+                    args.address_type.join(", "),
+                    matrix.combinations()
+                ));
+                print_matrix(&pb, &matrix);
+                matrix
+            } else {
+                // Types sharing a path share its derivation, and only the
+                // encoding differs
+                SearchMatrix::build(derivation_paths.clone(), &args.address_type, &[network])
+            };
+            // Each other type listed in the address file is checked at its
+            // conventional purpose
+            for address_type in address_file_types.iter().filter(|&&address_type| !args.address_type.iter().any(|t| t == address_type)) {
+                let extra = conventional_paths(address_type);
+                pb.println(format!("Address file lists {} addresses, also checking {} path(s)", address_type, extra.len()));
+                search_matrix = search_matrix.with_paths(extra, address_type, network);
+            }
+            search_matrix
+        }
+    };
+    if !args.coin_type_list.is_empty() {
+        search_matrix = search_matrix.with_coin_types(&args.coin_type_list).map_err(|e| {
+            error!("{}", e);
+            e
+        })?;
+        let coin_types: Vec<String> = args.coin_type_list.iter().map(|coin_type| format!("{}'", coin_type)).collect();
+        pb.println(format!(
+            "Coin types {}: {} combinations per candidate",
+            coin_types.join(", "),
+            search_matrix.combinations()
+        ));
+    }
+    args.toggle_hardening.sort_unstable();
+    args.toggle_hardening.dedup();
+    if !args.toggle_hardening.is_empty() {
+        // Each level doubles the paths derived per candidate
+        if args.toggle_hardening.len() > MAX_TOGGLED_LEVELS {
+            return Err(anyhow::anyhow!(
+                "--toggle-hardening accepts at most {} levels, got {}",
+                MAX_TOGGLED_LEVELS,
+                args.toggle_hardening.len()
+            ));
+        }
+        search_matrix = search_matrix.with_hardening_toggled(&args.toggle_hardening).map_err(|e| {
+            error!("{}", e);
+            e
+        })?;
+        pb.println(format!(
+            "Toggling hardening at levels {:?}: {} paths, {} combinations per candidate",
+            args.toggle_hardening,
+            search_matrix.paths.len(),
+            search_matrix.combinations()
+        ));
+    }
+    if args.address_index_range.is_some() || !args.change.is_empty() {
+        let indices = args.address_index_range.as_deref().map(pathspec::parse_range).transpose()?;
+        let chains = (!args.change.is_empty()).then_some(args.change.as_slice());
+        search_matrix = search_matrix.with_address_window(chains, indices).map_err(|e| {
+            error!("{}", e);
+            e
+        })?;
+        pb.println(format!(
+            "Address window: {} paths, {} combinations per candidate",
+            search_matrix.paths.len(),
+            search_matrix.combinations()
+        ));
+    }
+    // Legacy presets have no coin-type level, so they join after the
+    // expansions above rather than going through them
+    if let Some(names) = &args.legacy_paths {
+        let legacy = paths::legacy_paths(names).map_err(|e| {
+            error!("{}", e);
+            e
+        })?;
+        for path in &legacy {
+            pb.println(format!("Legacy path: {} (p2pkh)", path));
+        }
+        search_matrix = search_matrix.with_paths(legacy, "p2pkh", network);
+    }
+    // Prefixes given on the command line win over the coin definition's
+    search_matrix.overrides = AddressOverrides::parse(
+        args.bech32_hrp.as_deref(),
+        args.p2pkh_version.as_deref(),
+        args.p2sh_version.as_deref(),
+    )
+    .map_err(|e| {
+        error!("{}", e);
+        e
+    })?
+    .or(search_matrix.overrides);
+    if !search_matrix.overrides.is_empty() {
+        pb.println(format!("Address prefix overrides: {:?}", search_matrix.overrides));
+    }
+    if args.matrix.is_none() && args.coin.is_none() {
+        pb.println(format!("Network: {}", args.network));
+        if !listed_paths && !electrum_paths {
+            pb.println(format!("Address type: {}", args.address_type.join(", ")));
+        }
+    }
+    let passphrases = if args.passphrase_file.is_none() && args.passphrases.is_empty() {
+        if !args.passphrase_rules.is_empty() || args.passphrase_unicode_variants {
+            let e = anyhow::anyhow!(
+                "--passphrase-rules and --passphrase-unicode-variants need --passphrase or --passphrase-file"
+            );
+            error!("{}", e);
+            return Err(e);
+        }
+        Passphrases::none()
+    } else {
+        let mut words = args.passphrases.clone();
+        let mut sources = Vec::new();
+        if !words.is_empty() {
+            sources.push("--passphrase".to_string());
+        }
+        if let Some(file) = &args.passphrase_file {
+            words.extend(passphrase::read_list(file).map_err(|e| {
+                error!("{}", e);
+                e
+            })?);
+            sources.push(file.clone());
+        }
+        let mut passphrases = Passphrases::new(words, &args.passphrase_rules).map_err(|e| {
+            error!("{}", e);
+            e
+        })?;
+        if args.passphrase_unicode_variants {
+            passphrases = passphrases.with_unicode_variants();
+        }
+        pb.println(format!("Passphrases: up to {} per candidate from {}", passphrases.count(), sources.join(" and ")));
+        passphrases
+    };
+    let position_weights = match &args.position_weights {
+        Some(file) => {
+            let weights = PositionWeights::load(file, &known_words[fixed_words..], fixed_words, args.word_index_base).map_err(|e| {
+                error!("{}", e);
+                e
+            })?;
+            pb.println(format!("Ordering candidates by position likelihood from {}", file));
+            Some(weights)
+        }
+        None => None,
+    };
+    // Keys depend on everything that decides how a mnemonic is tested,
+    // targets included
+    let tested = match &args.tested_set {
+        Some(file) => {
+            let config = format!(
+                "{:?}|{:?}|{:?}|{:?}|{}|{:?}|{:?}",
+                args.seed_format,
+                search_matrix,
+                args.passphrases,
+                args.passphrase_rules,
+                passphrases.count(),
+                target_address,
+                args.address_pattern
+            );
+            let addresses = address_db.as_ref().map(|db| db.read().unwrap());
+            let files = [args.passphrase_file.as_deref(), args.address_bloom.as_deref(), args.hash_db.as_deref()];
+            let fingerprint = tested::fingerprint(&config, addresses.as_deref(), &files).map_err(|e| {
+                error!("{}", e);
+                e
+            })?;
+            drop(addresses);
+            let tested = TestedSet::open(file, &fingerprint).map_err(|e| {
+                error!("{}", e);
+                e
+            })?;
+            pb.println(format!("Tested set {}: {} mnemonics from earlier runs", file, tested.count()));
+            Some(tested)
+        }
+        None => None,
+    };
+    // The device computes seeds; keys, addresses, and matching stay on the
+    // CPU threads. Without one the search runs on the CPU as before.
+    let mut gpu = if args.gpu && estimate.is_none() {
+        match Gpu::open(args.accelerator, args.kernel_path.as_deref(), &args.gpu_devices, &passphrases) {
+            Ok(gpu) => {
+                let names = gpu.names().join(", ");
+                let accelerator = gpu.accelerator().name();
+                pb.println(format!(
+                    "GPU ({}): {} computing seeds, {} candidates per batch",
+                    accelerator, names, args.batch_size
+                ));
+                info!("{} GPU backend on {}", accelerator, names);
+                Some(gpu)
+            }
+            Err(e) => {
+                warn!("--gpu given but the GPU backend is unavailable: {}", e);
+                pb.println(format!("GPU backend not available ({}), searching on the CPU", e));
+                None
+            }
+        }
+    } else {
+        None
+    };
+    // The CPU threads compute seeds several candidates at a time on the
+    // vector units, one password per lane. A tested set skips candidates
+    // after their checksum, so those would be hashed for nothing
+    let backend = pbkdf2::Backend::detect();
+    let cpu_batch = (!args.no_simd
+        && backend.lanes() > 1
+        && args.seed_format == SeedFormat::Bip39
+        && tested.is_none())
+    .then(|| Pbkdf2Batch::new(backend, &passphrases));
+    if let Some(cpu_batch) = &cpu_batch {
+        pb.println(format!("CPU seeds: {}, {} at a time per thread", backend.name(), backend.lanes()));
+        info!("Batched PBKDF2 on {} ({} candidates per batch)", backend.name(), cpu_batch.candidates_per_batch());
+    }
+    pb.println(format!("Fixed words count: {}", fixed_words));
+    if total_permutations != space_size {
+        pb.println(format!("Index range: {}..{} of {}", args.start_index, end_index, space_size));
     }
-    pb.println(format!("Derivation path: {}", args.path));
-    pb.println(format!("Network: {}", args.network));
-    pb.println(format!("Address type: {}", args.address_type));
-    pb.println(format!("Fixed words count: {}", args.fixed_words));
     pb.println(format!("Total permutations to check: {}", total_permutations));
+    // Candidates that survive the checksum, times the addresses derived for
+    // each, for the chance that one of them fits a partial target by accident
+    let checksum_pass = checksum_pass(&args, total_words);
+    let derived =
+        total_permutations as f64 * checksum_pass * search_matrix.combinations() as f64 * passphrases.count() as f64;
+    if let Some(pattern) = &address_pattern {
+        let expected = derived * 2f64.powf(-pattern.known_bits());
+        warn!("Address pattern {} fixes ~{:.0} bits; ~{:.2e} false matches expected", pattern, pattern.known_bits(), expected);
+        pb.println(format!(
+            "Address pattern {} fixes about {:.0} bits of the address; expect about {:.2e} false matches over this search. \
+             Every hit is recorded and the search continues.",
+            pattern,
+            pattern.known_bits(),
+            expected
+        ));
+    }
+    if let Some(fingerprint) = &args.fingerprint {
+        let expected = derived * 2f64.powi(-xpub::FINGERPRINT_BITS);
+        warn!("Master fingerprint {}: ~{:.2e} false matches expected", fingerprint, expected);
+        pb.println(format!(
+            "A master fingerprint is only {} bits; expect about {:.2e} false matches over this search. Every hit is \
+             recorded and the search continues.",
+            xpub::FINGERPRINT_BITS,
+            expected
+        ));
+    }
 
-    let found = Arc::new(AtomicBool::new(false));
+    let draining = Arc::new(AtomicBool::new(false));
+    let matches = Arc::new(AtomicUsize::new(0));
+    let stop = Arc::new(StopConditions::new(
+        args.stop_after_matches,
+        args.stop_at_coverage,
+        args.stop_at_index,
+        args.min_speed,
+        Duration::from_secs(args.min_speed_window.max(1)),
+        args.max_runtime.map(Duration::from_secs),
+    ));
+    let live_stats = args.stats_file.as_deref().map(|file| Arc::new(LiveStatsWriter::new(file)));
+    let output = args.output.as_deref().map(|file| Arc::new(ResultsWriter::new(file)));
+    let summary = Arc::new(RunSummary::default());
+    let stage_stats = Arc::new(StageStats::default());
+    let stats_requested = Arc::new(AtomicBool::new(false));
+    let device_labels = gpu.as_ref().map(Gpu::labels).unwrap_or_default();
+    let worker_stats = Arc::new(WorkerStats::for_cpu_threads(num_threads).with_devices(device_labels));
+    signals::register_request(Request::Stats, Arc::clone(&stats_requested))?;
+    let reload_requested = Arc::new(AtomicBool::new(false));
+    if args.address_db_file.is_some() {
+        signals::register_request(Request::Reload, Arc::clone(&reload_requested))?;
+    }
     let processed = Arc::new(AtomicUsize::new(0));
     let start = Instant::now();
+    // A single address is matched by the bytes it wraps, so candidates are
+    // only encoded when those match. Any other single target (an xpub, a
+    // fingerprint, or an address that doesn't decode) is a set of one
+    let hash_db = match target_address.filter(|_| args.address.is_some()).and_then(hashdb::address_key) {
+        Some(key) => Some(HashDb::from_keys(vec![key])),
+        None => {
+            if let Some(target) = target_address {
+                address_db = Some(RwLock::new(HashSet::from([target.to_string()])));
+            }
+            hash_db
+        }
+    };
     let address_db = Arc::new(address_db);
     let secp = Arc::new(Secp256k1::new());
+
+    if let Some(seconds) = estimate {
+        pb.finish_and_clear();
+        if args.gpu {
+            println!("Timing the CPU threads only; a short run with --max-runtime shows what the GPU adds");
+        }
+        // Seedlists are timed on their own lines, anything else on words
+        // drawn at random, which fail the checksum as often as real candidates
+        let lines: Vec<Vec<String>> = match &args.seedlist {
+            Some(file) => seedlist::candidates(file, total_words, args.start_index, end_index)?.flatten().take(4096).collect(),
+            None => Vec::new(),
+        };
+        if let (Some(file), true) = (&args.seedlist, lines.is_empty()) {
+            return Err(anyhow::anyhow!("Seedlist {} has no {}-word lines in range to time", file, total_words));
+        }
+        let vocabulary = match args.seed_format {
+            SeedFormat::Monero => known_words.clone(),
+            _ => Bip39Wordlist::english().words(),
+        };
+        let sample = |index: u64| match lines.len() {
+            0 => estimate::drawn(&vocabulary, total_words, index),
+            n => lines[(index % n as u64) as usize].clone(),
+        };
+        let wordlist = Bip39Wordlist::english();
+        let address_db = address_db.as_ref().as_ref().map(|db| db.read().unwrap());
+        let targets = Targets {
+            addresses: address_db.as_deref(),
+            pattern: address_pattern.as_ref(),
+            bloom: address_bloom.as_ref(),
+            hash_db: hash_db.as_ref(),
+        };
+        let try_all = |candidates: &[Vec<String>], streams: Vec<Option<SeedStream>>| {
+            for (words, mut stream) in candidates.iter().zip(streams) {
+                let _ = try_mnemonic(
+                    words,
+                    args.seed_format,
+                    &search_matrix,
+                    &passphrases,
+                    stream.as_mut().map(|stream| stream as &mut dyn Iterator<Item = Seed>),
+                    tested.as_ref(),
+                    &targets,
+                    &secp,
+                    &wordlist,
+                    &summary,
+                    &stage_stats,
+                    false,
+                );
+            }
+        };
+        // Seeds are batched as the search batches them: candidates are
+        // gathered until enough pass the checksum to fill the lanes
+        let check = |candidates: &[Vec<String>]| {
+            let Some(cpu_batch) = &cpu_batch else {
+                return try_all(candidates, candidates.iter().map(|_| None).collect());
+            };
+            let (mut first, mut taken) = (0, 0);
+            for (i, words) in candidates.iter().enumerate() {
+                taken += usize::from(cpu_batch.takes(&[], words));
+                if taken == cpu_batch.candidates_per_batch() || i + 1 == candidates.len() {
+                    let batch: Vec<Option<Vec<String>>> = candidates[first..=i].iter().cloned().map(Some).collect();
+                    try_all(&candidates[first..=i], cpu_batch.seeds(&[], &batch));
+                    (first, taken) = (i + 1, 0);
+                }
+            }
+        };
+        let measured = estimate::benchmark(seconds, &sample, &check, &summary);
+        let size = estimate::SearchSize {
+            candidates: total_permutations,
+            space: space_size,
+            combinations: search_matrix.combinations(),
+            passphrases: passphrases.count(),
+            checksum_pass,
+        };
+        estimate::report(&size, &measured);
+        state_dir.release();
+        return Ok(ExitCode::SUCCESS);
+    }
     let progress_file = Arc::new(args.progress_file.clone());
     let batch_size = Arc::new(args.batch_size);
 
+    // On battery, park workers beyond --battery-threads and pause entirely
+    // below --battery-pause-percent until AC power returns. The daemon and
+    // dashboard use the same gate for pause/resume.
+    let battery_aware = args.battery_threads.is_some() || args.battery_pause_percent.is_some();
+    if battery_aware && power::read() == PowerSource::Unknown {
+        pb.println("Power source can't be detected on this system, battery options have no effect");
+    }
+    let remote_control = args.daemon || args.dashboard.is_some();
+    let throttle =
+        (battery_aware || remote_control || args.interactive).then(|| Arc::new(Throttle::new(num_threads)));
+
+    // Every candidate passes the process cap and the cap of the backend
+    // testing it, which is always the CPU for now
+    let mut rate_limits = Vec::new();
+    let mut gpu_rate_limit = None;
+    if let Some(rate) = args.max_rate {
+        rate_limits.push(RateLimiter::new(rate)?);
+    }
+    for value in &args.backend_max_rate {
+        let (backend, rate) = ratelimit::parse_backend_rate(value).map_err(|e| {
+            error!("{}", e);
+            e
+        })?;
+        match backend {
+            bench::Backend::Cpu => rate_limits.push(RateLimiter::new(rate)?),
+            bench::Backend::Gpu if gpu.is_some() => gpu_rate_limit = Some(RateLimiter::new(rate)?),
+            bench::Backend::Gpu => pb.println("No GPU backend is in use, so its rate cap has nothing to limit"),
+        }
+    }
+    if let Some(rate) = rate_limits.iter().map(RateLimiter::rate).reduce(f64::min) {
+        pb.println(format!("Rate capped at {} candidates/sec", rate));
+        info!("Rate capped at {} candidates/sec", rate);
+    }
+
     let bip39_wordlist = match Bip39Wordlist::new("bip39_wordlist.txt") {
         Ok(wordlist) => Arc::new(wordlist),
         Err(e) => {
@@ -384,49 +2506,147 @@ fn main() -> Result<()> {
         }
     };
 
+    // Abort before a long run rather than after, if derivation is broken
+    if args.skip_self_check {
+        pb.println("Skipping the startup self-check (--skip-self-check)");
+    } else {
+        match selftest::run(&secp, &bip39_wordlist) {
+            Ok(vectors) => {
+                pb.println(format!("Self-check passed ({} reference vectors)", vectors));
+                info!("Self-check passed ({} reference vectors)", vectors);
+            }
+            Err(e) => {
+                error!("{}", e);
+                state_dir.release();
+                return Err(e);
+            }
+        }
+    }
+
+    // Matches are sealed as they're found, so a recipient that can't be
+    // encrypted to is caught now rather than at the one match that counts
+    let recipient = match args.encrypt_result.as_deref().map(Recipient::parse).transpose() {
+        Ok(recipient) => recipient,
+        Err(e) => {
+            state_dir.release();
+            return Err(e);
+        }
+    };
+    if let Some(recipient) = &recipient {
+        if let Err(e) = recipient.encrypt(b"bip39_recovery --encrypt-result check") {
+            error!("{}", e);
+            state_dir.release();
+            return Err(e);
+        }
+        pb.println(format!("Matches will be saved encrypted to {}", recipient.describe()));
+        info!("Matches will be saved encrypted to {}", recipient.describe());
+    }
+
     // Load previous progress
-    let initial_processed = load_progress(&args.progress_file)?;
+    let checkpoint = match resume_token {
+        Some(token) => {
+            let mut checkpoint = Checkpoint::new(shard, &phase, &config, live_stats::unix_now());
+            checkpoint.processed = token.processed.min(total_permutations);
+            info!("Resuming from token: {} permutations processed", checkpoint.processed);
+            checkpoint
+        }
+        None => load_progress(&args.progress_file, shard, &phase, &config)?,
+    };
+    // Unrank straight to the resume point instead of regenerating the prefix
+    let resume_index = args.start_index.saturating_add(checkpoint.processed).min(end_index);
+    let frontier = FRONTIER.get_or_init(|| Frontier::new(resume_index, &checkpoint.ahead));
+    // Ranks done ahead of the frontier are skipped below but still count
+    let initial_processed = (checkpoint.processed + frontier.ahead_count()) as usize;
+    let _ = DERIVED.set((checkpoint.derived, Arc::clone(&summary)));
+    let _ = CHECKPOINT.set(Mutex::new(checkpoint));
     processed.store(initial_processed, Ordering::Relaxed);
     pb.set_position(initial_processed as u64);
     pb.println(format!("Loaded progress: {} permutations processed", initial_processed));
 
-    // Set up Ctrl+C handler
-    let processed_clone = Arc::clone(&processed);
-    let progress_file_clone = Arc::clone(&progress_file);
-    let pb_clone = Arc::clone(&pb);
-    ctrlc::set_handler(move || {
-        if let Err(e) = save_progress(&processed_clone, &progress_file_clone) {
-            eprintln!("Error saving progress: {}", e);
+    let notifier = if args.systemd {
+        let notifier = systemd::Notifier::from_env().map(Arc::new);
+        if notifier.is_none() {
+            pb.println("--systemd given but NOTIFY_SOCKET is not set, systemd notifications disabled");
         }
-        pb_clone.finish_with_message("Interrupted, progress saved");
-        process::exit(0);
-    }).map_err(|e| anyhow::anyhow!("Failed to set Ctrl+C handler: {}", e))?;
+        notifier
+    } else {
+        None
+    };
 
-    let fixed_words = known_words[..args.fixed_words].to_vec();
-    let permutable_words = known_words[args.fixed_words..].to_vec();
+    // Set up Ctrl+C (and Windows console close) handler. An interrupt stops
+    // the search like any other stop condition, and the progress, stats,
+    // and output are saved on the way out as usual; the handler waits for
+    // that, since Windows ends the process once a console close handler
+    // returns
+    let shut_down = Arc::new(AtomicBool::new(false));
+    {
+        let notifier = notifier.clone();
+        let stop = Arc::clone(&stop);
+        let draining = Arc::clone(&draining);
+        let throttle = throttle.clone();
+        let pb = Arc::clone(&pb);
+        let shut_down = Arc::clone(&shut_down);
+        signals::install_interrupt_handler(move || {
+            if let Some(notifier) = &notifier {
+                notifier.stopping();
+            }
+            stop_search(&stop, StopReason::Interrupted, &draining, &pb);
+            // Paused workers have to see the stop too
+            if let Some(throttle) = &throttle {
+                throttle.stop();
+            }
+            while !shut_down.load(Ordering::SeqCst) {
+                std::thread::sleep(Duration::from_millis(100));
+            }
+        })?;
+    }
 
-    if use_parallel {
-        permutable_words
-            .clone()
-            .into_iter()
-            .permutations(permutable_words.len())
-            .skip(initial_processed)
-            .par_bridge() // Use par_bridge for lazy parallel iteration
-            .for_each(|perm| {
-                if found.load(Ordering::Relaxed) {
-                    return;
-                }
-                let mut mnemonic_words = fixed_words.clone();
-                mnemonic_words.extend(perm.into_iter());
+    let permutable_words = known_words[fixed_words..].to_vec();
+    // Corrected candidates come whole, since fixed words may be misspelled
+    // or shortened too
+    let fixed_words = if fuzzy.is_some() { Vec::new() } else { known_words[..fixed_words].to_vec() };
+
+    // Workers check `draining` before each candidate, so a match or a stop
+    // condition drains the pool instead of tearing the process down
+    // mid-checkpoint. A candidate already in flight still finishes, and a
+    // verified match it turns up is recorded like any other.
+    // A `None` candidate is a tokenlist rank ruled out by a range anchor or an
+    // unusable seedlist line; it is counted but never derived.
+    let check_permutation = |(rank, perm): (u64, Option<Vec<String>>), seeds: Option<&mut dyn Iterator<Item = Seed>>| {
+        if let Some(throttle) = &throttle {
+            throttle.wait();
+        }
+        for limiter in &rate_limits {
+            limiter.acquire();
+        }
+        if draining.load(Ordering::Relaxed) {
+            return;
+        }
+        // Whether the rank is settled (evaluated, and a match recorded), so
+        // the frontier may move past it, and whether it counts as processed
+        let (settled, counted) = 'candidate: {
+            if let Some(perm) = perm {
+                let mut mnemonic_words = Wiped(fixed_words.clone());
+                mnemonic_words.extend(perm);
+                let address_db = address_db.as_ref().as_ref().map(|db| db.read().unwrap());
+                let targets = Targets {
+                    addresses: address_db.as_deref(),
+                    pattern: address_pattern.as_ref(),
+                    bloom: address_bloom.as_ref(),
+                    hash_db: hash_db.as_ref(),
+                };
                 let mnemonic_option = match try_mnemonic(
                     &mnemonic_words,
-                    network,
-                    &derivation_path,
-                    target_address,
-                    address_db.as_ref().as_ref(),
+                    args.seed_format,
+                    &search_matrix,
+                    &passphrases,
+                    seeds,
+                    tested.as_ref(),
+                    &targets,
                     &secp,
                     &bip39_wordlist,
-                    &args.address_type,
+                    &summary,
+                    &stage_stats,
                     args.debug,
                 ) {
                     Ok(result) => result,
@@ -434,92 +2654,542 @@ fn main() -> Result<()> {
                         if args.debug {
                             error!("Mnemonic try failed: {}", e);
                         }
-                        return;
+                        break 'candidate (false, false);
                     }
                 };
-                if let Some((mnemonic_str, matched_address)) = mnemonic_option {
-                    pb.println(format!("Match found! Mnemonic: {}, Address: {}", mnemonic_str, matched_address));
-                    pb.finish_with_message("Found match!");
-                    found.store(true, Ordering::Relaxed);
-                    process::exit(0);
+                // Wiped once it's been reported and saved
+                if let Some(found_match) = mnemonic_option.map(Wiped) {
+                    // Only announce what an independent derivation reproduces.
+                    // A device backend must feed its hits through here as well,
+                    // as candidates for this CPU path rather than as matches.
+                    let confirmed = match verify::rederive(&found_match, args.seed_format, &search_matrix.overrides, &secp) {
+                        Ok(address) if address == found_match.address => true,
+                        Ok(address) => {
+                            error!(
+                                "Match for {} ({}) failed cross-verification: reference derivation gave {}",
+                                found_match.address, found_match.variant, address
+                            );
+                            false
+                        }
+                        Err(e) => {
+                            error!("Match for {} ({}) failed cross-verification: {}", found_match.address, found_match.variant, e);
+                            false
+                        }
+                    };
+                    if !confirmed {
+                        pb.println(format!(
+                            "Discarded a match for {} that an independent derivation could not reproduce (see log)",
+                            found_match.address
+                        ));
+                        break 'candidate (false, false);
+                    }
+                    // With --find-all every hit is recorded and the search keeps
+                    // going; otherwise the first one drains the pool, and a
+                    // second one verified meanwhile is recorded all the same
+                    let match_count = matches.fetch_add(1, Ordering::SeqCst) + 1;
+                    if !args.find_all {
+                        draining.store(true, Ordering::SeqCst);
+                    }
+                    // Sealed before anything is written; a match that can't
+                    // be encrypted is still saved rather than lost
+                    let sealed = recipient.as_ref().and_then(|recipient| {
+                        match recipient.encrypt(result_text(&found_match, args.results_format).as_bytes()) {
+                            Ok(sealed) => Some(sealed),
+                            Err(e) => {
+                                error!("{}", e);
+                                pb.println(format!("Failed to encrypt the match, saving it unencrypted: {}", e));
+                                None
+                            }
+                        }
+                    });
+                    let hidden = args.hide_result && sealed.is_some();
+                    if hidden {
+                        pb.println(format!(
+                            "Match found! Address: {}, Matched: {} (mnemonic encrypted to {})",
+                            found_match.address, found_match.variant, args.results_file
+                        ));
+                    } else {
+                        pb.println(format!(
+                            "Match found! Mnemonic: {}, Address: {}, Matched: {}",
+                            found_match.mnemonic, found_match.address, found_match.variant
+                        ));
+                    }
+                    if !found_match.passphrase.is_empty() && !hidden {
+                        pb.println(format!("Passphrase: {}", found_match.passphrase));
+                    }
+                    // Normal forms look alike on screen; the bytes tell which one matched
+                    if !found_match.passphrase.is_ascii() && !hidden {
+                        let bytes: String = found_match.passphrase.bytes().map(|byte| format!("{:02x}", byte)).collect();
+                        pb.println(format!("Passphrase as hashed (UTF-8 hex): {}", bytes));
+                    }
+                    if args.fingerprint.is_some() {
+                        pb.println(
+                            "A fingerprint can match by chance; derive this mnemonic's addresses (the derive \
+                             subcommand) and check them against your records before trusting it",
+                        );
+                    }
+                    if address_pattern.is_some() {
+                        pb.println(
+                            "This hit only fits the address pattern; it was re-derived on the CPU, but check the full \
+                             address against your records before trusting it",
+                        );
+                    }
+                    if let Some(coin_type) = paths::coin_type(&found_match.variant.path).filter(|_| !args.coin_type_list.is_empty()) {
+                        pb.println(format!("Coin type: {}'", coin_type));
+                    }
+                    info!("Match found for address {} ({})", found_match.address, found_match.variant);
+                    if let Err(e) = save_result(&args.results_file, &found_match, args.results_format, sealed.as_deref()) {
+                        pb.println(format!("Failed to save result: {}", e));
+                    }
+                    if let Some(output) = &output {
+                        let mut record = MatchRecord::new(&found_match, rank, start.elapsed().as_secs_f64());
+                        if let Some(sealed) = &sealed {
+                            record.seal(sealed);
+                        }
+                        if let Err(e) = output.record_match(record) {
+                            pb.println(format!("Failed to write output file: {}", e));
+                        }
+                    }
+                    let usage = match (&chain, address_db.as_deref(), &hash_db, &address_bloom) {
+                        (Some(chain), ..) => Some(discovery::Usage::Chain(chain)),
+                        (None, Some(targets), ..) => Some(discovery::Usage::Targets(targets)),
+                        (None, None, Some(db), _) => Some(discovery::Usage::HashDb(db)),
+                        (None, None, None, Some(bloom)) => Some(discovery::Usage::Bloom(bloom)),
+                        (None, None, None, None) => None,
+                    };
+                    if let (true, Some(usage)) = (args.discover_accounts, usage) {
+                        match discovery::discover(
+                            &found_match,
+                            &args.coin_type_list,
+                            usage,
+                            &search_matrix.overrides,
+                            args.gap_limit,
+                            &secp,
+                        ) {
+                            Ok(accounts) => {
+                                for account in &accounts {
+                                    pb.println(format!(
+                                        "Account {}' (coin type {}'): {} used address(es)",
+                                        account.account,
+                                        account.coin_type,
+                                        account.used.len()
+                                    ));
+                                    for (path, address) in &account.used {
+                                        pb.println(format!("  {} {}", path, address));
+                                    }
+                                }
+                                info!("Account discovery for {} found {} used account(s)", found_match.address, accounts.len());
+                            }
+                            Err(e) => pb.println(format!("Account discovery failed: {}", e)),
+                        }
+                    }
+                    if let Some(reason) = stop.check_matches(match_count) {
+                        stop_search(&stop, reason, &draining, &pb);
+                    }
+                    if !args.find_all {
+                        break 'candidate (true, false);
+                    }
                 }
-                let count = processed.fetch_add(1, Ordering::Relaxed) + 1;
-                pb.set_position(count as u64);
-                let elapsed = start.elapsed().as_secs_f64();
-                let speed = if elapsed > 0.0 { (count as f64 / elapsed).round() } else { 0.0 };
-                pb.set_message(format!("Processed: {}, Speed: {:.0} hashes/sec", count, speed));
-                pb.tick();
-                if count % *batch_size == 0 {
-                    if let Err(e) = save_progress(&processed, &progress_file) {
-                        pb.println(format!("Failed to save progress: {}", e));
+            }
+            (true, true)
+        };
+        // A rank left unsettled is tried again on --resume
+        if settled {
+            frontier.complete(rank);
+        }
+        if !counted {
+            return;
+        }
+        let count = processed.fetch_add(1, Ordering::Relaxed) + 1;
+        if let Some(reason) = stop.check_progress(args.start_index, count as u64, total_permutations) {
+            stop_search(&stop, reason, &draining, &pb);
+        }
+        pb.set_position(count as u64);
+        worker_stats.record_cpu();
+        if count.is_multiple_of(*batch_size) {
+            if let Err(e) = save_progress(&processed, &progress_file) {
+                pb.println(format!("Failed to save progress: {}", e));
+            }
+        }
+    };
+
+    // Monitor thread: report throughput to systemd, keep its watchdog fed,
+    // and print stage timings when asked (SIGUSR1 on Unix)
+    let monitor_running = Arc::new(AtomicBool::new(true));
+    {
+        let running = Arc::clone(&monitor_running);
+        let processed = Arc::clone(&processed);
+        let notifier = notifier.clone();
+        let stage_stats = Arc::clone(&stage_stats);
+        let stats_requested = Arc::clone(&stats_requested);
+        let pb = Arc::clone(&pb);
+        let status_interval = notifier
+            .as_ref()
+            .and_then(|notifier| notifier.watchdog_interval())
+            .map_or(Duration::from_secs(5), |watchdog| watchdog.min(Duration::from_secs(5)));
+        let summary = Arc::clone(&summary);
+        let mut eta_model = EtaModel::new(total_words, (search_matrix.combinations() as u64).saturating_mul(passphrases.count()));
+        let worker_stats = Arc::clone(&worker_stats);
+        let mut worker_rates = WorkerRates::new(&worker_stats);
+        let worker_interval = args.worker_stats_interval.filter(|&secs| secs > 0).map(Duration::from_secs);
+        let live_stats = live_stats.clone();
+        let stats_interval = Duration::from_secs(args.stats_interval.max(1));
+        let matches = Arc::clone(&matches);
+        let reload_requested = Arc::clone(&reload_requested);
+        let address_db = Arc::clone(&address_db);
+        let address_db_file = args.address_db_file.clone();
+        let stop = Arc::clone(&stop);
+        let draining = Arc::clone(&draining);
+        let pausable = throttle.clone();
+        let throttle = throttle.clone().filter(|_| battery_aware);
+        let progress_file = Arc::clone(&progress_file);
+        let (battery_threads, battery_pause_percent) = (args.battery_threads, args.battery_pause_percent);
+        std::thread::spawn(move || {
+            let mut next_power_check = Instant::now();
+            let mut power_source = PowerSource::Unknown;
+            let mut next_stats_write = Instant::now();
+            let mut next_status = Instant::now();
+            let mut next_worker_report = worker_interval.map(|interval| Instant::now() + interval);
+            let mut next_estimate = Instant::now();
+            let mut status = String::new();
+            let mut speed_window = SpeedWindow::new(processed.load(Ordering::Relaxed) as u64);
+            while running.load(Ordering::Relaxed) {
+                // The ETA is driven by work units rather than raw permutations,
+                // since checksum rejects cost a tiny fraction of a derivation.
+                // Generated permutations and fully derived candidates are
+                // shown apart, so a fast rate of mostly filtered candidates
+                // isn't taken for derivation throughput
+                if Instant::now() >= next_estimate {
+                    if let Some(weight) = stage_stats.filter_weight() {
+                        eta_model.set_filter_weight(weight);
+                    }
+                    let (rejected, derived) = (summary.rejected(), summary.derived());
+                    eta_model.update(rejected, derived);
+                    let count = processed.load(Ordering::Relaxed);
+                    let remaining = total_permutations.saturating_sub(count as u64);
+                    let elapsed = start.elapsed().as_secs_f64();
+                    let done = count.saturating_sub(initial_processed) as f64;
+                    let speed = if elapsed > 0.0 { done / elapsed } else { 0.0 };
+                    let generation_rate = eta_model.generation_rate(rejected, derived).unwrap_or(speed);
+                    let eta = eta_model.eta_seconds(remaining, rejected, derived).unwrap_or(f64::INFINITY);
+                    let filtered = filtered_percent(rejected, derived)
+                        .map_or_else(|| "-".to_string(), |percent| format!("{:.1}%", percent));
+                    status = format!(
+                        "Generated: {} ({:.0}/s), Derived: {}, Filtered: {}, ETA: {}",
+                        count,
+                        generation_rate,
+                        derived_total().unwrap_or(derived),
+                        filtered,
+                        format_eta(eta)
+                    );
+                    pb.set_message(status.clone());
+                    next_estimate = Instant::now() + Duration::from_secs(1);
+
+                    if let Some(live_stats) = live_stats.as_ref().filter(|_| Instant::now() >= next_stats_write) {
+                        let stats = LiveStats {
+                            phase: "running",
+                            processed: count as u64,
+                            total: total_permutations,
+                            percent: if total_permutations > 0 { count as f64 * 100.0 / total_permutations as f64 } else { 100.0 },
+                            speed,
+                            eta_seconds: Some(eta).filter(|eta| eta.is_finite()),
+                            matches: matches.load(Ordering::Relaxed) as u64,
+                            elapsed_seconds: elapsed,
+                            last_checkpoint: last_checkpoint(),
+                            updated: live_stats::unix_now(),
+                        };
+                        if let Err(e) = live_stats.write(stats) {
+                            warn!("{}", e);
+                        }
+                        next_stats_write = Instant::now() + stats_interval;
                     }
                 }
-            });
-    } else {
-        for (_index, perm) in permutable_words
-            .clone()
-            .into_iter()
-            .permutations(permutable_words.len())
-            .enumerate()
-            .skip(initial_processed)
-        {
-            if found.load(Ordering::Relaxed) {
-                break;
-            }
-            let mut mnemonic_words = fixed_words.clone();
-            mnemonic_words.extend(perm.into_iter());
-            let mnemonic_option = match try_mnemonic(
-                &mnemonic_words,
-                network,
-                &derivation_path,
-                target_address,
-                address_db.as_ref().as_ref(),
-                &secp,
-                &bip39_wordlist,
-                &args.address_type,
-                args.debug,
-            ) {
-                Ok(result) => result,
-                Err(e) => {
-                    if args.debug {
-                        error!("Mnemonic try failed: {}", e);
+                if let Some(reason) = stop.check_runtime(start) {
+                    stop_search(&stop, reason, &draining, &pb);
+                }
+                if let Some(min_speed) = stop.min_speed {
+                    let count = processed.load(Ordering::Relaxed) as u64;
+                    // Deliberate pauses aren't a slowdown
+                    if pausable.as_ref().is_some_and(|throttle| throttle.is_paused() || throttle.is_held()) {
+                        speed_window = SpeedWindow::new(count);
+                    } else if let Some(speed) = speed_window.sample(count, stop.speed_window) {
+                        if speed < min_speed {
+                            info!("Throughput {:.0}/s over the last {:?} is below --min-speed {}", speed, stop.speed_window, min_speed);
+                            stop_search(&stop, StopReason::Throughput, &draining, &pb);
+                        }
                     }
-                    continue;
                 }
-            };
-            if let Some((mnemonic_str, matched_address)) = mnemonic_option {
-                pb.println(format!("Match found! Mnemonic: {}, Address: {}", mnemonic_str, matched_address));
-                pb.finish_with_message("Found match!");
-                found.store(true, Ordering::Relaxed);
-                process::exit(0);
-            }
-            let count = processed.fetch_add(1, Ordering::Relaxed) + 1;
-            pb.set_position(count as u64);
-            let elapsed = start.elapsed().as_secs_f64();
-            let speed = if elapsed > 0.0 { (count as f64 / elapsed).round() } else { 0.0 };
-            pb.set_message(format!("Processed: {}, Speed: {:.0} hashes/sec", count, speed));
-            pb.tick();
-            if count % *batch_size == 0 {
-                if let Err(e) = save_progress(&processed, &args.progress_file) {
-                    pb.println(format!("Failed to save progress: {}", e));
+                if let Some(notifier) = notifier.as_ref().filter(|_| Instant::now() >= next_status) {
+                    notifier.status(&status);
+                    notifier.watchdog();
+                    next_status = Instant::now() + status_interval;
                 }
+                if let (Some(at), Some(interval)) = (next_worker_report, worker_interval) {
+                    if Instant::now() >= at {
+                        if let Some(line) = worker_rates.report(&worker_stats) {
+                            info!("{}", line);
+                            pb.println(line);
+                        }
+                        next_worker_report = Some(Instant::now() + interval);
+                    }
+                }
+                if stats_requested.swap(false, Ordering::Relaxed) {
+                    for line in stage_stats.report() {
+                        pb.println(line);
+                    }
+                }
+                // SIGHUP: swap in the updated database without restarting the search
+                if reload_requested.swap(false, Ordering::Relaxed) {
+                    if let (Some(db), Some(db_file)) = (address_db.as_ref().as_ref(), &address_db_file) {
+                        match load_address_db(db_file) {
+                            Ok(reloaded) => {
+                                let count = reloaded.len();
+                                let previous = std::mem::replace(&mut *db.write().unwrap(), reloaded).len();
+                                pb.println(format!("Reloaded address database: {} addresses (was {})", count, previous));
+                                info!("Reloaded {} addresses from database (was {})", count, previous);
+                            }
+                            Err(e) => pb.println(format!("Failed to reload address database, keeping the current one: {}", e)),
+                        }
+                    }
+                }
+                if let Some(throttle) = throttle.as_ref().filter(|_| Instant::now() >= next_power_check) {
+                    let source = power::read();
+                    let (active, pause) = match source {
+                        PowerSource::Battery { percent } => (
+                            battery_threads.unwrap_or(num_threads),
+                            matches!((percent, battery_pause_percent), (Some(percent), Some(limit)) if percent <= limit),
+                        ),
+                        PowerSource::Ac | PowerSource::Unknown => (num_threads, false),
+                    };
+                    if source != power_source {
+                        info!("Power source changed to {:?}, running {} of {} threads", source, active, num_threads);
+                        power_source = source;
+                    }
+                    throttle.set_active(active);
+                    if pause && !throttle.is_paused() {
+                        throttle.set_paused(true);
+                        if let Err(e) = save_progress(&processed, &progress_file) {
+                            pb.println(format!("Failed to save progress: {}", e));
+                        }
+                        pb.println("Battery low, progress saved and search paused until AC power returns");
+                        info!("Paused on low battery ({:?})", source);
+                    } else if !pause && throttle.is_paused() {
+                        throttle.set_paused(false);
+                        pb.println("Power restored, search resumed");
+                        info!("Resumed after low-battery pause");
+                    }
+                    next_power_check = Instant::now() + POWER_POLL_INTERVAL;
+                }
+                std::thread::sleep(Duration::from_millis(250));
             }
+        });
+    }
+    if let (true, Some(throttle)) = (remote_control, &throttle) {
+        let control = Arc::new(daemon::Control {
+            processed: Arc::clone(&processed),
+            initial_processed,
+            total: total_permutations,
+            matches: Arc::clone(&matches),
+            throttle: Arc::clone(throttle),
+            address_db: Arc::clone(&address_db),
+            started: start,
+        });
+        if args.daemon {
+            let socket = state_dir.resolve(daemon::SOCKET_FILE);
+            daemon::serve(&socket, Arc::clone(&control))?;
+            state_dir.remove_on_release(&socket);
         }
+        if let Some(address) = &args.dashboard {
+            dashboard::serve(address, control)?;
+            pb.println(format!("Dashboard: http://{}", address));
+        }
+    }
+    if let (true, Some(throttle)) = (args.interactive, &throttle) {
+        let throttle = Arc::clone(throttle);
+        let processed = Arc::clone(&processed);
+        let progress_file = Arc::clone(&progress_file);
+        let stats_requested = Arc::clone(&stats_requested);
+        let summary = Arc::clone(&summary);
+        let pb_keys = Arc::clone(&pb);
+        keys::listen(move |key| match key {
+            keys::Key::Pause => {
+                let held = !throttle.is_held();
+                throttle.set_held(held);
+                if held {
+                    if let Err(e) = save_progress(&processed, &progress_file) {
+                        pb_keys.println(format!("Failed to save progress: {}", e));
+                    }
+                    pb_keys.println("Paused, progress saved; press p to resume");
+                } else {
+                    pb_keys.println("Resumed");
+                }
+            }
+            keys::Key::Checkpoint => match save_progress(&processed, &progress_file) {
+                Ok(()) => {
+                    let count = processed.load(Ordering::Relaxed);
+                    pb_keys.println(format!("Checkpoint saved: {} permutations processed", count));
+                }
+                Err(e) => pb_keys.println(format!("Failed to save progress: {}", e)),
+            },
+            keys::Key::Stats => {
+                for line in summary.report() {
+                    pb_keys.println(line);
+                }
+                // Stage timings come from the monitor thread, as for SIGUSR1
+                stats_requested.store(true, Ordering::Relaxed);
+            }
+            keys::Key::MoreThreads | keys::Key::FewerThreads => {
+                let active = match key {
+                    keys::Key::MoreThreads => throttle.active() + 1,
+                    _ => throttle.active().saturating_sub(1),
+                };
+                throttle.set_active(active);
+                pb_keys.println(format!("Running {} of {} threads", throttle.active(), num_threads));
+            }
+            keys::Key::Quit => {
+                pb_keys.println("Quitting, saving progress");
+                signals::interrupt();
+            }
+            keys::Key::Help => pb_keys.println(keys::HELP),
+        })?;
+        pb.println(keys::HELP);
     }
+    if let Some(notifier) = &notifier {
+        notifier.ready();
+    }
+
+    // Every source but seedlists and the weighted order unranks any range, so
+    // the CPU threads each build their own chunk of candidates; those two
+    // only walk forward from the resume point, as one shared iterator
+    let mut sequential: Option<Candidates> = None;
+    let source: Option<CandidateSource> =
+        match (tokenlist, &args.seedlist, substitutes, fuzzy, position_weights, missing, bounded, pool) {
+            (Some(tokenlist), _, _, _, _, _, _, _) => {
+                Some(Arc::new(move |start, end| Box::new(tokenlist.clone().into_range(start, end))))
+            }
+            (None, Some(file), _, _, _, _, _, _) => {
+                sequential = Some(Box::new(seedlist::candidates(file, total_words, resume_index, end_index)?));
+                None
+            }
+            (None, None, Some(substitutes), _, _, _, _, _) => {
+                Some(Arc::new(move |start, end| Box::new(substitutes.clone().into_range(start, end))))
+            }
+            (None, None, None, Some(fuzzy), _, _, _, _) => {
+                Some(Arc::new(move |start, end| Box::new(fuzzy.clone().into_range(start, end))))
+            }
+            (None, None, None, None, _, Some(missing), _, _) => Some(Arc::new(move |start, end| {
+                Box::new(missing.clone().into_range(permutable_words.clone(), start, end))
+            })),
+            // The weighted order has no closed-form unranking, so resuming
+            // walks the ranks before the resume point
+            (None, None, None, None, Some(weights), None, _, _) => {
+                sequential = Some(Box::new(
+                    WeightedRange::new(permutable_words, weights, resume_index, end_index).map(|(_, perm)| Some(perm)),
+                ));
+                None
+            }
+            (None, None, None, None, None, None, Some(bounded), _) => Some(Arc::new(move |start, end| {
+                Box::new(bounded.clone().into_range(permutable_words.clone(), start, end).map(Some))
+            })),
+            (None, None, None, None, None, None, None, Some(pool)) => Some(Arc::new(move |start, end| {
+                Box::new(pool.clone().into_range(permutable_words.clone(), start, end).map(Some))
+            })),
+            (None, None, None, None, None, None, None, None) => Some(Arc::new(move |start, end| {
+                Box::new(PermutationRange::new(permutable_words.clone(), start, end).map(|(_, perm)| Some(perm)))
+            })),
+        };
+    // Candidates cover the free positions, and the pinned words go back in.
+    // Each carries its rank, so the checkpoint can tell which are done;
+    // those a previous run finished out of order are skipped
+    let ranked = move |start: u64, candidates: Candidates| -> Ranked {
+        let candidates: Candidates = match pinned.clone() {
+            Some(pinned) => Box::new(candidates.map(move |perm| perm.map(|free| pinned.insert(free)))),
+            None => candidates,
+        };
+        Box::new((start..).zip(candidates).filter(|(rank, _)| !frontier.is_done(*rank)))
+    };
+    let feed = match (sequential, source) {
+        (Some(candidates), _) => Feed::sequential(ranked(resume_index, candidates)),
+        (None, Some(source)) => Feed::ranked(resume_index, end_index, move |start, end| ranked(start, source(start, end))),
+        (None, None) => unreachable!("every candidate source is sequential or ranked"),
+    };
+
+    // With a device, it computes each batch's seeds while the CPU threads
+    // derive from the last one. A device error hands the rest of the run to
+    // the CPU.
+    let device = gpu.as_mut().map(|gpu| pipeline::Device {
+        batch: args.batch_size.max(1) as u64,
+        seeds: Box::new(|batch: &Batch| {
+            if let Some(limiter) = &gpu_rate_limit {
+                for _ in 0..batch.len() {
+                    limiter.acquire();
+                }
+            }
+            match gpu.seeds(&fixed_words, &batch.candidates, &worker_stats) {
+                Ok(seeds) => Some(seeds),
+                Err(e) => {
+                    error!("GPU batch failed: {}", e);
+                    pb.println(format!("GPU batch failed ({}), continuing on the CPU", e));
+                    None
+                }
+            }
+        }),
+    });
+    let stopped = || draining.load(Ordering::Relaxed);
+    let cpu = |batch: Batch, seeds: Option<pipeline::Seeds>| {
+        pipeline::check_batch(batch, seeds, cpu_batch.as_ref(), &fixed_words, &stopped, &check_permutation)
+    };
+    pipeline::run(&feed, chunks::CHUNK_SIZE, device, &cpu, &stopped);
 
     let elapsed = start.elapsed().as_secs_f64();
     let processed_count = processed.load(Ordering::Relaxed);
+    let match_count = matches.load(Ordering::Relaxed);
     let final_message = format!(
         "Done! Processed {} permutations in {:.2} seconds, Found: {}",
-        processed_count, elapsed, found.load(Ordering::Relaxed)
+        processed_count, elapsed, match_count
     );
+    match stop.reason() {
+        Some(reason) => pb.finish_with_message(format!("Stopped: {}", reason.describe())),
+        None if match_count > 0 => pb.finish_with_message("Found match!"),
+        None => pb.finish_with_message("Search exhausted"),
+    }
     pb.println(final_message.clone());
     info!("{}", final_message);
+    if let Some(filtered) = filtered_percent(summary.rejected(), summary.derived()) {
+        let line = format!(
+            "Fully derived {} of {} generated permutations; the wordlist and checksum filters skipped {:.1}%",
+            derived_total().unwrap_or_else(|| summary.derived()),
+            processed_count,
+            filtered
+        );
+        pb.println(line.clone());
+        info!("{}", line);
+    }
 
-    if !found.load(Ordering::Relaxed) {
-        pb.println("No matching mnemonic found.".to_string());
+    if match_count == 0 {
+        pb.println("No matching mnemonic found.");
     } else {
-        pb.println("Search completed successfully.".to_string());
+        if args.find_all {
+            pb.println(format!("Recorded {} matches in {}", match_count, args.results_file));
+        }
+        pb.println("Search completed successfully.");
+    }
+
+    for line in summary.report() {
+        pb.println(line.clone());
+        info!("{}", line);
+    }
+    if let Some(bloom) = &address_bloom {
+        let (positives, false_positives) = bloom.positives();
+        let line = format!("Bloom filter hits: {} ({} not in the address list)", positives, false_positives);
+        pb.println(line.clone());
+        info!("{}", line);
+    }
+    for line in stage_stats.report() {
+        if args.stage_stats {
+            pb.println(line.clone());
+        }
+        info!("{}", line);
     }
 
     if elapsed > 0.0 {
@@ -528,10 +3198,49 @@ fn main() -> Result<()> {
         info!("Speed: {:.0} hashes/sec", speed);
     }
 
+    monitor_running.store(false, Ordering::Relaxed);
+    if let Some(notifier) = &notifier {
+        notifier.stopping();
+    }
+
     // Save final progress
     if let Err(e) = save_progress(&processed, &args.progress_file) {
         pb.println(format!("Failed to save final progress: {}", e));
     }
+    if stop.reason().is_some() {
+        print_resume_token(&pb);
+    }
+    if let Some(live_stats) = &live_stats {
+        let phase = match stop.reason() {
+            Some(StopReason::Interrupted) => "interrupted",
+            Some(_) => "stopped",
+            None if match_count > 0 => "found",
+            None => "finished",
+        };
+        if let Err(e) = live_stats.finish(phase, processed_count as u64, last_checkpoint()) {
+            pb.println(format!("Failed to write stats file: {}", e));
+        }
+    }
+    if let Some(output) = &output {
+        let status = match stop.reason() {
+            Some(StopReason::Interrupted) => "interrupted",
+            Some(_) => "stopped",
+            None if match_count > 0 => "found",
+            None => "finished",
+        };
+        let mut record = completion(status, processed_count, initial_processed, total_permutations, match_count, elapsed);
+        record.stop_reason = stop.reason().map(|reason| reason.describe().to_string());
+        if let Err(e) = output.complete(record) {
+            pb.println(format!("Failed to write output file: {}", e));
+        }
+    }
 
-    Ok(())
-}
\ No newline at end of file
+    keys::restore();
+    // Other threads may still hold the state directory, so the lock is
+    // released explicitly rather than on drop.
+    state_dir.release();
+    log::logger().flush();
+    shut_down.store(true, Ordering::SeqCst);
+
+    Ok(stop.reason().map_or(ExitCode::SUCCESS, |reason| ExitCode::from(reason.exit_code())))
+}
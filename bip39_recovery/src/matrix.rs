@@ -0,0 +1,218 @@
+use std::fmt;
+use std::fs;
+
+use anyhow::Result;
+use bitcoin::bip32::{ChildNumber, DerivationPath};
+use bitcoin::Network;
+use serde::Deserialize;
+
+use crate::overrides::AddressOverrides;
+use crate::{paths, pathspec};
+
+pub const ADDRESS_TYPES: [&str; 5] = ["p2pkh", "p2sh-p2wpkh", "p2wpkh", "p2tr", "eth"];
+
+pub fn parse_network(network: &str) -> Result<Network> {
+    match network.to_lowercase().as_str() {
+        "mainnet" => Ok(Network::Bitcoin),
+        // testnet4 shares testnet3's address prefixes (tb1, m/n, 2), which is
+        // all that matters for matching
+        "testnet" | "testnet3" | "testnet4" => Ok(Network::Testnet),
+        "signet" => Ok(Network::Signet),
+        "regtest" => Ok(Network::Regtest),
+        _ => Err(anyhow::anyhow!(
+            "Invalid network: {}. Use 'mainnet', 'testnet', 'testnet4', 'signet', or 'regtest'.",
+            network
+        )),
+    }
+}
+
+pub fn network_name(network: Network) -> &'static str {
+    match network {
+        Network::Bitcoin => "mainnet",
+        Network::Testnet => "testnet",
+        Network::Signet => "signet",
+        Network::Regtest => "regtest",
+        _ => "unknown",
+    }
+}
+
+/// `--matrix` file contents, e.g.
+///
+/// ```toml
+/// paths = ["m/44'/0'/0'/0/0", "m/84'/0'/0'/0/0"]
+/// address_types = ["p2pkh", "p2wpkh"]
+/// networks = ["mainnet", "testnet"]
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MatrixFile {
+    paths: Vec<String>,
+    address_types: Vec<String>,
+    #[serde(default = "default_networks")]
+    networks: Vec<String>,
+}
+
+fn default_networks() -> Vec<String> {
+    vec!["mainnet".to_string()]
+}
+
+/// One way of turning a derived key into an address.
+#[derive(Clone, Debug)]
+pub struct Encoding {
+    pub address_type: String,
+    pub network: Network,
+}
+
+/// Keys derived at `path` are encoded with every entry of `encodings`.
+#[derive(Clone, Debug)]
+pub struct PathEntry {
+    pub path: DerivationPath,
+    pub encodings: Vec<Encoding>,
+}
+
+/// Cross-product of derivation paths, address types, and networks evaluated
+/// for each candidate. The seed and master key are computed once and each
+/// path is derived once, however many encodings hang off it.
+#[derive(Clone, Debug)]
+pub struct SearchMatrix {
+    pub paths: Vec<PathEntry>,
+    pub overrides: AddressOverrides,
+}
+
+/// Adds `encodings` at `path`, onto an existing entry for the same path so
+/// it is only derived once.
+fn merge_entry(paths: &mut Vec<PathEntry>, path: DerivationPath, encodings: &[Encoding]) {
+    match paths.iter_mut().find(|existing| existing.path == path) {
+        Some(existing) => existing.encodings.extend(encodings.iter().cloned()),
+        None => paths.push(PathEntry { path, encodings: encodings.to_vec() }),
+    }
+}
+
+/// Which combination of the matrix produced a match.
+#[derive(Clone, Debug)]
+pub struct Variant {
+    pub path: DerivationPath,
+    pub address_type: String,
+    pub network: Network,
+}
+
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.path, self.address_type, network_name(self.network))
+    }
+}
+
+impl SearchMatrix {
+    pub fn single(path: DerivationPath, address_type: &str, network: Network) -> Self {
+        Self::build(vec![path], &[address_type.to_string()], &[network])
+    }
+
+    pub fn build(paths: Vec<DerivationPath>, address_types: &[String], networks: &[Network]) -> Self {
+        let encodings: Vec<Encoding> = address_types
+            .iter()
+            .flat_map(|address_type| {
+                networks.iter().map(move |&network| Encoding { address_type: address_type.to_lowercase(), network })
+            })
+            .collect();
+        let paths = paths.into_iter().map(|path| PathEntry { path, encodings: encodings.clone() }).collect();
+        Self { paths, overrides: AddressOverrides::default() }
+    }
+
+    pub fn from_file(file: &str) -> Result<Self> {
+        let content = fs::read_to_string(file)
+            .map_err(|e| anyhow::anyhow!("Failed to read matrix file {}: {}", file, e))?;
+        let spec: MatrixFile = toml::from_str(&content)
+            .map_err(|e| anyhow::anyhow!("Failed to parse matrix file {}: {}", file, e))?;
+        if spec.paths.is_empty() || spec.address_types.is_empty() || spec.networks.is_empty() {
+            return Err(anyhow::anyhow!("Matrix file {} needs at least one path, address type, and network", file));
+        }
+        let paths = spec
+            .paths
+            .iter()
+            .map(|path| pathspec::parse(path).map_err(|e| anyhow::anyhow!("Matrix file {}: {}", file, e)))
+            .collect::<Result<Vec<_>>>()?;
+        for address_type in &spec.address_types {
+            if !ADDRESS_TYPES.contains(&address_type.to_lowercase().as_str()) {
+                return Err(anyhow::anyhow!("Unsupported address type {} in matrix", address_type));
+            }
+        }
+        let networks = spec.networks.iter().map(|n| parse_network(n)).collect::<Result<Vec<_>>>()?;
+        Ok(Self::build(paths, &spec.address_types, &networks))
+    }
+
+    /// Adds `extra` paths checked as `address_type` on `network`, alongside
+    /// whatever the matrix already holds.
+    pub fn with_paths(mut self, extra: Vec<DerivationPath>, address_type: &str, network: Network) -> Self {
+        let encodings = [Encoding { address_type: address_type.to_string(), network }];
+        for path in extra {
+            merge_entry(&mut self.paths, path, &encodings);
+        }
+        self
+    }
+
+    /// Expands every path into one per coin type in `coin_types`, keeping its
+    /// encodings. Paths that come out the same are only derived once.
+    pub fn with_coin_types(self, coin_types: &[u32]) -> Result<Self> {
+        let mut paths: Vec<PathEntry> = Vec::new();
+        for entry in self.paths {
+            for &coin_type in coin_types {
+                let path = paths::with_coin_type(&entry.path, coin_type)
+                    .ok_or_else(|| anyhow::anyhow!("Derivation path {} has no coin-type level", entry.path))?;
+                merge_entry(&mut paths, path, &entry.encodings);
+            }
+        }
+        Ok(Self { paths, overrides: self.overrides })
+    }
+
+    /// Expands every path into each combination of hardened and
+    /// non-hardened at the 1-based `levels`, 2^levels variants per path.
+    pub fn with_hardening_toggled(self, levels: &[usize]) -> Result<Self> {
+        let mut paths: Vec<PathEntry> = Vec::new();
+        for entry in self.paths {
+            for mask in 0..1u32 << levels.len() {
+                let mut path = entry.path.clone();
+                for (bit, &level) in levels.iter().enumerate() {
+                    if mask & (1 << bit) != 0 {
+                        path = paths::toggle_hardening(&path, level).ok_or_else(|| {
+                            anyhow::anyhow!("Derivation path {} has no level {} to toggle", entry.path, level)
+                        })?;
+                    }
+                }
+                merge_entry(&mut paths, path, &entry.encodings);
+            }
+        }
+        Ok(Self { paths, overrides: self.overrides })
+    }
+
+    /// Expands every path into one per chain in `chains` and address index
+    /// in `indices`, replacing its last two levels; `None` keeps the path's
+    /// own. Paths under one chain stay together so their parent is derived
+    /// once per candidate.
+    pub fn with_address_window(self, chains: Option<&[u32]>, indices: Option<pathspec::Range>) -> Result<Self> {
+        let mut paths: Vec<PathEntry> = Vec::new();
+        for entry in self.paths {
+            let levels: Vec<ChildNumber> = entry.path.as_ref().to_vec();
+            let [.., ChildNumber::Normal { index: chain }, ChildNumber::Normal { index }] = levels[..] else {
+                return Err(anyhow::anyhow!(
+                    "Derivation path {} doesn't end in a non-hardened chain and address index",
+                    entry.path
+                ));
+            };
+            let chains = chains.map_or_else(|| vec![chain], <[u32]>::to_vec);
+            let indices = indices.unwrap_or(pathspec::Range { first: index, last: index });
+            for &chain in &chains {
+                for index in indices.first..=indices.last {
+                    let path = paths::with_address(&entry.path, chain, index)
+                        .ok_or_else(|| anyhow::anyhow!("Invalid chain {} or index {} for {}", chain, index, entry.path))?;
+                    merge_entry(&mut paths, path, &entry.encodings);
+                }
+            }
+        }
+        Ok(Self { paths, overrides: self.overrides })
+    }
+
+    /// Addresses checked per candidate.
+    pub fn combinations(&self) -> usize {
+        self.paths.iter().map(|entry| entry.encodings.len()).sum()
+    }
+}
@@ -0,0 +1,224 @@
+use anyhow::Result;
+use bip39::Language;
+
+use crate::permutation;
+
+const WORDS: u64 = 2048;
+
+/// Layout of a search where some words are lost outright: the permutable
+/// positions (those after the fixed words) hold the remaining known words
+/// in any order plus `count` unknown words, each any of the 2048. Unknown
+/// words sit at the `--missing-positions` given, or else at every choice of
+/// `count` permutable positions.
+///
+/// A rank splits into placement, ordering of the known words, and the
+/// unknown words themselves, innermost last, so consecutive candidates
+/// share their known words and differ only in the unknown ones.
+#[derive(Clone)]
+pub struct MissingWords {
+    count: usize,
+    permutable: usize,
+    placements: Vec<Vec<usize>>,
+    fills: u64,
+    orders: u64,
+    space: u64,
+}
+
+impl MissingWords {
+    /// `positions` are 1-based over the whole mnemonic, like --fixed-words
+    /// counts them; empty means anywhere after the fixed words.
+    pub fn new(total_words: usize, fixed_words: usize, count: usize, positions: &[usize]) -> Result<Self> {
+        let permutable = total_words.saturating_sub(fixed_words);
+        if count == 0 || count > permutable {
+            return Err(anyhow::anyhow!(
+                "--missing-words must be between 1 and the {} positions after the fixed words",
+                permutable
+            ));
+        }
+        let placements = if positions.is_empty() {
+            combinations(permutable, count)
+        } else {
+            if positions.len() != count {
+                return Err(anyhow::anyhow!("Give {} --missing-positions, one per missing word", count));
+            }
+            let mut placement = Vec::with_capacity(count);
+            for &position in positions {
+                if position <= fixed_words || position > total_words {
+                    return Err(anyhow::anyhow!(
+                        "Missing position {} is not after the fixed words (positions {}..={})",
+                        position,
+                        fixed_words + 1,
+                        total_words
+                    ));
+                }
+                placement.push(position - fixed_words - 1);
+            }
+            placement.sort_unstable();
+            placement.dedup();
+            if placement.len() != count {
+                return Err(anyhow::anyhow!("--missing-positions repeats a position"));
+            }
+            vec![placement]
+        };
+        let fills = WORDS.checked_pow(count as u32);
+        let orders = permutation::factorial(permutable - count);
+        let space = fills
+            .and_then(|fills| fills.checked_mul(orders))
+            .and_then(|space| space.checked_mul(placements.len() as u64));
+        let (Some(fills), Some(space)) = (fills, space) else {
+            return Err(anyhow::anyhow!("Too many candidates to enumerate with {} missing words", count));
+        };
+        Ok(Self { count, permutable, placements, fills, orders, space })
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn space(&self) -> u64 {
+        self.space
+    }
+
+    /// Candidates with ranks in `[start, end)`, each the words of the
+    /// permutable positions. `known` are the permutable known words.
+    pub fn into_range(self, known: Vec<String>, start: u64, end: u64) -> MissingRange {
+        let end = end.min(self.space);
+        MissingRange { layout: self, known, rank: start, end, outer: None, order: Vec::new() }
+    }
+}
+
+/// Every way to pick `k` of `0..n`, each ascending, in lexicographic order.
+pub(crate) fn combinations(n: usize, k: usize) -> Vec<Vec<usize>> {
+    let mut all = Vec::new();
+    let mut current: Vec<usize> = (0..k).collect();
+    loop {
+        all.push(current.clone());
+        let Some(i) = (0..k).rev().find(|&i| current[i] < n - k + i) else {
+            return all;
+        };
+        current[i] += 1;
+        for j in i + 1..k {
+            current[j] = current[j - 1] + 1;
+        }
+    }
+}
+
+pub struct MissingRange {
+    layout: MissingWords,
+    known: Vec<String>,
+    rank: u64,
+    end: u64,
+    // Placement and ordering of the last candidate, kept while only the
+    // unknown words change
+    outer: Option<u64>,
+    order: Vec<usize>,
+}
+
+impl Iterator for MissingRange {
+    type Item = Option<Vec<String>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.rank >= self.end {
+            return None;
+        }
+        let rank = self.rank;
+        self.rank += 1;
+        let layout = &self.layout;
+        let (outer, mut fill) = (rank / layout.fills, rank % layout.fills);
+        if self.outer != Some(outer) {
+            self.order = permutation::unrank(self.known.len(), outer % layout.orders);
+            self.outer = Some(outer);
+        }
+        let placement = &layout.placements[(outer / layout.orders) as usize];
+
+        let wordlist = Language::English.word_list();
+        let mut unknown = vec![""; layout.count];
+        for slot in unknown.iter_mut().rev() {
+            *slot = wordlist[(fill % WORDS) as usize];
+            fill /= WORDS;
+        }
+        let (mut unknown, mut known) = (unknown.into_iter(), self.order.iter());
+        let words = (0..layout.permutable)
+            .map(|position| {
+                if placement.contains(&position) {
+                    unknown.next().map(str::to_string)
+                } else {
+                    known.next().map(|&item| self.known[item].clone())
+                }
+            })
+            .collect();
+        Some(words)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    fn known() -> Vec<String> {
+        vec!["legal".to_string(), "winner".to_string()]
+    }
+
+    #[test]
+    fn combinations_in_order() {
+        assert_eq!(combinations(4, 2), [[0, 1], [0, 2], [0, 3], [1, 2], [1, 3], [2, 3]]);
+        assert_eq!(combinations(3, 3), [[0, 1, 2]]);
+        assert_eq!(combinations(5, 1).len(), 5);
+    }
+
+    /// Every placement, order, and unknown word comes exactly once, with the
+    /// unknown word changing fastest.
+    #[test]
+    fn enumerates_every_candidate_once() {
+        let layout = MissingWords::new(4, 1, 1, &[]).unwrap();
+        assert_eq!(layout.space(), 3 * 2 * 2048);
+        let all: Vec<Vec<String>> = layout.into_range(known(), 0, u64::MAX).map(Option::unwrap).collect();
+        assert_eq!(all.len(), 3 * 2 * 2048);
+        assert_eq!(all[0], ["abandon", "legal", "winner"]);
+        assert_eq!(all[1], ["ability", "legal", "winner"]);
+        assert_eq!(all[2048], ["abandon", "winner", "legal"]);
+        assert_eq!(all[2 * 2048], ["legal", "abandon", "winner"]);
+        // Only an unknown word equal to a known one repeats a candidate, as
+        // in [legal, legal, winner] from either of the first two placements
+        assert_eq!(all.iter().collect::<HashSet<_>>().len(), all.len() - 6);
+    }
+
+    #[test]
+    fn ranges_are_slices_of_the_whole() {
+        let layout = MissingWords::new(3, 0, 1, &[]).unwrap();
+        let all: Vec<Option<Vec<String>>> = layout.clone().into_range(known(), 0, u64::MAX).collect();
+        for (start, end) in [(0, 1), (2047, 2049), (4000, 4200), (6000, u64::MAX), (6144, 7000)] {
+            let range: Vec<Option<Vec<String>>> = layout.clone().into_range(known(), start, end).collect();
+            assert_eq!(range, all[start as usize..all.len().min(end as usize)], "ranks {}..{}", start, end);
+        }
+    }
+
+    #[test]
+    fn given_positions() {
+        // Position 3 of the mnemonic is the second permutable one
+        let layout = MissingWords::new(4, 1, 1, &[3]).unwrap();
+        assert_eq!(layout.space(), 2 * 2048);
+        assert!(layout.into_range(known(), 0, u64::MAX).all(|words| {
+            let words = words.unwrap();
+            known().contains(&words[0]) && known().contains(&words[2]) && words[0] != words[2]
+        }));
+
+        let two = MissingWords::new(4, 0, 2, &[4, 1]).unwrap();
+        assert_eq!((two.count(), two.space()), (2, 2 * 2048 * 2048));
+        let first: Vec<Vec<String>> = two.into_range(known(), 2048, 2050).map(Option::unwrap).collect();
+        assert_eq!(first, [["ability", "legal", "winner", "abandon"], ["ability", "legal", "winner", "ability"]]);
+    }
+
+    #[test]
+    fn rejects_bad_layouts() {
+        assert!(MissingWords::new(12, 2, 0, &[]).is_err());
+        assert!(MissingWords::new(12, 10, 3, &[]).is_err());
+        assert!(MissingWords::new(12, 2, 2, &[5]).is_err());
+        assert!(MissingWords::new(12, 2, 1, &[2]).is_err());
+        assert!(MissingWords::new(12, 2, 1, &[13]).is_err());
+        assert!(MissingWords::new(12, 2, 2, &[5, 5]).is_err());
+        assert!(MissingWords::new(24, 0, 6, &[]).is_err());
+    }
+}
@@ -0,0 +1,159 @@
+use std::sync::OnceLock;
+
+use crate::{ed25519, ethereum};
+
+/// Matrix encoding whose "address" is a Monero standard address.
+pub const XMR: &str = "xmr";
+
+/// Names `--coin` takes for Monero.
+pub const NAMES: [&str; 2] = ["xmr", "monero"];
+
+/// Words of a Monero seed: 24 that encode the spend key and a checksum word.
+pub const MNEMONIC_WORDS: usize = 25;
+
+/// Monero's English wordlist: 1626 words, each fixed by its first three
+/// letters.
+const WORDS: &str = include_str!("monero_english.txt");
+
+const PREFIX_LEN: usize = 3;
+
+/// Leading byte of a mainnet standard address.
+const MAINNET_TAG: u8 = 0x12;
+
+const ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// Base58 characters of each length of block, up to a full 8 bytes.
+const BLOCK_CHARS: [usize; 9] = [0, 2, 3, 5, 6, 7, 9, 10, 11];
+
+fn wordlist() -> &'static Vec<&'static str> {
+    static LIST: OnceLock<Vec<&'static str>> = OnceLock::new();
+    LIST.get_or_init(|| WORDS.lines().collect())
+}
+
+pub fn is_coin(name: &str) -> bool {
+    NAMES.contains(&name.to_lowercase().as_str())
+}
+
+pub fn index(word: &str) -> Option<u32> {
+    wordlist().binary_search(&word).ok().map(|index| index as u32)
+}
+
+fn prefix(word: &str) -> &str {
+    word.char_indices().nth(PREFIX_LEN).map_or(word, |(end, _)| &word[..end])
+}
+
+/// CRC-32 (IEEE), bit by bit; it only runs for the checksum word.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+/// Whether the last of 25 words repeats the one the CRC-32 of the others'
+/// prefixes picks.
+pub fn checksum_valid(words: &[String]) -> bool {
+    if words.len() != MNEMONIC_WORDS {
+        return false;
+    }
+    let prefixes: String = words[..MNEMONIC_WORDS - 1].iter().map(|word| prefix(word)).collect();
+    let picked = crc32(prefixes.as_bytes()) as usize % (MNEMONIC_WORDS - 1);
+    prefix(&words[picked]) == prefix(&words[MNEMONIC_WORDS - 1])
+}
+
+/// The private spend key the first 24 word indices encode, three words to
+/// each 32-bit little-endian chunk.
+pub fn spend_key(indices: &[u32]) -> [u8; 32] {
+    let n = wordlist().len() as u64;
+    let mut key = [0u8; 32];
+    for (chunk, words) in key.chunks_mut(4).zip(indices.chunks(3)) {
+        let [w1, w2, w3] = [words[0], words[1], words[2]].map(u64::from);
+        let value = w1 + n * ((n - w1 + w2) % n) + n * n * ((n - w2 + w3) % n);
+        chunk.copy_from_slice(&(value as u32).to_le_bytes());
+    }
+    ed25519::reduce(&key)
+}
+
+/// Monero's base58: 8-byte blocks encoded separately into 11 characters,
+/// a shorter last block into fewer.
+fn base58(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity(data.len() / 8 * 11 + 11);
+    for block in data.chunks(8) {
+        let mut value = block.iter().fold(0u64, |value, &byte| value << 8 | u64::from(byte));
+        let mut chars = vec![ALPHABET[0]; BLOCK_CHARS[block.len()]];
+        for char in chars.iter_mut().rev() {
+            *char = ALPHABET[(value % 58) as usize];
+            value /= 58;
+        }
+        encoded.extend(chars.into_iter().map(char::from));
+    }
+    encoded
+}
+
+/// The mainnet standard address of a private spend key: its public key and
+/// that of the view key derived from it, tagged and checksummed.
+pub fn address(spend_key: &[u8; 32]) -> String {
+    let view_key = ed25519::reduce(&ethereum::keccak256(spend_key));
+    let mut data = vec![MAINNET_TAG];
+    data.extend_from_slice(&ed25519::public_key(spend_key));
+    data.extend_from_slice(&ed25519::public_key(&view_key));
+    let checksum = ethereum::keccak256(&data);
+    data.extend_from_slice(&checksum[..4]);
+    base58(&data)
+}
+
+/// The address of a 25-word seed, or `None` when a word isn't on the list
+/// or the checksum word doesn't fit.
+pub fn seed_address(words: &[String]) -> Option<String> {
+    if !checksum_valid(words) {
+        return None;
+    }
+    let indices = words[..MNEMONIC_WORDS - 1].iter().map(|word| index(word)).collect::<Option<Vec<u32>>>()?;
+    Some(address(&spend_key(&indices)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEED: &str = "sequence atlas unveil summon pebbles tuesday beer rudely snake rockets different fuselage \
+                        woven tagged bested dented vegan hover rapid fawns obvious muppet randomly seasons randomly";
+
+    fn words(phrase: &str) -> Vec<String> {
+        phrase.split_whitespace().map(str::to_string).collect()
+    }
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    /// Spend key and address as an independent implementation of Monero's
+    /// word decoding, Keccak, Ed25519, and base58 derives them.
+    #[test]
+    fn seed_to_address() {
+        let words = words(SEED);
+        assert!(checksum_valid(&words));
+        let indices: Vec<u32> = words[..24].iter().map(|word| index(word).unwrap()).collect();
+        assert_eq!(hex(&spend_key(&indices)), "b0ef6bd527b9b23b9ceef70dc8b4cd1ee83ca14541964e764ad23f5151204f0f");
+        assert_eq!(
+            seed_address(&words).as_deref(),
+            Some("46PAiPrNjr2XS82k2ovp5EUYLzBt9pYNW2LXUFsZiv8S3Mt21FZ5qQaAroko1enzw3eGr9qC7X1D7Geoo2RrAotYPvzt9vB")
+        );
+    }
+
+    #[test]
+    fn checksum_word() {
+        let mut words = words(SEED);
+        // Only the prefix counts
+        words[24] = "randomize".to_string();
+        assert!(checksum_valid(&words));
+        words[24] = "seasons".to_string();
+        assert!(!checksum_valid(&words));
+        assert_eq!(seed_address(&words), None);
+        assert!(!checksum_valid(&words[..24]));
+    }
+}
@@ -0,0 +1,125 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use anyhow::Result;
+use bip39_recovery::matrix::network_name;
+use bip39_recovery::Match;
+use bitcoin::bip32::ChildNumber;
+use serde::Serialize;
+
+use crate::live_stats::unix_now;
+
+/// A match as recorded in the `--output` file.
+#[derive(Clone, Debug, Serialize)]
+pub struct MatchRecord {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mnemonic: Option<String>,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub passphrase: String,
+    /// The armored result in place of the mnemonic and passphrase, with
+    /// `--encrypt-result`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encrypted: Option<String>,
+    pub address: String,
+    pub derivation_path: String,
+    pub address_type: String,
+    pub network: &'static str,
+    /// The last level of the path when it isn't hardened, the address index
+    /// under the usual layouts.
+    pub index: Option<u32>,
+    /// Rank of the candidate in the search order, as `--start-index` counts.
+    pub rank: u64,
+    pub elapsed_seconds: f64,
+    pub found_at: u64,
+}
+
+impl MatchRecord {
+    pub fn new(found: &Match, rank: u64, elapsed_seconds: f64) -> Self {
+        let index = match found.variant.path.into_iter().last() {
+            Some(ChildNumber::Normal { index }) => Some(*index),
+            _ => None,
+        };
+        Self {
+            mnemonic: Some(found.mnemonic.clone()),
+            passphrase: found.passphrase.clone(),
+            encrypted: None,
+            address: found.address.clone(),
+            derivation_path: found.variant.path.to_string(),
+            address_type: found.variant.address_type.clone(),
+            network: network_name(found.variant.network),
+            index,
+            rank,
+            elapsed_seconds,
+            found_at: unix_now(),
+        }
+    }
+
+    /// Replaces the secrets with their encrypted form.
+    pub fn seal(&mut self, sealed: &str) {
+        self.mnemonic = None;
+        self.passphrase.clear();
+        self.encrypted = Some(sealed.to_string());
+    }
+}
+
+/// How a run ended, recorded once it does.
+#[derive(Clone, Debug, Serialize)]
+pub struct Completion {
+    /// `found`, `finished`, `stopped`, or `interrupted`.
+    pub status: &'static str,
+    pub stop_reason: Option<String>,
+    pub processed: u64,
+    pub total: u64,
+    /// Candidates fully derived, across resumes; the rest were filtered out.
+    pub derived: Option<u64>,
+    pub filtered_percent: Option<f64>,
+    pub matches: u64,
+    pub elapsed_seconds: f64,
+    pub speed: f64,
+    pub finished_at: u64,
+}
+
+#[derive(Serialize)]
+struct Results {
+    started: u64,
+    matches: Vec<MatchRecord>,
+    completion: Option<Completion>,
+}
+
+/// Keeps the `--output` file, a JSON document of this run's matches and,
+/// once it ends, how it ended. Every record rewrites the whole file
+/// atomically (temp file + rename), so readers always find valid JSON.
+pub struct ResultsWriter {
+    path: PathBuf,
+    results: Mutex<Results>,
+}
+
+impl ResultsWriter {
+    pub fn new(path: &str) -> Self {
+        let results = Results { started: unix_now(), matches: Vec::new(), completion: None };
+        Self { path: PathBuf::from(path), results: Mutex::new(results) }
+    }
+
+    pub fn record_match(&self, record: MatchRecord) -> Result<()> {
+        let mut results = self.results.lock().unwrap();
+        results.matches.push(record);
+        self.write(&results)
+    }
+
+    pub fn complete(&self, completion: Completion) -> Result<()> {
+        let mut results = self.results.lock().unwrap();
+        results.completion = Some(completion);
+        self.write(&results)
+    }
+
+    fn write(&self, results: &Results) -> Result<()> {
+        let json = serde_json::to_string_pretty(results)?;
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        fs::write(&tmp, json).map_err(|e| anyhow::anyhow!("Failed to write output file {:?}: {}", tmp, e))?;
+        fs::rename(&tmp, &self.path)
+            .map_err(|e| anyhow::anyhow!("Failed to replace output file {}: {}", self.path.display(), e))?;
+        Ok(())
+    }
+}
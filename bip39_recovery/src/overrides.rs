@@ -0,0 +1,78 @@
+use anyhow::Result;
+use bech32::Hrp;
+use bitcoin::hashes::{hash160, Hash};
+
+use crate::taproot;
+
+/// Address prefixes for chains that reuse Bitcoin's key derivation but
+/// aren't known to rust-bitcoin. Each override replaces the network's own
+/// prefix for the address types it applies to.
+#[derive(Clone, Debug, Default)]
+pub struct AddressOverrides {
+    pub bech32_hrp: Option<Hrp>,
+    pub p2pkh_version: Option<u8>,
+    pub p2sh_version: Option<u8>,
+}
+
+/// Accepts decimal (`48`) or hex (`0x30`) version bytes.
+fn parse_version(flag: &str, value: &str) -> Result<u8> {
+    let parsed = match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+        Some(hex) => u8::from_str_radix(hex, 16),
+        None => value.parse::<u8>(),
+    };
+    parsed.map_err(|e| anyhow::anyhow!("Invalid {} {}: {}", flag, value, e))
+}
+
+impl AddressOverrides {
+    pub fn parse(bech32_hrp: Option<&str>, p2pkh_version: Option<&str>, p2sh_version: Option<&str>) -> Result<Self> {
+        let bech32_hrp = bech32_hrp
+            .map(|hrp| Hrp::parse(&hrp.to_lowercase()).map_err(|e| anyhow::anyhow!("Invalid --bech32-hrp {}: {}", hrp, e)))
+            .transpose()?;
+        Ok(Self {
+            bech32_hrp,
+            p2pkh_version: p2pkh_version.map(|v| parse_version("--p2pkh-version", v)).transpose()?,
+            p2sh_version: p2sh_version.map(|v| parse_version("--p2sh-version", v)).transpose()?,
+        })
+    }
+
+    /// Each prefix from `self`, falling back to `fallback`'s.
+    pub fn or(self, fallback: Self) -> Self {
+        Self {
+            bech32_hrp: self.bech32_hrp.or(fallback.bech32_hrp),
+            p2pkh_version: self.p2pkh_version.or(fallback.p2pkh_version),
+            p2sh_version: self.p2sh_version.or(fallback.p2sh_version),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bech32_hrp.is_none() && self.p2pkh_version.is_none() && self.p2sh_version.is_none()
+    }
+
+    /// Encodes `pubkey` as `address_type` with the overridden prefix, or
+    /// `None` when no override applies to that type.
+    pub fn encode(&self, pubkey: &bitcoin::PublicKey, address_type: &str) -> Option<Result<String>> {
+        let key_hash = || hash160::Hash::hash(&pubkey.to_bytes());
+        let base58 = |version: u8, hash: hash160::Hash| {
+            let mut payload = vec![version];
+            payload.extend_from_slice(hash.as_byte_array());
+            bitcoin::base58::encode_check(&payload)
+        };
+        match address_type {
+            "p2pkh" => self.p2pkh_version.map(|version| Ok(base58(version, key_hash()))),
+            "p2sh-p2wpkh" => self.p2sh_version.map(|version| {
+                let mut redeem_script = vec![0x00, 0x14];
+                redeem_script.extend_from_slice(key_hash().as_byte_array());
+                Ok(base58(version, hash160::Hash::hash(&redeem_script)))
+            }),
+            "p2wpkh" => self.bech32_hrp.map(|hrp| {
+                bech32::segwit::encode_v0(hrp, key_hash().as_byte_array())
+                    .map_err(|e| anyhow::anyhow!("Failed to encode bech32 address: {}", e))
+            }),
+            "p2tr" => self.bech32_hrp.map(|hrp| {
+                bech32::segwit::encode_v1(hrp, &taproot::output_key(pubkey).to_inner().serialize())
+                    .map_err(|e| anyhow::anyhow!("Failed to encode bech32m address: {}", e))
+            }),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,560 @@
+use std::fs;
+
+use anyhow::Result;
+use unicode_normalization::UnicodeNormalization;
+
+/// One hashcat rule function. Positions use hashcat's `0-9A-Z` encoding.
+#[derive(Clone, Copy, Debug)]
+enum Op {
+    Noop,
+    Lower,
+    Upper,
+    Capitalize,
+    InvertCapitalize,
+    ToggleAll,
+    Toggle(usize),
+    Reverse,
+    Duplicate,
+    DuplicateTimes(usize),
+    Reflect,
+    RotateLeft,
+    RotateRight,
+    Append(char),
+    Prepend(char),
+    DeleteFirst,
+    DeleteLast,
+    DeleteAt(usize),
+    Extract(usize, usize),
+    Omit(usize, usize),
+    Insert(usize, char),
+    Overwrite(usize, char),
+    Truncate(usize),
+    Replace(char, char),
+    Purge(char),
+    DuplicateFirst(usize),
+    DuplicateLast(usize),
+    DuplicateEach,
+    SwapFront,
+    SwapBack,
+    Swap(usize, usize),
+    Title,
+    RejectLonger(usize),
+    RejectShorter(usize),
+    RejectLengthNot(usize),
+    RejectContains(char),
+    RejectMissing(char),
+}
+
+type Rule = Vec<Op>;
+
+fn parse_position(c: Option<char>) -> Result<usize> {
+    match c {
+        Some(c @ '0'..='9') => Ok(c as usize - '0' as usize),
+        Some(c @ 'A'..='Z') => Ok(c as usize - 'A' as usize + 10),
+        Some(c) => Err(anyhow::anyhow!("invalid position {}", c)),
+        None => Err(anyhow::anyhow!("missing position")),
+    }
+}
+
+fn parse_char(c: Option<char>) -> Result<char> {
+    c.ok_or_else(|| anyhow::anyhow!("missing character"))
+}
+
+/// Parses one line of a hashcat `.rule` file. Whitespace between functions
+/// is ignored, as hashcat does.
+fn parse_rule(line: &str) -> Result<Rule> {
+    let mut chars = line.chars();
+    let mut rule = Vec::new();
+    while let Some(c) = chars.next() {
+        let op = match c {
+            ' ' | '\t' => continue,
+            ':' => Op::Noop,
+            'l' => Op::Lower,
+            'u' => Op::Upper,
+            'c' => Op::Capitalize,
+            'C' => Op::InvertCapitalize,
+            't' => Op::ToggleAll,
+            'T' => Op::Toggle(parse_position(chars.next())?),
+            'r' => Op::Reverse,
+            'd' => Op::Duplicate,
+            'p' => Op::DuplicateTimes(parse_position(chars.next())?),
+            'f' => Op::Reflect,
+            '{' => Op::RotateLeft,
+            '}' => Op::RotateRight,
+            '$' => Op::Append(parse_char(chars.next())?),
+            '^' => Op::Prepend(parse_char(chars.next())?),
+            '[' => Op::DeleteFirst,
+            ']' => Op::DeleteLast,
+            'D' => Op::DeleteAt(parse_position(chars.next())?),
+            'x' => Op::Extract(parse_position(chars.next())?, parse_position(chars.next())?),
+            'O' => Op::Omit(parse_position(chars.next())?, parse_position(chars.next())?),
+            'i' => Op::Insert(parse_position(chars.next())?, parse_char(chars.next())?),
+            'o' => Op::Overwrite(parse_position(chars.next())?, parse_char(chars.next())?),
+            '\'' => Op::Truncate(parse_position(chars.next())?),
+            's' => Op::Replace(parse_char(chars.next())?, parse_char(chars.next())?),
+            '@' => Op::Purge(parse_char(chars.next())?),
+            'z' => Op::DuplicateFirst(parse_position(chars.next())?),
+            'Z' => Op::DuplicateLast(parse_position(chars.next())?),
+            'q' => Op::DuplicateEach,
+            'k' => Op::SwapFront,
+            'K' => Op::SwapBack,
+            '*' => Op::Swap(parse_position(chars.next())?, parse_position(chars.next())?),
+            'E' => Op::Title,
+            '<' => Op::RejectLonger(parse_position(chars.next())?),
+            '>' => Op::RejectShorter(parse_position(chars.next())?),
+            '_' => Op::RejectLengthNot(parse_position(chars.next())?),
+            '!' => Op::RejectContains(parse_char(chars.next())?),
+            '/' => Op::RejectMissing(parse_char(chars.next())?),
+            other => return Err(anyhow::anyhow!("unsupported rule function {}", other)),
+        };
+        rule.push(op);
+    }
+    Ok(rule)
+}
+
+fn toggle(c: char) -> char {
+    if c.is_lowercase() {
+        c.to_uppercase().next().unwrap_or(c)
+    } else {
+        c.to_lowercase().next().unwrap_or(c)
+    }
+}
+
+/// Applies `rule` to `word`, or returns `None` if a reject function drops it.
+/// Functions addressing a position past the end leave the word unchanged.
+fn apply(rule: &[Op], word: &str) -> Option<String> {
+    let mut w: Vec<char> = word.chars().collect();
+    for op in rule {
+        match *op {
+            Op::Noop => {}
+            Op::Lower => w = w.iter().flat_map(|c| c.to_lowercase()).collect(),
+            Op::Upper => w = w.iter().flat_map(|c| c.to_uppercase()).collect(),
+            Op::Capitalize | Op::InvertCapitalize => {
+                let first_upper = matches!(op, Op::Capitalize);
+                for (i, c) in w.iter_mut().enumerate() {
+                    let upper = (i == 0) == first_upper;
+                    *c = if upper { c.to_ascii_uppercase() } else { c.to_ascii_lowercase() };
+                }
+            }
+            Op::ToggleAll => w.iter_mut().for_each(|c| *c = toggle(*c)),
+            Op::Toggle(n) => {
+                if let Some(c) = w.get_mut(n) {
+                    *c = toggle(*c);
+                }
+            }
+            Op::Reverse => w.reverse(),
+            Op::Duplicate => w = w.repeat(2),
+            Op::DuplicateTimes(n) => w = w.repeat(n + 1),
+            Op::Reflect => w.extend(w.clone().into_iter().rev()),
+            Op::RotateLeft => {
+                if !w.is_empty() {
+                    w.rotate_left(1);
+                }
+            }
+            Op::RotateRight => {
+                if !w.is_empty() {
+                    w.rotate_right(1);
+                }
+            }
+            Op::Append(c) => w.push(c),
+            Op::Prepend(c) => w.insert(0, c),
+            Op::DeleteFirst => {
+                if !w.is_empty() {
+                    w.remove(0);
+                }
+            }
+            Op::DeleteLast => {
+                w.pop();
+            }
+            Op::DeleteAt(n) => {
+                if n < w.len() {
+                    w.remove(n);
+                }
+            }
+            Op::Extract(n, m) => {
+                if n < w.len() {
+                    w = w[n..(n + m).min(w.len())].to_vec();
+                }
+            }
+            Op::Omit(n, m) => {
+                if n < w.len() {
+                    w.drain(n..(n + m).min(w.len()));
+                }
+            }
+            Op::Insert(n, c) => {
+                if n <= w.len() {
+                    w.insert(n, c);
+                }
+            }
+            Op::Overwrite(n, c) => {
+                if let Some(slot) = w.get_mut(n) {
+                    *slot = c;
+                }
+            }
+            Op::Truncate(n) => w.truncate(n),
+            Op::Replace(from, to) => w.iter_mut().filter(|c| **c == from).for_each(|c| *c = to),
+            Op::Purge(c) => w.retain(|&x| x != c),
+            Op::DuplicateFirst(n) => {
+                if let Some(&first) = w.first() {
+                    w.splice(0..0, std::iter::repeat_n(first, n));
+                }
+            }
+            Op::DuplicateLast(n) => {
+                if let Some(&last) = w.last() {
+                    w.extend(std::iter::repeat_n(last, n));
+                }
+            }
+            Op::DuplicateEach => w = w.iter().flat_map(|&c| [c, c]).collect(),
+            Op::SwapFront => {
+                if w.len() >= 2 {
+                    w.swap(0, 1);
+                }
+            }
+            Op::SwapBack => {
+                let len = w.len();
+                if len >= 2 {
+                    w.swap(len - 1, len - 2);
+                }
+            }
+            Op::Swap(n, m) => {
+                if n < w.len() && m < w.len() {
+                    w.swap(n, m);
+                }
+            }
+            Op::Title => {
+                let mut start = true;
+                for c in w.iter_mut() {
+                    *c = if start { c.to_ascii_uppercase() } else { c.to_ascii_lowercase() };
+                    start = *c == ' ';
+                }
+            }
+            Op::RejectLonger(n) => {
+                if w.len() > n {
+                    return None;
+                }
+            }
+            Op::RejectShorter(n) => {
+                if w.len() < n {
+                    return None;
+                }
+            }
+            Op::RejectLengthNot(n) => {
+                if w.len() != n {
+                    return None;
+                }
+            }
+            Op::RejectContains(c) => {
+                if w.contains(&c) {
+                    return None;
+                }
+            }
+            Op::RejectMissing(c) => {
+                if !w.contains(&c) {
+                    return None;
+                }
+            }
+        }
+    }
+    Some(w.into_iter().collect())
+}
+
+fn load_rules(file: &str) -> Result<Vec<Rule>> {
+    let content = fs::read_to_string(file)
+        .map_err(|e| anyhow::anyhow!("Failed to read rules file {}: {}", file, e))?;
+    let mut rules = Vec::new();
+    for (number, line) in content.lines().enumerate() {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let rule = parse_rule(line)
+            .map_err(|e| anyhow::anyhow!("Invalid rule on line {} of {}: {}", number + 1, file, e))?;
+        rules.push(rule);
+    }
+    if rules.is_empty() {
+        return Err(anyhow::anyhow!("Rules file {} contains no rules", file));
+    }
+    Ok(rules)
+}
+
+/// The lines of a passphrase list, one passphrase each. A blank line is the
+/// empty passphrase, so a list can try plain BIP-39 alongside the others.
+pub fn read_list(file: &str) -> Result<Vec<String>> {
+    let content =
+        fs::read_to_string(file).map_err(|e| anyhow::anyhow!("Failed to read passphrase file {}: {}", file, e))?;
+    let words: Vec<String> = content.lines().map(|line| line.trim_end_matches('\r').to_string()).collect();
+    if words.is_empty() {
+        return Err(anyhow::anyhow!("Passphrase file {} is empty", file));
+    }
+    Ok(words)
+}
+
+/// BIP-39 passphrases to try with every mnemonic that passes the checksum:
+/// each line of the passphrase file run through every rule. Several rule
+/// files combine like hashcat's `-r a.rule -r b.rule`, applying one rule
+/// from each in turn. Candidates are generated on the fly rather than
+/// expanded up front.
+///
+/// Candidates are the exact strings hashed into the seed, so they're NFKD
+/// normalized here as BIP-39 requires, and seeds are made with
+/// `to_seed_normalized` rather than normalizing again.
+pub struct Passphrases {
+    words: Vec<String>,
+    rules: Vec<Rule>,
+    unicode_variants: bool,
+}
+
+impl Passphrases {
+    /// The empty passphrase only, i.e. plain BIP-39.
+    pub fn none() -> Self {
+        Self { words: vec![String::new()], rules: vec![Vec::new()], unicode_variants: false }
+    }
+
+    pub fn load(file: &str, rule_files: &[String]) -> Result<Self> {
+        Self::new(read_list(file)?, rule_files)
+    }
+
+    /// `words` given directly, e.g. from `--passphrase`, each expanded by the
+    /// rules in `rule_files` as a loaded list would be.
+    pub fn new(words: Vec<String>, rule_files: &[String]) -> Result<Self> {
+        if words.is_empty() {
+            return Err(anyhow::anyhow!("No passphrases to try"));
+        }
+        let mut rules: Vec<Rule> = vec![Vec::new()];
+        for rule_file in rule_files {
+            let next = load_rules(rule_file)?;
+            rules = rules
+                .iter()
+                .flat_map(|chain| next.iter().map(move |rule| chain.iter().chain(rule).copied().collect()))
+                .collect();
+        }
+        Ok(Self { words, rules, unicode_variants: false })
+    }
+
+    /// Also tries each candidate as other wallets may have encoded it; see
+    /// [`unicode_variants`].
+    pub fn with_unicode_variants(mut self) -> Self {
+        self.unicode_variants = true;
+        self
+    }
+
+    /// Upper bound on passphrases per mnemonic; reject functions may drop some.
+    pub fn count(&self) -> u64 {
+        let variants = if self.unicode_variants { MAX_VARIANTS } else { 1 };
+        (self.words.len() as u64).saturating_mul(self.rules.len() as u64).saturating_mul(variants)
+    }
+
+    pub fn candidates(&self) -> impl Iterator<Item = String> + '_ {
+        self.words
+            .iter()
+            .flat_map(move |word| self.rules.iter().filter_map(move |rule| apply(rule, word)))
+            .flat_map(move |candidate| {
+                if self.unicode_variants {
+                    unicode_variants(&candidate)
+                } else {
+                    vec![candidate.nfkd().collect()]
+                }
+            })
+    }
+}
+
+/// Most strings [`unicode_variants`] gives for one passphrase.
+const MAX_VARIANTS: u64 = 17;
+
+/// The byte strings a wallet may have hashed for `passphrase`, the BIP-39
+/// NFKD form first. Wallets that skipped normalization hashed the text as
+/// typed, which is usually NFC, so the other normal forms are tried as is.
+/// Then the text is tried with curly quotes straightened (or an apostrophe
+/// curled, as phone keyboards do), and garbled the common ways: its UTF-8
+/// read back as Latin-1 or Windows-1252, or, when it already looks garbled,
+/// repaired. Each of those is tried both normalized and as is. Variants
+/// that come out the same are only tried once.
+pub fn unicode_variants(passphrase: &str) -> Vec<String> {
+    let mut variants: Vec<String> = Vec::new();
+    let mut push = |variant: String| {
+        if !variants.contains(&variant) {
+            variants.push(variant);
+        }
+    };
+    push(passphrase.nfkd().collect());
+    push(passphrase.to_string());
+    push(passphrase.nfc().collect());
+    push(passphrase.nfd().collect());
+    push(passphrase.nfkc().collect());
+
+    let mut typed = vec![straight_quotes(passphrase), passphrase.replace('\'', "\u{2019}")];
+    typed.push(passphrase.bytes().map(char::from).collect());
+    typed.push(passphrase.bytes().map(windows_1252).collect());
+    typed.extend(repair_mojibake(passphrase));
+    for variant in typed {
+        push(variant.nfkd().collect());
+        push(variant);
+    }
+    variants
+}
+
+fn straight_quotes(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{2032}' => '\'',
+            '\u{201C}' | '\u{201D}' | '\u{201E}' | '\u{2033}' => '"',
+            c => c,
+        })
+        .collect()
+}
+
+/// What Windows-1252 shows for a byte. Bytes it leaves undefined decode to
+/// the C1 control of the same value, as browsers do.
+fn windows_1252(byte: u8) -> char {
+    const HIGH: [char; 32] = [
+        '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8D}', 'Ž', '\u{8F}', '\u{90}', '‘',
+        '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9D}', 'ž', 'Ÿ',
+    ];
+    match byte {
+        0x80..=0x9F => HIGH[usize::from(byte - 0x80)],
+        _ => char::from(byte),
+    }
+}
+
+/// The text that garbled into `text`, if it reads as UTF-8 once its
+/// characters are taken back as Latin-1 or Windows-1252 bytes.
+fn repair_mojibake(text: &str) -> Vec<String> {
+    if text.is_ascii() {
+        return Vec::new();
+    }
+    let latin1: Option<Vec<u8>> = text.chars().map(|c| u8::try_from(u32::from(c)).ok()).collect();
+    let cp1252: Option<Vec<u8>> = text
+        .chars()
+        .map(|c| (0..=u8::MAX).find(|&byte| windows_1252(byte) == c))
+        .collect();
+    [latin1, cp1252].into_iter().flatten().filter_map(|bytes| String::from_utf8(bytes).ok()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule_file(name: &str, content: &str) -> String {
+        let path = std::env::temp_dir().join(format!("bip39_recovery-rules-{}-{}", name, std::process::id()));
+        fs::write(&path, content).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    fn applied(rule: &str, word: &str) -> Option<String> {
+        apply(&parse_rule(rule).unwrap(), word)
+    }
+
+    /// The examples of hashcat's rule documentation.
+    #[test]
+    fn hashcat_examples() {
+        let cases = [
+            (":", "p@ssW0rd"),
+            ("l", "p@ssw0rd"),
+            ("u", "P@SSW0RD"),
+            ("c", "P@ssw0rd"),
+            ("C", "p@SSW0RD"),
+            ("t", "P@SSw0RD"),
+            ("T3", "p@sSW0rd"),
+            ("r", "dr0Wss@p"),
+            ("d", "p@ssW0rdp@ssW0rd"),
+            ("p2", "p@ssW0rdp@ssW0rdp@ssW0rd"),
+            ("f", "p@ssW0rddr0Wss@p"),
+            ("{", "@ssW0rdp"),
+            ("}", "dp@ssW0r"),
+            ("$1", "p@ssW0rd1"),
+            ("^1", "1p@ssW0rd"),
+            ("[", "@ssW0rd"),
+            ("]", "p@ssW0r"),
+            ("D3", "p@sW0rd"),
+            ("x04", "p@ss"),
+            ("O12", "psW0rd"),
+            ("i4!", "p@ss!W0rd"),
+            ("o3$", "p@s$W0rd"),
+            ("'6", "p@ssW0"),
+            ("ss$", "p@$$W0rd"),
+            ("@s", "p@W0rd"),
+            ("z2", "ppp@ssW0rd"),
+            ("Z2", "p@ssW0rddd"),
+            ("q", "pp@@ssssWW00rrdd"),
+            ("k", "@pssW0rd"),
+            ("K", "p@ssW0dr"),
+            ("*34", "p@sWs0rd"),
+        ];
+        for (rule, expected) in cases {
+            assert_eq!(applied(rule, "p@ssW0rd").as_deref(), Some(expected), "rule {}", rule);
+        }
+        assert_eq!(applied("E", "p@ssW0rd w0rld").as_deref(), Some("P@ssw0rd W0rld"));
+    }
+
+    #[test]
+    fn chains_rejects_and_positions() {
+        assert_eq!(applied("c $2 $0 $1 $9", "satoshi").as_deref(), Some("Satoshi2019"));
+        // Positions past the end leave the word alone
+        assert_eq!(applied("T9 D9 oA! *0Z x91", "word").as_deref(), Some("word"));
+        assert_eq!(applied("^x [ ] } {", "").as_deref(), Some(""));
+
+        assert_eq!(applied("<4", "word").as_deref(), Some("word"));
+        assert_eq!(applied("<3", "word"), None);
+        assert_eq!(applied(">5", "word"), None);
+        assert_eq!(applied("_4", "word").as_deref(), Some("word"));
+        assert_eq!(applied("$s _4", "word"), None);
+        assert_eq!(applied("!o", "word"), None);
+        assert_eq!(applied("/x", "word"), None);
+        assert_eq!(applied("/x $x", "word"), None);
+        assert_eq!(applied("$x /x", "word").as_deref(), Some("wordx"));
+
+        assert!(parse_rule("T").is_err());
+        assert!(parse_rule("Ta").is_err());
+        assert!(parse_rule("$").is_err());
+        assert!(parse_rule("X").is_err());
+    }
+
+    /// Several rule files apply one rule from each, in order, to every word.
+    #[test]
+    fn rule_files_combine() {
+        let first = rule_file("first", "# keep or append\n:\n$1\n");
+        let second = rule_file("second", "u\nc\n\n");
+        let rejects = rule_file("rejects", "<2\n");
+        let words = vec!["abc".to_string(), "de".to_string()];
+
+        let passphrases = Passphrases::new(words.clone(), &[first.clone(), second.clone()]).unwrap();
+        assert_eq!(passphrases.count(), 8);
+        let candidates: Vec<String> = passphrases.candidates().collect();
+        assert_eq!(candidates, ["ABC", "Abc", "ABC1", "Abc1", "DE", "De", "DE1", "De1"]);
+
+        // Reject functions drop candidates below the count
+        let passphrases = Passphrases::new(words, std::slice::from_ref(&rejects)).unwrap();
+        assert_eq!(passphrases.count(), 2);
+        assert_eq!(passphrases.candidates().collect::<Vec<_>>(), ["de"]);
+
+        let empty = rule_file("empty", "# nothing\n");
+        let invalid = rule_file("invalid", ":\nX\n");
+        assert!(Passphrases::new(vec!["a".to_string()], std::slice::from_ref(&empty)).is_err());
+        assert!(Passphrases::new(vec!["a".to_string()], std::slice::from_ref(&invalid)).is_err());
+        assert!(Passphrases::new(Vec::new(), &[]).is_err());
+        for file in [first, second, rejects, empty, invalid] {
+            let _ = fs::remove_file(file);
+        }
+    }
+
+    #[test]
+    fn candidates_are_normalized() {
+        let passphrases = Passphrases::new(vec!["caf\u{e9}".to_string()], &[]).unwrap();
+        assert_eq!(passphrases.candidates().collect::<Vec<_>>(), ["cafe\u{301}"]);
+        assert_eq!(Passphrases::none().candidates().collect::<Vec<_>>(), [""]);
+    }
+
+    #[test]
+    fn variants() {
+        let variants = unicode_variants("caf\u{e9}");
+        assert_eq!(variants[..2], ["cafe\u{301}", "caf\u{e9}"]);
+        assert!(variants.contains(&"caf\u{c3}\u{a9}".to_string()));
+        assert!(variants.len() as u64 <= MAX_VARIANTS);
+        assert_eq!(variants.iter().collect::<std::collections::HashSet<_>>().len(), variants.len());
+
+        // Garbled text is also tried repaired
+        assert!(unicode_variants("caf\u{c3}\u{a9}").contains(&"caf\u{e9}".to_string()));
+        assert!(unicode_variants("it\u{2019}s").contains(&"it's".to_string()));
+        assert!(unicode_variants("it's").contains(&"it\u{2019}s".to_string()));
+        assert_eq!(unicode_variants("plain"), ["plain"]);
+    }
+}
@@ -0,0 +1,149 @@
+use anyhow::Result;
+use bitcoin::bip32::{ChildNumber, DerivationPath};
+use bitcoin::Network;
+
+/// Pre-BIP44 layouts selectable with `--legacy-paths`: name, first receive
+/// key, and the wallets known to use it. All of them used p2pkh addresses.
+pub const LEGACY_PATHS: [(&str, &str, &str); 4] = [
+    ("breadwallet", "m/0'/0/0", "breadwallet, single hardened account"),
+    ("multibit-hd", "m/0'/0/0", "Multibit HD and other bitcoinj wallets"),
+    ("single-chain", "m/0/0", "single external chain directly under the master key"),
+    ("mycelium", "m/44'/0'/0'/0/0", "early Mycelium accounts"),
+];
+
+/// Derivation paths of the `names` presets, all of them when `names` is
+/// empty. Presets sharing a path appear once.
+pub fn legacy_paths(names: &[String]) -> Result<Vec<DerivationPath>> {
+    for name in names {
+        if !LEGACY_PATHS.iter().any(|(preset, _, _)| preset.eq_ignore_ascii_case(name)) {
+            let known: Vec<&str> = LEGACY_PATHS.iter().map(|(preset, _, _)| *preset).collect();
+            return Err(anyhow::anyhow!("Unknown legacy path preset {} (available: {})", name, known.join(", ")));
+        }
+    }
+    let mut paths: Vec<DerivationPath> = Vec::new();
+    for (preset, path, _) in LEGACY_PATHS {
+        if names.is_empty() || names.iter().any(|name| preset.eq_ignore_ascii_case(name)) {
+            let path: DerivationPath = path.parse()?;
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+    }
+    Ok(paths)
+}
+
+/// Layouts selectable with `--path-preset`: name, BIP-43 purpose, and the
+/// address type wallets derive under it.
+pub const PATH_PRESETS: [(&str, u32, &str); 4] =
+    [("bip44", 44, "p2pkh"), ("bip49", 49, "p2sh-p2wpkh"), ("bip84", 84, "p2wpkh"), ("bip86", 86, "p2tr")];
+
+/// First receive paths of the `names` presets, `m/<purpose>'/<coin>'/0'/0/0`
+/// with coin type 0' on mainnet and 1' otherwise, in the order given.
+pub fn preset_paths(names: &[String], network: Network) -> Result<Vec<DerivationPath>> {
+    let coin_type = if network == Network::Bitcoin { 0 } else { 1 };
+    let mut paths: Vec<DerivationPath> = Vec::new();
+    for name in names {
+        let (_, purpose, _) = PATH_PRESETS.iter().find(|(preset, _, _)| preset.eq_ignore_ascii_case(name.trim())).ok_or_else(|| {
+            let known: Vec<&str> = PATH_PRESETS.iter().map(|(preset, _, _)| *preset).collect();
+            anyhow::anyhow!("Unknown path preset {} (available: {})", name, known.join(", "))
+        })?;
+        let path = DerivationPath::from(vec![
+            ChildNumber::Hardened { index: *purpose },
+            ChildNumber::Hardened { index: coin_type },
+            ChildNumber::Hardened { index: 0 },
+            ChildNumber::Normal { index: 0 },
+            ChildNumber::Normal { index: 0 },
+        ]);
+        if !paths.contains(&path) {
+            paths.push(path);
+        }
+    }
+    Ok(paths)
+}
+
+/// The address type a path's purpose implies, as for each of several paths
+/// checked in one run: Ethereum under coin type 60', otherwise the type of
+/// the matching preset.
+pub fn default_address_type(path: &DerivationPath) -> Option<&'static str> {
+    let purpose = path_purpose(path)?;
+    if purpose == 44 && coin_type(path) == Some(60) {
+        return Some("eth");
+    }
+    PATH_PRESETS.iter().find(|(_, preset, _)| *preset == purpose).map(|(_, _, address_type)| *address_type)
+}
+
+/// BIP-43 purpose that wallets conventionally pair with each address type.
+pub fn conventional_purpose(address_type: &str) -> Option<u32> {
+    match address_type.to_lowercase().as_str() {
+        "p2pkh" => Some(44),
+        "p2sh-p2wpkh" => Some(49),
+        "p2wpkh" => Some(84),
+        "p2tr" => Some(86),
+        "eth" => Some(44),
+        _ => None,
+    }
+}
+
+/// The purpose level of a BIP-43 style path, if it has one.
+fn path_purpose(path: &DerivationPath) -> Option<u32> {
+    match path.into_iter().next() {
+        Some(ChildNumber::Hardened { index }) if matches!(*index, 44 | 49 | 84 | 86) => Some(*index),
+        _ => None,
+    }
+}
+
+/// Describes the mismatch when the path's purpose isn't the one wallets use
+/// for `address_type`, e.g. `m/44'/...` with native segwit.
+pub fn purpose_mismatch(path: &DerivationPath, address_type: &str) -> Option<(u32, u32)> {
+    let expected = conventional_purpose(address_type)?;
+    let actual = path_purpose(path)?;
+    (actual != expected).then_some((actual, expected))
+}
+
+/// The coin-type level of a BIP-44 style path, if it is hardened.
+pub fn coin_type(path: &DerivationPath) -> Option<u32> {
+    match path.into_iter().nth(1) {
+        Some(ChildNumber::Hardened { index }) => Some(*index),
+        _ => None,
+    }
+}
+
+/// Replaces the coin-type level of `path` with hardened `coin_type`, or
+/// `None` if the path is too short to have one.
+pub fn with_coin_type(path: &DerivationPath, coin_type: u32) -> Option<DerivationPath> {
+    let mut children: Vec<ChildNumber> = path.into_iter().copied().collect();
+    *children.get_mut(1)? = ChildNumber::Hardened { index: coin_type };
+    Some(DerivationPath::from(children))
+}
+
+/// Flips hardening at 1-based `level` of `path`, or `None` if the path is
+/// shallower than that.
+pub fn toggle_hardening(path: &DerivationPath, level: usize) -> Option<DerivationPath> {
+    let mut children: Vec<ChildNumber> = path.into_iter().copied().collect();
+    let child = children.get_mut(level.checked_sub(1)?)?;
+    *child = match *child {
+        ChildNumber::Hardened { index } => ChildNumber::Normal { index },
+        ChildNumber::Normal { index } => ChildNumber::Hardened { index },
+    };
+    Some(DerivationPath::from(children))
+}
+
+/// Replaces the last two levels of `path`, the chain and address index of
+/// a BIP-44 style path, keeping the rest.
+pub fn with_address(path: &DerivationPath, chain: u32, index: u32) -> Option<DerivationPath> {
+    let mut children: Vec<ChildNumber> = path.into_iter().copied().collect();
+    let split = children.len().checked_sub(2)?;
+    children.truncate(split);
+    children.push(ChildNumber::from_normal_idx(chain).ok()?);
+    children.push(ChildNumber::from_normal_idx(index).ok()?);
+    Some(DerivationPath::from(children))
+}
+
+/// Replaces the purpose level of `path` with `purpose`, keeping the rest.
+pub fn with_purpose(path: &DerivationPath, purpose: u32) -> DerivationPath {
+    let mut children: Vec<ChildNumber> = path.into_iter().copied().collect();
+    if let Some(first) = children.first_mut() {
+        *first = ChildNumber::Hardened { index: purpose };
+    }
+    DerivationPath::from(children)
+}
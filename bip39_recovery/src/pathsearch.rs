@@ -0,0 +1,235 @@
+use std::collections::HashSet;
+use std::fs;
+
+use anyhow::Result;
+use bip39::{Language, Mnemonic};
+use bitcoin::bip32::{ChildNumber, DerivationPath, Xpriv};
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::Network;
+use clap::ValueEnum;
+use indicatif::{ProgressBar, ProgressStyle};
+use rayon::prelude::*;
+use secp256k1::Secp256k1;
+
+use crate::coverage::{Checkpoint, Shard};
+use crate::matrix::{self, Variant, ADDRESS_TYPES};
+use crate::passphrase::Passphrases;
+use crate::{live_stats, pathspec, targets, Match, ResultsFormat};
+
+/// Which form each enumerated level takes.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum Hardening {
+    Hardened,
+    Normal,
+    Both,
+}
+
+/// Paths checked between checkpoints.
+const BATCH: u64 = 10_000;
+
+const PHASE: &str = "path-search";
+
+/// Settings of a `path-search` run.
+pub struct PathSearch {
+    pub mnemonic_file: String,
+    pub passphrase_file: Option<String>,
+    pub address: Option<String>,
+    pub address_file: Option<String>,
+    pub prefix: String,
+    pub max_depth: usize,
+    pub index_range: String,
+    pub hardening: Hardening,
+    pub address_types: Vec<String>,
+    pub network: String,
+    pub progress_file: String,
+    pub results_file: String,
+}
+
+/// Every path of 1 to `max_depth` levels below a prefix, each level one of
+/// `choices`, ranked shortest first and then in the order of `choices`.
+struct PathSpace {
+    prefix: Vec<ChildNumber>,
+    choices: Vec<ChildNumber>,
+    max_depth: usize,
+}
+
+impl PathSpace {
+    fn size(&self) -> u64 {
+        let n = self.choices.len() as u64;
+        (1..=self.max_depth as u32).fold(0u64, |size, depth| size.saturating_add(n.saturating_pow(depth)))
+    }
+
+    fn path_at(&self, mut rank: u64) -> DerivationPath {
+        let n = self.choices.len() as u64;
+        let mut depth = 1;
+        while rank >= n.pow(depth) {
+            rank -= n.pow(depth);
+            depth += 1;
+        }
+        let mut tail = vec![ChildNumber::Normal { index: 0 }; depth as usize];
+        for level in tail.iter_mut().rev() {
+            *level = self.choices[(rank % n) as usize];
+            rank /= n;
+        }
+        DerivationPath::from([self.prefix.as_slice(), tail.as_slice()].concat())
+    }
+}
+
+/// `path-search`: the mnemonic is known but not the derivation path. Every
+/// path up to `max_depth` levels below `prefix` is derived, with each level
+/// an index from `index_range` in the chosen hardening, and checked as each
+/// address type. Progress is checkpointed per batch and every match is
+/// written to the results file.
+pub fn run(search: PathSearch) -> Result<()> {
+    let phrase = fs::read_to_string(&search.mnemonic_file)
+        .map_err(|e| anyhow::anyhow!("Failed to read mnemonic file {}: {}", search.mnemonic_file, e))?;
+    let mnemonic = Mnemonic::parse_in_normalized(Language::English, phrase.trim())
+        .map_err(|e| anyhow::anyhow!("Invalid mnemonic in {}: {}", search.mnemonic_file, e))?;
+    let network = matrix::parse_network(&search.network)?;
+    for address_type in &search.address_types {
+        if !ADDRESS_TYPES.contains(&address_type.as_str()) {
+            return Err(anyhow::anyhow!("Unsupported address type {}", address_type));
+        }
+    }
+    let targets: HashSet<String> = match (&search.address, &search.address_file) {
+        (Some(address), None) => HashSet::from([address.clone()]),
+        (None, Some(file)) => targets::load(file)?.addresses,
+        _ => return Err(anyhow::anyhow!("Give exactly one of --address or --address-file")),
+    };
+
+    let range = pathspec::parse_range(&search.index_range)?;
+    let hardenings: &[bool] = match search.hardening {
+        Hardening::Hardened => &[true],
+        Hardening::Normal => &[false],
+        Hardening::Both => &[true, false],
+    };
+    let choices: Vec<ChildNumber> = (range.first..=range.last)
+        .flat_map(|index| {
+            hardenings.iter().map(move |&hardened| {
+                if hardened {
+                    ChildNumber::Hardened { index }
+                } else {
+                    ChildNumber::Normal { index }
+                }
+            })
+        })
+        .collect();
+    let prefix = pathspec::parse(&search.prefix)?;
+    let space = PathSpace {
+        prefix: prefix.as_ref().to_vec(),
+        choices,
+        max_depth: search.max_depth.max(1),
+    };
+    let total = space.size();
+    if total == u64::MAX {
+        return Err(anyhow::anyhow!("Too many paths to enumerate; lower --max-depth or narrow --index-range"));
+    }
+
+    let passphrases = match &search.passphrase_file {
+        Some(file) => Passphrases::load(file, &[])?,
+        None => Passphrases::none(),
+    };
+    let masters = passphrases
+        .candidates()
+        .map(|passphrase| {
+            let master = Xpriv::new_master(Network::Bitcoin, &mnemonic.to_seed_normalized(&passphrase))?;
+            Ok((passphrase, master))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    // Everything but the mnemonic decides what a rank means and how it's checked
+    let mut settings = vec![
+        format!("prefix {}", prefix),
+        format!("depth {}", space.max_depth),
+        format!("range {}-{} {:?}", range.first, range.last, search.hardening),
+        format!("types {:?} {}", search.address_types, matrix::network_name(network)),
+        format!("passphrases {:?}", passphrases.candidates().collect::<Vec<_>>()),
+    ];
+    let mut sorted_targets: Vec<&String> = targets.iter().collect();
+    sorted_targets.sort();
+    settings.push(format!("targets {:?}", sorted_targets));
+    let config = sha256::Hash::hash(settings.join("\n").as_bytes()).to_string()[..16].to_string();
+
+    let shard = Shard { start: 0, end: total, space: total };
+    let mut checkpoint = Checkpoint::new(shard, PHASE, &config, live_stats::unix_now());
+    if let Ok(content) = fs::read_to_string(&search.progress_file) {
+        let saved = Checkpoint::parse(&content)
+            .map_err(|e| anyhow::anyhow!("Failed to parse progress file {}: {}", search.progress_file, e))?;
+        if saved.config.as_deref() != Some(config.as_str()) {
+            return Err(anyhow::anyhow!(
+                "Progress file {} belongs to a different path search; remove it or pass another --progress-file",
+                search.progress_file
+            ));
+        }
+        checkpoint.processed = saved.processed.min(total);
+        checkpoint.started = saved.started;
+        checkpoint.samples = saved.samples;
+    }
+
+    println!(
+        "Searching {} paths below {} (depth 1-{}, indices {}-{}, {:?}) as {} for {} target(s)",
+        total,
+        prefix,
+        space.max_depth,
+        range.first,
+        range.last,
+        search.hardening,
+        search.address_types.join(", "),
+        targets.len()
+    );
+    let pb = ProgressBar::new(total);
+    pb.set_style(
+        ProgressStyle::with_template("[{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} ({percent}%) | {msg}")
+            .unwrap()
+            .progress_chars("##-"),
+    );
+    pb.set_position(checkpoint.processed);
+
+    let secp = Secp256k1::new();
+    let mut found = 0;
+    let mut start = checkpoint.processed;
+    while start < total {
+        let end = start.saturating_add(BATCH).min(total);
+        let matches: Vec<Match> = (start..end)
+            .into_par_iter()
+            .flat_map_iter(|rank| {
+                let path = space.path_at(rank);
+                let mut matches = Vec::new();
+                for (passphrase, master) in &masters {
+                    let Ok(child) = master.derive_priv(&secp, &path) else { continue };
+                    let pubkey = bitcoin::PublicKey::new(child.private_key.public_key(&secp));
+                    for address_type in &search.address_types {
+                        let Some(Ok(address)) = crate::encode_address(&pubkey, address_type, network) else { continue };
+                        if targets.contains(&address) {
+                            let variant = Variant { path: path.clone(), address_type: address_type.clone(), network };
+                            let passphrase = passphrase.clone();
+                            matches.push(Match { mnemonic: mnemonic.to_string(), passphrase, address, variant });
+                        }
+                    }
+                }
+                matches
+            })
+            .collect();
+        for found_match in &matches {
+            pb.println(format!("Match found! Address: {}, Matched: {}", found_match.address, found_match.variant));
+            if !found_match.passphrase.is_empty() {
+                pb.println(format!("Passphrase: {}", found_match.passphrase));
+            }
+            crate::save_result(&search.results_file, found_match, ResultsFormat::Plain, None)?;
+        }
+        found += matches.len();
+        start = end;
+        checkpoint.record(live_stats::unix_now(), start);
+        fs::write(&search.progress_file, checkpoint.render())
+            .map_err(|e| anyhow::anyhow!("Failed to write progress file {}: {}", search.progress_file, e))?;
+        pb.set_position(start);
+        pb.set_message(format!("{} match(es)", found));
+    }
+    pb.finish_with_message(format!("{} match(es)", found));
+    if found > 0 {
+        println!("Recorded {} match(es) in {}", found, search.results_file);
+    } else {
+        println!("No path below {} reproduces a target address", prefix);
+    }
+    Ok(())
+}
@@ -0,0 +1,195 @@
+use anyhow::Result;
+use bitcoin::bip32::{ChildNumber, DerivationPath};
+
+/// An inclusive range of child indices a `*` placeholder expands over.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Range {
+    pub first: u32,
+    pub last: u32,
+}
+
+/// Parses `a-b` or `a..=b` (inclusive), `a..b` (exclusive), or a single
+/// index.
+pub fn parse_range(range: &str) -> Result<Range> {
+    let (first, last, exclusive) = if let Some((first, last)) = range.split_once("..=") {
+        (first, last, false)
+    } else if let Some((first, last)) = range.split_once("..") {
+        (first, last, true)
+    } else {
+        let (first, last) = range.split_once('-').unwrap_or((range, range));
+        (first, last, false)
+    };
+    let parse = |value: &str| -> Result<u32> {
+        let index: u32 = value
+            .trim()
+            .parse()
+            .map_err(|e| anyhow::anyhow!("Invalid path range {}: {} is not an index ({})", range, value.trim(), e))?;
+        check_index(index)?;
+        Ok(index)
+    };
+    let (first, mut last) = (parse(first)?, parse(last)?);
+    if exclusive {
+        last = last
+            .checked_sub(1)
+            .filter(|&last| last >= first)
+            .ok_or_else(|| anyhow::anyhow!("Invalid path range {}: it is empty", range))?;
+    }
+    if first > last {
+        return Err(anyhow::anyhow!("Invalid path range {}: {} comes after {}", range, first, last));
+    }
+    Ok(Range { first, last })
+}
+
+/// Most paths a template may expand to; each is derived for every candidate.
+const MAX_EXPANDED: u64 = 100_000;
+
+/// One level of a path template.
+#[derive(Clone, Copy, Debug)]
+enum Step {
+    Fixed(ChildNumber),
+    Placeholder { hardened: bool },
+}
+
+/// Parses a derivation path. `'`, `h`, and `H` all mark a hardened step, and
+/// the leading `m/` may be left out.
+pub fn parse(path: &str) -> Result<DerivationPath> {
+    let steps = parse_template(path)?;
+    let children = steps
+        .iter()
+        .map(|step| match step {
+            Step::Fixed(child) => Ok(*child),
+            Step::Placeholder { .. } => {
+                Err(anyhow::anyhow!("Invalid derivation path {}: `*` needs a range from --path-ranges", path))
+            }
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(DerivationPath::from(children))
+}
+
+/// Parses a path template and expands each `*` (or hardened `*'`) over the
+/// range given for it in `ranges`, in order of appearance, e.g.
+/// `m/84'/0'/*'/0/*` with ranges `0-2` and `0-19` gives 60 paths.
+pub fn expand(path: &str, ranges: &[Range]) -> Result<Vec<DerivationPath>> {
+    let steps = parse_template(path)?;
+    let placeholders = steps.iter().filter(|step| matches!(step, Step::Placeholder { .. })).count();
+    if placeholders != ranges.len() {
+        return Err(anyhow::anyhow!(
+            "Derivation path {} has {} `*` placeholder(s) but {} range(s) were given",
+            path,
+            placeholders,
+            ranges.len()
+        ));
+    }
+    let count = ranges.iter().fold(1u64, |count, range| count.saturating_mul(u64::from(range.last - range.first) + 1));
+    if count > MAX_EXPANDED {
+        return Err(anyhow::anyhow!(
+            "Derivation path {} expands to {} paths over the given ranges, more than the {} allowed",
+            path,
+            count,
+            MAX_EXPANDED
+        ));
+    }
+    let mut paths: Vec<Vec<ChildNumber>> = vec![Vec::new()];
+    let mut ranges = ranges.iter();
+    for step in steps {
+        let children: Vec<ChildNumber> = match step {
+            Step::Fixed(child) => vec![child],
+            Step::Placeholder { hardened } => {
+                let range = ranges.next().copied().unwrap_or(Range { first: 0, last: 0 });
+                (range.first..=range.last).map(|index| child_number(index, hardened)).collect::<Result<_>>()?
+            }
+        };
+        paths = paths
+            .into_iter()
+            .flat_map(|prefix| {
+                children.iter().map(move |child| {
+                    let mut path = prefix.clone();
+                    path.push(*child);
+                    path
+                })
+            })
+            .collect();
+    }
+    Ok(paths.into_iter().map(DerivationPath::from).collect())
+}
+
+/// Expands a comma-separated list of path templates, the ranges taken by
+/// the `*` placeholders in order across the whole list, e.g.
+/// `m/84'/0'/*'/0/0,m/44'/0'/0'/0/*` with ranges `0-2` and `0-19`. A path
+/// listed twice is derived once.
+pub fn expand_list(paths: &str, ranges: &[Range]) -> Result<Vec<DerivationPath>> {
+    let templates: Vec<&str> = paths.split(',').map(str::trim).filter(|path| !path.is_empty()).collect();
+    if templates.len() <= 1 {
+        return expand(paths, ranges);
+    }
+    let mut counts = Vec::with_capacity(templates.len());
+    for template in &templates {
+        counts.push(parse_template(template)?.iter().filter(|step| matches!(step, Step::Placeholder { .. })).count());
+    }
+    if counts.iter().sum::<usize>() != ranges.len() {
+        return Err(anyhow::anyhow!(
+            "Derivation paths {} have {} `*` placeholder(s) but {} range(s) were given",
+            paths,
+            counts.iter().sum::<usize>(),
+            ranges.len()
+        ));
+    }
+    let mut expanded: Vec<DerivationPath> = Vec::new();
+    let mut ranges = ranges;
+    for (template, count) in templates.iter().zip(counts) {
+        let (own, rest) = ranges.split_at(count);
+        ranges = rest;
+        for path in expand(template, own)? {
+            if !expanded.contains(&path) {
+                expanded.push(path);
+            }
+        }
+    }
+    Ok(expanded)
+}
+
+fn parse_template(path: &str) -> Result<Vec<Step>> {
+    let trimmed = path.trim();
+    let body = match trimmed.strip_prefix('m').or_else(|| trimmed.strip_prefix('M')) {
+        Some(rest) => rest.strip_prefix('/').unwrap_or(rest),
+        None => trimmed,
+    };
+    if body.is_empty() {
+        return Ok(Vec::new());
+    }
+    body.split('/')
+        .enumerate()
+        .map(|(level, step)| {
+            parse_step(step)
+                .map_err(|e| anyhow::anyhow!("Invalid derivation path {}: level {} ({}): {}", path, level + 1, step, e))
+        })
+        .collect()
+}
+
+fn parse_step(step: &str) -> Result<Step> {
+    let step = step.trim();
+    let (index, hardened) = match step.strip_suffix(['\'', 'h', 'H']) {
+        Some(index) => (index, true),
+        None => (step, false),
+    };
+    if index == "*" {
+        return Ok(Step::Placeholder { hardened });
+    }
+    if index.is_empty() {
+        return Err(anyhow::anyhow!("empty step"));
+    }
+    let index: u32 = index.parse().map_err(|_| anyhow::anyhow!("{} is not an index", index))?;
+    Ok(Step::Fixed(child_number(index, hardened)?))
+}
+
+fn check_index(index: u32) -> Result<()> {
+    if index >= 1 << 31 {
+        return Err(anyhow::anyhow!("index {} is past the largest child index, 2147483647", index));
+    }
+    Ok(())
+}
+
+fn child_number(index: u32, hardened: bool) -> Result<ChildNumber> {
+    check_index(index)?;
+    Ok(if hardened { ChildNumber::Hardened { index } } else { ChildNumber::Normal { index } })
+}
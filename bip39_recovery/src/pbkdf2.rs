@@ -0,0 +1,742 @@
+use bitcoin::hashes::{sha512, Hash};
+
+use crate::entropy;
+use crate::gpu::Seed;
+use crate::passphrase::Passphrases;
+use crate::wipe::{Wipe, Wiped};
+
+/// BIP-39's PBKDF2 rounds.
+const ROUNDS: usize = 2048;
+
+/// SHA-512 block size, which is also HMAC's key size.
+const BLOCK: usize = 128;
+
+/// Most lanes any backend runs at once.
+const MAX_LANES: usize = 8;
+
+const IV: [u64; 8] = [
+    0x6a09e667f3bcc908, 0xbb67ae8584caa73b, 0x3c6ef372fe94f82b, 0xa54ff53a5f1d36f1,
+    0x510e527fade682d1, 0x9b05688c2b3e6c1f, 0x1f83d9abfb41bd6b, 0x5be0cd19137e2179,
+];
+
+const K: [u64; 80] = [
+    0x428a2f98d728ae22, 0x7137449123ef65cd, 0xb5c0fbcfec4d3b2f, 0xe9b5dba58189dbbc, 0x3956c25bf348b538,
+    0x59f111f1b605d019, 0x923f82a4af194f9b, 0xab1c5ed5da6d8118, 0xd807aa98a3030242, 0x12835b0145706fbe,
+    0x243185be4ee4b28c, 0x550c7dc3d5ffb4e2, 0x72be5d74f27b896f, 0x80deb1fe3b1696b1, 0x9bdc06a725c71235,
+    0xc19bf174cf692694, 0xe49b69c19ef14ad2, 0xefbe4786384f25e3, 0x0fc19dc68b8cd5b5, 0x240ca1cc77ac9c65,
+    0x2de92c6f592b0275, 0x4a7484aa6ea6e483, 0x5cb0a9dcbd41fbd4, 0x76f988da831153b5, 0x983e5152ee66dfab,
+    0xa831c66d2db43210, 0xb00327c898fb213f, 0xbf597fc7beef0ee4, 0xc6e00bf33da88fc2, 0xd5a79147930aa725,
+    0x06ca6351e003826f, 0x142929670a0e6e70, 0x27b70a8546d22ffc, 0x2e1b21385c26c926, 0x4d2c6dfc5ac42aed,
+    0x53380d139d95b3df, 0x650a73548baf63de, 0x766a0abb3c77b2a8, 0x81c2c92e47edaee6, 0x92722c851482353b,
+    0xa2bfe8a14cf10364, 0xa81a664bbc423001, 0xc24b8b70d0f89791, 0xc76c51a30654be30, 0xd192e819d6ef5218,
+    0xd69906245565a910, 0xf40e35855771202a, 0x106aa07032bbd1b8, 0x19a4c116b8d2d0c8, 0x1e376c085141ab53,
+    0x2748774cdf8eeb99, 0x34b0bcb5e19b48a8, 0x391c0cb3c5c95a63, 0x4ed8aa4ae3418acb, 0x5b9cca4f7763e373,
+    0x682e6ff3d6b2b8a3, 0x748f82ee5defb2fc, 0x78a5636f43172f60, 0x84c87814a1f0ab72, 0x8cc702081a6439ec,
+    0x90befffa23631e28, 0xa4506cebde82bde9, 0xbef9a3f7b2c67915, 0xc67178f2e372532b, 0xca273eceea26619c,
+    0xd186b8c721c0c207, 0xeada7dd6cde0eb1e, 0xf57d4f7fee6ed178, 0x06f067aa72176fba, 0x0a637dc5a2c898a6,
+    0x113f9804bef90dae, 0x1b710b35131c471b, 0x28db77f523047d84, 0x32caab7b40c72493, 0x3c9ebe0a15c9bebc,
+    0x431d67c49c100d4c, 0x4cc5d4becb3e42b6, 0x597f299cfc657e2a, 0x5fcb6fab3ad6faec, 0x6c44198c4a475817,
+];
+
+/// Instruction set the batched PBKDF2 runs on, each lane hashing a
+/// different password.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    /// AVX-512F: eight lanes, with a native 64-bit rotate
+    Avx512,
+    /// AVX2: four lanes
+    Avx2,
+    /// NEON: two lanes
+    Neon,
+    /// One lane of plain 64-bit arithmetic
+    Scalar,
+}
+
+impl Backend {
+    /// The widest backend this CPU supports.
+    pub fn detect() -> Self {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("avx512f") {
+                return Backend::Avx512;
+            }
+            if is_x86_feature_detected!("avx2") {
+                return Backend::Avx2;
+            }
+        }
+        #[cfg(target_arch = "aarch64")]
+        {
+            if std::arch::is_aarch64_feature_detected!("neon") {
+                return Backend::Neon;
+            }
+        }
+        Backend::Scalar
+    }
+
+    /// Passwords hashed at once.
+    pub fn lanes(self) -> usize {
+        match self {
+            Backend::Avx512 => 8,
+            Backend::Avx2 => 4,
+            Backend::Neon => 2,
+            Backend::Scalar => 1,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Backend::Avx512 => "AVX-512",
+            Backend::Avx2 => "AVX2",
+            Backend::Neon => "NEON",
+            Backend::Scalar => "scalar",
+        }
+    }
+}
+
+/// PBKDF2-HMAC-SHA512 with BIP-39's 2048 rounds of each `(password, salt)`,
+/// a backend's worth of lanes at a time. Only the first round, which
+/// depends on the salt, is hashed one password at a time; the other 2047
+/// are two compressions per lane from the precomputed HMAC pads.
+pub fn derive(backend: Backend, inputs: &[(&[u8], &[u8])]) -> Vec<Seed> {
+    let mut seeds = Vec::with_capacity(inputs.len());
+    for group in inputs.chunks(backend.lanes()) {
+        // A short last group repeats its first password in the spare lanes
+        let mut inner = [[0u64; 8]; MAX_LANES];
+        let mut outer = [[0u64; 8]; MAX_LANES];
+        let mut block = [[0u64; 8]; MAX_LANES];
+        for lane in 0..backend.lanes() {
+            let (password, salt) = group.get(lane).unwrap_or(&group[0]);
+            (inner[lane], outer[lane]) = pads(password);
+            block[lane] = first_round(&inner[lane], &outer[lane], salt);
+        }
+        let mut sum = block;
+        let lanes = backend.lanes();
+        // SAFETY: each backend is only chosen once `detect` found its features
+        unsafe {
+            match backend {
+                #[cfg(target_arch = "x86_64")]
+                Backend::Avx512 => x86::rounds_avx512(&inner, &outer, &mut block, &mut sum),
+                #[cfg(target_arch = "x86_64")]
+                Backend::Avx2 => x86::rounds_avx2(&inner, &outer, &mut block, &mut sum),
+                #[cfg(target_arch = "aarch64")]
+                Backend::Neon => arm::rounds_neon(&inner, &outer, &mut block, &mut sum),
+                _ => {
+                    for lane in 0..lanes {
+                        let one = lane..lane + 1;
+                        rounds::<u64>(&inner[one.clone()], &outer[one.clone()], &mut block[one.clone()], &mut sum[one]);
+                    }
+                }
+            }
+        }
+        for words in &sum[..group.len()] {
+            let mut seed = [0u8; 64];
+            for (bytes, word) in seed.chunks_exact_mut(8).zip(words) {
+                bytes.copy_from_slice(&word.to_be_bytes());
+            }
+            seeds.push(seed);
+        }
+    }
+    seeds
+}
+
+/// Seeds computed a batch of candidates at a time on the CPU's vector
+/// units, the way [`crate::gpu::Gpu`] computes them on a device.
+/// Passphrases are taken from the [`Passphrases`] iterator a lane-sized
+/// group at a time, so a long list is never expanded in full.
+pub struct Pbkdf2Batch<'a> {
+    backend: Backend,
+    passphrases: &'a Passphrases,
+}
+
+impl<'a> Pbkdf2Batch<'a> {
+    /// `passphrases` are tried with every candidate, and seeds come back in
+    /// their order.
+    pub fn new(backend: Backend, passphrases: &'a Passphrases) -> Self {
+        Self { backend, passphrases }
+    }
+
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    /// Candidates that fill the lanes once each of their passphrases takes one.
+    pub fn candidates_per_batch(&self) -> usize {
+        (self.backend.lanes() as u64).div_ceil(self.passphrases.count().max(1)).max(1) as usize
+    }
+
+    /// Whether the whole mnemonic `fixed` + `perm` is on the wordlist and
+    /// passes its checksum, so it needs seeds.
+    pub fn takes(&self, fixed: &[String], perm: &[String]) -> bool {
+        mnemonic(fixed, perm).is_some()
+    }
+
+    /// Seeds for each candidate of `batch` (the words after `fixed`), one per
+    /// passphrase. The first lane group of each candidate is derived here
+    /// for the whole batch at once, and the rest as the stream reaches them.
+    /// `None` leaves a candidate to the usual checks, which reject it: a
+    /// word is off the list or the checksum fails.
+    pub fn seeds(&self, fixed: &[String], batch: &[Option<Vec<String>>]) -> Vec<Option<SeedStream<'a>>> {
+        let mut streams: Vec<Option<SeedStream>> = batch
+            .iter()
+            .map(|perm| perm.as_deref().and_then(|perm| mnemonic(fixed, perm)).map(|mnemonic| self.stream(mnemonic, 0)))
+            .collect();
+        let salts: Vec<Vec<Vec<u8>>> = streams
+            .iter_mut()
+            .flatten()
+            .map(|stream| stream.passphrases.by_ref().take(self.backend.lanes()).map(|passphrase| salt(&passphrase)).collect())
+            .collect();
+        let inputs: Vec<(&[u8], &[u8])> = streams
+            .iter()
+            .flatten()
+            .zip(&salts)
+            .flat_map(|(stream, salts)| salts.iter().map(|salt| (stream.mnemonic.as_bytes(), salt.as_slice())))
+            .collect();
+        let seeds = Wiped(derive(self.backend, &inputs));
+        let mut seeds = seeds.iter();
+        for (stream, salts) in streams.iter_mut().flatten().zip(&salts) {
+            stream.ready.extend(seeds.by_ref().take(salts.len()).copied());
+        }
+        streams
+    }
+
+    /// The rest of the seeds of `fixed` + `perm` after `seeds`, which a
+    /// device computed for its first passphrases.
+    pub fn resume(&self, fixed: &[String], perm: &[String], seeds: Vec<Seed>) -> Option<SeedStream<'a>> {
+        let mut stream = self.stream(mnemonic(fixed, perm)?, seeds.len());
+        *stream.ready = seeds;
+        Some(stream)
+    }
+
+    fn stream(&self, mnemonic: String, skip: usize) -> SeedStream<'a> {
+        SeedStream {
+            backend: self.backend,
+            mnemonic: Wiped(mnemonic),
+            passphrases: Box::new(self.passphrases.candidates().skip(skip)),
+            ready: Wiped(Vec::new()),
+            next: 0,
+        }
+    }
+}
+
+/// One candidate's seeds, one per passphrase in order, from a
+/// [`Pbkdf2Batch`]. The next lane group of passphrases is derived when the
+/// last one runs out.
+pub struct SeedStream<'a> {
+    backend: Backend,
+    mnemonic: Wiped<String>,
+    passphrases: Box<dyn Iterator<Item = String> + 'a>,
+    ready: Wiped<Vec<Seed>>,
+    next: usize,
+}
+
+impl Iterator for SeedStream<'_> {
+    type Item = Seed;
+
+    fn next(&mut self) -> Option<Seed> {
+        if self.next == self.ready.len() {
+            let salts: Vec<Vec<u8>> =
+                self.passphrases.by_ref().take(self.backend.lanes()).map(|passphrase| salt(&passphrase)).collect();
+            let inputs: Vec<(&[u8], &[u8])> =
+                salts.iter().map(|salt| (self.mnemonic.as_bytes(), salt.as_slice())).collect();
+            self.ready.wipe();
+            *self.ready = derive(self.backend, &inputs);
+            self.next = 0;
+        }
+        let seed = self.ready.get(self.next).copied();
+        self.next += 1;
+        seed
+    }
+}
+
+/// BIP-39's PBKDF2 salt for `passphrase`.
+fn salt(passphrase: &str) -> Vec<u8> {
+    [b"mnemonic", passphrase.as_bytes()].concat()
+}
+
+/// The phrase of a candidate whose words are all on the list and whose
+/// checksum holds; other lengths are left to the usual checks too.
+fn mnemonic(fixed: &[String], perm: &[String]) -> Option<String> {
+    let words: Vec<&String> = fixed.iter().chain(perm).collect();
+    let indices = entropy::indices(&words)?;
+    if entropy::checksum_bits(indices.len()).is_none() || !entropy::is_valid(&indices) {
+        return None;
+    }
+    Some(words.iter().map(|word| word.as_str()).collect::<Vec<_>>().join(" "))
+}
+
+/// SHA-512 states after the HMAC inner and outer pads of `password`.
+fn pads(password: &[u8]) -> ([u64; 8], [u64; 8]) {
+    let hashed;
+    let key = if password.len() > BLOCK {
+        hashed = sha512::Hash::hash(password).to_byte_array();
+        &hashed[..]
+    } else {
+        password
+    };
+    let mut inner = [0x36u8; BLOCK];
+    let mut outer = [0x5cu8; BLOCK];
+    for (i, byte) in key.iter().enumerate() {
+        inner[i] ^= byte;
+        outer[i] ^= byte;
+    }
+    let mut inner_state = IV;
+    let mut outer_state = IV;
+    compress_bytes(&mut inner_state, &inner);
+    compress_bytes(&mut outer_state, &outer);
+    (inner_state, outer_state)
+}
+
+/// U1 = HMAC(password, salt || 1), from the pad states.
+fn first_round(inner: &[u64; 8], outer: &[u64; 8], salt: &[u8]) -> [u64; 8] {
+    let mut message = salt.to_vec();
+    message.extend_from_slice(&1u32.to_be_bytes());
+    let inner_hash = finish(*inner, &message);
+    let inner_bytes: Vec<u8> = inner_hash.iter().flat_map(|word| word.to_be_bytes()).collect();
+    finish(*outer, &inner_bytes)
+}
+
+/// SHA-512 of a pad block followed by `message`, from the state after the pad.
+fn finish(mut state: [u64; 8], message: &[u8]) -> [u64; 8] {
+    let bits = ((BLOCK + message.len()) as u128) * 8;
+    let mut padded = message.to_vec();
+    padded.push(0x80);
+    while padded.len() % BLOCK != BLOCK - 16 {
+        padded.push(0);
+    }
+    padded.extend_from_slice(&bits.to_be_bytes());
+    for block in padded.chunks_exact(BLOCK) {
+        compress_bytes(&mut state, block.try_into().expect("128-byte block"));
+    }
+    state
+}
+
+fn compress_bytes(state: &mut [u64; 8], block: &[u8; BLOCK]) {
+    let words: [u64; 16] =
+        std::array::from_fn(|i| u64::from_be_bytes(block[i * 8..i * 8 + 8].try_into().expect("8 bytes")));
+    // SAFETY: the scalar lanes are plain integer arithmetic
+    unsafe { compress::<u64>(state, &words) }
+}
+
+/// A vector of 64-bit lanes. The methods are unsafe because the vector
+/// backends need their CPU features, which the caller has checked.
+trait Lanes: Copy {
+    unsafe fn splat(value: u64) -> Self;
+    unsafe fn load(lanes: &[u64; MAX_LANES]) -> Self;
+    unsafe fn store(self, lanes: &mut [u64; MAX_LANES]);
+    unsafe fn add(self, other: Self) -> Self;
+    unsafe fn xor(self, other: Self) -> Self;
+    unsafe fn and(self, other: Self) -> Self;
+    /// `!self & other`
+    unsafe fn andnot(self, other: Self) -> Self;
+    unsafe fn rotr(self, bits: u32) -> Self;
+    unsafe fn shr(self, bits: u32) -> Self;
+}
+
+impl Lanes for u64 {
+    #[inline(always)]
+    unsafe fn splat(value: u64) -> Self {
+        value
+    }
+    #[inline(always)]
+    unsafe fn load(lanes: &[u64; MAX_LANES]) -> Self {
+        lanes[0]
+    }
+    #[inline(always)]
+    unsafe fn store(self, lanes: &mut [u64; MAX_LANES]) {
+        lanes[0] = self;
+    }
+    #[inline(always)]
+    unsafe fn add(self, other: Self) -> Self {
+        self.wrapping_add(other)
+    }
+    #[inline(always)]
+    unsafe fn xor(self, other: Self) -> Self {
+        self ^ other
+    }
+    #[inline(always)]
+    unsafe fn and(self, other: Self) -> Self {
+        self & other
+    }
+    #[inline(always)]
+    unsafe fn andnot(self, other: Self) -> Self {
+        !self & other
+    }
+    #[inline(always)]
+    unsafe fn rotr(self, bits: u32) -> Self {
+        self.rotate_right(bits)
+    }
+    #[inline(always)]
+    unsafe fn shr(self, bits: u32) -> Self {
+        self >> bits
+    }
+}
+
+/// One SHA-512 compression of `block` into `state`, in every lane.
+#[inline(always)]
+unsafe fn compress<V: Lanes>(state: &mut [V; 8], block: &[V; 16]) {
+    let mut w = *block;
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+    for (i, &k) in K.iter().enumerate() {
+        if i >= 16 {
+            let w15 = w[(i + 1) & 15];
+            let w2 = w[(i + 14) & 15];
+            let s0 = w15.rotr(1).xor(w15.rotr(8)).xor(w15.shr(7));
+            let s1 = w2.rotr(19).xor(w2.rotr(61)).xor(w2.shr(6));
+            w[i & 15] = w[i & 15].add(s0).add(w[(i + 9) & 15]).add(s1);
+        }
+        let s1 = e.rotr(14).xor(e.rotr(18)).xor(e.rotr(41));
+        let ch = e.and(f).xor(e.andnot(g));
+        let t1 = h.add(s1).add(ch).add(V::splat(k)).add(w[i & 15]);
+        let s0 = a.rotr(28).xor(a.rotr(34)).xor(a.rotr(39));
+        let maj = a.and(b).xor(a.and(c)).xor(b.and(c));
+        let t2 = s0.add(maj);
+        h = g;
+        g = f;
+        f = e;
+        e = d.add(t1);
+        d = c;
+        c = b;
+        b = a;
+        a = t1.add(t2);
+    }
+    for (word, value) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+        *word = word.add(value);
+    }
+}
+
+/// Rounds 2 to 2048 in every lane: `block` holds U1 and `sum` their XOR
+/// so far, one row per lane.
+#[inline(always)]
+unsafe fn rounds<V: Lanes>(inner: &[[u64; 8]], outer: &[[u64; 8]], block: &mut [[u64; 8]], sum: &mut [[u64; 8]]) {
+    let gather = |rows: &[[u64; 8]], word: usize| {
+        let mut lanes = [0u64; MAX_LANES];
+        for (lane, row) in lanes.iter_mut().zip(rows) {
+            *lane = row[word];
+        }
+        lanes
+    };
+    let mut inner_state = [V::splat(0); 8];
+    let mut outer_state = [V::splat(0); 8];
+    let mut u = [V::splat(0); 8];
+    let mut t = [V::splat(0); 8];
+    for word in 0..8 {
+        inner_state[word] = V::load(&gather(inner, word));
+        outer_state[word] = V::load(&gather(outer, word));
+        u[word] = V::load(&gather(block, word));
+        t[word] = V::load(&gather(sum, word));
+    }
+    // Each HMAC message is 64 bytes after a 128-byte pad block, so one padded
+    // block: the message, the 0x80 marker, and the 1536-bit length
+    let mut padded = [V::splat(0); 16];
+    padded[8] = V::splat(0x8000_0000_0000_0000);
+    padded[15] = V::splat(((BLOCK + 64) * 8) as u64);
+    for _ in 1..ROUNDS {
+        padded[..8].copy_from_slice(&u);
+        let mut state = inner_state;
+        compress(&mut state, &padded);
+        padded[..8].copy_from_slice(&state);
+        u = outer_state;
+        compress(&mut u, &padded);
+        for word in 0..8 {
+            t[word] = t[word].xor(u[word]);
+        }
+    }
+    for word in 0..8 {
+        let mut lanes = [0u64; MAX_LANES];
+        u[word].store(&mut lanes);
+        for (row, lane) in block.iter_mut().zip(lanes) {
+            row[word] = lane;
+        }
+        t[word].store(&mut lanes);
+        for (row, lane) in sum.iter_mut().zip(lanes) {
+            row[word] = lane;
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use std::arch::x86_64::*;
+
+    use super::{rounds, Lanes, MAX_LANES};
+
+    #[derive(Clone, Copy)]
+    pub struct Avx2(__m256i);
+
+    impl Lanes for Avx2 {
+        #[inline]
+        #[target_feature(enable = "avx2")]
+        unsafe fn splat(value: u64) -> Self {
+            Avx2(_mm256_set1_epi64x(value as i64))
+        }
+        #[inline]
+        #[target_feature(enable = "avx2")]
+        unsafe fn load(lanes: &[u64; MAX_LANES]) -> Self {
+            Avx2(_mm256_loadu_si256(lanes.as_ptr().cast()))
+        }
+        #[inline]
+        #[target_feature(enable = "avx2")]
+        unsafe fn store(self, lanes: &mut [u64; MAX_LANES]) {
+            _mm256_storeu_si256(lanes.as_mut_ptr().cast(), self.0)
+        }
+        #[inline]
+        #[target_feature(enable = "avx2")]
+        unsafe fn add(self, other: Self) -> Self {
+            Avx2(_mm256_add_epi64(self.0, other.0))
+        }
+        #[inline]
+        #[target_feature(enable = "avx2")]
+        unsafe fn xor(self, other: Self) -> Self {
+            Avx2(_mm256_xor_si256(self.0, other.0))
+        }
+        #[inline]
+        #[target_feature(enable = "avx2")]
+        unsafe fn and(self, other: Self) -> Self {
+            Avx2(_mm256_and_si256(self.0, other.0))
+        }
+        #[inline]
+        #[target_feature(enable = "avx2")]
+        unsafe fn andnot(self, other: Self) -> Self {
+            Avx2(_mm256_andnot_si256(self.0, other.0))
+        }
+        #[inline]
+        #[target_feature(enable = "avx2")]
+        unsafe fn rotr(self, bits: u32) -> Self {
+            let right = _mm256_srl_epi64(self.0, _mm_cvtsi32_si128(bits as i32));
+            let left = _mm256_sll_epi64(self.0, _mm_cvtsi32_si128(64 - bits as i32));
+            Avx2(_mm256_or_si256(right, left))
+        }
+        #[inline]
+        #[target_feature(enable = "avx2")]
+        unsafe fn shr(self, bits: u32) -> Self {
+            Avx2(_mm256_srl_epi64(self.0, _mm_cvtsi32_si128(bits as i32)))
+        }
+    }
+
+    #[derive(Clone, Copy)]
+    pub struct Avx512(__m512i);
+
+    impl Lanes for Avx512 {
+        #[inline]
+        #[target_feature(enable = "avx512f")]
+        unsafe fn splat(value: u64) -> Self {
+            Avx512(_mm512_set1_epi64(value as i64))
+        }
+        #[inline]
+        #[target_feature(enable = "avx512f")]
+        unsafe fn load(lanes: &[u64; MAX_LANES]) -> Self {
+            Avx512(_mm512_loadu_si512(lanes.as_ptr().cast()))
+        }
+        #[inline]
+        #[target_feature(enable = "avx512f")]
+        unsafe fn store(self, lanes: &mut [u64; MAX_LANES]) {
+            _mm512_storeu_si512(lanes.as_mut_ptr().cast(), self.0)
+        }
+        #[inline]
+        #[target_feature(enable = "avx512f")]
+        unsafe fn add(self, other: Self) -> Self {
+            Avx512(_mm512_add_epi64(self.0, other.0))
+        }
+        #[inline]
+        #[target_feature(enable = "avx512f")]
+        unsafe fn xor(self, other: Self) -> Self {
+            Avx512(_mm512_xor_si512(self.0, other.0))
+        }
+        #[inline]
+        #[target_feature(enable = "avx512f")]
+        unsafe fn and(self, other: Self) -> Self {
+            Avx512(_mm512_and_si512(self.0, other.0))
+        }
+        #[inline]
+        #[target_feature(enable = "avx512f")]
+        unsafe fn andnot(self, other: Self) -> Self {
+            Avx512(_mm512_andnot_si512(self.0, other.0))
+        }
+        #[inline]
+        #[target_feature(enable = "avx512f")]
+        unsafe fn rotr(self, bits: u32) -> Self {
+            Avx512(_mm512_rorv_epi64(self.0, _mm512_set1_epi64(i64::from(bits))))
+        }
+        #[inline]
+        #[target_feature(enable = "avx512f")]
+        unsafe fn shr(self, bits: u32) -> Self {
+            Avx512(_mm512_srl_epi64(self.0, _mm_cvtsi32_si128(bits as i32)))
+        }
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn rounds_avx2(
+        inner: &[[u64; 8]; MAX_LANES],
+        outer: &[[u64; 8]; MAX_LANES],
+        block: &mut [[u64; 8]; MAX_LANES],
+        sum: &mut [[u64; 8]; MAX_LANES],
+    ) {
+        rounds::<Avx2>(&inner[..4], &outer[..4], &mut block[..4], &mut sum[..4])
+    }
+
+    #[target_feature(enable = "avx512f")]
+    pub unsafe fn rounds_avx512(
+        inner: &[[u64; 8]; MAX_LANES],
+        outer: &[[u64; 8]; MAX_LANES],
+        block: &mut [[u64; 8]; MAX_LANES],
+        sum: &mut [[u64; 8]; MAX_LANES],
+    ) {
+        rounds::<Avx512>(inner, outer, block, sum)
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+mod arm {
+    use std::arch::aarch64::*;
+
+    use super::{rounds, Lanes, MAX_LANES};
+
+    #[derive(Clone, Copy)]
+    pub struct Neon(uint64x2_t);
+
+    impl Lanes for Neon {
+        #[inline]
+        #[target_feature(enable = "neon")]
+        unsafe fn splat(value: u64) -> Self {
+            Neon(vdupq_n_u64(value))
+        }
+        #[inline]
+        #[target_feature(enable = "neon")]
+        unsafe fn load(lanes: &[u64; MAX_LANES]) -> Self {
+            Neon(vld1q_u64(lanes.as_ptr()))
+        }
+        #[inline]
+        #[target_feature(enable = "neon")]
+        unsafe fn store(self, lanes: &mut [u64; MAX_LANES]) {
+            vst1q_u64(lanes.as_mut_ptr(), self.0)
+        }
+        #[inline]
+        #[target_feature(enable = "neon")]
+        unsafe fn add(self, other: Self) -> Self {
+            Neon(vaddq_u64(self.0, other.0))
+        }
+        #[inline]
+        #[target_feature(enable = "neon")]
+        unsafe fn xor(self, other: Self) -> Self {
+            Neon(veorq_u64(self.0, other.0))
+        }
+        #[inline]
+        #[target_feature(enable = "neon")]
+        unsafe fn and(self, other: Self) -> Self {
+            Neon(vandq_u64(self.0, other.0))
+        }
+        #[inline]
+        #[target_feature(enable = "neon")]
+        unsafe fn andnot(self, other: Self) -> Self {
+            // BIC clears the bits of its first operand set in the second
+            Neon(vbicq_u64(other.0, self.0))
+        }
+        #[inline]
+        #[target_feature(enable = "neon")]
+        unsafe fn rotr(self, bits: u32) -> Self {
+            // A negative count shifts right
+            let right = vshlq_u64(self.0, vdupq_n_s64(-i64::from(bits)));
+            let left = vshlq_u64(self.0, vdupq_n_s64(64 - i64::from(bits)));
+            Neon(vorrq_u64(right, left))
+        }
+        #[inline]
+        #[target_feature(enable = "neon")]
+        unsafe fn shr(self, bits: u32) -> Self {
+            Neon(vshlq_u64(self.0, vdupq_n_s64(-i64::from(bits))))
+        }
+    }
+
+    #[target_feature(enable = "neon")]
+    pub unsafe fn rounds_neon(
+        inner: &[[u64; 8]; MAX_LANES],
+        outer: &[[u64; 8]; MAX_LANES],
+        block: &mut [[u64; 8]; MAX_LANES],
+        sum: &mut [[u64; 8]; MAX_LANES],
+    ) {
+        rounds::<Neon>(&inner[..2], &outer[..2], &mut block[..2], &mut sum[..2])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bip39::Mnemonic;
+    use unicode_normalization::UnicodeNormalization;
+
+    use super::*;
+
+    /// Every backend this CPU can run; the others can't be checked here.
+    fn backends() -> Vec<Backend> {
+        let mut backends = vec![Backend::Scalar];
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("avx2") {
+                backends.push(Backend::Avx2);
+            }
+            if is_x86_feature_detected!("avx512f") {
+                backends.push(Backend::Avx512);
+            }
+        }
+        #[cfg(target_arch = "aarch64")]
+        {
+            if std::arch::is_aarch64_feature_detected!("neon") {
+                backends.push(Backend::Neon);
+            }
+        }
+        backends
+    }
+
+    /// Mnemonics of every length, so passwords both fit in a block and
+    /// are hashed down to one first.
+    fn mnemonics(count: usize) -> Vec<Mnemonic> {
+        (0..count)
+            .map(|i| {
+                let len = entropy::entropy_len(entropy::WORD_COUNTS[i % entropy::WORD_COUNTS.len()]).unwrap();
+                let entropy: Vec<u8> = (0..len).map(|j| (i * 31 + j * 7) as u8).collect();
+                Mnemonic::from_entropy(&entropy).unwrap()
+            })
+            .collect()
+    }
+
+    fn check(backend: Backend, mnemonics: &[Mnemonic], passphrase: &str) {
+        let phrases: Vec<String> = mnemonics.iter().map(|mnemonic| mnemonic.to_string()).collect();
+        // Passphrases reach the salt normalized, as BIP-39 hashes them
+        let salt = salt(&passphrase.nfkd().collect::<String>());
+        let inputs: Vec<(&[u8], &[u8])> = phrases.iter().map(|phrase| (phrase.as_bytes(), salt.as_slice())).collect();
+        let seeds = derive(backend, &inputs);
+        assert_eq!(seeds.len(), mnemonics.len());
+        for (mnemonic, seed) in mnemonics.iter().zip(&seeds) {
+            assert_eq!(*seed, mnemonic.to_seed(passphrase), "{} with {:?} on {}", mnemonic, passphrase, backend.name());
+        }
+    }
+
+    #[test]
+    fn every_backend_matches_bip39() {
+        for backend in backends() {
+            check(backend, &mnemonics(2 * backend.lanes()), "");
+            check(backend, &mnemonics(3), "TREZOR");
+        }
+    }
+
+    #[test]
+    fn short_last_groups() {
+        for backend in backends() {
+            for count in [1, backend.lanes().saturating_sub(1).max(1), backend.lanes() + 1] {
+                check(backend, &mnemonics(count), "");
+            }
+        }
+        assert!(derive(Backend::Scalar, &[]).is_empty());
+    }
+
+    /// Salts long enough that the first round's message spans more than
+    /// one SHA-512 block: a 99-byte passphrase is the longest whose salt,
+    /// counter, and padding still fit in one.
+    #[test]
+    fn multi_block_salts() {
+        for backend in backends() {
+            for len in [99, 100, 300] {
+                check(backend, &mnemonics(2), &"p".repeat(len));
+            }
+            check(backend, &mnemonics(2), &"ünïcödé ".repeat(20));
+        }
+    }
+}
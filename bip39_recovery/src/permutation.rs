@@ -0,0 +1,201 @@
+/// Number of orderings of `n` words, saturating at `u64::MAX` for spaces that
+/// could never be exhausted anyway.
+pub fn factorial(n: usize) -> u64 {
+    let mut result: u64 = 1;
+    for i in 1..=n {
+        result = result.saturating_mul(i as u64);
+    }
+    result
+}
+
+/// Position indices of the `rank`-th permutation of `n` items in
+/// lexicographic order, using the factorial number system. Ranks beyond the
+/// space wrap, so callers are expected to bound them by `factorial(n)`.
+pub fn unrank(n: usize, mut rank: u64) -> Vec<usize> {
+    // Lehmer code: digit i counts the unused items smaller than the one at i
+    let mut code = vec![0usize; n];
+    for k in 0..n {
+        let radix = (k + 1) as u64;
+        code[n - 1 - k] = (rank % radix) as usize;
+        rank /= radix;
+    }
+    let mut remaining: Vec<usize> = (0..n).collect();
+    code.into_iter().map(|digit| remaining.remove(digit)).collect()
+}
+
+/// Advances `indices` to the next lexicographic permutation, returning false
+/// once the last one has been reached.
+fn next_permutation(indices: &mut [usize]) -> bool {
+    let n = indices.len();
+    if n < 2 {
+        return false;
+    }
+    let mut i = n - 1;
+    while i > 0 && indices[i - 1] >= indices[i] {
+        i -= 1;
+    }
+    if i == 0 {
+        return false;
+    }
+    let mut j = n - 1;
+    while indices[j] <= indices[i - 1] {
+        j -= 1;
+    }
+    indices.swap(i - 1, j);
+    indices[i..].reverse();
+    true
+}
+
+/// Lazily yields the permutations of `items` with ranks in `[start, end)`,
+/// in the same order as `itertools::permutations`, without generating the
+/// ones before `start`.
+pub struct PermutationRange<T> {
+    items: Vec<T>,
+    indices: Vec<usize>,
+    rank: u64,
+    end: u64,
+    // For each item, the index of an earlier equal item, when deduplicating
+    earlier_duplicate: Option<Vec<Option<usize>>>,
+}
+
+impl<T: Clone> PermutationRange<T> {
+    pub fn new(items: Vec<T>, start: u64, end: u64) -> Self {
+        let end = end.min(factorial(items.len()));
+        let indices = unrank(items.len(), start);
+        Self { items, indices, rank: start, end, earlier_duplicate: None }
+    }
+
+    /// Whether the current arrangement is the first of those that only
+    /// differ by swapping equal items: equal items must keep their order.
+    fn is_canonical(&self) -> bool {
+        let Some(earlier) = &self.earlier_duplicate else {
+            return true;
+        };
+        let mut position = vec![0; self.indices.len()];
+        for (pos, &item) in self.indices.iter().enumerate() {
+            position[item] = pos;
+        }
+        earlier
+            .iter()
+            .enumerate()
+            .all(|(item, prev)| prev.is_none_or(|prev| position[prev] < position[item]))
+    }
+}
+
+impl<T: Clone + PartialEq> PermutationRange<T> {
+    /// Skips arrangements that repeat an earlier one because the input holds
+    /// the same word more than once. Skipped ranks are still consumed.
+    pub fn skip_duplicates(mut self) -> Self {
+        let earlier: Vec<Option<usize>> = (0..self.items.len())
+            .map(|i| (0..i).rev().find(|&j| self.items[j] == self.items[i]))
+            .collect();
+        if earlier.iter().any(Option::is_some) {
+            self.earlier_duplicate = Some(earlier);
+        }
+        self
+    }
+}
+
+impl<T: Clone> Iterator for PermutationRange<T> {
+    type Item = (u64, Vec<T>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.rank < self.end {
+            let canonical = self.is_canonical();
+            let perm = canonical.then(|| self.indices.iter().map(|&i| self.items[i].clone()).collect());
+            let rank = self.rank;
+            self.rank += 1;
+            if !next_permutation(&mut self.indices) {
+                self.end = self.rank;
+            }
+            if let Some(perm) = perm {
+                return Some((rank, perm));
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.end.saturating_sub(self.rank);
+        let remaining = usize::try_from(remaining).unwrap_or(usize::MAX);
+        let lower = if self.earlier_duplicate.is_some() { 0 } else { remaining };
+        (lower, Some(remaining))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use itertools::Itertools;
+
+    use super::*;
+
+    #[test]
+    fn unrank_counts_in_lexicographic_order() {
+        for n in 0..=6 {
+            let mut next: Vec<usize> = (0..n).collect();
+            let mut seen = HashSet::new();
+            for rank in 0..factorial(n) {
+                let perm = unrank(n, rank);
+                assert_eq!(perm, next, "rank {} of {}!", rank, n);
+                assert!(seen.insert(perm.clone()), "rank {} of {}! repeats", rank, n);
+                // Ordered as the position lists compare
+                if rank > 0 {
+                    assert!(unrank(n, rank - 1) < perm);
+                }
+                let advanced = next_permutation(&mut next);
+                assert_eq!(advanced, rank + 1 < factorial(n), "rank {} of {}!", rank, n);
+            }
+            assert_eq!(seen.len() as u64, factorial(n));
+        }
+    }
+
+    #[test]
+    fn factorial_saturates() {
+        assert_eq!((factorial(0), factorial(1), factorial(12)), (1, 1, 479_001_600));
+        assert_eq!(factorial(20), 2_432_902_008_176_640_000);
+        assert_eq!(factorial(21), u64::MAX);
+    }
+
+    /// Each distinct arrangement of a list with repeated words comes once,
+    /// at the rank of its first occurrence.
+    #[test]
+    fn skip_duplicates_yields_each_arrangement_once() {
+        let words = vec!["a", "b", "a", "c", "b"];
+        let all: Vec<(u64, Vec<&str>)> = PermutationRange::new(words.clone(), 0, u64::MAX).collect();
+        let mut first = Vec::new();
+        let mut seen = HashSet::new();
+        for (rank, perm) in &all {
+            if seen.insert(perm.clone()) {
+                first.push((*rank, perm.clone()));
+            }
+        }
+        let deduplicated: Vec<(u64, Vec<&str>)> =
+            PermutationRange::new(words.clone(), 0, u64::MAX).skip_duplicates().collect();
+        // 5! / (2! * 2!)
+        assert_eq!(deduplicated.len(), 30);
+        assert_eq!(deduplicated, first);
+
+        // A range that starts mid-way keeps the same ranks
+        let tail: Vec<(u64, Vec<&str>)> = PermutationRange::new(words.clone(), 50, 100).skip_duplicates().collect();
+        let expected: Vec<(u64, Vec<&str>)> =
+            first.iter().filter(|(rank, _)| (50..100).contains(rank)).cloned().collect();
+        assert_eq!(tail, expected);
+
+        // Without repeats nothing is skipped
+        let distinct = PermutationRange::new(vec!["a", "b", "c"], 0, u64::MAX).skip_duplicates();
+        assert_eq!(distinct.count(), 6);
+    }
+
+    #[test]
+    fn resumes_at_the_saved_rank() {
+        let words = vec!["a", "b", "c", "d", "e", "f"];
+        let all: Vec<(u64, Vec<&str>)> =
+            words.iter().copied().permutations(words.len()).enumerate().map(|(rank, perm)| (rank as u64, perm)).collect();
+        for start in [0, 1, 119, 500, 719, 720, 1000] {
+            let resumed: Vec<(u64, Vec<&str>)> = PermutationRange::new(words.clone(), start, u64::MAX).collect();
+            assert_eq!(resumed, all[all.len().min(start as usize)..], "resumed at rank {}", start);
+        }
+    }
+}
@@ -0,0 +1,240 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc;
+use std::sync::Mutex;
+
+use crate::gpu::Seed;
+use crate::pbkdf2::Pbkdf2Batch;
+use crate::wipe::Wiped;
+
+/// Seeded batches a device may have waiting for the CPU threads before it
+/// blocks: one being matched and one ready, while it computes the next.
+pub const DEVICE_QUEUE: usize = 2;
+
+/// Candidates, each with its rank; `None` is a rank that's counted but has
+/// nothing to derive.
+pub type Ranked = Box<dyn Iterator<Item = (u64, Option<Vec<String>>)> + Send>;
+
+/// Consecutive candidates as they pass between stages.
+pub struct Batch {
+    pub ranks: Vec<u64>,
+    pub candidates: Vec<Option<Vec<String>>>,
+}
+
+impl Batch {
+    fn take(candidates: &mut impl Iterator<Item = (u64, Option<Vec<String>>)>, len: u64) -> Self {
+        let (ranks, candidates) = candidates.take(len as usize).unzip();
+        Self { ranks, candidates }
+    }
+
+    pub fn len(&self) -> usize {
+        self.ranks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranks.is_empty()
+    }
+
+    pub fn into_ranked(self) -> Ranked {
+        Box::new(self.ranks.into_iter().zip(self.candidates))
+    }
+}
+
+/// Seeds of a batch, one list per candidate (one seed per passphrase), or
+/// `None` for a candidate left to the CPU.
+pub type Seeds = Vec<Option<Vec<Seed>>>;
+
+/// The generate stage: hands out batches in rank order to whichever worker
+/// asks next.
+pub enum Feed<'a> {
+    /// A source that unranks any range. Workers take ranges off a shared
+    /// cursor, as [`chunks`](crate::chunks) does, and build their own
+    /// candidates.
+    Ranked {
+        next: AtomicU64,
+        end: u64,
+        build: Box<dyn Fn(u64, u64) -> Ranked + Sync + 'a>,
+    },
+    /// A source that only walks forward, shared behind a lock.
+    Sequential(Mutex<Ranked>),
+}
+
+impl<'a> Feed<'a> {
+    pub fn ranked(start: u64, end: u64, build: impl Fn(u64, u64) -> Ranked + Sync + 'a) -> Self {
+        Feed::Ranked { next: AtomicU64::new(start), end, build: Box::new(build) }
+    }
+
+    pub fn sequential(candidates: Ranked) -> Self {
+        Feed::Sequential(Mutex::new(candidates))
+    }
+
+    /// The next batch of up to `len` candidates, or `None` once the source
+    /// is exhausted. Ranks a previous run finished are already filtered
+    /// out, so a batch can be shorter, but never empty.
+    pub fn next(&self, len: u64) -> Option<Batch> {
+        let len = len.max(1);
+        match self {
+            Feed::Ranked { next, end, build } => loop {
+                let first = next.fetch_add(len, Ordering::Relaxed);
+                if first >= *end {
+                    return None;
+                }
+                let batch = Batch::take(&mut build(first, first.saturating_add(len).min(*end)), len);
+                if !batch.is_empty() {
+                    return Some(batch);
+                }
+            },
+            Feed::Sequential(candidates) => {
+                let batch = Batch::take(&mut *candidates.lock().unwrap(), len);
+                (!batch.is_empty()).then_some(batch)
+            }
+        }
+    }
+}
+
+/// A device's seed stage: the checksum filter and PBKDF2 for a whole batch
+/// at once. It runs on the calling thread, since device handles stay on the
+/// thread that opened them, and `None` retires it (the device failed).
+pub struct Device<'a> {
+    /// Candidates per batch the device takes.
+    pub batch: u64,
+    pub seeds: SeedStage<'a>,
+}
+
+pub type SeedStage<'a> = Box<dyn FnMut(&Batch) -> Option<Seeds> + 'a>;
+
+/// Runs the search as stages: `feed` generates batches, an optional
+/// `device` filters and seeds them, and the CPU threads of the current
+/// rayon pool match. `cpu` takes a batch with the device's seeds, or
+/// without them to filter, seed, and match itself.
+///
+/// The device sends seeded batches over a channel of [`DEVICE_QUEUE`]
+/// batches, so it blocks rather than running ahead when the CPU threads
+/// can't keep up. A CPU thread matches a seeded batch when one is waiting,
+/// and otherwise takes `cpu_batch` candidates of its own from the feed, so
+/// both kinds of worker share one run. A device that fails hands its batch
+/// to the CPU threads unseeded, and they finish the search. Workers stop
+/// taking batches once `stopped` returns true.
+pub fn run(
+    feed: &Feed,
+    cpu_batch: u64,
+    device: Option<Device>,
+    cpu: &(dyn Fn(Batch, Option<Seeds>) + Sync),
+    stopped: &(dyn Fn() -> bool + Sync),
+) {
+    let Some(mut device) = device else {
+        rayon::broadcast(|_| {
+            while let Some(batch) = feed.next(cpu_batch).filter(|_| !stopped()) {
+                cpu(batch, None);
+            }
+        });
+        return;
+    };
+    let (seeded, queue) = mpsc::sync_channel(DEVICE_QUEUE);
+    let queue = Mutex::new(queue);
+    std::thread::scope(|scope| {
+        // The CPU threads own the queue, so once they stop it closes and a
+        // device blocked on a full queue gets an error rather than waiting
+        scope.spawn(move || {
+            let queue = queue;
+            rayon::broadcast(|_| loop {
+                if stopped() {
+                    return;
+                }
+                // The queue's lock is released before matching
+                let waiting = queue.lock().unwrap().try_recv();
+                if let Ok((batch, seeds)) = waiting {
+                    cpu(batch, seeds);
+                    continue;
+                }
+                if let Some(batch) = feed.next(cpu_batch) {
+                    cpu(batch, None);
+                    continue;
+                }
+                // Nothing left to generate: wait on the device's last batches
+                let waiting = queue.lock().unwrap().recv();
+                match waiting {
+                    Ok((batch, seeds)) => cpu(batch, seeds),
+                    Err(_) => return,
+                }
+            });
+        });
+        while let Some(batch) = feed.next(device.batch).filter(|_| !stopped()) {
+            let seeds = (device.seeds)(&batch);
+            let failed = seeds.is_none();
+            if seeded.send((batch, seeds)).is_err() || failed {
+                break;
+            }
+        }
+        drop(seeded);
+    });
+}
+
+/// Checks one candidate and its rank, with its seeds when they were computed.
+pub type Check<'a> = dyn Fn((u64, Option<Vec<String>>), Option<&mut dyn Iterator<Item = Seed>>) + 'a;
+
+/// The CPU stage of [`run`], shared by the CLI and
+/// [`RecoveryEngine`](crate::RecoveryEngine): `check` each candidate of
+/// `batch` in rank order. A batch the device seeded only needs matching. Any
+/// other gets its seeds a batch at a time on `simd`, when the CPU has vector
+/// units for it: candidates are gathered until enough pass the checksum to
+/// fill the lanes, and the rest get no seeds and are rejected as usual.
+/// `fixed` are the words in front of every candidate.
+pub fn check_batch(
+    batch: Batch,
+    seeds: Option<Seeds>,
+    simd: Option<&Pbkdf2Batch>,
+    fixed: &[String],
+    stopped: &dyn Fn() -> bool,
+    check: &Check,
+) {
+    if let Some(seeds) = seeds {
+        // The device seeds the first passphrases, and any others stream on
+        // the vector units as they are reached
+        let mut seeds = Wiped(seeds);
+        for ((rank, perm), seeds) in batch.ranks.into_iter().zip(batch.candidates).zip(seeds.iter_mut()) {
+            if stopped() {
+                break;
+            }
+            let seeds = Wiped(seeds.take());
+            let mut stream = simd.zip(perm.as_deref()).zip(seeds.as_ref()).and_then(|((simd, perm), seeds)| {
+                simd.resume(fixed, perm, seeds.clone())
+            });
+            let mut seeded = seeds.iter().flatten().copied();
+            let seeds: Option<&mut dyn Iterator<Item = Seed>> = match (&mut stream, seeds.is_some()) {
+                (Some(stream), _) => Some(stream),
+                (None, true) => Some(&mut seeded),
+                (None, false) => None,
+            };
+            check((rank, perm), seeds);
+        }
+        return;
+    }
+    let mut candidates = batch.into_ranked();
+    let Some(simd) = simd else {
+        for candidate in candidates {
+            if stopped() {
+                break;
+            }
+            check(candidate, None);
+        }
+        return;
+    };
+    while !stopped() {
+        let (mut ranks, mut batch, mut taken) = (Vec::new(), Vec::new(), 0);
+        for (rank, perm) in candidates.by_ref() {
+            taken += usize::from(perm.as_ref().is_some_and(|perm| simd.takes(fixed, perm)));
+            ranks.push(rank);
+            batch.push(perm);
+            if taken == simd.candidates_per_batch() {
+                break;
+            }
+        }
+        if ranks.is_empty() {
+            break;
+        }
+        let streams = simd.seeds(fixed, &batch);
+        for (candidate, mut stream) in ranks.into_iter().zip(batch).zip(streams) {
+            check(candidate, stream.as_mut().map(|stream| stream as &mut dyn Iterator<Item = Seed>));
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use anyhow::Result;
+
+use crate::{missing, permutation};
+
+/// Most choices of words from a pool that are enumerated; each is kept in
+/// memory for ranking.
+const MAX_SELECTIONS: u64 = 10_000_000;
+
+/// Layout of a search over a pool of candidate words larger than the
+/// permutable positions: every choice of `slots` of the `pool` words, in
+/// every order or, when the order is known, only as given.
+///
+/// A rank splits into the choice and then its order, innermost last, so
+/// consecutive candidates hold the same words.
+#[derive(Clone)]
+pub struct WordPool {
+    slots: usize,
+    selections: Vec<Vec<usize>>,
+    orders: u64,
+    space: u64,
+}
+
+impl WordPool {
+    pub fn new(pool: usize, slots: usize, ordered: bool) -> Result<Self> {
+        if pool < slots {
+            return Err(anyhow::anyhow!(
+                "--pool-size leaves {} words for the {} positions after the fixed words; it must be at least the number of positions",
+                pool,
+                slots
+            ));
+        }
+        let choices = (0..slots).try_fold(1u64, |count, i| {
+            count.checked_mul((pool - i) as u64).map(|count| count / (i as u64 + 1))
+        });
+        if choices.is_none_or(|choices| choices > MAX_SELECTIONS) {
+            return Err(anyhow::anyhow!(
+                "Too many ways to choose {} of {} pool words to enumerate (at most {})",
+                slots,
+                pool,
+                MAX_SELECTIONS
+            ));
+        }
+        let selections = missing::combinations(pool, slots);
+        let orders = if ordered { 1 } else { permutation::factorial(slots) };
+        let space = orders
+            .checked_mul(selections.len() as u64)
+            .ok_or_else(|| anyhow::anyhow!("Too many candidates to enumerate from a pool of {} words", pool))?;
+        Ok(Self { slots, selections, orders, space })
+    }
+
+    /// Ways to choose the words, whatever their order.
+    pub fn selections(&self) -> usize {
+        self.selections.len()
+    }
+
+    pub fn space(&self) -> u64 {
+        self.space
+    }
+
+    /// Candidates with ranks in `[start, end)`, each the words of the
+    /// permutable positions. `pool` are the pool words after the fixed ones.
+    pub fn into_range(self, pool: Vec<String>, start: u64, end: u64) -> impl Iterator<Item = Vec<String>> {
+        let end = end.min(self.space);
+        (start..end).map(move |rank| {
+            let selection = &self.selections[(rank / self.orders) as usize];
+            let order = if self.orders == 1 { (0..self.slots).collect() } else { permutation::unrank(self.slots, rank % self.orders) };
+            order.into_iter().map(|slot| pool[selection[slot]].clone()).collect()
+        })
+    }
+}
@@ -0,0 +1,89 @@
+use anyhow::Result;
+use bip39::Language;
+
+use crate::wordindex::{IndexBase, Resolver};
+
+/// Words remembered at particular positions, as `--known-positions`
+/// pins them. The search runs over the other positions as if they were a
+/// shorter mnemonic, and each candidate has the pinned words put back.
+#[derive(Clone, Debug)]
+pub struct KnownPositions {
+    /// 0-based positions over the whole mnemonic, in order
+    words: Vec<(usize, String)>,
+}
+
+impl KnownPositions {
+    /// Parses `<position>:<word>` entries, positions counted from 1 over a
+    /// mnemonic of `total_words`; the word may be given by its number.
+    pub fn parse(entries: &[String], total_words: usize, base: IndexBase) -> Result<Self> {
+        let mut split = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let (position, word) = entry
+                .split_once(':')
+                .ok_or_else(|| anyhow::anyhow!("Known position {} is not <position>:<word>", entry))?;
+            let position: usize = position
+                .trim()
+                .parse()
+                .map_err(|e| anyhow::anyhow!("Known position {}: invalid position {}: {}", entry, position.trim(), e))?;
+            if position == 0 || position > total_words {
+                return Err(anyhow::anyhow!(
+                    "Known position {}: position {} is outside 1..={}",
+                    entry,
+                    position,
+                    total_words
+                ));
+            }
+            split.push((position, word.trim()));
+        }
+        let resolver = Resolver::new(base, split.iter().map(|(_, word)| *word), "--known-positions")?;
+        let mut words: Vec<(usize, String)> = Vec::with_capacity(split.len());
+        for (position, word) in split {
+            let word = resolver.word(word)?.to_lowercase();
+            if !Language::English.word_list().contains(&word.as_str()) {
+                return Err(anyhow::anyhow!("Known position {}: {} is not a BIP39 word", position, word));
+            }
+            if words.iter().any(|(pinned, _)| *pinned == position - 1) {
+                return Err(anyhow::anyhow!("Position {} is given more than once in --known-positions", position));
+            }
+            words.push((position - 1, word));
+        }
+        if words.len() >= total_words {
+            return Err(anyhow::anyhow!("--known-positions pins every position; leave at least one to search"));
+        }
+        words.sort_unstable();
+        Ok(Self { words })
+    }
+
+    /// Number of pinned positions.
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    /// The pinned words with their 1-based positions.
+    pub fn words(&self) -> impl Iterator<Item = (usize, &str)> {
+        self.words.iter().map(|(position, word)| (position + 1, word.as_str()))
+    }
+
+    /// Where 1-based `position` of the whole mnemonic falls among the
+    /// positions that aren't pinned, also 1-based.
+    pub fn free_position(&self, position: usize) -> Result<usize> {
+        if self.words.iter().any(|(pinned, _)| *pinned + 1 == position) {
+            return Err(anyhow::anyhow!("Position {} is pinned by --known-positions", position));
+        }
+        Ok(position - self.words.iter().filter(|(pinned, _)| *pinned + 1 < position).count())
+    }
+
+    /// The whole mnemonic: `free` filling the positions that aren't pinned,
+    /// in order.
+    pub fn insert(&self, free: Vec<String>) -> Vec<String> {
+        let mut words = free;
+        for (position, word) in &self.words {
+            words.insert((*position).min(words.len()), word.clone());
+        }
+        words
+    }
+}
@@ -0,0 +1,155 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// What the machine is currently running on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PowerSource {
+    Ac,
+    Battery { percent: Option<u8> },
+    Unknown,
+}
+
+/// Reads the power source from `/sys/class/power_supply`.
+#[cfg(target_os = "linux")]
+pub fn read() -> PowerSource {
+    use std::fs;
+
+    let Ok(entries) = fs::read_dir("/sys/class/power_supply") else {
+        return PowerSource::Unknown;
+    };
+    let read = |path: &std::path::Path, name: &str| fs::read_to_string(path.join(name)).map(|s| s.trim().to_string()).ok();
+    let mut mains_online = None;
+    let mut battery = None;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        match read(&path, "type").as_deref() {
+            Some("Mains") | Some("USB") => {
+                if read(&path, "online").as_deref() == Some("1") {
+                    mains_online = Some(true);
+                } else {
+                    mains_online.get_or_insert(false);
+                }
+            }
+            Some("Battery") => {
+                let discharging = read(&path, "status").as_deref() == Some("Discharging");
+                let percent = read(&path, "capacity").and_then(|capacity| capacity.parse::<u8>().ok());
+                battery = Some((discharging, percent));
+            }
+            _ => {}
+        }
+    }
+    match (mains_online, battery) {
+        (Some(true), _) => PowerSource::Ac,
+        (_, Some((true, percent))) => PowerSource::Battery { percent },
+        (Some(false), Some((false, percent))) => PowerSource::Battery { percent },
+        (_, Some(_)) => PowerSource::Ac,
+        _ => PowerSource::Unknown,
+    }
+}
+
+/// Parses `pmset -g batt`, e.g. `Now drawing from 'Battery Power'` followed
+/// by `-InternalBattery-0 (id=...)	87%; discharging; ...`.
+#[cfg(target_os = "macos")]
+pub fn read() -> PowerSource {
+    let Ok(output) = std::process::Command::new("pmset").args(["-g", "batt"]).output() else {
+        return PowerSource::Unknown;
+    };
+    let text = String::from_utf8_lossy(&output.stdout);
+    let percent = text
+        .split_whitespace()
+        .find_map(|field| field.strip_suffix("%;").and_then(|percent| percent.parse::<u8>().ok()));
+    if text.contains("'AC Power'") {
+        PowerSource::Ac
+    } else if text.contains("'Battery Power'") {
+        PowerSource::Battery { percent }
+    } else {
+        PowerSource::Unknown
+    }
+}
+
+#[cfg(windows)]
+pub fn read() -> PowerSource {
+    use windows_sys::Win32::System::Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+
+    // SAFETY: GetSystemPowerStatus only writes into the struct we pass
+    let mut status: SYSTEM_POWER_STATUS = unsafe { std::mem::zeroed() };
+    if unsafe { GetSystemPowerStatus(&mut status) } == 0 {
+        return PowerSource::Unknown;
+    }
+    let percent = (status.BatteryLifePercent <= 100).then_some(status.BatteryLifePercent);
+    match status.ACLineStatus {
+        1 => PowerSource::Ac,
+        0 => PowerSource::Battery { percent },
+        _ => PowerSource::Unknown,
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+pub fn read() -> PowerSource {
+    PowerSource::Unknown
+}
+
+/// Limits how many workers keep searching and pauses them all when asked.
+/// Workers call [`Throttle::wait`] before each candidate; the monitor thread
+/// adjusts the limits as the power source changes. A hold (e.g. a `pause`
+/// sent to the daemon) is tracked separately so power changes don't lift it.
+pub struct Throttle {
+    threads: usize,
+    active: AtomicUsize,
+    paused: AtomicBool,
+    held: AtomicBool,
+    stopped: AtomicBool,
+}
+
+impl Throttle {
+    pub fn new(threads: usize) -> Self {
+        Self {
+            threads,
+            active: AtomicUsize::new(threads),
+            paused: AtomicBool::new(false),
+            held: AtomicBool::new(false),
+            stopped: AtomicBool::new(false),
+        }
+    }
+
+    /// Lets only the first `active` rayon threads pick up work.
+    pub fn set_active(&self, active: usize) {
+        self.active.store(active.clamp(1, self.threads.max(1)), Ordering::Relaxed);
+    }
+
+    pub fn active(&self) -> usize {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Relaxed);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    pub fn set_held(&self, held: bool) {
+        self.held.store(held, Ordering::Relaxed);
+    }
+
+    pub fn is_held(&self) -> bool {
+        self.held.load(Ordering::Relaxed)
+    }
+
+    /// Lets every worker through from now on, whatever the other limits
+    /// say, so a stopping search isn't stuck behind a pause.
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+
+    /// Blocks the calling worker while it's paused, held, or parked.
+    pub fn wait(&self) {
+        let index = rayon::current_thread_index().unwrap_or(0);
+        while !self.stopped.load(Ordering::Relaxed)
+            && (self.is_paused() || self.is_held() || index >= self.active.load(Ordering::Relaxed))
+        {
+            std::thread::sleep(Duration::from_millis(500));
+        }
+    }
+}
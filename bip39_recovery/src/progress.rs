@@ -0,0 +1,101 @@
+use std::time::Instant;
+
+/// Weight of the cheap checksum/wordlist stage relative to one full
+/// derivation, used until stage timings have been sampled.
+const DEFAULT_FILTER_WEIGHT: f64 = 0.002;
+
+/// Examined candidates needed before the observed checksum pass rate is
+/// trusted over the theoretical one.
+const MIN_OBSERVED: u64 = 10_000;
+
+/// Smoothing factor for the work-rate moving average.
+const RATE_SMOOTHING: f64 = 0.2;
+
+/// Models the actual work behind each permutation so the ETA stays
+/// predictive: rejected candidates cost almost nothing while those passing
+/// the checksum pay for every derivation (paths x address types x indices).
+/// The ETA is the remaining permutations over the generation rate this
+/// projects, since permutations are what's left to go through.
+pub struct EtaModel {
+    expected_pass_rate: f64,
+    derivations_per_candidate: f64,
+    filter_weight: f64,
+    last: Option<(Instant, f64)>,
+    rate: Option<f64>,
+}
+
+impl EtaModel {
+    pub fn new(total_words: usize, derivations_per_candidate: u64) -> Self {
+        // A phrase of n words carries n/3 checksum bits
+        let checksum_bits = (total_words / 3) as i32;
+        Self {
+            expected_pass_rate: 0.5f64.powi(checksum_bits),
+            derivations_per_candidate: derivations_per_candidate.max(1) as f64,
+            filter_weight: DEFAULT_FILTER_WEIGHT,
+            last: None,
+            rate: None,
+        }
+    }
+
+    /// Uses measured stage timings for the filter-vs-derivation cost ratio.
+    pub fn set_filter_weight(&mut self, weight: f64) {
+        if weight.is_finite() && weight > 0.0 {
+            self.filter_weight = weight;
+        }
+    }
+
+    fn pass_rate(&self, rejected: u64, derived: u64) -> f64 {
+        let examined = rejected + derived;
+        if examined >= MIN_OBSERVED {
+            derived as f64 / examined as f64
+        } else {
+            self.expected_pass_rate
+        }
+    }
+
+    /// Work units already spent on the given candidate outcomes.
+    pub fn units_done(&self, rejected: u64, derived: u64) -> f64 {
+        rejected as f64 * self.filter_weight + derived as f64 * self.derivations_per_candidate
+    }
+
+    fn units_per_candidate(&self, rejected: u64, derived: u64) -> f64 {
+        let pass_rate = self.pass_rate(rejected, derived);
+        (1.0 - pass_rate) * self.filter_weight + pass_rate * self.derivations_per_candidate
+    }
+
+    /// Feeds the work done so far; call at a steady cadence.
+    pub fn update(&mut self, rejected: u64, derived: u64) {
+        let now = Instant::now();
+        let units = self.units_done(rejected, derived);
+        if let Some((then, previous)) = self.last {
+            let seconds = now.duration_since(then).as_secs_f64();
+            if seconds > 0.0 {
+                let instant_rate = (units - previous) / seconds;
+                self.rate = Some(match self.rate {
+                    Some(rate) => rate + RATE_SMOOTHING * (instant_rate - rate),
+                    None => instant_rate,
+                });
+            }
+        }
+        self.last = Some((now, units));
+    }
+
+    /// Permutations generated per second, filtered or derived alike, if a
+    /// rate is known.
+    pub fn generation_rate(&self, rejected: u64, derived: u64) -> Option<f64> {
+        let rate = self.rate.filter(|rate| *rate > 0.0)?;
+        Some(rate / self.units_per_candidate(rejected, derived))
+    }
+
+    /// Seconds left for `remaining` unexamined candidates, if a rate is known.
+    pub fn eta_seconds(&self, remaining: u64, rejected: u64, derived: u64) -> Option<f64> {
+        Some(remaining as f64 / self.generation_rate(rejected, derived)?)
+    }
+}
+
+/// Percentage of examined candidates the wordlist and checksum filters
+/// discarded before any derivation, once there are some.
+pub fn filtered_percent(rejected: u64, derived: u64) -> Option<f64> {
+    let examined = rejected + derived;
+    (examined > 0).then(|| rejected as f64 * 100.0 / examined as f64)
+}
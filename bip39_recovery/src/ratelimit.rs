@@ -0,0 +1,64 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use clap::ValueEnum;
+
+use crate::bench::Backend;
+
+/// Slots a limiter may have banked while idle, as time: after a stall the
+/// workers catch up by at most this much before being held to the rate.
+const SLACK: Duration = Duration::from_millis(50);
+
+/// Caps how many candidates per second pass through it, across all the
+/// workers sharing it. Each candidate reserves the next slot on a schedule
+/// spaced `1 / rate` apart and sleeps until it comes, so oversleeping one
+/// candidate doesn't lower the rate of the rest.
+pub struct RateLimiter {
+    rate: f64,
+    start: Instant,
+    interval: u64,
+    next: AtomicU64,
+}
+
+impl RateLimiter {
+    pub fn new(rate: f64) -> Result<Self> {
+        if !rate.is_finite() || rate <= 0.0 {
+            return Err(anyhow::anyhow!("Invalid rate {}: must be a positive number of candidates per second", rate));
+        }
+        let interval = (1e9 / rate).round().max(1.0) as u64;
+        Ok(Self { rate, start: Instant::now(), interval, next: AtomicU64::new(0) })
+    }
+
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    /// Blocks until the caller may test one more candidate.
+    pub fn acquire(&self) {
+        let now = self.start.elapsed().as_nanos() as u64;
+        let floor = now.saturating_sub(SLACK.as_nanos() as u64);
+        let mut next = self.next.load(Ordering::Relaxed);
+        let slot = loop {
+            let slot = next.max(floor);
+            match self.next.compare_exchange_weak(next, slot + self.interval, Ordering::Relaxed, Ordering::Relaxed) {
+                Ok(_) => break slot,
+                Err(actual) => next = actual,
+            }
+        };
+        if slot > now {
+            std::thread::sleep(Duration::from_nanos(slot - now));
+        }
+    }
+}
+
+/// Parses `<backend>=<candidates per second>`, e.g. `cpu=5000`.
+pub fn parse_backend_rate(value: &str) -> Result<(Backend, f64)> {
+    let (backend, rate) = value
+        .split_once('=')
+        .ok_or_else(|| anyhow::anyhow!("Invalid backend rate {}: expected <backend>=<rate>, e.g. cpu=5000", value))?;
+    let backend = Backend::from_str(backend.trim(), true)
+        .map_err(|_| anyhow::anyhow!("Invalid backend rate {}: unknown backend {}", value, backend.trim()))?;
+    let rate: f64 = rate.trim().parse().map_err(|e| anyhow::anyhow!("Invalid backend rate {}: {}", value, e))?;
+    Ok((backend, rate))
+}
@@ -0,0 +1,40 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Turns redaction of logged mnemonics on or off (`--redact`). It's on
+/// unless a run turns it off.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// A phrase as the log shows it: whole with redaction off, otherwise the
+/// start of a SHA-256 of its word indices, enough to tell candidates apart
+/// and to find one again from its words.
+pub fn phrase<I: Into<u32> + Copy>(words: &[String], indices: &[I]) -> String {
+    if !enabled() {
+        return words.join(" ");
+    }
+    let mut engine = sha256::Hash::engine();
+    for &index in indices {
+        engine.input(&index.into().to_be_bytes());
+    }
+    let hash = sha256::Hash::from_engine(engine).to_byte_array();
+    let hex: String = hash[..6].iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("<{} words #{}>", words.len(), hex)
+}
+
+/// A single word as the log shows it.
+pub fn word(word: &str) -> &str {
+    if enabled() {
+        "<redacted>"
+    } else {
+        word
+    }
+}
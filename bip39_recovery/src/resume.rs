@@ -0,0 +1,389 @@
+use std::fs;
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::coverage::{self, Checkpoint, Coverage};
+use crate::positions::KnownPositions;
+use crate::substitutes::Substitutes;
+use crate::tokenlist::TokenList;
+use crate::Arrangement;
+
+#[derive(Serialize)]
+struct MergeReport<'a> {
+    coverage: &'a Coverage,
+    covered_count: u64,
+    remaining: Vec<(u64, u64)>,
+    assignments: Vec<Vec<(u64, u64)>>,
+}
+
+/// `resume merge`: folds the checkpoints of several shards into one coverage
+/// map and splits what's left into `shards` new assignments.
+pub fn merge(files: &[String], shards: usize, output: Option<&str>) -> Result<()> {
+    if files.is_empty() {
+        return Err(anyhow::anyhow!("No checkpoint files given"));
+    }
+    let mut space = None;
+    let mut phase: Option<String> = None;
+    let mut ranges = Vec::new();
+    for file in files {
+        let checkpoint = Checkpoint::load(file)?;
+        let Some(shard) = checkpoint.shard else {
+            return Err(anyhow::anyhow!(
+                "Checkpoint {} has no range information; it was written by an older version",
+                file
+            ));
+        };
+        match &phase {
+            Some(phase) if phase != checkpoint.phase() => {
+                return Err(anyhow::anyhow!(
+                    "Checkpoint {} belongs to phase '{}', expected '{}'; use 'resume coverage' to map several phases",
+                    file,
+                    checkpoint.phase(),
+                    phase
+                ));
+            }
+            _ => phase = Some(checkpoint.phase().to_string()),
+        }
+        match space {
+            Some(space) if space != shard.space => {
+                return Err(anyhow::anyhow!(
+                    "Checkpoint {} covers a space of {} permutations, expected {}; shards must come from the same search",
+                    file,
+                    shard.space,
+                    space
+                ));
+            }
+            _ => space = Some(shard.space),
+        }
+        match checkpoint.covered() {
+            Some((start, end)) => {
+                println!("{}: covered {}..{} of assigned {}..{}", file, start, end, shard.start, shard.end);
+                ranges.push((start, end));
+            }
+            None => println!("{}: nothing covered yet of assigned {}..{}", file, shard.start, shard.end),
+        }
+    }
+
+    let coverage = Coverage::new(space.unwrap_or(0), ranges);
+    let remaining = coverage.remaining();
+    let covered_count = coverage.covered_count();
+    let percent = if coverage.space > 0 { covered_count as f64 * 100.0 / coverage.space as f64 } else { 100.0 };
+    println!();
+    println!("Covered {} of {} permutations ({:.4}%)", covered_count, coverage.space, percent);
+    for (start, end) in &coverage.covered {
+        println!("  covered   {}..{}", start, end);
+    }
+    for (start, end) in &remaining {
+        println!("  remaining {}..{}", start, end);
+    }
+
+    let assignments = coverage::split_ranges(&remaining, shards);
+    if assignments.is_empty() {
+        println!("\nThe whole space has been covered.");
+    } else {
+        println!("\nAssignments for {} shard(s):", assignments.len());
+        for (i, assignment) in assignments.iter().enumerate() {
+            let size: u64 = assignment.iter().map(|(start, end)| end - start).sum();
+            println!("  shard {} ({} permutations):", i + 1, size);
+            for (start, end) in assignment {
+                println!("    --start-index {} --end-index {}", start, end);
+            }
+        }
+    }
+
+    if let Some(output) = output {
+        let report = MergeReport { coverage: &coverage, covered_count, remaining, assignments };
+        fs::write(output, serde_json::to_string_pretty(&report)?)
+            .map_err(|e| anyhow::anyhow!("Failed to write coverage map {}: {}", output, e))?;
+        println!("\nCoverage map written to {}", output);
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct ShardCoverage {
+    file: String,
+    assigned: (u64, u64),
+    processed: u64,
+    covered: Option<(u64, u64)>,
+}
+
+#[derive(Serialize)]
+struct PhaseCoverage {
+    phase: String,
+    space: u64,
+    covered_count: u64,
+    percent: f64,
+    covered: Vec<(u64, u64)>,
+    remaining: Vec<(u64, u64)>,
+    shards: Vec<ShardCoverage>,
+}
+
+#[derive(Serialize)]
+struct CoverageMap {
+    phases: Vec<PhaseCoverage>,
+}
+
+/// `resume coverage`: groups checkpoints by phase and reports, per phase,
+/// what each shard covered and what the shards cover together.
+pub fn coverage_map(files: &[String], output: Option<&str>, svg: Option<&str>) -> Result<()> {
+    if files.is_empty() {
+        return Err(anyhow::anyhow!("No checkpoint files given"));
+    }
+    // Phases in the order they first appear
+    let mut groups: Vec<(String, u64, Vec<ShardCoverage>)> = Vec::new();
+    for file in files {
+        let checkpoint = Checkpoint::load(file)?;
+        let Some(shard) = checkpoint.shard else {
+            return Err(anyhow::anyhow!(
+                "Checkpoint {} has no range information; it was written by an older version",
+                file
+            ));
+        };
+        let entry = ShardCoverage {
+            file: file.clone(),
+            assigned: (shard.start, shard.end),
+            processed: checkpoint.processed,
+            covered: checkpoint.covered(),
+        };
+        match groups.iter_mut().find(|(phase, _, _)| phase == checkpoint.phase()) {
+            Some((phase, space, _)) if *space != shard.space => {
+                return Err(anyhow::anyhow!(
+                    "Checkpoint {} covers a space of {} permutations, but phase '{}' has {}; shards of a phase must come from the same search",
+                    file,
+                    shard.space,
+                    phase,
+                    space
+                ));
+            }
+            Some((_, _, shards)) => shards.push(entry),
+            None => groups.push((checkpoint.phase().to_string(), shard.space, vec![entry])),
+        }
+    }
+
+    let phases: Vec<PhaseCoverage> = groups
+        .into_iter()
+        .map(|(phase, space, shards)| {
+            let coverage = Coverage::new(space, shards.iter().filter_map(|shard| shard.covered).collect());
+            let covered_count = coverage.covered_count();
+            PhaseCoverage {
+                phase,
+                space,
+                covered_count,
+                percent: if space > 0 { covered_count as f64 * 100.0 / space as f64 } else { 100.0 },
+                remaining: coverage.remaining(),
+                covered: coverage.covered,
+                shards,
+            }
+        })
+        .collect();
+
+    for phase in &phases {
+        println!("Phase '{}': covered {} of {} ({:.4}%)", phase.phase, phase.covered_count, phase.space, phase.percent);
+        for shard in &phase.shards {
+            match shard.covered {
+                Some((start, end)) => println!(
+                    "  {}: covered {}..{} of assigned {}..{}",
+                    shard.file, start, end, shard.assigned.0, shard.assigned.1
+                ),
+                None => println!("  {}: nothing covered yet of assigned {}..{}", shard.file, shard.assigned.0, shard.assigned.1),
+            }
+        }
+        if phase.remaining.is_empty() {
+            println!("  exhausted");
+        }
+        for (start, end) in &phase.remaining {
+            println!("  remaining {}..{}", start, end);
+        }
+    }
+
+    let map = CoverageMap { phases };
+    if let Some(output) = output {
+        fs::write(output, serde_json::to_string_pretty(&map)?)
+            .map_err(|e| anyhow::anyhow!("Failed to write coverage map {}: {}", output, e))?;
+        println!("\nCoverage map written to {}", output);
+    }
+    if let Some(svg) = svg {
+        fs::write(svg, render_svg(&map)).map_err(|e| anyhow::anyhow!("Failed to write coverage image {}: {}", svg, e))?;
+        println!("Coverage image written to {}", svg);
+    }
+    Ok(())
+}
+
+const SVG_WIDTH: f64 = 800.0;
+const SVG_MARGIN: f64 = 10.0;
+
+/// Horizontal bars over the index space: one per phase for the union, with
+/// a thinner one below it per shard showing its assignment and progress.
+fn render_svg(map: &CoverageMap) -> String {
+    let bar_width = SVG_WIDTH - 2.0 * SVG_MARGIN;
+    let mut body = String::new();
+    let mut y = SVG_MARGIN;
+    let mut rect = |y: f64, height: f64, space: u64, (start, end): (u64, u64), fill: &str| {
+        let scale = |index: u64| SVG_MARGIN + bar_width * index as f64 / space.max(1) as f64;
+        // Keep slivers visible, however small the covered slice
+        let width = (scale(end) - scale(start)).max(1.0);
+        body.push_str(&format!(
+            "<rect x=\"{:.2}\" y=\"{:.2}\" width=\"{:.2}\" height=\"{:.2}\" fill=\"{}\"/>\n",
+            scale(start),
+            y,
+            width,
+            height,
+            fill
+        ));
+    };
+    let mut labels = Vec::new();
+    for phase in &map.phases {
+        labels.push((y + 12.0, format!("{} ({:.4}% of {})", phase.phase, phase.percent, phase.space), true));
+        y += 18.0;
+        rect(y, 16.0, phase.space, (0, phase.space), "#e5e7eb");
+        for &range in &phase.covered {
+            rect(y, 16.0, phase.space, range, "#16a34a");
+        }
+        y += 22.0;
+        for shard in &phase.shards {
+            labels.push((y + 9.0, shard.file.clone(), false));
+            y += 12.0;
+            rect(y, 6.0, phase.space, shard.assigned, "#bfdbfe");
+            if let Some(range) = shard.covered {
+                rect(y, 6.0, phase.space, range, "#2563eb");
+            }
+            y += 10.0;
+        }
+        y += 8.0;
+    }
+    for (label_y, text, bold) in labels {
+        body.push_str(&format!(
+            "<text x=\"{}\" y=\"{:.2}\" font-family=\"sans-serif\" font-size=\"{}\"{}>{}</text>\n",
+            SVG_MARGIN,
+            label_y,
+            if bold { 12 } else { 9 },
+            if bold { " font-weight=\"bold\"" } else { "" },
+            escape(&text)
+        ));
+    }
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{:.0}\">\n{}</svg>\n",
+        SVG_WIDTH,
+        y + SVG_MARGIN,
+        body
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// `resume info`: prints what a checkpoint records and, when the search's
+/// arguments are given, whether resuming under them continues the same
+/// search.
+pub fn info(file: &str, search: &[String]) -> Result<()> {
+    let checkpoint = Checkpoint::load(file)?;
+    let now = crate::live_stats::unix_now();
+    println!("Checkpoint {}", file);
+    println!("  phase:         {}", checkpoint.phase());
+    println!("  configuration: {}", checkpoint.config.as_deref().unwrap_or("not recorded"));
+    match checkpoint.shard {
+        Some(shard) => {
+            let size = shard.end - shard.start;
+            let done = checkpoint.processed.min(size);
+            let percent = if size > 0 { done as f64 * 100.0 / size as f64 } else { 100.0 };
+            println!("  range:         {}..{} of {}", shard.start, shard.end, shard.space);
+            println!("  processed:     {} of {} ({:.4}%)", checkpoint.processed, size, percent);
+            match checkpoint.covered() {
+                Some((start, end)) => println!("  covered:       {}..{}", start, end),
+                None => println!("  covered:       nothing yet"),
+            }
+            if !checkpoint.ahead.is_empty() {
+                let ranks: u64 = checkpoint.ahead.iter().map(|(start, end)| end - start).sum();
+                println!("  done ahead:    {} in {} range(s) past the processed count", ranks, checkpoint.ahead.len());
+            }
+            if done < size {
+                println!("  remaining:     {}..{}", shard.start + done, shard.end);
+            }
+        }
+        None => println!("  processed:     {} (no range recorded)", checkpoint.processed),
+    }
+    if let Some(started) = checkpoint.started {
+        println!("  started:       {} ({} ago)", started, crate::format_eta(now.saturating_sub(started) as f64));
+    }
+    if let Some(&(at, _)) = checkpoint.samples.last() {
+        println!("  last sample:   {} ({} ago)", at, crate::format_eta(now.saturating_sub(at) as f64));
+    }
+
+    if checkpoint.samples.len() > 1 {
+        println!();
+        println!("Throughput between samples:");
+        for pair in checkpoint.samples.windows(2) {
+            let ((from, from_count), (to, to_count)) = (pair[0], pair[1]);
+            let rate = to_count.saturating_sub(from_count) as f64 / to.saturating_sub(from).max(1) as f64;
+            println!("  {}..{}: {:.0} candidates/s", from, to, rate);
+        }
+    }
+
+    if search.is_empty() {
+        return Ok(());
+    }
+    let (args, _) = crate::parse_search(search)?;
+    let total_words = args.total_words.unwrap_or_default();
+    let fixed_words = args.fixed_words.unwrap_or_default();
+    let tokenlist = args.tokenlist.as_deref().map(|file| TokenList::load(file, total_words, args.word_index_base)).transpose()?;
+    let substitutes =
+        args.candidates_file.as_deref().map(|file| Substitutes::load(file, total_words, args.word_index_base)).transpose()?;
+    let pinned = crate::known_positions(&args, total_words)?;
+    let free_words = total_words - pinned.as_ref().map_or(0, KnownPositions::len);
+    let missing = crate::missing_words(&args, free_words, fixed_words, pinned.as_ref())?;
+    let fuzzy = if args.fuzzy_words || args.prefix_mode {
+        let words = crate::load_known_words(&args, free_words)?;
+        crate::expand_known_words(&args, &words, fixed_words)?
+    } else {
+        None
+    };
+    let bounded = crate::bounded_order(&args, free_words, fixed_words)?;
+    let pool = crate::word_pool(&args, free_words, fixed_words)?;
+    let space = crate::search_space(
+        tokenlist.as_ref(),
+        args.seedlist.as_deref(),
+        substitutes.as_ref(),
+        fuzzy.as_ref(),
+        missing.as_ref(),
+        Arrangement::of(bounded.as_ref(), pool.as_ref(), free_words.saturating_sub(fixed_words)),
+    )?;
+    let end = args.end_index.unwrap_or(space).min(space);
+    let config = crate::config_hash(&args)?;
+    let phase = crate::phase_name(&args);
+
+    let mut problems = Vec::new();
+    match checkpoint.config.as_deref() {
+        Some(saved) if saved != config => {
+            problems.push(format!("configuration {} differs from the search's {}", saved, config))
+        }
+        Some(_) => {}
+        None => problems.push("the checkpoint predates configuration hashes, so settings can't be compared".to_string()),
+    }
+    if checkpoint.phase() != phase {
+        problems.push(format!("phase '{}' differs from the search's '{}'", checkpoint.phase(), phase));
+    }
+    if let Some(shard) = checkpoint.shard {
+        if shard.space != space {
+            problems.push(format!("space of {} differs from the search's {}", shard.space, space));
+        }
+        if (shard.start, shard.end) != (args.start_index, end) {
+            problems.push(format!(
+                "range {}..{} differs from the search's {}..{}",
+                shard.start, shard.end, args.start_index, end
+            ));
+        }
+    }
+    println!();
+    if problems.is_empty() {
+        println!("Compatible: resuming with these arguments continues the same search");
+    } else {
+        println!("Not compatible:");
+        for problem in &problems {
+            println!("  - {}", problem);
+        }
+    }
+    Ok(())
+}
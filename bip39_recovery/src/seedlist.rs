@@ -0,0 +1,64 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+use anyhow::Result;
+use log::debug;
+
+/// Parses one line of a btcrecover seedlist or passwordlist. Plain
+/// space-separated phrases are accepted, as are the comma-separated and
+/// Python tuple/list forms (`('abandon', 'ability', ...)`) printed by
+/// `--listseeds`. Lines without exactly `total_words` words give `None`.
+pub fn parse_line(line: &str, total_words: usize) -> Option<Vec<String>> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with("#--") {
+        return None;
+    }
+    let words: Vec<String> = line
+        .split(|c: char| c.is_whitespace() || matches!(c, ',' | '(' | ')' | '[' | ']' | '\'' | '"'))
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    (words.len() == total_words).then_some(words)
+}
+
+fn open(file: &str) -> Result<BufReader<File>> {
+    let handle = File::open(file).map_err(|e| anyhow::anyhow!("Failed to open seedlist {}: {}", file, e))?;
+    Ok(BufReader::new(handle))
+}
+
+/// Number of lines in the seedlist, which is the size of its search space.
+pub fn count(file: &str) -> Result<u64> {
+    let mut lines = 0u64;
+    for line in open(file)?.lines() {
+        line.map_err(|e| anyhow::anyhow!("Failed to read seedlist {}: {}", file, e))?;
+        lines += 1;
+    }
+    Ok(lines)
+}
+
+/// Lines `[start, end)` of the seedlist as candidates. Every line is one
+/// rank, so unusable lines yield `None` and still count towards progress.
+pub fn candidates(
+    file: &str,
+    total_words: usize,
+    start: u64,
+    end: u64,
+) -> Result<impl Iterator<Item = Option<Vec<String>>> + Send> {
+    let file = file.to_string();
+    let lines = open(&file)?.lines().enumerate();
+    let skip = usize::try_from(start).unwrap_or(usize::MAX);
+    let take = usize::try_from(end.saturating_sub(start)).unwrap_or(usize::MAX);
+    Ok(lines.skip(skip).take(take).map(move |(number, line)| match line {
+        Ok(line) => {
+            let words = parse_line(&line, total_words);
+            if words.is_none() {
+                debug!("Skipping seedlist line {}: not a {}-word phrase", number + 1, total_words);
+            }
+            words
+        }
+        Err(e) => {
+            debug!("Failed to read seedlist {} line {}: {}", file, number + 1, e);
+            None
+        }
+    }))
+}
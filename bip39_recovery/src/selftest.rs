@@ -0,0 +1,129 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+use bip39::{Language, Mnemonic};
+use bitcoin::bip32::{DerivationPath, Xpriv};
+use bitcoin::Network;
+use secp256k1::Secp256k1;
+
+use crate::entropy;
+use crate::matrix::SearchMatrix;
+use crate::passphrase::Passphrases;
+use crate::pbkdf2::{self, Backend};
+use crate::stages::StageStats;
+use crate::summary::RunSummary;
+use crate::{Bip39Wordlist, SeedFormat, Targets};
+
+const ABANDON_ABOUT: &str =
+    "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+/// Trezor BIP-39 vectors: mnemonic with passphrase `TREZOR` to seed and
+/// BIP-32 root key. The second is longer than a SHA-512 block, so HMAC
+/// hashes it down to a key first.
+const SEED_VECTORS: [(&str, &str, &str); 2] = [
+    (
+        ABANDON_ABOUT,
+        "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04",
+        "xprv9s21ZrQH143K3h3fDYiay8mocZ3afhfULfb5GX8kCBdno77K4HiA15Tg23wpbeF1pLfs1c5SPmYHrEpTuuRhxMwvKDwqdKiGJS9XFKzUsAF",
+    ),
+    (
+        "letter advice cage absurd amount doctor acoustic avoid letter advice cage absurd amount doctor acoustic avoid letter advice cage absurd amount doctor acoustic bless",
+        "c0c519bd0e91a2ed54357d9d1ebef6f5af218a153624cf4f2da911a0ed8f7a09e2ef61af0aca007096df430022f7a2b6fb91661a9589097069720d015e4e982f",
+        "xprv9s21ZrQH143K3CSnQNYC3MqAAqHwxeTLhDbhF43A4ss4ciWNmCY9zQGvAKUSqVUf2vPHBTSE1rB2pg4avopqSiLVzXEU8KziNnVPauTqLRo",
+    ),
+];
+
+/// First receive address for each supported type, from the BIP-44/49/84/86
+/// specifications (no passphrase).
+const ADDRESS_VECTORS: [(&str, &str, &str, &str); 4] = [
+    ("m/44'/0'/0'/0/0", "p2pkh", "mainnet", "1LqBGSKuX5yYUonjxT5qGfpUsXKYYWeabA"),
+    ("m/49'/1'/0'/0/0", "p2sh-p2wpkh", "testnet", "2Mww8dCYPUpKHofjgcXcBCEGmniw9CoaiD2"),
+    ("m/84'/0'/0'/0/0", "p2wpkh", "mainnet", "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu"),
+    ("m/86'/0'/0'/0/0", "p2tr", "mainnet", "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr"),
+];
+
+/// Runs the reference vectors through the same code the search uses and
+/// fails on the first mismatch, so a broken derivation can't silently waste
+/// a long run. Returns the number of vectors checked.
+pub fn run(secp: &Secp256k1<secp256k1::All>, wordlist: &Bip39Wordlist) -> Result<usize> {
+    for (phrase, seed_hex, xprv) in SEED_VECTORS {
+        let mnemonic = Mnemonic::parse_in_normalized(Language::English, phrase)
+            .map_err(|e| anyhow::anyhow!("Self-check: vector mnemonic rejected: {}", e))?;
+        let seed = mnemonic.to_seed("TREZOR");
+        let actual_hex: String = seed.iter().map(|byte| format!("{:02x}", byte)).collect();
+        if actual_hex != seed_hex {
+            return Err(anyhow::anyhow!("Self-check: BIP-39 seed mismatch, expected {} got {}", seed_hex, actual_hex));
+        }
+        // The batched PBKDF2 has to agree on this CPU's vector units and without them
+        for backend in [Backend::detect(), Backend::Scalar] {
+            if pbkdf2::derive(backend, &[(phrase.as_bytes(), b"mnemonicTREZOR")]) != [seed] {
+                return Err(anyhow::anyhow!("Self-check: {} batched PBKDF2 disagrees with BIP-39", backend.name()));
+            }
+        }
+        let master = Xpriv::new_master(Network::Bitcoin, &seed)?;
+        if master.to_string() != xprv {
+            return Err(anyhow::anyhow!("Self-check: BIP-32 root key mismatch, expected {} got {}", xprv, master));
+        }
+    }
+
+    // The checksum prefilter reads word indices straight from the file
+    for (index, word) in Language::English.word_list().iter().enumerate() {
+        if wordlist.index(word) != Some(index as u16) {
+            return Err(anyhow::anyhow!(
+                "Self-check: bip39_wordlist.txt is not the standard English list in BIP-39 order ({} should be word {})",
+                word,
+                index
+            ));
+        }
+    }
+
+    // Entropy and checksum splitting for every length, against the bip39
+    // crate's own encoding
+    for words in entropy::WORD_COUNTS {
+        let len = entropy::entropy_len(words).unwrap_or_default();
+        let bytes: Vec<u8> = (0..len).map(|i| (i as u8).wrapping_mul(37).wrapping_add(words as u8)).collect();
+        let mnemonic = Mnemonic::from_entropy(&bytes)?;
+        let mut indices: Vec<u16> = mnemonic.word_indices().map(|index| index as u16).collect();
+        match entropy::split(&indices) {
+            Some((decoded, cs)) if decoded == bytes && cs == entropy::checksum(&bytes) => {}
+            _ => return Err(anyhow::anyhow!("Self-check: {}-word entropy/checksum split disagrees with BIP-39", words)),
+        }
+        // The last word always carries checksum bits, so flipping its lowest
+        // bit must invalidate the phrase
+        if let Some(last) = indices.last_mut() {
+            *last ^= 1;
+        }
+        if entropy::is_valid(&indices) {
+            return Err(anyhow::anyhow!("Self-check: {}-word checksum accepted a corrupted phrase", words));
+        }
+    }
+
+    let words: Vec<String> = ABANDON_ABOUT.split_whitespace().map(str::to_string).collect();
+    for (path, address_type, network, address) in ADDRESS_VECTORS {
+        let path: DerivationPath = path.parse()?;
+        let matrix = SearchMatrix::single(path.clone(), address_type, crate::matrix::parse_network(network)?);
+        let found = crate::try_mnemonic(
+            &words,
+            SeedFormat::Bip39,
+            &matrix,
+            &Passphrases::none(),
+            None,
+            None,
+            &Targets { addresses: Some(&HashSet::from([address.to_string()])), ..Targets::default() },
+            secp,
+            wordlist,
+            &RunSummary::default(),
+            &StageStats::default(),
+            false,
+        )?;
+        if found.is_none() {
+            return Err(anyhow::anyhow!(
+                "Self-check: {} at {} did not derive the reference address {}",
+                address_type,
+                path,
+                address
+            ));
+        }
+    }
+    Ok(SEED_VECTORS.len() + entropy::WORD_COUNTS.len() + ADDRESS_VECTORS.len())
+}
@@ -0,0 +1,200 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
+
+use anyhow::Result;
+use itertools::Itertools;
+use rayon::prelude::*;
+
+use crate::derive::{self, Layout, TableFormat};
+use crate::permutation::{self, PermutationRange};
+use crate::slip39::{self, Share};
+
+/// Orderings and fillings tried per share before giving up on it.
+const MAX_SHARE_SPACE: u64 = 1 << 36;
+
+/// Ways of picking one reading of each share that are combined.
+const MAX_SELECTIONS: usize = 100_000;
+
+/// Orderings checked per rayon task.
+const CHUNK: u64 = 1 << 12;
+
+/// Words a wordlist index stands for.
+const WORDS: u16 = 1024;
+
+/// Settings of a `slip39` run.
+pub struct Slip39 {
+    pub shares: Vec<String>,
+    pub shares_file: Option<String>,
+    pub fixed_words: Option<usize>,
+    pub passphrase: String,
+    pub address: Option<String>,
+    pub address_types: Vec<String>,
+    pub account_paths: Vec<String>,
+    pub accounts: String,
+    pub index_range: String,
+    pub network: String,
+    pub results_file: String,
+}
+
+/// The words of one share as remembered: a word, or `?` for one that's lost.
+fn parse_share(line: &str) -> Result<Vec<Option<u16>>> {
+    line.split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|word| !word.is_empty())
+        .map(|word| match word {
+            "?" => Ok(None),
+            word => slip39::word_index(word)
+                .map(Some)
+                .ok_or_else(|| anyhow::anyhow!("{} is not a SLIP-39 word", word)),
+        })
+        .collect()
+}
+
+/// Every reading of a share that passes its checksum: the first `fixed`
+/// words in place, the others in any order, and each `?` as every word.
+fn readings(words: &[Option<u16>], fixed: usize) -> Result<Vec<Vec<u16>>> {
+    if !slip39::SHARE_LENGTHS.contains(&words.len()) {
+        return Err(anyhow::anyhow!("A share has 20 or 33 words, got {}", words.len()));
+    }
+    let fixed = fixed.min(words.len());
+    let (head, tail) = words.split_at(fixed);
+    let orderings = permutation::factorial(tail.len());
+    let unknown = words.iter().filter(|word| word.is_none()).count() as u32;
+    let space = u64::from(WORDS).checked_pow(unknown).and_then(|fillings| fillings.checked_mul(orderings));
+    if space.is_none_or(|space| space > MAX_SHARE_SPACE) {
+        return Err(anyhow::anyhow!(
+            "{} orderings with {} lost word(s) is too many to search; raise --fixed-words",
+            orderings,
+            unknown
+        ));
+    }
+    let readings = (0..orderings.div_ceil(CHUNK))
+        .into_par_iter()
+        .flat_map_iter(|chunk| {
+            PermutationRange::new(tail.to_vec(), chunk * CHUNK, (chunk + 1) * CHUNK)
+                .skip_duplicates()
+                .flat_map(|(_, order)| {
+                    let share: Vec<Option<u16>> = head.iter().copied().chain(order).collect();
+                    fillings(&share)
+                })
+        })
+        .collect();
+    Ok(readings)
+}
+
+/// `share` with each `?` replaced by every word, keeping the ones whose
+/// checksum holds.
+fn fillings(share: &[Option<u16>]) -> Vec<Vec<u16>> {
+    let lost: Vec<usize> = share.iter().positions(Option::is_none).collect();
+    let mut indices: Vec<u16> = share.iter().map(|word| word.unwrap_or(0)).collect();
+    let mut found = Vec::new();
+    loop {
+        if slip39::checksum_valid(&indices) && Share::from_indices(&indices).is_ok() {
+            found.push(indices.clone());
+        }
+        // Counts through the lost words like digits
+        let Some(carry) = lost.iter().position(|&position| indices[position] + 1 < WORDS) else { break };
+        for &position in &lost[..carry] {
+            indices[position] = 0;
+        }
+        indices[lost[carry]] += 1;
+    }
+    found
+}
+
+fn phrase(indices: &[u16]) -> String {
+    indices.iter().map(|&index| slip39::word(index)).join(" ")
+}
+
+/// `slip39`: recovers a SLIP-39 (Shamir) backup from shares whose words are
+/// out of order or partly lost. Each share's readings are narrowed by its
+/// checksum, then every pick of one reading per share is combined; a pick
+/// that reaches the thresholds and passes the digest yields the master
+/// secret, which with `address` must also derive it.
+pub fn run(search: Slip39) -> Result<()> {
+    let mut lines = search.shares.clone();
+    if let Some(file) = &search.shares_file {
+        let content =
+            fs::read_to_string(file).map_err(|e| anyhow::anyhow!("Failed to read shares file {}: {}", file, e))?;
+        lines.extend(content.lines().filter(|line| !line.trim().is_empty()).map(str::to_string));
+    }
+    if lines.is_empty() {
+        return Err(anyhow::anyhow!("Give shares with --share or --shares-file"));
+    }
+    let layout = Layout::new(
+        &search.address_types,
+        &search.account_paths,
+        &search.accounts,
+        &search.index_range,
+        false,
+        &search.network,
+    )?;
+
+    let mut candidates = Vec::with_capacity(lines.len());
+    for (number, line) in lines.iter().enumerate() {
+        let words = parse_share(line)?;
+        let fixed = search.fixed_words.unwrap_or(words.len());
+        let found = readings(&words, fixed).map_err(|e| anyhow::anyhow!("Share {}: {}", number + 1, e))?;
+        println!("Share {}: {} reading(s) pass the checksum", number + 1, found.len());
+        if found.is_empty() {
+            return Err(anyhow::anyhow!("Share {} has no reading with a valid checksum", number + 1));
+        }
+        candidates.push(found);
+    }
+    let selections = candidates.iter().try_fold(1usize, |product, found| product.checked_mul(found.len()));
+    if selections.is_none_or(|selections| selections > MAX_SELECTIONS) {
+        return Err(anyhow::anyhow!("Too many combinations of share readings; raise --fixed-words"));
+    }
+
+    // Picks that agree on an encrypted secret only need decrypting once
+    let mut tried = HashSet::new();
+    let mut last_error = None;
+    let mut recovered = 0;
+    for pick in candidates.iter().multi_cartesian_product() {
+        let shares = pick.iter().map(|indices| Share::from_indices(indices)).collect::<Result<Vec<_>>>()?;
+        let encrypted = match slip39::combine(&shares) {
+            Ok(encrypted) => encrypted,
+            Err(e) => {
+                last_error = Some(e);
+                continue;
+            }
+        };
+        if !tried.insert(encrypted.clone()) {
+            continue;
+        }
+        let secret = slip39::decrypt(&encrypted, &search.passphrase, &shares[0]);
+        let rows = layout.rows(&secret)?;
+        if let Some(target) = &search.address {
+            if !rows.iter().any(|row| &row.address == target) {
+                continue;
+            }
+        }
+        recovered += 1;
+        println!("Recovered a master secret from:");
+        for indices in &pick {
+            println!("  {}", phrase(indices));
+        }
+        derive::print_rows(&rows, TableFormat::Table)?;
+        save(&search.results_file, &pick, &secret)?;
+    }
+    match (recovered, last_error) {
+        (0, Some(e)) if tried.is_empty() => Err(e),
+        (0, _) => Err(anyhow::anyhow!("No combination of the shares derives the target address")),
+        _ => {
+            println!("Recorded {} master secret(s) in {}", recovered, search.results_file);
+            Ok(())
+        }
+    }
+}
+
+fn save(results_file: &str, shares: &[&Vec<u16>], secret: &[u8]) -> Result<()> {
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(results_file)
+        .map_err(|e| anyhow::anyhow!("Failed to open results file {}: {}", results_file, e))?;
+    let secret: String = secret.iter().map(|byte| format!("{:02x}", byte)).collect();
+    let shares: Vec<String> = shares.iter().map(|indices| phrase(indices)).collect();
+    writeln!(file, "{} {}", secret, shares.join(" | "))
+        .map_err(|e| anyhow::anyhow!("Failed to write to results file {}: {}", results_file, e))
+}
@@ -0,0 +1,192 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::positions::KnownPositions;
+use crate::substitutes::Substitutes;
+use crate::tokenlist::TokenList;
+use crate::{Args, Arrangement};
+
+/// A machine taking part in the campaign and its measured throughput.
+struct Machine {
+    name: String,
+    rate: f64,
+}
+
+/// Reads `<name> <candidates per second>` lines, e.g. rates taken from
+/// `bench` on each machine.
+fn load_machines(file: &str) -> Result<Vec<Machine>> {
+    let content =
+        fs::read_to_string(file).map_err(|e| anyhow::anyhow!("Failed to read machines file {}: {}", file, e))?;
+    let mut machines: Vec<Machine> = Vec::new();
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [name, rate] = fields[..] else {
+            return Err(anyhow::anyhow!("{} line {}: expected '<name> <rate>', got '{}'", file, number + 1, line));
+        };
+        let rate: f64 = rate
+            .parse()
+            .map_err(|e| anyhow::anyhow!("{} line {}: invalid rate {}: {}", file, number + 1, rate, e))?;
+        if !(rate.is_finite() && rate > 0.0) {
+            return Err(anyhow::anyhow!("{} line {}: rate must be positive, got {}", file, number + 1, rate));
+        }
+        // Names become state directories and file names
+        if !name.chars().all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c)) || name.starts_with('.') {
+            return Err(anyhow::anyhow!("{} line {}: machine name {} must be letters, digits, '-', '_', or '.'", file, number + 1, name));
+        }
+        if machines.iter().any(|machine| machine.name == name) {
+            return Err(anyhow::anyhow!("{} line {}: machine {} listed twice", file, number + 1, name));
+        }
+        machines.push(Machine { name: name.to_string(), rate });
+    }
+    if machines.is_empty() {
+        return Err(anyhow::anyhow!("Machines file {} lists no machines", file));
+    }
+    Ok(machines)
+}
+
+/// Splits `[start, end)` into consecutive ranges sized by each rate, so every
+/// machine should finish at about the same time.
+fn split_by_rate(start: u64, end: u64, rates: &[f64]) -> Vec<(u64, u64)> {
+    let total_rate: f64 = rates.iter().sum();
+    let size = end - start;
+    let mut ranges = Vec::with_capacity(rates.len());
+    let mut cursor = start;
+    let mut assigned_rate = 0.0;
+    for (i, rate) in rates.iter().enumerate() {
+        assigned_rate += rate;
+        // The cumulative share keeps rounding from drifting, and the last
+        // machine always ends exactly at `end`
+        let next = if i + 1 == rates.len() {
+            end
+        } else {
+            start + ((size as f64 * assigned_rate / total_rate) as u64).min(size)
+        };
+        ranges.push((cursor, next.max(cursor)));
+        cursor = next.max(cursor);
+    }
+    ranges
+}
+
+/// Options `shard-plan` sets itself on each machine's command line.
+const PLANNED_OPTIONS: [&str; 3] = ["--start-index", "--end-index", "--state-dir"];
+
+/// `search` without any of the planned options, in either `--opt value` or
+/// `--opt=value` form.
+fn strip_planned(search: &[String]) -> Vec<String> {
+    let mut kept = Vec::new();
+    let mut args = search.iter();
+    while let Some(arg) = args.next() {
+        if PLANNED_OPTIONS.contains(&arg.as_str()) {
+            args.next();
+        } else if !PLANNED_OPTIONS.iter().any(|option| arg.starts_with(&format!("{}=", option))) {
+            kept.push(arg.clone());
+        }
+    }
+    kept
+}
+
+/// Quotes `arg` for a POSIX shell when it needs it.
+fn shell_quote(arg: &str) -> String {
+    if !arg.is_empty() && arg.chars().all(|c| c.is_ascii_alphanumeric() || "-_./,:=@%+".contains(c)) {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// Candidate ranks in the whole space of the search `args` describes.
+pub fn space(args: &Args) -> Result<u64> {
+    let total_words = args.total_words.unwrap_or_default();
+    let fixed_words = args.fixed_words.unwrap_or_default();
+    let tokenlist = args.tokenlist.as_deref().map(|file| TokenList::load(file, total_words, args.word_index_base)).transpose()?;
+    let substitutes =
+        args.candidates_file.as_deref().map(|file| Substitutes::load(file, total_words, args.word_index_base)).transpose()?;
+    let pinned = crate::known_positions(args, total_words)?;
+    let free_words = total_words - pinned.as_ref().map_or(0, KnownPositions::len);
+    let missing = crate::missing_words(args, free_words, fixed_words, pinned.as_ref())?;
+    let fuzzy = if args.fuzzy_words || args.prefix_mode {
+        let words = crate::load_known_words(args, free_words)?;
+        crate::expand_known_words(args, &words, fixed_words)?
+    } else {
+        None
+    };
+    let bounded = crate::bounded_order(args, free_words, fixed_words)?;
+    let pool = crate::word_pool(args, free_words, fixed_words)?;
+    crate::search_space(
+        tokenlist.as_ref(),
+        args.seedlist.as_deref(),
+        substitutes.as_ref(),
+        fuzzy.as_ref(),
+        missing.as_ref(),
+        Arrangement::of(bounded.as_ref(), pool.as_ref(), free_words.saturating_sub(fixed_words)),
+    )
+}
+
+/// `shard-plan`: sizes each machine's index range by its throughput and
+/// prints the command line it should run, optionally writing each one to
+/// `<output_dir>/<name>.sh`.
+pub fn run(machines_file: &str, output_dir: Option<&str>, search: &[String]) -> Result<()> {
+    let machines = load_machines(machines_file)?;
+    let (args, _) = crate::parse_search(search)?;
+    let space = space(&args)?;
+    let end = args.end_index.unwrap_or(space).min(space);
+    let start = args.start_index.min(end);
+
+    let rates: Vec<f64> = machines.iter().map(|machine| machine.rate).collect();
+    let ranges = split_by_rate(start, end, &rates);
+    let total_rate: f64 = rates.iter().sum();
+    println!(
+        "Splitting {}..{} ({} candidates of {}) across {} machines, about {:.1} hours at {:.0} candidates/s combined",
+        start,
+        end,
+        end - start,
+        space,
+        machines.len(),
+        (end - start) as f64 / total_rate / 3600.0,
+        total_rate
+    );
+
+    let base = strip_planned(search);
+    if let Some(dir) = output_dir {
+        fs::create_dir_all(dir).map_err(|e| anyhow::anyhow!("Failed to create output directory {}: {}", dir, e))?;
+    }
+    for (machine, &(range_start, range_end)) in machines.iter().zip(&ranges) {
+        let mut command = vec!["bip39_recovery".to_string()];
+        command.extend(base.iter().cloned());
+        command.extend([
+            "--start-index".to_string(),
+            range_start.to_string(),
+            "--end-index".to_string(),
+            range_end.to_string(),
+            "--state-dir".to_string(),
+            machine.name.clone(),
+        ]);
+        let line = command.iter().map(|arg| shell_quote(arg)).collect::<Vec<_>>().join(" ");
+        println!();
+        println!(
+            "{}: {}..{} ({} candidates, about {:.1} hours at {:.0}/s)",
+            machine.name,
+            range_start,
+            range_end,
+            range_end - range_start,
+            (range_end - range_start) as f64 / machine.rate / 3600.0,
+            machine.rate
+        );
+        println!("  {}", line);
+        if let Some(dir) = output_dir {
+            let file = Path::new(dir).join(format!("{}.sh", machine.name));
+            fs::write(&file, format!("#!/bin/sh\n# Work unit for {}\nexec {}\n", machine.name, line))
+                .map_err(|e| anyhow::anyhow!("Failed to write work unit {}: {}", file.display(), e))?;
+        }
+    }
+    if let Some(dir) = output_dir {
+        println!("\nWork units written to {}", dir);
+    }
+    Ok(())
+}
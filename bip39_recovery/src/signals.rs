@@ -0,0 +1,121 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+
+use anyhow::Result;
+
+type InterruptHandler = Box<dyn Fn() + Send + Sync + 'static>;
+
+static HANDLER: OnceLock<InterruptHandler> = OnceLock::new();
+static HANDLING: AtomicBool = AtomicBool::new(false);
+
+/// Runs `handler` (checkpoint and exit) when the user interrupts the run.
+/// Besides Ctrl+C this covers SIGTERM on Unix and, on Windows, the console
+/// window being closed and the user logging off or shutting down.
+pub fn install_interrupt_handler(handler: impl Fn() + Send + Sync + 'static) -> Result<()> {
+    HANDLER
+        .set(Box::new(handler))
+        .map_err(|_| anyhow::anyhow!("Interrupt handler already installed"))?;
+    ctrlc::set_handler(run_handler).map_err(|e| anyhow::anyhow!("Failed to set Ctrl+C handler: {}", e))?;
+    #[cfg(unix)]
+    unix::install_terminate_handler()?;
+    #[cfg(windows)]
+    windows::install_console_handler()?;
+    Ok(())
+}
+
+/// Checkpoints and exits through the installed handler, as if the user had
+/// pressed Ctrl+C.
+pub fn interrupt() {
+    run_handler();
+}
+
+/// Out-of-band requests a running search reacts to.
+#[derive(Clone, Copy, Debug)]
+pub enum Request {
+    /// Print stage timings (SIGUSR1)
+    Stats,
+    /// Re-read the target database (SIGHUP)
+    Reload,
+}
+
+/// Sets `flag` whenever the signal mapped to `request` arrives. A no-op on
+/// platforms without those signals.
+pub fn register_request(request: Request, flag: Arc<AtomicBool>) -> Result<()> {
+    #[cfg(unix)]
+    {
+        let signal = match request {
+            Request::Stats => signal_hook::consts::SIGUSR1,
+            Request::Reload => signal_hook::consts::SIGHUP,
+        };
+        signal_hook::flag::register(signal, flag)
+            .map_err(|e| anyhow::anyhow!("Failed to set {:?} signal handler: {}", request, e))?;
+    }
+    #[cfg(not(unix))]
+    let _ = (request, flag);
+    Ok(())
+}
+
+fn run_handler() {
+    // A Ctrl+C followed by closing the window must not checkpoint twice
+    if HANDLING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    if let Some(handler) = HANDLER.get() {
+        handler();
+    }
+}
+
+#[cfg(unix)]
+mod unix {
+    use anyhow::Result;
+    use signal_hook::consts::SIGTERM;
+    use signal_hook::iterator::Signals;
+
+    // SIGTERM (e.g. `systemctl stop`) checkpoints like Ctrl+C. The handler
+    // runs on a regular thread since it does file I/O.
+    pub fn install_terminate_handler() -> Result<()> {
+        let mut signals =
+            Signals::new([SIGTERM]).map_err(|e| anyhow::anyhow!("Failed to set SIGTERM handler: {}", e))?;
+        std::thread::spawn(move || {
+            if signals.forever().next().is_some() {
+                super::run_handler();
+            }
+        });
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use anyhow::Result;
+    use windows_sys::Win32::Foundation::{BOOL, FALSE, TRUE};
+    use windows_sys::Win32::System::Console::{
+        SetConsoleCtrlHandler, CTRL_CLOSE_EVENT, CTRL_LOGOFF_EVENT, CTRL_SHUTDOWN_EVENT,
+    };
+
+    // ctrlc only signals its own thread and returns, after which Windows
+    // terminates the process for close/logoff/shutdown events. Doing the
+    // checkpoint inside the console handler keeps the process alive until the
+    // progress is on disk.
+    unsafe extern "system" fn console_handler(ctrl_type: u32) -> BOOL {
+        match ctrl_type {
+            CTRL_CLOSE_EVENT | CTRL_LOGOFF_EVENT | CTRL_SHUTDOWN_EVENT => {
+                super::run_handler();
+                TRUE
+            }
+            // Ctrl+C and Ctrl+Break fall through to the ctrlc handler
+            _ => FALSE,
+        }
+    }
+
+    pub fn install_console_handler() -> Result<()> {
+        // Handlers registered later run first, so this sees events before ctrlc
+        if unsafe { SetConsoleCtrlHandler(Some(console_handler), TRUE) } == FALSE {
+            return Err(anyhow::anyhow!(
+                "Failed to set console control handler: {}",
+                std::io::Error::last_os_error()
+            ));
+        }
+        Ok(())
+    }
+}
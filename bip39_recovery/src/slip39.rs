@@ -0,0 +1,356 @@
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+use anyhow::Result;
+use bitcoin::hashes::hmac::{Hmac, HmacEngine};
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+
+/// The SLIP-39 wordlist: 1024 words, each fixed by its first four letters.
+const WORDS: &str = include_str!("slip39_english.txt");
+
+/// Words of the checksum at the end of every share.
+const CHECKSUM_WORDS: usize = 3;
+
+/// Words of the identifier, flags, and group and member fields.
+const METADATA_WORDS: usize = 4 + CHECKSUM_WORDS;
+
+/// Share lengths for 128- and 256-bit master secrets.
+pub const SHARE_LENGTHS: [usize; 2] = [20, 33];
+
+/// PBKDF2 rounds of the whole Feistel cipher at iteration exponent 0.
+const BASE_ITERATIONS: u32 = 10_000;
+
+const FEISTEL_ROUNDS: u8 = 4;
+
+/// Interpolation points of the secret and of its digest share.
+const SECRET_INDEX: u8 = 255;
+const DIGEST_INDEX: u8 = 254;
+
+const DIGEST_LEN: usize = 4;
+
+fn wordlist() -> &'static Vec<&'static str> {
+    static LIST: OnceLock<Vec<&'static str>> = OnceLock::new();
+    LIST.get_or_init(|| WORDS.lines().collect())
+}
+
+/// Index of `word`, which may be abbreviated to its first four letters.
+pub fn word_index(word: &str) -> Option<u16> {
+    let word = word.to_lowercase();
+    let list = wordlist();
+    let found = match list.binary_search(&word.as_str()) {
+        Ok(index) => Some(index),
+        Err(_) if word.len() >= 4 => list.iter().position(|candidate| candidate.starts_with(&word[..4])),
+        Err(_) => None,
+    };
+    found.map(|index| index as u16)
+}
+
+pub fn word(index: u16) -> &'static str {
+    wordlist()[usize::from(index)]
+}
+
+/// RS1024 over the customization string and the share's words, which is 1
+/// for a valid share; a random ordering passes about one time in 2^30.
+fn polymod(customization: &[u8], indices: &[u16]) -> u32 {
+    const GENERATOR: [u32; 10] = [
+        0xE0E040, 0x1C1C080, 0x3838100, 0x7070200, 0xE0E0009, 0x1C0C2412, 0x38086C24, 0x3090FC48, 0x21B1F890, 0x3F3F120,
+    ];
+    let mut checksum: u32 = 1;
+    for value in customization.iter().map(|&byte| u32::from(byte)).chain(indices.iter().map(|&index| u32::from(index))) {
+        let top = checksum >> 20;
+        checksum = (checksum & 0xFFFFF) << 10 ^ value;
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 != 0 {
+                checksum ^= generator;
+            }
+        }
+    }
+    checksum
+}
+
+/// Whether the words of a share carry a valid checksum, under the
+/// customization its extendable flag selects.
+pub fn checksum_valid(indices: &[u16]) -> bool {
+    if indices.len() < 2 {
+        return false;
+    }
+    let customization: &[u8] = if indices[1] & (1 << 4) != 0 { b"shamir_extendable" } else { b"shamir" };
+    polymod(customization, indices) == 1
+}
+
+/// One share, parsed from its words.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Share {
+    pub identifier: u16,
+    pub extendable: bool,
+    pub iteration_exponent: u8,
+    pub group_index: u8,
+    pub group_threshold: u8,
+    pub group_count: u8,
+    pub member_index: u8,
+    pub member_threshold: u8,
+    pub value: Vec<u8>,
+}
+
+impl Share {
+    /// Parses a share from its word indices, checking the checksum and the
+    /// padding of the value.
+    pub fn from_indices(indices: &[u16]) -> Result<Self> {
+        if !SHARE_LENGTHS.contains(&indices.len()) {
+            return Err(anyhow::anyhow!("A share has 20 or 33 words, got {}", indices.len()));
+        }
+        if !checksum_valid(indices) {
+            return Err(anyhow::anyhow!("Share checksum is invalid"));
+        }
+        let field = |word: usize, shift: u32, bits: u32| ((indices[word] >> shift) & ((1 << bits) - 1)) as u8;
+        let identifier = indices[0] << 5 | indices[1] >> 5;
+        let group_threshold = field(2, 2, 4) + 1;
+        let group_count = ((indices[2] & 0b11) << 2 | indices[3] >> 8) as u8 + 1;
+
+        // The value words hold the secret behind zero padding bits
+        let value_words = &indices[METADATA_WORDS - CHECKSUM_WORDS..indices.len() - CHECKSUM_WORDS];
+        let value_bits = value_words.len() * 10;
+        let padding = value_bits % 16;
+        let mut bits: Vec<bool> =
+            value_words.iter().flat_map(|&word| (0..10).rev().map(move |bit| (word >> bit) & 1 != 0)).collect();
+        if bits.drain(..padding).any(|bit| bit) {
+            return Err(anyhow::anyhow!("Share padding is not zero"));
+        }
+        let value = bits.chunks(8).map(|byte| byte.iter().fold(0u8, |acc, &bit| acc << 1 | u8::from(bit))).collect();
+
+        if group_threshold > group_count {
+            return Err(anyhow::anyhow!("Share group threshold {} exceeds its group count {}", group_threshold, group_count));
+        }
+        Ok(Self {
+            identifier,
+            extendable: indices[1] & (1 << 4) != 0,
+            iteration_exponent: field(1, 0, 4),
+            group_index: field(2, 6, 4),
+            group_threshold,
+            group_count,
+            member_index: field(3, 4, 4),
+            member_threshold: field(3, 0, 4) + 1,
+            value,
+        })
+    }
+
+    pub fn from_words(words: &[&str]) -> Result<Self> {
+        let indices = words
+            .iter()
+            .map(|word| word_index(word).ok_or_else(|| anyhow::anyhow!("{} is not a SLIP-39 word", word)))
+            .collect::<Result<Vec<u16>>>()?;
+        Self::from_indices(&indices)
+    }
+
+    /// Whether `other` can belong to the same backup.
+    pub fn compatible(&self, other: &Share) -> bool {
+        (self.identifier, self.extendable, self.iteration_exponent, self.group_threshold, self.group_count)
+            == (other.identifier, other.extendable, other.iteration_exponent, other.group_threshold, other.group_count)
+            && self.value.len() == other.value.len()
+    }
+}
+
+/// Arithmetic in GF(256) with the Rijndael polynomial, as log and exp
+/// tables over the generator 3.
+struct Field {
+    exp: [u8; 255],
+    log: [u8; 256],
+}
+
+impl Field {
+    fn new() -> Self {
+        let mut field = Self { exp: [0; 255], log: [0; 256] };
+        let mut value: u16 = 1;
+        for i in 0..255 {
+            field.exp[i] = value as u8;
+            field.log[value as usize] = i as u8;
+            value ^= value << 1;
+            if value & 0x100 != 0 {
+                value ^= 0x11B;
+            }
+        }
+        field
+    }
+
+    /// The value at `x` of the polynomial through `points`.
+    fn interpolate(&self, points: &[(u8, &[u8])], x: u8) -> Vec<u8> {
+        if let Some((_, value)) = points.iter().find(|(point, _)| *point == x) {
+            return value.to_vec();
+        }
+        let log = |value: u8| u32::from(self.log[usize::from(value)]);
+        let log_product: u32 = points.iter().map(|&(point, _)| log(point ^ x)).sum();
+        let mut result = vec![0u8; points[0].1.len()];
+        for &(point, value) in points {
+            let others: u32 = points.iter().map(|&(other, _)| log(point ^ other)).sum();
+            // Each sum is under 255 * 17 with at most 16 points, so adding
+            // 255 * 34 keeps the difference positive
+            let basis = (log_product + 255 * 34 - log(point ^ x) - others) % 255;
+            for (byte, &share) in result.iter_mut().zip(value) {
+                if share != 0 {
+                    *byte ^= self.exp[((log(share) + basis) % 255) as usize];
+                }
+            }
+        }
+        result
+    }
+}
+
+fn hmac_sha256(key: &[u8], data: &[&[u8]]) -> [u8; 32] {
+    let mut engine = HmacEngine::<sha256::Hash>::new(key);
+    for part in data {
+        engine.input(part);
+    }
+    Hmac::<sha256::Hash>::from_engine(engine).to_byte_array()
+}
+
+/// The secret shared by `points` with `threshold`, checked against its
+/// digest share when there is more than one.
+fn recover(field: &Field, threshold: u8, points: &[(u8, &[u8])]) -> Result<Vec<u8>> {
+    if points.len() < usize::from(threshold) {
+        return Err(anyhow::anyhow!("{} of {} shares needed", points.len(), threshold));
+    }
+    let points = &points[..usize::from(threshold)];
+    if threshold == 1 {
+        return Ok(points[0].1.to_vec());
+    }
+    let secret = field.interpolate(points, SECRET_INDEX);
+    let digest = field.interpolate(points, DIGEST_INDEX);
+    if hmac_sha256(&digest[DIGEST_LEN..], &[&secret])[..DIGEST_LEN] != digest[..DIGEST_LEN] {
+        return Err(anyhow::anyhow!("Shares don't agree on a secret (digest mismatch)"));
+    }
+    Ok(secret)
+}
+
+/// The encrypted master secret of `shares`, once each group that's needed
+/// has its member threshold.
+pub fn combine(shares: &[Share]) -> Result<Vec<u8>> {
+    let first = shares.first().ok_or_else(|| anyhow::anyhow!("No shares given"))?;
+    if let Some(other) = shares.iter().find(|share| !first.compatible(share)) {
+        return Err(anyhow::anyhow!(
+            "Shares {} and {} are from different backups",
+            first.identifier,
+            other.identifier
+        ));
+    }
+    let mut groups: BTreeMap<u8, Vec<&Share>> = BTreeMap::new();
+    for share in shares {
+        let members = groups.entry(share.group_index).or_default();
+        if !members.iter().any(|member| member.member_index == share.member_index) {
+            members.push(share);
+        }
+    }
+    let field = Field::new();
+    let mut group_secrets = Vec::new();
+    for (&group_index, members) in &groups {
+        let points: Vec<(u8, &[u8])> = members.iter().map(|share| (share.member_index, share.value.as_slice())).collect();
+        // A group short of its threshold may simply not be needed
+        if let Ok(secret) = recover(&field, members[0].member_threshold, &points) {
+            group_secrets.push((group_index, secret));
+        }
+    }
+    let points: Vec<(u8, &[u8])> = group_secrets.iter().map(|(index, secret)| (*index, secret.as_slice())).collect();
+    recover(&field, first.group_threshold, &points)
+        .map_err(|e| anyhow::anyhow!("Groups: {} (complete groups: {} of {} needed)", e, points.len(), first.group_threshold))
+}
+
+/// PBKDF2-HMAC-SHA256 with a single output block, as much of it as `len`.
+fn pbkdf2_block(password: &[u8], salt: &[u8], rounds: u32, len: usize) -> Vec<u8> {
+    let mut block = hmac_sha256(password, &[salt, &1u32.to_be_bytes()]);
+    let mut output = block;
+    for _ in 1..rounds {
+        block = hmac_sha256(password, &[&block]);
+        for (byte, next) in output.iter_mut().zip(block) {
+            *byte ^= next;
+        }
+    }
+    output[..len].to_vec()
+}
+
+/// The master secret: the encrypted one run back through the four-round
+/// Feistel cipher keyed by the passphrase.
+pub fn decrypt(encrypted: &[u8], passphrase: &str, share: &Share) -> Vec<u8> {
+    let half = encrypted.len() / 2;
+    let (mut left, mut right) = (encrypted[..half].to_vec(), encrypted[half..].to_vec());
+    let mut salt_prefix = Vec::new();
+    if !share.extendable {
+        salt_prefix.extend_from_slice(b"shamir");
+        salt_prefix.extend_from_slice(&share.identifier.to_be_bytes());
+    }
+    let rounds = (BASE_ITERATIONS << share.iteration_exponent) / u32::from(FEISTEL_ROUNDS);
+    for round in (0..FEISTEL_ROUNDS).rev() {
+        let mut password = vec![round];
+        password.extend_from_slice(passphrase.as_bytes());
+        let salt = [salt_prefix.as_slice(), right.as_slice()].concat();
+        let key = pbkdf2_block(&password, &salt, rounds, half);
+        let next: Vec<u8> = left.iter().zip(&key).map(|(byte, key)| byte ^ key).collect();
+        left = std::mem::replace(&mut right, next);
+    }
+    [right, left].concat()
+}
+
+/// The master secret behind `shares`, which is also the BIP32 seed.
+pub fn master_secret(shares: &[Share], passphrase: &str) -> Result<Vec<u8>> {
+    let encrypted = combine(shares)?;
+    Ok(decrypt(&encrypted, passphrase, &shares[0]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Shares of a SLIP-0039 reference vector, split into words.
+    fn shares(mnemonics: &[&str]) -> Result<Vec<Share>> {
+        mnemonics.iter().map(|mnemonic| Share::from_words(&mnemonic.split_whitespace().collect::<Vec<_>>())).collect()
+    }
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    /// Vector 1: a single 128-bit share, no sharing.
+    #[test]
+    fn valid_mnemonic_without_sharing() {
+        let shares = shares(&[
+            "duckling enlarge academic academic agency result length solution fridge kidney coal piece deal \
+             husband erode duke ajar critical decision keyboard",
+        ])
+        .unwrap();
+        assert_eq!(hex(&master_secret(&shares, "TREZOR").unwrap()), "bb54aac4b89dc868ba37d9cc21b2cece");
+    }
+
+    /// Vector 2: vector 1 with its last word changed.
+    #[test]
+    fn invalid_checksum() {
+        let words = "duckling enlarge academic academic agency result length solution fridge kidney coal piece \
+                     deal husband erode duke ajar critical decision kidney";
+        let indices: Vec<u16> = words.split_whitespace().map(|word| word_index(word).unwrap()).collect();
+        assert!(!checksum_valid(&indices));
+        assert!(shares(&[words]).is_err());
+    }
+
+    /// Vector 4: two shares of a 2-of-3 split, which are enough in either
+    /// order, while one alone isn't.
+    #[test]
+    fn basic_sharing_two_of_three() {
+        let mnemonics = [
+            "shadow pistol academic always adequate wildlife fancy gross oasis cylinder mustang wrist rescue \
+             view short owner flip making coding armed",
+            "shadow pistol academic acid actress prayer class unknown daughter sweater depict flip twice unkind \
+             craft early superior advocate guest smoking",
+        ];
+        let both = shares(&mnemonics).unwrap();
+        assert_eq!((both[0].member_threshold, both[0].group_threshold), (2, 1));
+        assert_eq!(hex(&master_secret(&both, "TREZOR").unwrap()), "b43ceb7e57a0ea8766221624d01b0864");
+        let reversed: Vec<Share> = both.iter().rev().cloned().collect();
+        assert_eq!(hex(&master_secret(&reversed, "TREZOR").unwrap()), "b43ceb7e57a0ea8766221624d01b0864");
+        assert!(combine(&both[..1]).is_err());
+    }
+
+    #[test]
+    fn wordlist_round_trips() {
+        for index in 0..1024 {
+            assert_eq!(word_index(word(index)), Some(index));
+        }
+        assert_eq!(word_index("bitcoin"), None);
+    }
+}
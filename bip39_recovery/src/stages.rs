@@ -0,0 +1,121 @@
+use std::cell::Cell;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+/// Only one candidate in this many is timed, keeping `Instant::now()` calls
+/// out of the hot loop for the rest.
+const SAMPLE_EVERY: u32 = 64;
+
+#[derive(Clone, Copy, Debug)]
+pub enum Stage {
+    Checksum,
+    Pbkdf2,
+    Bip32,
+    Hashing,
+    Matching,
+}
+
+const STAGES: [(Stage, &str); 5] = [
+    (Stage::Checksum, "Checksum filter"),
+    (Stage::Pbkdf2, "PBKDF2 seed"),
+    (Stage::Bip32, "BIP-32 derivation"),
+    (Stage::Hashing, "Address hashing"),
+    (Stage::Matching, "Target matching"),
+];
+
+thread_local! {
+    static SAMPLE_COUNTER: Cell<u32> = const { Cell::new(0) };
+}
+
+/// Sampled wall-clock time spent in each pipeline stage across all workers.
+#[derive(Default)]
+pub struct StageStats {
+    nanos: [AtomicU64; 5],
+    samples: [AtomicU64; 5],
+}
+
+impl StageStats {
+    /// Starts timing the current candidate if it falls on the sampling grid;
+    /// otherwise the returned sample's laps are no-ops.
+    pub fn sample(&self) -> StageSample<'_> {
+        let sampled = SAMPLE_COUNTER.with(|counter| {
+            let next = counter.get().wrapping_add(1);
+            counter.set(next);
+            next % SAMPLE_EVERY == 0
+        });
+        StageSample { stats: self, last: sampled.then(Instant::now) }
+    }
+
+    fn record(&self, stage: Stage, nanos: u64) {
+        self.nanos[stage as usize].fetch_add(nanos, Ordering::Relaxed);
+        self.samples[stage as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn average_nanos(&self, stage: Stage) -> Option<f64> {
+        let samples = self.samples[stage as usize].load(Ordering::Relaxed);
+        (samples > 0).then(|| self.nanos[stage as usize].load(Ordering::Relaxed) as f64 / samples as f64)
+    }
+
+    /// Cost of the checksum filter relative to deriving one candidate.
+    pub fn filter_weight(&self) -> Option<f64> {
+        let filter = self.average_nanos(Stage::Checksum)?;
+        let derive: f64 = [Stage::Pbkdf2, Stage::Bip32, Stage::Hashing, Stage::Matching]
+            .into_iter()
+            .filter_map(|stage| self.average_nanos(stage))
+            .sum();
+        (derive > 0.0).then(|| filter / derive)
+    }
+
+    pub fn report(&self) -> Vec<String> {
+        let totals: Vec<(u64, u64)> = STAGES
+            .iter()
+            .map(|(stage, _)| {
+                let i = *stage as usize;
+                (self.nanos[i].load(Ordering::Relaxed), self.samples[i].load(Ordering::Relaxed))
+            })
+            .collect();
+        let per_candidate: Vec<f64> = totals
+            .iter()
+            .map(|&(nanos, samples)| if samples > 0 { nanos as f64 / samples as f64 } else { 0.0 })
+            .collect();
+        // Weight each stage by how often it's reached, e.g. PBKDF2 only runs
+        // for candidates that survive the checksum filter
+        let reached = totals[0].1.max(1) as f64;
+        let weighted: Vec<f64> = per_candidate
+            .iter()
+            .zip(&totals)
+            .map(|(avg, &(_, samples))| avg * samples as f64 / reached)
+            .collect();
+        let weighted_total: f64 = weighted.iter().sum();
+
+        let mut lines = vec![format!("Stage timings ({} sampled candidates, 1 in {}):", totals[0].1, SAMPLE_EVERY)];
+        for (((_, label), avg), share) in STAGES.iter().zip(&per_candidate).zip(&weighted) {
+            let rate = if *avg > 0.0 { 1e9 / avg } else { 0.0 };
+            let percent = if weighted_total > 0.0 { share * 100.0 / weighted_total } else { 0.0 };
+            lines.push(format!(
+                "  {:<18} {:>10.2} us/candidate {:>12.0} /sec/thread {:>6.1}% of time",
+                label,
+                avg / 1000.0,
+                rate,
+                percent
+            ));
+        }
+        lines
+    }
+}
+
+pub struct StageSample<'a> {
+    stats: &'a StageStats,
+    last: Option<Instant>,
+}
+
+impl StageSample<'_> {
+    /// Charges the time since the previous lap to `stage`.
+    pub fn lap(&mut self, stage: Stage) {
+        if let Some(last) = self.last {
+            let now = Instant::now();
+            self.stats.record(stage, now.duration_since(last).as_nanos() as u64);
+            self.last = Some(now);
+        }
+    }
+}
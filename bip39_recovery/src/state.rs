@@ -0,0 +1,130 @@
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::Result;
+use log::{info, warn};
+
+const LOCK_FILE_NAME: &str = "run.lock";
+
+/// Directory holding every artifact of a single recovery job (log, progress,
+/// results), guarded by a lock file so two runs can't share the same state.
+pub struct StateDir {
+    root: Option<PathBuf>,
+    lock: Option<RunLock>,
+    // Run-scoped files (pidfile, control socket) removed along with the lock
+    ephemeral: Mutex<Vec<PathBuf>>,
+}
+
+impl StateDir {
+    /// Without a state directory, artifacts keep resolving against the CWD
+    /// and no lock is taken, matching the historical behaviour.
+    pub fn open(root: Option<&str>) -> Result<Self> {
+        let Some(root) = root else {
+            return Ok(Self { root: None, lock: None, ephemeral: Mutex::new(Vec::new()) });
+        };
+        let root = PathBuf::from(root);
+        fs::create_dir_all(&root)
+            .map_err(|e| anyhow::anyhow!("Failed to create state directory {}: {}", root.display(), e))?;
+        let lock = RunLock::acquire(root.join(LOCK_FILE_NAME))?;
+        Ok(Self { root: Some(root), lock: Some(lock), ephemeral: Mutex::new(Vec::new()) })
+    }
+
+    /// Resolves paths inside `root` without locking it, for tools that talk
+    /// to the run that holds the lock.
+    pub fn open_unlocked(root: &str) -> Self {
+        Self { root: Some(PathBuf::from(root)), lock: None, ephemeral: Mutex::new(Vec::new()) }
+    }
+
+    /// Relative artifact paths are placed inside the state directory;
+    /// absolute paths are left untouched.
+    pub fn resolve(&self, file: &str) -> String {
+        match &self.root {
+            Some(root) if Path::new(file).is_relative() => root.join(file).to_string_lossy().into_owned(),
+            _ => file.to_string(),
+        }
+    }
+
+    /// Deletes `file` when the run releases the directory.
+    pub fn remove_on_release(&self, file: &str) {
+        self.ephemeral.lock().unwrap().push(PathBuf::from(file));
+    }
+
+    pub fn release(&self) {
+        for file in self.ephemeral.lock().unwrap().drain(..) {
+            if let Err(e) = fs::remove_file(&file) {
+                if e.kind() != ErrorKind::NotFound {
+                    warn!("Failed to remove {}: {}", file.display(), e);
+                }
+            }
+        }
+        if let Some(lock) = &self.lock {
+            lock.release();
+        }
+    }
+}
+
+pub struct RunLock {
+    path: PathBuf,
+}
+
+impl RunLock {
+    fn acquire(path: PathBuf) -> Result<Self> {
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    writeln!(file, "{}", std::process::id())
+                        .map_err(|e| anyhow::anyhow!("Failed to write lock file {}: {}", path.display(), e))?;
+                    info!("Acquired run lock {}", path.display());
+                    return Ok(Self { path });
+                }
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    let owner = fs::read_to_string(&path).unwrap_or_default();
+                    let owner = owner.trim();
+                    if lock_is_stale(owner) {
+                        warn!("Removing stale run lock {} (pid {} is not running)", path.display(), owner);
+                        fs::remove_file(&path)
+                            .map_err(|e| anyhow::anyhow!("Failed to remove stale lock file {}: {}", path.display(), e))?;
+                        continue;
+                    }
+                    return Err(anyhow::anyhow!(
+                        "State directory is locked by another run (pid {}). Remove {} if that run is no longer active.",
+                        if owner.is_empty() { "unknown" } else { owner },
+                        path.display()
+                    ));
+                }
+                Err(e) => {
+                    return Err(anyhow::anyhow!("Failed to create lock file {}: {}", path.display(), e));
+                }
+            }
+        }
+    }
+
+    fn release(&self) {
+        if let Err(e) = fs::remove_file(&self.path) {
+            if e.kind() != ErrorKind::NotFound {
+                warn!("Failed to remove lock file {}: {}", self.path.display(), e);
+            }
+        }
+    }
+}
+
+impl Drop for RunLock {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn lock_is_stale(owner: &str) -> bool {
+    match owner.parse::<u32>() {
+        Ok(pid) => !Path::new(&format!("/proc/{}", pid)).exists(),
+        Err(_) => false,
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn lock_is_stale(_owner: &str) -> bool {
+    false
+}
@@ -0,0 +1,117 @@
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+/// Why a search ended before exhausting its range. Each reason exits with
+/// its own status so scripts can tell them apart from a finished search (0),
+/// except an interrupt, which the user asked for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    Matches,
+    Coverage,
+    Index,
+    Throughput,
+    Runtime,
+    Interrupted,
+}
+
+impl StopReason {
+    pub fn exit_code(self) -> u8 {
+        match self {
+            StopReason::Matches => 10,
+            StopReason::Coverage => 11,
+            StopReason::Index => 12,
+            StopReason::Throughput => 13,
+            StopReason::Runtime => 14,
+            StopReason::Interrupted => 0,
+        }
+    }
+
+    pub fn describe(self) -> &'static str {
+        match self {
+            StopReason::Matches => "match limit reached",
+            StopReason::Coverage => "coverage target reached",
+            StopReason::Index => "stop index reached",
+            StopReason::Throughput => "throughput fell below the minimum",
+            StopReason::Runtime => "maximum runtime reached",
+            StopReason::Interrupted => "interrupted",
+        }
+    }
+}
+
+/// Criteria that end a search early with a checkpoint, beyond the first
+/// match that ends it by default.
+pub struct StopConditions {
+    pub max_matches: Option<usize>,
+    pub coverage_percent: Option<f64>,
+    pub stop_index: Option<u64>,
+    pub min_speed: Option<f64>,
+    pub speed_window: Duration,
+    pub max_runtime: Option<Duration>,
+    reason: OnceLock<StopReason>,
+}
+
+impl StopConditions {
+    pub fn new(
+        max_matches: Option<usize>,
+        coverage_percent: Option<f64>,
+        stop_index: Option<u64>,
+        min_speed: Option<f64>,
+        speed_window: Duration,
+        max_runtime: Option<Duration>,
+    ) -> Self {
+        Self { max_matches, coverage_percent, stop_index, min_speed, speed_window, max_runtime, reason: OnceLock::new() }
+    }
+
+    /// Records `reason` unless another condition already fired. Returns
+    /// whether this call was the one that stopped the search.
+    pub fn trigger(&self, reason: StopReason) -> bool {
+        self.reason.set(reason).is_ok()
+    }
+
+    pub fn reason(&self) -> Option<StopReason> {
+        self.reason.get().copied()
+    }
+
+    pub fn check_matches(&self, matches: usize) -> Option<StopReason> {
+        self.max_matches.filter(|&limit| matches >= limit).map(|_| StopReason::Matches)
+    }
+
+    /// Checks the index and coverage limits after `processed` candidates of
+    /// a `total`-candidate range starting at rank `start`.
+    pub fn check_progress(&self, start: u64, processed: u64, total: u64) -> Option<StopReason> {
+        if self.stop_index.is_some_and(|index| start.saturating_add(processed) >= index) {
+            return Some(StopReason::Index);
+        }
+        let percent = if total > 0 { processed as f64 * 100.0 / total as f64 } else { 100.0 };
+        self.coverage_percent.filter(|&target| percent >= target).map(|_| StopReason::Coverage)
+    }
+
+    pub fn check_runtime(&self, started: Instant) -> Option<StopReason> {
+        self.max_runtime.filter(|&limit| started.elapsed() >= limit).map(|_| StopReason::Runtime)
+    }
+}
+
+/// Throughput over fixed windows, so a slowdown is judged on recent work
+/// rather than the run's average.
+pub struct SpeedWindow {
+    started: Instant,
+    processed: u64,
+}
+
+impl SpeedWindow {
+    pub fn new(processed: u64) -> Self {
+        Self { started: Instant::now(), processed }
+    }
+
+    /// Candidates per second over the window once it is `length` long, then
+    /// starts the next window.
+    pub fn sample(&mut self, processed: u64, length: Duration) -> Option<f64> {
+        let elapsed = self.started.elapsed();
+        if elapsed < length {
+            return None;
+        }
+        let speed = processed.saturating_sub(self.processed) as f64 / elapsed.as_secs_f64();
+        *self = Self::new(processed);
+        Some(speed)
+    }
+}
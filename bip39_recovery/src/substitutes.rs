@@ -0,0 +1,134 @@
+use std::fs;
+
+use anyhow::Result;
+use bip39::Language;
+
+use crate::tokenlist;
+use crate::wordindex::{IndexBase, Resolver};
+
+/// A candidates file: the words each position may hold, one line per
+/// position in mnemonic order, for backups with smudged or ambiguous words:
+///
+/// ```text
+/// # comment
+/// cat,car,card        first word is one of these
+/// ability             second word is known
+/// 1024 1025           word numbers work too (see --word-index-base)
+/// ac%2a               as do tokenlist wildcards
+/// ```
+///
+/// Every combination is a candidate, ranked like a number whose digits are
+/// the choices per position, the last position turning fastest.
+#[derive(Clone, Debug)]
+pub struct Substitutes {
+    positions: Vec<Vec<String>>,
+    space: u64,
+}
+
+impl Substitutes {
+    pub fn load(file: &str, total_words: usize, base: IndexBase) -> Result<Self> {
+        let content = fs::read_to_string(file)
+            .map_err(|e| anyhow::anyhow!("Failed to read candidates file {}: {}", file, e))?;
+        Self::parse(&content, total_words, base)
+            .map_err(|e| anyhow::anyhow!("Invalid candidates file {}: {}", file, e))
+    }
+
+    pub fn parse(content: &str, total_words: usize, base: IndexBase) -> Result<Self> {
+        let lines: Vec<(usize, Vec<&str>)> = content
+            .lines()
+            .enumerate()
+            .map(|(number, line)| (number, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+            .map(|(number, line)| {
+                let entries = line.split(|c: char| c == ',' || c.is_whitespace()).filter(|entry| !entry.is_empty());
+                (number, entries.collect())
+            })
+            .collect();
+        if lines.len() != total_words {
+            return Err(anyhow::anyhow!("{} positions listed, expected {}", lines.len(), total_words));
+        }
+        let resolver = Resolver::new(
+            base,
+            lines.iter().flat_map(|(_, entries)| entries.iter().copied()),
+            "the candidates file",
+        )?;
+
+        let wordlist = Language::English.word_list();
+        let mut positions = Vec::with_capacity(lines.len());
+        for (number, entries) in lines {
+            let mut words: Vec<String> = Vec::new();
+            for entry in entries {
+                let expanded = if entry.contains('%') {
+                    tokenlist::expand_wildcard(entry)
+                } else {
+                    resolver.word(entry).map(|word| vec![word.to_lowercase()])
+                }
+                .map_err(|e| anyhow::anyhow!("line {}: {}", number + 1, e))?;
+                for word in expanded {
+                    if !wordlist.contains(&word.as_str()) {
+                        return Err(anyhow::anyhow!("line {}: {} is not a BIP39 word", number + 1, word));
+                    }
+                    if !words.contains(&word) {
+                        words.push(word);
+                    }
+                }
+            }
+            if words.is_empty() {
+                return Err(anyhow::anyhow!("line {}: no words listed", number + 1));
+            }
+            positions.push(words);
+        }
+        let space = positions.iter().try_fold(1u64, |space, words| space.checked_mul(words.len() as u64));
+        let space = space.ok_or_else(|| anyhow::anyhow!("too many combinations to rank"))?;
+        Ok(Self { positions, space })
+    }
+
+    /// Number of combinations, each one candidate rank.
+    pub fn space(&self) -> u64 {
+        self.space
+    }
+
+    /// Choices per position, in order.
+    pub fn choices(&self) -> Vec<usize> {
+        self.positions.iter().map(Vec::len).collect()
+    }
+
+    /// Candidates with ranks in `[start, end)`.
+    pub fn into_range(self, start: u64, end: u64) -> SubstituteRange {
+        let end = end.min(self.space);
+        let mut digits = vec![0; self.positions.len()];
+        let mut rest = start;
+        for (digit, words) in digits.iter_mut().zip(&self.positions).rev() {
+            *digit = (rest % words.len() as u64) as usize;
+            rest /= words.len() as u64;
+        }
+        SubstituteRange { positions: self.positions, digits, rank: start, end }
+    }
+}
+
+pub struct SubstituteRange {
+    positions: Vec<Vec<String>>,
+    digits: Vec<usize>,
+    rank: u64,
+    end: u64,
+}
+
+impl Iterator for SubstituteRange {
+    type Item = Option<Vec<String>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.rank >= self.end {
+            return None;
+        }
+        let words = self.digits.iter().zip(&self.positions).map(|(&digit, words)| words[digit].clone()).collect();
+        self.rank += 1;
+        for (digit, words) in self.digits.iter_mut().zip(&self.positions).rev() {
+            *digit += 1;
+            if *digit < words.len() {
+                break;
+            }
+            *digit = 0;
+        }
+        Some(Some(words))
+    }
+}
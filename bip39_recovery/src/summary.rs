@@ -0,0 +1,101 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Why candidates did or didn't make it through the pipeline, collected
+/// across all workers and printed when the run ends.
+#[derive(Default)]
+pub struct RunSummary {
+    wordlist_rejected: AtomicU64,
+    checksum_rejected: AtomicU64,
+    already_tested: AtomicU64,
+    derivation_errors: AtomicU64,
+    encoding_errors: AtomicU64,
+    unsupported_type: AtomicU64,
+    no_match: AtomicU64,
+    matched: AtomicU64,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Outcome {
+    WordlistRejected,
+    ChecksumRejected,
+    AlreadyTested,
+    DerivationError,
+    EncodingError,
+    UnsupportedType,
+    NoMatch,
+    Matched,
+}
+
+impl RunSummary {
+    pub fn record(&self, outcome: Outcome) {
+        let counter = match outcome {
+            Outcome::WordlistRejected => &self.wordlist_rejected,
+            Outcome::ChecksumRejected => &self.checksum_rejected,
+            Outcome::AlreadyTested => &self.already_tested,
+            Outcome::DerivationError => &self.derivation_errors,
+            Outcome::EncodingError => &self.encoding_errors,
+            Outcome::UnsupportedType => &self.unsupported_type,
+            Outcome::NoMatch => &self.no_match,
+            Outcome::Matched => &self.matched,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Candidates discarded before any key derivation.
+    pub fn rejected(&self) -> u64 {
+        self.wordlist_rejected.load(Ordering::Relaxed)
+            + self.checksum_rejected.load(Ordering::Relaxed)
+            + self.already_tested.load(Ordering::Relaxed)
+    }
+
+    /// Candidates that went through seed and key derivation.
+    pub fn derived(&self) -> u64 {
+        self.derivation_errors.load(Ordering::Relaxed)
+            + self.encoding_errors.load(Ordering::Relaxed)
+            + self.unsupported_type.load(Ordering::Relaxed)
+            + self.no_match.load(Ordering::Relaxed)
+            + self.matched.load(Ordering::Relaxed)
+    }
+
+    fn rows(&self) -> Vec<(&'static str, u64)> {
+        vec![
+            ("Rejected by wordlist check", self.wordlist_rejected.load(Ordering::Relaxed)),
+            ("Rejected by BIP-39 checksum", self.checksum_rejected.load(Ordering::Relaxed)),
+            ("Tested in an earlier run", self.already_tested.load(Ordering::Relaxed)),
+            ("Key derivation errors", self.derivation_errors.load(Ordering::Relaxed)),
+            ("Address encoding errors", self.encoding_errors.load(Ordering::Relaxed)),
+            ("Unsupported address type", self.unsupported_type.load(Ordering::Relaxed)),
+            ("Derived, no match", self.no_match.load(Ordering::Relaxed)),
+            ("Matched", self.matched.load(Ordering::Relaxed)),
+        ]
+    }
+
+    /// Breakdown lines plus hints for systemic misconfiguration, e.g. every
+    /// candidate failing the wordlist check.
+    pub fn report(&self) -> Vec<String> {
+        let rows = self.rows();
+        let total: u64 = rows.iter().map(|(_, count)| count).sum();
+        let mut lines = vec![format!("Candidate summary ({} examined):", total)];
+        for (label, count) in &rows {
+            if *count == 0 {
+                continue;
+            }
+            let percent = *count as f64 * 100.0 / total as f64;
+            lines.push(format!("  {:<30} {:>14} ({:>6.2}%)", label, count, percent));
+        }
+        if total == 0 {
+            return lines;
+        }
+        let all = |count: &AtomicU64| count.load(Ordering::Relaxed) == total;
+        if all(&self.wordlist_rejected) {
+            lines.push("Every candidate contained a word outside the BIP-39 wordlist; check the spelling of the known words.".to_string());
+        } else if all(&self.unsupported_type) {
+            lines.push("The address type is not supported; use p2pkh, p2sh-p2wpkh, p2wpkh, or p2tr.".to_string());
+        } else if all(&self.derivation_errors) {
+            lines.push("Every candidate failed key derivation; check the derivation path.".to_string());
+        } else if all(&self.encoding_errors) {
+            lines.push("Every candidate failed address encoding; check the network and address type.".to_string());
+        }
+        lines
+    }
+}
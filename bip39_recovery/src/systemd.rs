@@ -0,0 +1,99 @@
+use std::env;
+use std::time::Duration;
+
+use log::{debug, warn};
+
+/// Minimal sd_notify(3) client, so the tool can run as a `Type=notify`
+/// service with a watchdog without linking libsystemd.
+pub struct Notifier {
+    #[cfg(target_os = "linux")]
+    socket: std::os::unix::net::UnixDatagram,
+    #[cfg(target_os = "linux")]
+    address: std::os::unix::net::SocketAddr,
+    watchdog: Option<Duration>,
+}
+
+impl Notifier {
+    /// Returns `None` when not started by systemd (no `NOTIFY_SOCKET`).
+    #[cfg(target_os = "linux")]
+    pub fn from_env() -> Option<Self> {
+        use std::os::linux::net::SocketAddrExt;
+        use std::os::unix::net::{SocketAddr, UnixDatagram};
+
+        let path = env::var("NOTIFY_SOCKET").ok()?;
+        let address = match path.strip_prefix('@') {
+            Some(name) => SocketAddr::from_abstract_name(name.as_bytes()),
+            None => SocketAddr::from_pathname(&path),
+        };
+        let address = match address {
+            Ok(address) => address,
+            Err(e) => {
+                warn!("Invalid NOTIFY_SOCKET {}: {}", path, e);
+                return None;
+            }
+        };
+        let socket = match UnixDatagram::unbound() {
+            Ok(socket) => socket,
+            Err(e) => {
+                warn!("Failed to create notify socket: {}", e);
+                return None;
+            }
+        };
+        Some(Self { socket, address, watchdog: watchdog_from_env() })
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn from_env() -> Option<Self> {
+        warn!("--systemd is only supported on Linux");
+        None
+    }
+
+    /// How often the service manager expects a `WATCHDOG=1` ping, already
+    /// halved as recommended by sd_watchdog_enabled(3).
+    pub fn watchdog_interval(&self) -> Option<Duration> {
+        self.watchdog.map(|timeout| timeout / 2)
+    }
+
+    pub fn ready(&self) {
+        self.notify("READY=1");
+    }
+
+    pub fn status(&self, status: &str) {
+        // Newlines would split the message into separate assignments
+        self.notify(&format!("STATUS={}", status.replace('\n', " ")));
+    }
+
+    pub fn watchdog(&self) {
+        self.notify("WATCHDOG=1");
+    }
+
+    pub fn stopping(&self) {
+        self.notify("STOPPING=1");
+    }
+
+    #[cfg(target_os = "linux")]
+    fn notify(&self, state: &str) {
+        if let Err(e) = self.socket.send_to_addr(state.as_bytes(), &self.address) {
+            warn!("Failed to notify systemd ({}): {}", state, e);
+        } else {
+            debug!("Notified systemd: {}", state);
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn notify(&self, _state: &str) {}
+}
+
+fn watchdog_from_env() -> Option<Duration> {
+    // WATCHDOG_PID, when set, names the process that should send the pings
+    if let Ok(pid) = env::var("WATCHDOG_PID") {
+        if pid.parse::<u32>().ok() != Some(std::process::id()) {
+            return None;
+        }
+    }
+    let usec = env::var("WATCHDOG_USEC").ok()?.parse::<u64>().ok()?;
+    if usec == 0 {
+        return None;
+    }
+    Some(Duration::from_micros(usec))
+}
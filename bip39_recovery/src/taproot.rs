@@ -0,0 +1,23 @@
+use std::sync::OnceLock;
+
+use bitcoin::key::{TapTweak, TweakedPublicKey, UntweakedPublicKey};
+use bitcoin::{Address, Network};
+use secp256k1::{Secp256k1, VerifyOnly};
+
+/// Context for the BIP-341 tweak, shared by encoders that have none at hand.
+fn secp() -> &'static Secp256k1<VerifyOnly> {
+    static SECP: OnceLock<Secp256k1<VerifyOnly>> = OnceLock::new();
+    SECP.get_or_init(Secp256k1::verification_only)
+}
+
+/// The BIP-86 output key for a single-key wallet: the x-only key tweaked
+/// with no script tree.
+pub fn output_key(pubkey: &bitcoin::PublicKey) -> TweakedPublicKey {
+    let internal = UntweakedPublicKey::from(pubkey.inner);
+    internal.tap_tweak(secp(), None).0
+}
+
+/// The key-path-only P2TR (`bc1p...`) address of `pubkey`.
+pub fn address(pubkey: &bitcoin::PublicKey, network: Network) -> Address {
+    Address::p2tr_tweaked(output_key(pubkey), network)
+}
@@ -0,0 +1,119 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::str::FromStr;
+
+use anyhow::Result;
+use bitcoin::{Address, AddressType};
+
+use crate::ethereum;
+
+/// The addresses in an `--address-file` and the address types their
+/// encodings imply.
+pub struct TargetList {
+    pub addresses: HashSet<String>,
+    /// Supported types found among the entries, in order of first appearance
+    pub address_types: Vec<&'static str>,
+    /// Entries whose type couldn't be told from the encoding, e.g. other
+    /// coins' prefixes or script types the search doesn't derive
+    pub undetected: Vec<String>,
+}
+
+/// Reads one address per line; blank lines and `#` comments are skipped.
+pub fn load(file: &str) -> Result<TargetList> {
+    let content =
+        fs::read_to_string(file).map_err(|e| anyhow::anyhow!("Failed to read address file {}: {}", file, e))?;
+    let mut targets = TargetList { addresses: HashSet::new(), address_types: Vec::new(), undetected: Vec::new() };
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let address = ethereum::normalize(line).map_err(|e| anyhow::anyhow!("Address file {}: {}", file, e))?;
+        if !targets.addresses.insert(address.clone()) {
+            continue;
+        }
+        match detect(&address) {
+            Some(address_type) if !targets.address_types.contains(&address_type) => {
+                targets.address_types.push(address_type)
+            }
+            Some(_) => {}
+            None => targets.undetected.push(address),
+        }
+    }
+    if targets.addresses.is_empty() {
+        return Err(anyhow::anyhow!("Address file {} lists no addresses", file));
+    }
+    Ok(targets)
+}
+
+/// The address type an address's encoding implies. Pay-to-script-hash
+/// addresses are taken as `p2sh-p2wpkh`, the only script the search wraps,
+/// and `0x` accounts as `eth`.
+fn detect(address: &str) -> Option<&'static str> {
+    if ethereum::is_address(address) {
+        return Some(ethereum::ETH);
+    }
+    let address = Address::from_str(address).ok()?.assume_checked();
+    match address.address_type()? {
+        AddressType::P2pkh => Some("p2pkh"),
+        AddressType::P2sh => Some("p2sh-p2wpkh"),
+        AddressType::P2wpkh => Some("p2wpkh"),
+        AddressType::P2tr => Some("p2tr"),
+        _ => None,
+    }
+}
+
+/// A partially remembered address, e.g. `bc1qxy…k3t9`: the known start and
+/// end of its encoded form, with the middle left out.
+#[derive(Clone, Debug)]
+pub struct AddressPattern {
+    pub prefix: String,
+    pub suffix: String,
+}
+
+impl AddressPattern {
+    /// Splits on `…`, `...`, or `*` between the known start and end.
+    pub fn parse(pattern: &str) -> Result<Self> {
+        let pattern = pattern.trim();
+        let (prefix, suffix) = ["…", "...", "*"]
+            .iter()
+            .find_map(|separator| pattern.split_once(separator))
+            .ok_or_else(|| {
+                anyhow::anyhow!("Invalid address pattern {}: separate the known start and end with …, ..., or *", pattern)
+            })?;
+        if prefix.is_empty() && suffix.is_empty() {
+            return Err(anyhow::anyhow!("Invalid address pattern {}: nothing of the address is given", pattern));
+        }
+        Ok(Self { prefix: prefix.to_string(), suffix: suffix.to_string() })
+    }
+
+    pub fn matches(&self, address: &str) -> bool {
+        address.len() >= self.prefix.len() + self.suffix.len()
+            && address.starts_with(&self.prefix)
+            && address.ends_with(&self.suffix)
+    }
+
+    /// Roughly how many bits of a random address the pattern pins down. The
+    /// human-readable part and witness version of a bech32 address, or the
+    /// version character of a base58 one, are the same for every candidate
+    /// and don't count.
+    pub fn known_bits(&self) -> f64 {
+        let bech32_lead = self
+            .prefix
+            .find('1')
+            .filter(|&at| at > 0 && self.prefix[..at].bytes().all(|b| b.is_ascii_lowercase()))
+            .map(|at| at + 2);
+        let (lead, bits_per_char) = match bech32_lead {
+            Some(lead) => (lead, 5.0),
+            None => (1, 58f64.log2()),
+        };
+        let known = self.prefix.chars().count().saturating_sub(lead) + self.suffix.chars().count();
+        known as f64 * bits_per_char
+    }
+}
+
+impl fmt::Display for AddressPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}…{}", self.prefix, self.suffix)
+    }
+}
@@ -0,0 +1,185 @@
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::sync::{Mutex, RwLock};
+
+use anyhow::Result;
+use bip39::Mnemonic;
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+
+// Bytes kept from each key hash; 128 bits keeps a false skip out of reach
+// for any space this tool can search
+const KEY_LEN: usize = 16;
+
+type Key = [u8; KEY_LEN];
+
+/// Mnemonics fully tested in earlier runs, shared across runs through an
+/// append-only file of key hashes. A key covers the mnemonic's entropy and a
+/// fingerprint of the search configuration (paths, address types,
+/// passphrases, targets; see [`fingerprint`]), so candidates are only skipped
+/// when they were tested the same way, whatever fixed/permutable split
+/// produced them.
+pub struct TestedSet {
+    fingerprint: sha256::Hash,
+    seen: RwLock<HashSet<Key>>,
+    file: Mutex<File>,
+}
+
+/// Fingerprint of how mnemonics are tested: `config` describes the search
+/// matrix and passphrase settings, `addresses` is the loaded target set, in
+/// any order, and `files` are the inputs counted by content (a passphrase
+/// file, an address bloom list, a hash database), so editing any of them
+/// starts a fresh set.
+pub fn fingerprint(config: &str, addresses: Option<&HashSet<String>>, files: &[Option<&str>]) -> Result<String> {
+    let mut engine = sha256::Hash::engine();
+    engine.input(config.as_bytes());
+    if let Some(addresses) = addresses {
+        let mut sorted: Vec<&String> = addresses.iter().collect();
+        sorted.sort();
+        engine.input(format!("\n{} targets\n", sorted.len()).as_bytes());
+        for address in sorted {
+            engine.input(address.as_bytes());
+            engine.input(b"\n");
+        }
+    }
+    for file in files {
+        let Some(file) = file else {
+            engine.input(b"-\n");
+            continue;
+        };
+        let mut content = File::open(file).map_err(|e| anyhow::anyhow!("Failed to open {}: {}", file, e))?;
+        let mut file_engine = sha256::Hash::engine();
+        io::copy(&mut content, &mut file_engine).map_err(|e| anyhow::anyhow!("Failed to read {}: {}", file, e))?;
+        engine.input(sha256::Hash::from_engine(file_engine).as_byte_array());
+    }
+    Ok(sha256::Hash::from_engine(engine).to_string())
+}
+
+impl TestedSet {
+    pub fn open(path: &str, fingerprint: &str) -> Result<Self> {
+        let content = match fs::read(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(anyhow::anyhow!("Failed to read tested set {}: {}", path, e)),
+        };
+        // A trailing partial record is what an interrupted write leaves
+        let seen = content
+            .chunks_exact(KEY_LEN)
+            .map(|chunk| chunk.try_into().expect("chunks_exact yields full keys"))
+            .collect();
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| anyhow::anyhow!("Failed to open tested set {}: {}", path, e))?;
+        if content.len() % KEY_LEN != 0 {
+            file.set_len((content.len() - content.len() % KEY_LEN) as u64)
+                .map_err(|e| anyhow::anyhow!("Failed to repair tested set {}: {}", path, e))?;
+        }
+        Ok(Self { fingerprint: sha256::Hash::hash(fingerprint.as_bytes()), seen: RwLock::new(seen), file: Mutex::new(file) })
+    }
+
+    pub fn count(&self) -> usize {
+        self.seen.read().unwrap().len()
+    }
+
+    fn key(&self, mnemonic: &Mnemonic) -> Key {
+        let mut engine = sha256::Hash::engine();
+        engine.input(self.fingerprint.as_byte_array());
+        engine.input(&mnemonic.to_entropy());
+        let hash = sha256::Hash::from_engine(engine);
+        hash.as_byte_array()[..KEY_LEN].try_into().expect("hash is longer than a key")
+    }
+
+    pub fn contains(&self, mnemonic: &Mnemonic) -> bool {
+        self.seen.read().unwrap().contains(&self.key(mnemonic))
+    }
+
+    /// Records `mnemonic` as tested. Each key is written straight through, so
+    /// an interrupted run loses nothing it finished.
+    pub fn insert(&self, mnemonic: &Mnemonic) -> Result<()> {
+        let key = self.key(mnemonic);
+        if !self.seen.write().unwrap().insert(key) {
+            return Ok(());
+        }
+        self.file
+            .lock()
+            .unwrap()
+            .write_all(&key)
+            .map_err(|e| anyhow::anyhow!("Failed to append to tested set: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MNEMONIC: &str = "legal winner thank year wave sausage worth useful legal winner thank yellow";
+
+    fn set_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("bip39_recovery-tested-{}-{}", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        path.to_string_lossy().into_owned()
+    }
+
+    fn targets(addresses: &[&str]) -> HashSet<String> {
+        addresses.iter().map(|address| address.to_string()).collect()
+    }
+
+    #[test]
+    fn remembers_across_runs() {
+        let path = set_path("remembers");
+        let mnemonic = Mnemonic::parse(MNEMONIC).unwrap();
+        let tested = TestedSet::open(&path, "config").unwrap();
+        assert!(!tested.contains(&mnemonic));
+        tested.insert(&mnemonic).unwrap();
+        tested.insert(&mnemonic).unwrap();
+        drop(tested);
+        assert_eq!(fs::metadata(&path).unwrap().len(), KEY_LEN as u64);
+        let tested = TestedSet::open(&path, "config").unwrap();
+        assert_eq!(tested.count(), 1);
+        assert!(tested.contains(&mnemonic));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn drops_a_partial_record() {
+        let path = set_path("partial");
+        let mnemonic = Mnemonic::parse(MNEMONIC).unwrap();
+        TestedSet::open(&path, "config").unwrap().insert(&mnemonic).unwrap();
+        OpenOptions::new().append(true).open(&path).unwrap().write_all(&[1, 2, 3]).unwrap();
+        let tested = TestedSet::open(&path, "config").unwrap();
+        assert_eq!(tested.count(), 1);
+        assert_eq!(fs::metadata(&path).unwrap().len(), KEY_LEN as u64);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn changing_the_target_invalidates_the_set() {
+        let path = set_path("target");
+        let mnemonic = Mnemonic::parse(MNEMONIC).unwrap();
+        let first = fingerprint("config", Some(&targets(&["1A", "1B"])), &[None]).unwrap();
+        TestedSet::open(&path, &first).unwrap().insert(&mnemonic).unwrap();
+        // The same targets in another order are the same search
+        let reordered = fingerprint("config", Some(&targets(&["1B", "1A"])), &[None]).unwrap();
+        assert!(TestedSet::open(&path, &reordered).unwrap().contains(&mnemonic));
+        let other = fingerprint("config", Some(&targets(&["1A", "1C"])), &[None]).unwrap();
+        assert!(!TestedSet::open(&path, &other).unwrap().contains(&mnemonic));
+        let none = fingerprint("config", None, &[None]).unwrap();
+        assert!(!TestedSet::open(&path, &none).unwrap().contains(&mnemonic));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn files_count_by_content() {
+        let file = set_path("passphrases");
+        fs::write(&file, "one\ntwo\n").unwrap();
+        let before = fingerprint("config", None, &[Some(&file)]).unwrap();
+        assert_eq!(fingerprint("config", None, &[Some(&file)]).unwrap(), before);
+        fs::write(&file, "one\nthree\n").unwrap();
+        assert_ne!(fingerprint("config", None, &[Some(&file)]).unwrap(), before);
+        assert_ne!(fingerprint("config", None, &[None]).unwrap(), before);
+        fs::remove_file(&file).unwrap();
+        assert!(fingerprint("config", None, &[Some(&file)]).is_err());
+    }
+}
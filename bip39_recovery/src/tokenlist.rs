@@ -0,0 +1,456 @@
+use std::fs;
+
+use anyhow::Result;
+use bip39::Language;
+
+use crate::permutation::{self, PermutationRange};
+use crate::wordindex::{IndexBase, Resolver};
+
+/// Where a token may appear in the mnemonic, as 0-based positions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Anchor {
+    Anywhere,
+    At(usize),
+    Between(usize, usize),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Token {
+    word: String,
+    anchor: Anchor,
+}
+
+/// Tokens on one line are mutually exclusive; a `+` line must contribute one.
+#[derive(Clone, Debug)]
+struct Line {
+    tokens: Vec<usize>,
+    required: bool,
+}
+
+/// One way of picking a token (or none) from every line that adds up to a
+/// full mnemonic. Tokens anchored to a single position are pinned there and
+/// the rest are permuted over the free positions.
+#[derive(Clone, Debug)]
+struct Selection {
+    first_rank: u64,
+    pinned: Vec<Option<usize>>,
+    free: Vec<usize>,
+}
+
+/// A btcrecover-style tokenlist:
+///
+/// ```text
+/// # comment
+/// + ^abandon          required, first word
+/// ^2^ability          second word, if used
+/// zoo$                last word, if used
+/// ^3,5^able about     one of these at positions 3 to 5
+/// + acc%2a            any BIP39 word matching the wildcard
+/// 1024                word number 1024 (see --word-index-base)
+/// ```
+///
+/// Candidates are ranked selection by selection, then by the permutation of
+/// the free tokens, so the usual index ranges, sharding, and resume apply.
+#[derive(Clone)]
+pub struct TokenList {
+    total_words: usize,
+    tokens: Vec<Token>,
+    lines: Vec<Line>,
+    selections: Vec<Selection>,
+    space: u64,
+}
+
+impl TokenList {
+    pub fn load(file: &str, total_words: usize, base: IndexBase) -> Result<Self> {
+        let content = fs::read_to_string(file)
+            .map_err(|e| anyhow::anyhow!("Failed to read tokenlist {}: {}", file, e))?;
+        Self::parse(&content, total_words, base).map_err(|e| anyhow::anyhow!("Invalid tokenlist {}: {}", file, e))
+    }
+
+    pub fn parse(content: &str, total_words: usize, base: IndexBase) -> Result<Self> {
+        if total_words == 0 {
+            return Err(anyhow::anyhow!("total words must be at least 1"));
+        }
+        // Anchors first, so word numbers can be counted over the whole list
+        let mut parsed = Vec::new();
+        for (number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (required, rest) = match line.strip_prefix('+') {
+                Some(rest) if rest.starts_with(char::is_whitespace) => (true, rest),
+                _ => (false, line),
+            };
+            let mut raws = Vec::new();
+            for raw in rest.split_whitespace() {
+                let (anchor, word) = parse_anchor(raw, total_words)
+                    .map_err(|e| anyhow::anyhow!("line {}: {}", number + 1, e))?;
+                if word.is_empty() {
+                    return Err(anyhow::anyhow!("line {}: anchor {} has no token", number + 1, raw));
+                }
+                raws.push((anchor, word));
+            }
+            parsed.push((number, required, raws));
+        }
+        let resolver = Resolver::new(
+            base,
+            parsed.iter().flat_map(|(_, _, raws)| raws.iter().map(|&(_, word)| word)),
+            "the tokenlist",
+        )?;
+
+        let mut tokens = Vec::new();
+        let mut lines = Vec::new();
+        for (number, required, raws) in parsed {
+            let mut ids: Vec<usize> = Vec::new();
+            for (anchor, word) in raws {
+                let words = if word.contains('%') {
+                    expand_wildcard(word).map_err(|e| anyhow::anyhow!("line {}: {}", number + 1, e))?
+                } else {
+                    vec![resolver.word(word).map_err(|e| anyhow::anyhow!("line {}: {}", number + 1, e))?]
+                };
+                for word in words {
+                    if !ids.iter().any(|&id| tokens[id] == Token { word: word.clone(), anchor }) {
+                        ids.push(tokens.len());
+                        tokens.push(Token { word, anchor });
+                    }
+                }
+            }
+            if !ids.is_empty() {
+                lines.push(Line { tokens: ids, required });
+            }
+        }
+
+        let mut list = Self { total_words, tokens, lines, selections: Vec::new(), space: 0 };
+        let mut pinned = vec![None; total_words];
+        let mut free = Vec::new();
+        list.select(0, &mut pinned, &mut free);
+        if list.selections.is_empty() {
+            return Err(anyhow::anyhow!("no combination of tokens makes a {}-word mnemonic", total_words));
+        }
+        Ok(list)
+    }
+
+    /// Walks every choice per line, keeping those that fill exactly
+    /// `total_words` positions without two tokens pinned to the same one.
+    fn select(&mut self, line: usize, pinned: &mut [Option<usize>], free: &mut Vec<usize>) {
+        let used = pinned.iter().filter(|slot| slot.is_some()).count() + free.len();
+        if used > self.total_words || used + (self.lines.len() - line) < self.total_words {
+            return;
+        }
+        if line == self.lines.len() {
+            self.selections.push(Selection { first_rank: self.space, pinned: pinned.to_vec(), free: free.clone() });
+            self.space = self.space.saturating_add(permutation::factorial(free.len()));
+            return;
+        }
+        if !self.lines[line].required {
+            self.select(line + 1, pinned, free);
+        }
+        for i in 0..self.lines[line].tokens.len() {
+            let id = self.lines[line].tokens[i];
+            match self.tokens[id].anchor {
+                Anchor::At(position) => {
+                    if pinned[position].is_none() {
+                        pinned[position] = Some(id);
+                        self.select(line + 1, pinned, free);
+                        pinned[position] = None;
+                    }
+                }
+                Anchor::Anywhere | Anchor::Between(..) => {
+                    free.push(id);
+                    self.select(line + 1, pinned, free);
+                    free.pop();
+                }
+            }
+        }
+    }
+
+    /// Number of candidate ranks, including arrangements that a range
+    /// anchor later rules out.
+    pub fn space(&self) -> u64 {
+        self.space
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn selection_count(&self) -> usize {
+        self.selections.len()
+    }
+
+    /// Candidates with ranks in `[start, end)`. Ranks whose arrangement breaks
+    /// a range anchor yield `None` so they still count towards progress.
+    pub fn into_range(self, start: u64, end: u64) -> TokenRange {
+        let end = end.min(self.space);
+        let selection = self.selections.partition_point(|s| s.first_rank <= start).saturating_sub(1);
+        let first_rank = self.selections[selection].first_rank;
+        let perms = PermutationRange::new(self.selections[selection].free.clone(), start - first_rank, end - first_rank);
+        TokenRange { list: self, selection, perms, rank: start, end }
+    }
+}
+
+pub struct TokenRange {
+    list: TokenList,
+    selection: usize,
+    perms: PermutationRange<usize>,
+    rank: u64,
+    end: u64,
+}
+
+impl TokenRange {
+    fn arrange(&self, perm: Vec<usize>) -> Option<Vec<String>> {
+        let selection = &self.list.selections[self.selection];
+        let mut free = perm.into_iter();
+        let mut words = Vec::with_capacity(selection.pinned.len());
+        for (position, slot) in selection.pinned.iter().enumerate() {
+            let id = match slot {
+                Some(id) => *id,
+                None => free.next()?,
+            };
+            let token = &self.list.tokens[id];
+            if let Anchor::Between(first, last) = token.anchor {
+                if position < first || position > last {
+                    return None;
+                }
+            }
+            words.push(token.word.clone());
+        }
+        Some(words)
+    }
+}
+
+impl Iterator for TokenRange {
+    type Item = Option<Vec<String>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.rank < self.end {
+            if let Some((_, perm)) = self.perms.next() {
+                self.rank += 1;
+                return Some(self.arrange(perm));
+            }
+            self.selection += 1;
+            let selection = self.list.selections.get(self.selection)?;
+            self.perms = PermutationRange::new(selection.free.clone(), 0, self.end - selection.first_rank);
+        }
+        None
+    }
+}
+
+/// Splits `^N^word`, `^N,M^word`, `^word`, and `word$` into an anchor and the
+/// bare token. Positions in the file are 1-based.
+fn parse_anchor(raw: &str, total_words: usize) -> Result<(Anchor, &str)> {
+    if let Some(rest) = raw.strip_prefix('^') {
+        if let Some((spec, word)) = rest.split_once('^') {
+            if !spec.is_empty() && spec.chars().all(|c| c.is_ascii_digit() || c == ',') {
+                let anchor = match spec.split_once(',') {
+                    Some((first, last)) => {
+                        let first = if first.is_empty() { 1 } else { parse_position(first, total_words)? };
+                        let last = if last.is_empty() { total_words } else { parse_position(last, total_words)? };
+                        if first > last {
+                            return Err(anyhow::anyhow!("empty position range in {}", raw));
+                        }
+                        Anchor::Between(first - 1, last - 1)
+                    }
+                    None => Anchor::At(parse_position(spec, total_words)? - 1),
+                };
+                return Ok((anchor, word));
+            }
+        }
+        return Ok((Anchor::At(0), rest));
+    }
+    if let Some(word) = raw.strip_suffix('$') {
+        return Ok((Anchor::At(total_words - 1), word));
+    }
+    Ok((Anchor::Anywhere, raw))
+}
+
+fn parse_position(value: &str, total_words: usize) -> Result<usize> {
+    let position = value.parse::<usize>().map_err(|e| anyhow::anyhow!("invalid position {}: {}", value, e))?;
+    if position == 0 || position > total_words {
+        return Err(anyhow::anyhow!("position {} is outside 1..={}", position, total_words));
+    }
+    Ok(position)
+}
+
+enum Piece {
+    Literal(char),
+    Class { chars: Vec<char>, min: usize, max: usize },
+}
+
+/// Expands a wildcard token (`%a`, `%d`, `%n`, `%A`, `%[abc]`, with optional
+/// `%N` or `%N,M` repeat counts, and `%%` for a literal `%`) into the BIP39
+/// words it matches.
+pub(crate) fn expand_wildcard(token: &str) -> Result<Vec<String>> {
+    let pieces = parse_wildcard(token)?;
+    let words: Vec<String> = Language::English
+        .word_list()
+        .iter()
+        .filter(|word| {
+            let chars: Vec<char> = word.chars().collect();
+            wildcard_matches(&pieces, &chars)
+        })
+        .map(|word| word.to_string())
+        .collect();
+    if words.is_empty() {
+        return Err(anyhow::anyhow!("wildcard {} matches no BIP39 word", token));
+    }
+    Ok(words)
+}
+
+fn parse_wildcard(token: &str) -> Result<Vec<Piece>> {
+    let mut pieces = Vec::new();
+    let mut chars = token.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            pieces.push(Piece::Literal(c));
+            continue;
+        }
+        let mut counts = String::new();
+        while let Some(&c) = chars.peek().filter(|c| c.is_ascii_digit() || **c == ',') {
+            counts.push(c);
+            chars.next();
+        }
+        let (min, max) = match counts.split_once(',') {
+            Some((min, max)) => (
+                if min.is_empty() { 0 } else { parse_count(min)? },
+                if max.is_empty() { usize::MAX } else { parse_count(max)? },
+            ),
+            None if counts.is_empty() => (1, 1),
+            None => {
+                let count = parse_count(&counts)?;
+                (count, count)
+            }
+        };
+        let class: Vec<char> = match chars.next() {
+            Some('%') if counts.is_empty() => {
+                pieces.push(Piece::Literal('%'));
+                continue;
+            }
+            Some('a') => ('a'..='z').collect(),
+            Some('A') => ('A'..='Z').collect(),
+            Some('d') => ('0'..='9').collect(),
+            Some('n') => ('a'..='z').chain('0'..='9').collect(),
+            Some('[') => {
+                let set: Vec<char> = chars.by_ref().take_while(|&c| c != ']').collect();
+                if set.is_empty() {
+                    return Err(anyhow::anyhow!("empty character set in wildcard {}", token));
+                }
+                set
+            }
+            other => {
+                return Err(anyhow::anyhow!(
+                    "unsupported wildcard %{}{} in {}",
+                    counts,
+                    other.map(String::from).unwrap_or_default(),
+                    token
+                ))
+            }
+        };
+        if min > max {
+            return Err(anyhow::anyhow!("empty repeat range in wildcard {}", token));
+        }
+        pieces.push(Piece::Class { chars: class, min, max });
+    }
+    Ok(pieces)
+}
+
+fn parse_count(value: &str) -> Result<usize> {
+    value.parse::<usize>().map_err(|e| anyhow::anyhow!("invalid wildcard count {}: {}", value, e))
+}
+
+fn wildcard_matches(pieces: &[Piece], word: &[char]) -> bool {
+    match pieces.split_first() {
+        None => word.is_empty(),
+        Some((Piece::Literal(c), rest)) => word.first() == Some(c) && wildcard_matches(rest, &word[1..]),
+        Some((Piece::Class { chars, min, max }, rest)) => (*min..=(*max).min(word.len())).any(|n| {
+            word[..n].iter().all(|c| chars.contains(c)) && wildcard_matches(rest, &word[n..])
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidates(list: &TokenList) -> Vec<Option<String>> {
+        list.clone().into_range(0, u64::MAX).map(|words| words.map(|words| words.join(" "))).collect()
+    }
+
+    #[test]
+    fn anchors() {
+        assert_eq!(parse_anchor("word", 12).unwrap(), (Anchor::Anywhere, "word"));
+        assert_eq!(parse_anchor("^word", 12).unwrap(), (Anchor::At(0), "word"));
+        assert_eq!(parse_anchor("^3^word", 12).unwrap(), (Anchor::At(2), "word"));
+        assert_eq!(parse_anchor("^3,5^word", 12).unwrap(), (Anchor::Between(2, 4), "word"));
+        assert_eq!(parse_anchor("^,5^word", 12).unwrap(), (Anchor::Between(0, 4), "word"));
+        assert_eq!(parse_anchor("^3,^word", 12).unwrap(), (Anchor::Between(2, 11), "word"));
+        assert_eq!(parse_anchor("word$", 12).unwrap(), (Anchor::At(11), "word"));
+        // Not a position, so the second caret belongs to the token
+        assert_eq!(parse_anchor("^x^word", 12).unwrap(), (Anchor::At(0), "x^word"));
+
+        assert!(parse_anchor("^0^word", 12).is_err());
+        assert!(parse_anchor("^13^word", 12).is_err());
+        assert!(parse_anchor("^5,3^word", 12).is_err());
+    }
+
+    #[test]
+    fn wildcards() {
+        assert_eq!(expand_wildcard("zo%a").unwrap(), ["zoo"]);
+        assert_eq!(expand_wildcard("ab%2,3a").unwrap(), ["able", "about", "above", "abuse"]);
+        assert_eq!(expand_wildcard("ca%[td]").unwrap(), ["cat"]);
+        assert_eq!(expand_wildcard("cat%,a").unwrap(), ["cat", "catalog", "catch", "category", "cattle"]);
+        assert!(expand_wildcard("zo%d").is_err());
+        assert!(expand_wildcard("zo%x").is_err());
+        assert!(expand_wildcard("zo%[]").is_err());
+    }
+
+    #[test]
+    fn enumerates_every_selection() {
+        let list = TokenList::parse("# first word is known\n+ ^a\nb c\nd\ne$\n", 3, IndexBase::Auto).unwrap();
+        assert_eq!((list.line_count(), list.selection_count(), list.space()), (4, 5, 7));
+        let mut found: Vec<String> = candidates(&list).into_iter().map(Option::unwrap).collect();
+        found.sort();
+        assert_eq!(found, ["a b d", "a b e", "a c d", "a c e", "a d b", "a d c", "a d e"]);
+    }
+
+    #[test]
+    fn range_anchors_rule_out_ranks() {
+        let list = TokenList::parse("^2,3^x\ny\nz", 3, IndexBase::Auto).unwrap();
+        assert_eq!(list.space(), 6);
+        let found = candidates(&list);
+        assert_eq!(found.len(), 6);
+        let mut kept: Vec<String> = found.into_iter().flatten().collect();
+        kept.sort();
+        assert_eq!(kept, ["y x z", "y z x", "z x y", "z y x"]);
+    }
+
+    #[test]
+    fn ranges_are_slices_of_the_whole() {
+        let list = TokenList::parse("+ ^a\nb c\nd\ne$\n", 3, IndexBase::Auto).unwrap();
+        let all = candidates(&list);
+        for start in 0..=all.len() as u64 {
+            for end in start..=all.len() as u64 + 1 {
+                let range: Vec<Option<String>> = list
+                    .clone()
+                    .into_range(start, end)
+                    .map(|words| words.map(|words| words.join(" ")))
+                    .collect();
+                assert_eq!(range, all[start as usize..all.len().min(end as usize)], "ranks {}..{}", start, end);
+            }
+        }
+    }
+
+    #[test]
+    fn word_numbers_and_errors() {
+        let list = TokenList::parse("+ 1\n+ zo%a", 2, IndexBase::One).unwrap();
+        let found: Vec<Option<String>> = candidates(&list);
+        assert!(found.contains(&Some("abandon zoo".to_string())));
+        assert_eq!(found.len(), 2);
+
+        assert!(TokenList::parse("a\nb", 3, IndexBase::Auto).is_err());
+        assert!(TokenList::parse("+ ^a\n+ ^b", 2, IndexBase::Auto).is_err());
+        assert!(TokenList::parse("^2^", 2, IndexBase::Auto).is_err());
+        assert!(TokenList::parse("a", 0, IndexBase::Auto).is_err());
+    }
+}
@@ -0,0 +1,77 @@
+use anyhow::Result;
+use bip39::{Language, Mnemonic};
+use bitcoin::bip32::{ChildNumber, DerivationPath, Xpriv, Xpub};
+use bitcoin::key::UntweakedPublicKey;
+use bitcoin::{Address, Network, ScriptBuf};
+use secp256k1::Secp256k1;
+
+use crate::{electrum, ethereum, lightning, monero, xpub};
+use crate::overrides::AddressOverrides;
+use crate::{Match, SeedFormat};
+
+/// Re-derives a reported match along an independent route before it is
+/// announced: the mnemonic is re-parsed from its string, the non-hardened
+/// tail of the path is derived publicly from the xpub, and the address is
+/// rebuilt from its output script instead of the search's encoder. Returns
+/// the address this route arrives at.
+pub fn rederive(
+    found: &Match,
+    seed_format: SeedFormat,
+    overrides: &AddressOverrides,
+    secp: &Secp256k1<secp256k1::All>,
+) -> Result<String> {
+    // A Monero seed is its spend key, with no path or passphrase to follow
+    if seed_format == SeedFormat::Monero {
+        let words: Vec<String> = found.mnemonic.split(' ').map(str::to_string).collect();
+        return monero::seed_address(&words).ok_or_else(|| anyhow::anyhow!("Reference check rejected the Monero seed"));
+    }
+    let seed = match seed_format {
+        SeedFormat::Bip39 => Mnemonic::parse_in(Language::English, &found.mnemonic)
+            .map_err(|e| anyhow::anyhow!("Reference parse rejected the mnemonic: {}", e))?
+            .to_seed_normalized(&found.passphrase),
+        SeedFormat::Electrum => {
+            if electrum::seed_type(&found.mnemonic).is_none() {
+                return Err(anyhow::anyhow!("Reference check found no Electrum seed version"));
+            }
+            electrum::to_seed(&found.mnemonic, &found.passphrase)
+        }
+        SeedFormat::Monero => unreachable!("Monero seeds return above"),
+    };
+    // A node key doesn't depend on the path, only on the re-parsed seed
+    if found.variant.address_type == lightning::CLN_NODE_ID {
+        return lightning::cln_node_id(&seed, secp);
+    }
+    let master = Xpriv::new_master(Network::Bitcoin, &seed)?;
+    // An account key is the path's own, so there is no public tail to derive
+    if found.variant.address_type == xpub::ACCOUNT_XPUB {
+        return Ok(xpub::encode(&master.derive_priv(secp, &found.variant.path)?, secp));
+    }
+    if found.variant.address_type == xpub::MASTER_FINGERPRINT {
+        return Ok(xpub::fingerprint(&master.derive_priv(secp, &found.variant.path)?, secp));
+    }
+
+    let children: Vec<ChildNumber> = found.variant.path.as_ref().to_vec();
+    let split = children.iter().rposition(ChildNumber::is_hardened).map_or(0, |i| i + 1);
+    let account = master.derive_priv(secp, &DerivationPath::from(children[..split].to_vec()))?;
+    let xpub = Xpub::from_priv(secp, &account).derive_pub(secp, &DerivationPath::from(children[split..].to_vec()))?;
+    let pubkey = bitcoin::PublicKey::new(xpub.public_key);
+    // An account hashes the uncompressed key and has no output script
+    if found.variant.address_type == ethereum::ETH {
+        return Ok(ethereum::address(&pubkey.inner));
+    }
+
+    if let Some(custom) = overrides.encode(&pubkey, &found.variant.address_type) {
+        return custom;
+    }
+    let wpkh = || pubkey.wpubkey_hash().ok_or_else(|| anyhow::anyhow!("Key is not compressed"));
+    let script = match found.variant.address_type.as_str() {
+        "p2pkh" => ScriptBuf::new_p2pkh(&pubkey.pubkey_hash()),
+        "p2wpkh" => ScriptBuf::new_p2wpkh(&wpkh()?),
+        "p2sh-p2wpkh" => ScriptBuf::new_p2sh(&ScriptBuf::new_p2wpkh(&wpkh()?).script_hash()),
+        "p2tr" => ScriptBuf::new_p2tr(secp, UntweakedPublicKey::from(pubkey.inner), None),
+        other => return Err(anyhow::anyhow!("No reference encoding for address type {}", other)),
+    };
+    let address = Address::from_script(&script, found.variant.network)
+        .map_err(|e| anyhow::anyhow!("Reference encoding failed: {}", e))?;
+    Ok(address.to_string())
+}
@@ -0,0 +1,322 @@
+use std::fs;
+
+use anyhow::Result;
+
+use crate::wordindex::{IndexBase, Resolver};
+
+/// Stand-in for a probability of zero: such arrangements are ordered last
+/// rather than dropped, since recollections can be wrong.
+const FLOOR: f64 = 1e-9;
+
+/// Width of a likelihood band: each band holds arrangements at most half as
+/// likely as the band before it.
+const BAND: f64 = std::f64::consts::LN_2;
+
+/// Slack for rounding when pruning against a band, so an arrangement right
+/// on an edge is never lost; membership itself is decided exactly.
+const EPSILON: f64 = 1e-9;
+
+/// Log-probability of each permutable word at each unknown position,
+/// `log[slot][item]`.
+pub struct PositionWeights {
+    log: Vec<Vec<f64>>,
+}
+
+impl PositionWeights {
+    /// Reads `<position> <word> <probability>` lines, positions counted from 1
+    /// over the whole mnemonic; the word may be given by its number. Within a position, words that aren't listed
+    /// share whatever probability the listed ones leave; positions that
+    /// aren't mentioned at all are uniform.
+    pub fn load(file: &str, words: &[String], fixed_words: usize, base: IndexBase) -> Result<Self> {
+        let content = fs::read_to_string(file)
+            .map_err(|e| anyhow::anyhow!("Failed to read position weights file {}: {}", file, e))?;
+        let listed = content
+            .lines()
+            .filter(|line| !line.trim_start().starts_with('#'))
+            .filter_map(|line| line.split_whitespace().nth(1));
+        let resolver = Resolver::new(base, listed, file)?;
+        let n = words.len();
+        let mut given: Vec<Vec<Option<f64>>> = vec![vec![None; n]; n];
+        for (number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [position, word, probability] = fields[..] else {
+                return Err(anyhow::anyhow!(
+                    "{} line {}: expected '<position> <word> <probability>', got '{}'",
+                    file,
+                    number + 1,
+                    line
+                ));
+            };
+            let position: usize = position
+                .parse()
+                .map_err(|e| anyhow::anyhow!("{} line {}: invalid position {}: {}", file, number + 1, position, e))?;
+            if position <= fixed_words || position > fixed_words + n {
+                return Err(anyhow::anyhow!(
+                    "{} line {}: position {} is not one of the unknown positions {}..={}",
+                    file,
+                    number + 1,
+                    position,
+                    fixed_words + 1,
+                    fixed_words + n
+                ));
+            }
+            let probability: f64 = probability.parse().map_err(|e| {
+                anyhow::anyhow!("{} line {}: invalid probability {}: {}", file, number + 1, probability, e)
+            })?;
+            if !(0.0..=1.0).contains(&probability) {
+                return Err(anyhow::anyhow!("{} line {}: probability {} is not in 0..=1", file, number + 1, probability));
+            }
+            let word = resolver
+                .word(word)
+                .map_err(|e| anyhow::anyhow!("{} line {}: {}", file, number + 1, e))?
+                .to_lowercase();
+            let items: Vec<usize> = (0..n).filter(|&i| words[i] == word).collect();
+            if items.is_empty() {
+                return Err(anyhow::anyhow!("{} line {}: {} is not one of the words being permuted", file, number + 1, word));
+            }
+            for item in items {
+                given[position - fixed_words - 1][item] = Some(probability);
+            }
+        }
+
+        let log = given
+            .into_iter()
+            .map(|slot| {
+                let listed: f64 = slot.iter().flatten().sum();
+                let unlisted = slot.iter().filter(|p| p.is_none()).count();
+                let rest = if unlisted > 0 { (1.0 - listed).max(0.0) / unlisted as f64 } else { 0.0 };
+                slot.into_iter().map(|p| p.unwrap_or(rest).max(FLOOR).ln()).collect()
+            })
+            .collect();
+        Ok(Self { log })
+    }
+}
+
+/// Lazily yields the arrangements of `items` with ranks in `[start, end)`,
+/// most likely first. Arrangements are produced in bands of halving joint
+/// likelihood, each band walked depth-first in lexicographic order, so the
+/// order is fixed for a given weights file and ranks can be resumed or
+/// sharded like `PermutationRange`'s. Without any weights there is a single
+/// band and the order is the plain lexicographic one.
+pub struct WeightedRange<T> {
+    items: Vec<T>,
+    log: Vec<Vec<f64>>,
+    top: f64,
+    bands: usize,
+    band: usize,
+    path: Vec<usize>,
+    used: Vec<bool>,
+    cursor: Vec<usize>,
+    sums: Vec<f64>,
+    rank: u64,
+    end: u64,
+}
+
+impl<T: Clone> WeightedRange<T> {
+    pub fn new(items: Vec<T>, weights: PositionWeights, start: u64, end: u64) -> Self {
+        let n = items.len();
+        let log = weights.log;
+        let used = vec![false; n];
+        let (top, bottom) = bounds(&log, &used, 0, 0.0);
+        let bands = ((top - bottom) / BAND).floor() as usize + 1;
+        let mut range = Self {
+            items,
+            log,
+            top,
+            bands,
+            band: 0,
+            path: Vec::with_capacity(n),
+            used,
+            cursor: vec![0; n + 1],
+            sums: vec![0.0; n + 1],
+            rank: 0,
+            end,
+        };
+        while range.rank < start && range.advance().is_some() {
+            range.rank += 1;
+        }
+        range
+    }
+
+    /// The likelihood interval `(low, high]` of the current band.
+    fn band_limits(&self) -> (f64, f64) {
+        let high = if self.band == 0 { f64::INFINITY } else { self.top - self.band as f64 * BAND };
+        let low = if self.band + 1 == self.bands { f64::NEG_INFINITY } else { self.top - (self.band + 1) as f64 * BAND };
+        (low, high)
+    }
+
+    fn pop(&mut self) {
+        if let Some(item) = self.path.pop() {
+            self.used[item] = false;
+        }
+    }
+
+    /// Item indices of the next arrangement, or `None` once every band is done.
+    fn advance(&mut self) -> Option<Vec<usize>> {
+        let n = self.items.len();
+        while self.band < self.bands {
+            let depth = self.path.len();
+            let (low, high) = self.band_limits();
+            if depth == n {
+                let total = self.sums[n];
+                let arrangement = self.path.clone();
+                if n == 0 {
+                    self.band = self.bands;
+                } else {
+                    self.pop();
+                }
+                if total > low && total <= high {
+                    return Some(arrangement);
+                }
+                continue;
+            }
+            let next = (self.cursor[depth]..n).find(|&item| {
+                if self.used[item] {
+                    return false;
+                }
+                let sum = self.sums[depth] + self.log[depth][item];
+                self.used[item] = true;
+                let (upper, lower) = bounds(&self.log, &self.used, depth + 1, sum);
+                self.used[item] = false;
+                // Skip subtrees lying wholly outside this band
+                upper > low - EPSILON && lower <= high + EPSILON
+            });
+            match next {
+                Some(item) => {
+                    self.cursor[depth] = item + 1;
+                    self.sums[depth + 1] = self.sums[depth] + self.log[depth][item];
+                    self.used[item] = true;
+                    self.path.push(item);
+                    self.cursor[depth + 1] = 0;
+                }
+                None if depth == 0 => {
+                    self.band += 1;
+                    self.cursor[0] = 0;
+                }
+                None => self.pop(),
+            }
+        }
+        None
+    }
+}
+
+/// Best and worst total log-likelihood reachable from a partial arrangement
+/// filling slots before `depth` with `sum`, ignoring that two slots can't
+/// take the same item.
+fn bounds(log: &[Vec<f64>], used: &[bool], depth: usize, sum: f64) -> (f64, f64) {
+    let mut upper = sum;
+    let mut lower = sum;
+    for slot in &log[depth..] {
+        let free = slot.iter().zip(used).filter(|(_, &used)| !used).map(|(&p, _)| p);
+        upper += free.clone().fold(f64::NEG_INFINITY, f64::max);
+        lower += free.fold(f64::INFINITY, f64::min);
+    }
+    (upper, lower)
+}
+
+impl<T: Clone> Iterator for WeightedRange<T> {
+    type Item = (u64, Vec<T>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.rank >= self.end {
+            return None;
+        }
+        let arrangement = self.advance()?;
+        let rank = self.rank;
+        self.rank += 1;
+        Some((rank, arrangement.into_iter().map(|i| self.items[i].clone()).collect()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use super::*;
+
+    const GUESSES: &str = "# first word, surely\n1 cat 0.7\n2 ant 0.5\n2 bee 0.5\n5 eel 0\n";
+
+    fn words() -> Vec<String> {
+        ["ant", "bee", "cat", "dog", "eel"].iter().map(|word| word.to_string()).collect()
+    }
+
+    fn load(name: &str, content: &str) -> Result<PositionWeights> {
+        let path = std::env::temp_dir().join(format!("bip39_recovery-weights-{}-{}", name, std::process::id()));
+        fs::write(&path, content).unwrap();
+        let weights = PositionWeights::load(&path.to_string_lossy(), &words(), 0, IndexBase::Auto);
+        let _ = fs::remove_file(&path);
+        weights
+    }
+
+    fn likelihood(log: &[Vec<f64>], arrangement: &[String]) -> f64 {
+        let item = |word: &String| words().iter().position(|w| w == word).unwrap();
+        arrangement.iter().enumerate().map(|(slot, word)| log[slot][item(word)]).sum()
+    }
+
+    #[test]
+    fn unweighted_is_lexicographic() {
+        let weights = load("unweighted", "# nothing known\n").unwrap();
+        let all: Vec<Vec<String>> = WeightedRange::new(words(), weights, 0, u64::MAX).map(|(_, words)| words).collect();
+        assert_eq!(all, words().into_iter().permutations(5).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn unlisted_words_share_the_rest() {
+        let log = load("rest", GUESSES).unwrap().log;
+        let expected = [
+            [0.075, 0.075, 0.7, 0.075, 0.075],
+            [0.5, 0.5, FLOOR, FLOOR, FLOOR],
+            [0.2; 5],
+            [0.2; 5],
+            [0.25, 0.25, 0.25, 0.25, FLOOR],
+        ];
+        for (slot, probabilities) in expected.iter().enumerate() {
+            for (item, probability) in probabilities.iter().enumerate() {
+                assert!((log[slot][item] - probability.ln()).abs() < 1e-12, "slot {} item {}", slot, item);
+            }
+        }
+    }
+
+    /// Every arrangement comes once, in bands that never grow more likely.
+    #[test]
+    fn most_likely_first() {
+        let log = load("order", GUESSES).unwrap().log;
+        let all: Vec<(u64, Vec<String>)> =
+            WeightedRange::new(words(), PositionWeights { log: log.clone() }, 0, u64::MAX).collect();
+        assert_eq!(all.len(), 120);
+        assert!(all.iter().map(|(rank, _)| *rank).eq(0..120));
+        assert_eq!(all.iter().map(|(_, words)| words).unique().count(), 120);
+        assert_eq!(all[0].1, ["cat", "ant", "bee", "eel", "dog"]);
+
+        let top = likelihood(&log, &all[0].1);
+        let bands: Vec<usize> =
+            all.iter().map(|(_, words)| ((top - likelihood(&log, words)) / BAND + EPSILON).floor() as usize).collect();
+        assert!(bands.windows(2).all(|pair| pair[0] <= pair[1]), "{:?}", bands);
+        // The four orders of the top band for each of ant and bee second
+        assert_eq!(bands.iter().filter(|&&band| band == 0).count(), 8);
+    }
+
+    #[test]
+    fn ranges_are_slices_of_the_whole() {
+        let all: Vec<(u64, Vec<String>)> =
+            WeightedRange::new(words(), load("whole", GUESSES).unwrap(), 0, u64::MAX).collect();
+        for (start, end) in [(0, 1), (7, 9), (50, 90), (119, 200), (120, 130)] {
+            let range: Vec<(u64, Vec<String>)> =
+                WeightedRange::new(words(), load("slice", GUESSES).unwrap(), start, end).collect();
+            assert_eq!(range, all[start as usize..all.len().min(end as usize)], "ranks {}..{}", start, end);
+        }
+    }
+
+    #[test]
+    fn rejects_bad_lines() {
+        assert!(load("fields", "1 cat\n").is_err());
+        assert!(load("position", "6 cat 0.5\n").is_err());
+        assert!(load("zero", "0 cat 0.5\n").is_err());
+        assert!(load("probability", "1 cat 1.5\n").is_err());
+        assert!(load("word", "1 fox 0.5\n").is_err());
+    }
+}
@@ -0,0 +1,103 @@
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{compiler_fence, Ordering};
+
+use bitcoin::bip32::{ChildNumber, Xpriv};
+
+use crate::Match;
+
+/// Overwrites `bytes` with zeros. The stores are volatile, so they aren't
+/// dropped as dead even though nothing reads the buffer again.
+pub fn bytes(bytes: &mut [u8]) {
+    for byte in bytes.iter_mut() {
+        // SAFETY: `byte` is a valid, aligned, exclusive reference
+        unsafe { std::ptr::write_volatile(byte, 0) };
+    }
+    compiler_fence(Ordering::SeqCst);
+}
+
+/// Secret material that can be overwritten in place. Only the buffer in hand
+/// is wiped: copies the compiler or an earlier reallocation left behind
+/// aren't reachable from here.
+pub trait Wipe {
+    fn wipe(&mut self);
+}
+
+impl<const N: usize> Wipe for [u8; N] {
+    fn wipe(&mut self) {
+        bytes(self);
+    }
+}
+
+impl Wipe for Vec<u8> {
+    fn wipe(&mut self) {
+        bytes(self);
+        self.clear();
+    }
+}
+
+impl Wipe for String {
+    fn wipe(&mut self) {
+        // SAFETY: zeros are valid UTF-8
+        bytes(unsafe { self.as_bytes_mut() });
+        self.clear();
+    }
+}
+
+impl<T: Wipe> Wipe for Vec<T> {
+    fn wipe(&mut self) {
+        self.iter_mut().for_each(Wipe::wipe);
+        self.clear();
+    }
+}
+
+impl<T: Wipe> Wipe for Option<T> {
+    fn wipe(&mut self) {
+        if let Some(value) = self {
+            value.wipe();
+        }
+    }
+}
+
+impl Wipe for Xpriv {
+    fn wipe(&mut self) {
+        self.private_key.non_secure_erase();
+    }
+}
+
+/// A level of the derivation path with its key.
+impl Wipe for (ChildNumber, Xpriv) {
+    fn wipe(&mut self) {
+        self.1.wipe();
+    }
+}
+
+/// The mnemonic and passphrase; the address and path are kept for reports.
+impl Wipe for Match {
+    fn wipe(&mut self) {
+        self.mnemonic.wipe();
+        self.passphrase.wipe();
+    }
+}
+
+/// A value wiped when it goes out of scope.
+pub struct Wiped<T: Wipe>(pub T);
+
+impl<T: Wipe> Deref for Wiped<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: Wipe> DerefMut for Wiped<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T: Wipe> Drop for Wiped<T> {
+    fn drop(&mut self) {
+        self.0.wipe();
+    }
+}
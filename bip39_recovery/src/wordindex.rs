@@ -0,0 +1,100 @@
+use anyhow::Result;
+use bip39::Language;
+use clap::ValueEnum;
+use log::warn;
+
+/// How word numbers on a backup are counted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum IndexBase {
+    /// Tell from the numbers given: a 0 means 0-based, a 2048 means 1-based,
+    /// and anything else is taken as 1-based with a warning
+    Auto,
+    /// 0 is `abandon`, as in the 11-bit values the mnemonic encodes
+    Zero,
+    /// 1 is `abandon`, as most printed cards and steel plates count
+    One,
+}
+
+const WORDS: u32 = 2048;
+
+/// Binary entries are 11 bits (a raw word value) or 12 (room for 2048), so
+/// they can't be mistaken for decimal numbers, which have at most 4 digits.
+const BINARY_DIGITS: [usize; 2] = [11, 12];
+
+/// A word, its number in the wordlist in decimal, or the same number in
+/// binary as punched into a grid.
+fn number(entry: &str) -> Option<u32> {
+    let entry = entry.trim();
+    if BINARY_DIGITS.contains(&entry.len()) && entry.bytes().all(|b| b == b'0' || b == b'1') {
+        return u32::from_str_radix(entry, 2).ok();
+    }
+    if !entry.is_empty() && entry.len() <= 4 && entry.bytes().all(|b| b.is_ascii_digit()) {
+        return entry.parse().ok();
+    }
+    None
+}
+
+/// Turns word numbers into words, counting them the same way throughout
+/// one input.
+pub struct Resolver {
+    zero_based: bool,
+}
+
+impl Resolver {
+    /// Settles how `entries` count, where `source` names them in messages.
+    pub fn new<'a>(base: IndexBase, entries: impl IntoIterator<Item = &'a str>, source: &str) -> Result<Self> {
+        let numbers: Vec<u32> = entries.into_iter().filter_map(number).collect();
+        let zero_based = match base {
+            IndexBase::Zero => true,
+            IndexBase::One => false,
+            IndexBase::Auto => match (numbers.contains(&0), numbers.contains(&WORDS)) {
+                (true, true) => {
+                    return Err(anyhow::anyhow!(
+                        "{} has both word number 0 and 2048; pass --word-index-base to say how they count",
+                        source
+                    ))
+                }
+                (true, false) => true,
+                (false, true) => false,
+                (false, false) => {
+                    // Console logging is off, so say it on the terminal too
+                    if !numbers.is_empty() {
+                        let note = format!(
+                            "{} gives words by number without a 0 or 2048 to tell how they count; taking them as \
+                             1-based (1 = abandon). If nothing is found, rerun with --word-index-base zero",
+                            source
+                        );
+                        println!("{}", note);
+                        warn!("{}", note);
+                    }
+                    false
+                }
+            },
+        };
+        Ok(Self { zero_based })
+    }
+
+    /// `entry` itself if it isn't a number, otherwise the word it numbers.
+    pub fn word(&self, entry: &str) -> Result<String> {
+        let Some(value) = number(entry) else {
+            return Ok(entry.to_string());
+        };
+        let index = if self.zero_based { Some(value) } else { value.checked_sub(1) };
+        let (first, last) = if self.zero_based { (0, WORDS - 1) } else { (1, WORDS) };
+        match index.filter(|&index| index < WORDS) {
+            Some(index) => Ok(Language::English.word_list()[index as usize].to_string()),
+            None => Err(anyhow::anyhow!(
+                "Word number {} is outside {}..={} ({}-based)",
+                entry.trim(),
+                first,
+                last,
+                if self.zero_based { 0 } else { 1 }
+            )),
+        }
+    }
+
+    /// Converts every entry of a list counted as a whole.
+    pub fn words(&self, entries: &[String]) -> Result<Vec<String>> {
+        entries.iter().map(|entry| self.word(entry)).collect()
+    }
+}
@@ -0,0 +1,358 @@
+use std::fs;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::workserver::{Capabilities, Claim, Hello, Reply, Report, Unit};
+use crate::{beacon, signals};
+
+/// How often a running unit reports its progress, which also renews its
+/// lease.
+const HEARTBEAT: Duration = Duration::from_secs(10);
+
+/// How long the worker keeps trying to reach a server that doesn't answer.
+const UNREACHABLE_LIMIT: Duration = Duration::from_secs(300);
+
+/// How long a unit gets to stop on its own after an interrupt before it's
+/// killed; its last checkpoint is kept for the next time.
+const STOP_GRACE: Duration = Duration::from_secs(10);
+
+/// Options each unit's run gets from the worker, which local options can't
+/// set.
+const UNIT_OPTIONS: [&str; 6] = ["--config", "--start-index", "--end-index", "--state-dir", "--output", "--stats-file"];
+
+/// How `worker` runs.
+pub struct WorkerOptions {
+    /// `http://host:port` of the work server; found by a probe to
+    /// `discover` when not given
+    pub server: Option<String>,
+    pub discover: String,
+    pub name: String,
+    pub token: Option<String>,
+    pub state_dir: String,
+    /// Options for this machine added to the job, e.g. `--gpu`
+    pub local: Vec<String>,
+}
+
+/// How a unit ended.
+enum Outcome {
+    /// Searched to the end (or to a match); claim another
+    Done,
+    /// The server ended the search
+    Stopped,
+    /// The worker was interrupted
+    Interrupted,
+}
+
+struct Client<'a> {
+    host: &'a str,
+    token: Option<&'a str>,
+}
+
+impl Client<'_> {
+    fn new<'a>(server: &'a str, token: Option<&'a str>) -> Result<Client<'a>> {
+        let host = server
+            .strip_prefix("http://")
+            .ok_or_else(|| anyhow::anyhow!("The work server is given as http://host:port, not {}", server))?
+            .trim_end_matches('/');
+        Ok(Client { host, token })
+    }
+
+    fn post<T: DeserializeOwned>(&self, path: &str, body: &impl Serialize) -> Result<T> {
+        let body = serde_json::to_string(body)?;
+        let mut stream = TcpStream::connect(self.host)?;
+        stream.set_read_timeout(Some(Duration::from_secs(30)))?;
+        let authorization = self.token.map(|token| format!("Authorization: Bearer {}\r\n", token)).unwrap_or_default();
+        write!(
+            stream,
+            "POST {} HTTP/1.1\r\nHost: {}\r\n{}Content-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            path,
+            self.host,
+            authorization,
+            body.len(),
+            body
+        )?;
+        stream.flush()?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response)?;
+        let (head, body) = response.split_once("\r\n\r\n").unwrap_or((&response, ""));
+        let status = head.lines().next().unwrap_or_default();
+        if status.split_whitespace().nth(1) != Some("200") {
+            return Err(anyhow::anyhow!("Work server answered {}: {}", status, body.trim()));
+        }
+        serde_json::from_str(body).map_err(|e| anyhow::anyhow!("Invalid answer from the work server: {}", e))
+    }
+
+    /// `post`, retried while the server can't be reached (it may be
+    /// restarting) up to [`UNREACHABLE_LIMIT`].
+    fn post_retrying<T: DeserializeOwned>(&self, path: &str, body: &impl Serialize, stop: &AtomicBool) -> Result<T> {
+        let first_failure = Instant::now();
+        loop {
+            match self.post(path, body) {
+                Ok(reply) => return Ok(reply),
+                Err(e) if e.is::<std::io::Error>() && first_failure.elapsed() < UNREACHABLE_LIMIT && !stop.load(Ordering::SeqCst) => {
+                    eprintln!("Can't reach the work server at {} ({}); retrying", self.host, e);
+                    sleep_unless(Duration::from_secs(crate::workserver::WAIT_SECONDS), stop);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// Sleeps for `duration`, or less once `stop` is set.
+fn sleep_unless(duration: Duration, stop: &AtomicBool) {
+    let until = Instant::now() + duration;
+    while Instant::now() < until && !stop.load(Ordering::SeqCst) {
+        std::thread::sleep(Duration::from_millis(200));
+    }
+}
+
+/// Matches recorded in a unit's `--output` file so far.
+fn recorded_matches(output: &Path) -> Vec<serde_json::Value> {
+    fs::read_to_string(output)
+        .ok()
+        .and_then(|json| serde_json::from_str::<serde_json::Value>(&json).ok())
+        .and_then(|results| results["matches"].as_array().cloned())
+        .unwrap_or_default()
+}
+
+/// How a unit's run ended, from its `--output` file.
+fn completion_status(output: &Path) -> Option<String> {
+    let json = fs::read_to_string(output).ok()?;
+    let results: serde_json::Value = serde_json::from_str(&json).ok()?;
+    results["completion"]["status"].as_str().map(str::to_string)
+}
+
+/// Candidates the unit's run has checked, from its `--stats-file`.
+fn processed(stats: &Path) -> u64 {
+    fs::read_to_string(stats)
+        .ok()
+        .and_then(|json| serde_json::from_str::<serde_json::Value>(&json).ok())
+        .and_then(|stats| stats["processed"].as_u64())
+        .unwrap_or(0)
+}
+
+struct UnitRun<'a> {
+    client: &'a Client<'a>,
+    options: &'a WorkerOptions,
+    unit: Unit,
+    dir: PathBuf,
+    /// Matches of the unit already passed on to the server
+    reported: usize,
+}
+
+impl UnitRun<'_> {
+    fn report(&self, processed: u64, record: Option<serde_json::Value>) -> Report {
+        Report { worker: self.options.name.clone(), start: self.unit.start, end: self.unit.end, processed, record }
+    }
+
+    /// Passes on matches the unit found since the last call. False when the
+    /// server says to stop.
+    fn report_matches(&mut self, stop: &AtomicBool) -> Result<bool> {
+        let matches = recorded_matches(&self.dir.join("output.json"));
+        let mut proceed = true;
+        for record in matches.into_iter().skip(self.reported) {
+            let reply: Reply = self.client.post_retrying("/match", &self.report(0, Some(record)), stop)?;
+            proceed &= reply.proceed;
+            self.reported += 1;
+        }
+        Ok(proceed)
+    }
+
+    /// Runs the unit as a search of its own in a subdirectory of the
+    /// worker's state directory, resuming the checkpoint an earlier claim of
+    /// it left there.
+    fn run(&mut self, stop: &AtomicBool) -> Result<Outcome> {
+        fs::create_dir_all(&self.dir)
+            .map_err(|e| anyhow::anyhow!("Failed to create unit directory {}: {}", self.dir.display(), e))?;
+        let job = self.dir.join("job.toml");
+        fs::write(&job, &self.unit.job).map_err(|e| anyhow::anyhow!("Failed to write {}: {}", job.display(), e))?;
+        let exe = std::env::current_exe().map_err(|e| anyhow::anyhow!("Can't find this program to run units: {}", e))?;
+        let mut child = Command::new(exe)
+            .arg("--config")
+            .arg(&job)
+            .args(["--start-index", &self.unit.start.to_string(), "--end-index", &self.unit.end.to_string()])
+            .arg("--state-dir")
+            .arg(&self.dir)
+            .args(["--output", "output.json", "--stats-file", "stats.json"])
+            .args(&self.options.local)
+            .spawn()
+            .map_err(|e| anyhow::anyhow!("Failed to start unit {}..{}: {}", self.unit.start, self.unit.end, e))?;
+        let status = match self.watch(&mut child, stop) {
+            Ok(Some(status)) => status,
+            Ok(None) => return Ok(Outcome::Stopped),
+            Err(e) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(e);
+            }
+        };
+        let proceed = self.report_matches(stop)?;
+        match completion_status(&self.dir.join("output.json")).as_deref() {
+            Some("found" | "finished") => {
+                let reply: Reply = self.client.post_retrying("/done", &self.report(self.unit.end - self.unit.start, None), stop)?;
+                // Only the units with matches are worth keeping
+                if self.reported == 0 {
+                    let _ = fs::remove_dir_all(&self.dir);
+                }
+                Ok(if reply.proceed && proceed { Outcome::Done } else { Outcome::Stopped })
+            }
+            outcome => {
+                let _ = self.client.post::<Reply>("/release", &self.report(0, None));
+                match outcome {
+                    Some("interrupted") => Ok(Outcome::Interrupted),
+                    _ if stop.load(Ordering::SeqCst) => Ok(Outcome::Interrupted),
+                    Some(other) => Err(anyhow::anyhow!(
+                        "Unit {}..{} {} before its end; a job's stop conditions don't apply to units",
+                        self.unit.start,
+                        self.unit.end,
+                        other
+                    )),
+                    None => Err(anyhow::anyhow!(
+                        "Unit {}..{} failed ({}); see {}",
+                        self.unit.start,
+                        self.unit.end,
+                        status,
+                        self.dir.join("recovery.log").display()
+                    )),
+                }
+            }
+        }
+    }
+
+    /// Reports on the unit's run until it exits, and kills it if the server
+    /// ends the search (`None`) or it outstays an interrupt.
+    fn watch(&mut self, child: &mut Child, stop: &AtomicBool) -> Result<Option<std::process::ExitStatus>> {
+        let mut beat = Instant::now();
+        let mut stopping = None;
+        loop {
+            if let Some(status) = child.try_wait()? {
+                return Ok(Some(status));
+            }
+            // Ctrl+C reaches the unit's run too, which checkpoints and exits
+            if stop.load(Ordering::SeqCst) {
+                let since = *stopping.get_or_insert_with(Instant::now);
+                if since.elapsed() > STOP_GRACE {
+                    child.kill()?;
+                    return Ok(Some(child.wait()?));
+                }
+            } else if beat.elapsed() >= HEARTBEAT {
+                beat = Instant::now();
+                let mut proceed = self.report_matches(stop)?;
+                // An unreachable server is no reason to stop searching
+                let progress = self.report(processed(&self.dir.join("stats.json")), None);
+                match self.client.post::<Reply>("/progress", &progress) {
+                    Ok(reply) => proceed &= reply.proceed,
+                    Err(e) => eprintln!("Failed to report progress to the work server: {}", e),
+                }
+                if !proceed {
+                    println!("The work server ended the search; stopping unit {}..{}", self.unit.start, self.unit.end);
+                    child.kill()?;
+                    child.wait()?;
+                    return Ok(None);
+                }
+            }
+            std::thread::sleep(Duration::from_millis(200));
+        }
+    }
+}
+
+/// The value of `option` among the local options, as `--option value` or
+/// `--option=value`.
+fn local_value<'a>(local: &'a [String], option: &str) -> Option<&'a str> {
+    local.iter().enumerate().find_map(|(i, arg)| match arg.strip_prefix(option) {
+        Some("") => local.get(i + 1).map(String::as_str),
+        Some(value) => value.strip_prefix('='),
+        None => None,
+    })
+}
+
+/// What the worker tells the server it brings, before it has finished a
+/// unit to measure its rate by.
+fn capabilities(local: &[String]) -> Capabilities {
+    let threads = local_value(local, "--threads")
+        .and_then(|threads| threads.parse().ok())
+        .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |threads| threads.get()));
+    Capabilities { threads, gpu: local.iter().any(|arg| arg == "--gpu"), rate: None }
+}
+
+/// Probes for a work server on the local network until one answers or the
+/// worker is interrupted.
+fn discover(options: &WorkerOptions, stop: &AtomicBool) -> Result<Option<String>> {
+    println!("Looking for a work server via {}", options.discover);
+    while !stop.load(Ordering::SeqCst) {
+        let wait = Duration::from_secs(crate::workserver::WAIT_SECONDS);
+        if let Some(server) = beacon::find(&options.discover, options.token.is_some(), wait)? {
+            return Ok(Some(server));
+        }
+        eprintln!("No work server answered yet; is one running with --announce?");
+    }
+    Ok(None)
+}
+
+/// `worker`: claims units from the work server and searches each as a run
+/// of its own, with `local` options added, until the server says the search
+/// is over or the worker is interrupted.
+pub fn run(options: WorkerOptions) -> Result<()> {
+    if let Some(option) = options
+        .local
+        .iter()
+        .find(|arg| UNIT_OPTIONS.iter().any(|option| arg == option || arg.starts_with(&format!("{}=", option))))
+    {
+        return Err(anyhow::anyhow!("{} is set by the worker for each unit", option));
+    }
+    let stop = Arc::new(AtomicBool::new(false));
+    let flag = Arc::clone(&stop);
+    signals::install_interrupt_handler(move || flag.store(true, Ordering::SeqCst))?;
+    let server = match &options.server {
+        Some(server) => server.clone(),
+        None => match discover(&options, &stop)? {
+            Some(server) => server,
+            None => {
+                println!("Worker interrupted before finding a work server");
+                return Ok(());
+            }
+        },
+    };
+    let client = Client::new(&server, options.token.as_deref())?;
+    fs::create_dir_all(&options.state_dir)
+        .map_err(|e| anyhow::anyhow!("Failed to create state directory {}: {}", options.state_dir, e))?;
+    println!("Worker {} taking units from {}", options.name, server);
+    let mut capabilities = capabilities(&options.local);
+    while !stop.load(Ordering::SeqCst) {
+        let hello = Hello { worker: options.name.clone(), capabilities: capabilities.clone() };
+        let claim: Claim = client.post_retrying("/claim", &hello, &stop)?;
+        let unit = match claim {
+            Claim::Unit(unit) => unit,
+            Claim::Wait(seconds) => {
+                sleep_unless(Duration::from_secs(seconds), &stop);
+                continue;
+            }
+            Claim::Stop(reason) => {
+                println!("The search is over ({})", reason);
+                return Ok(());
+            }
+        };
+        println!("Searching unit {}..{}", unit.start, unit.end);
+        let dir = Path::new(&options.state_dir).join(format!("unit-{}-{}", unit.start, unit.end));
+        let started = Instant::now();
+        let candidates = unit.end - unit.start;
+        let mut run = UnitRun { client: &client, options: &options, unit, dir, reported: 0 };
+        match run.run(&stop)? {
+            Outcome::Done => capabilities.rate = Some(candidates as f64 / started.elapsed().as_secs_f64().max(1e-3)),
+            Outcome::Stopped => continue,
+            Outcome::Interrupted => break,
+        }
+    }
+    println!("Worker interrupted; its unit went back to the server");
+    Ok(())
+}
@@ -0,0 +1,100 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+/// Candidates processed per worker (CPU thread or accelerator device), so a
+/// throttling core or idle GPU stands out during long runs.
+pub struct WorkerStats {
+    labels: Vec<String>,
+    counts: Vec<AtomicU64>,
+    // Slots before this are CPU threads, the rest devices
+    devices_from: usize,
+}
+
+impl WorkerStats {
+    /// One slot per rayon thread plus one for work done on the main thread.
+    pub fn for_cpu_threads(threads: usize) -> Self {
+        let mut labels: Vec<String> = (0..threads).map(|i| format!("cpu{}", i)).collect();
+        labels.push("main".to_string());
+        let counts = labels.iter().map(|_| AtomicU64::new(0)).collect();
+        let devices_from = labels.len();
+        Self { labels, counts, devices_from }
+    }
+
+    /// Adds a slot for each accelerator device, e.g. `gpu0`.
+    pub fn with_devices(mut self, labels: Vec<String>) -> Self {
+        self.counts.extend(labels.iter().map(|_| AtomicU64::new(0)));
+        self.labels.extend(labels);
+        self
+    }
+
+    /// Counts one candidate for the calling CPU thread.
+    pub fn record_cpu(&self) {
+        let slot = rayon::current_thread_index()
+            .filter(|&i| i < self.counts.len() - 1)
+            .unwrap_or(self.counts.len() - 1);
+        self.counts[slot].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Counts `count` candidates for the device labelled `label`.
+    pub fn record_device(&self, label: &str, count: u64) {
+        if let Some(slot) = self.labels[self.devices_from..].iter().position(|own| own == label) {
+            self.counts[self.devices_from + slot].fetch_add(count, Ordering::Relaxed);
+        }
+    }
+
+    fn snapshot(&self) -> Vec<u64> {
+        self.counts.iter().map(|count| count.load(Ordering::Relaxed)).collect()
+    }
+}
+
+/// Turns successive snapshots of [`WorkerStats`] into per-worker rates.
+pub struct WorkerRates {
+    last: Vec<u64>,
+    at: Instant,
+}
+
+impl WorkerRates {
+    pub fn new(stats: &WorkerStats) -> Self {
+        Self { last: stats.snapshot(), at: Instant::now() }
+    }
+
+    /// One line per interval, e.g. `cpu0 812/s | cpu1 790/s | cpu2 402/s (slow)`.
+    /// Idle workers are omitted; workers under half the median of their kind
+    /// (CPU threads or devices) are flagged.
+    pub fn report(&mut self, stats: &WorkerStats) -> Option<String> {
+        let now = stats.snapshot();
+        let seconds = self.at.elapsed().as_secs_f64();
+        let rates: Vec<(usize, f64)> = now
+            .iter()
+            .zip(&self.last)
+            .map(|(now, last)| now.saturating_sub(*last) as f64 / seconds.max(f64::EPSILON))
+            .enumerate()
+            .filter(|(i, rate)| *rate > 0.0 || now[*i] > 0)
+            .collect();
+        self.last = now;
+        self.at = Instant::now();
+        if rates.is_empty() {
+            return None;
+        }
+
+        // A device seeds far more candidates than a thread derives
+        let median = |device: bool| {
+            let mut sorted: Vec<f64> =
+                rates.iter().filter(|(i, _)| (*i >= stats.devices_from) == device).map(|(_, rate)| *rate).collect();
+            sorted.sort_by(|a, b| a.total_cmp(b));
+            sorted.get(sorted.len() / 2).copied().unwrap_or(0.0)
+        };
+        let (cpu_median, device_median) = (median(false), median(true));
+        // Devices seed candidates the CPU threads then derive; count them once
+        let total: f64 = rates.iter().filter(|(i, _)| *i < stats.devices_from).map(|(_, rate)| rate).sum();
+        let entries: Vec<String> = rates
+            .iter()
+            .map(|(i, rate)| {
+                let median = if *i >= stats.devices_from { device_median } else { cpu_median };
+                let flag = if *rate < median * 0.5 { " (slow)" } else { "" };
+                format!("{} {:.0}/s{}", stats.labels[*i], rate, flag)
+            })
+            .collect();
+        Some(format!("Workers ({:.0}/s total): {}", total, entries.join(" | ")))
+    }
+}
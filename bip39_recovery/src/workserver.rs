@@ -0,0 +1,741 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use clap::CommandFactory;
+use serde::{Deserialize, Serialize};
+
+use crate::beacon;
+use crate::signals;
+use crate::state::StateDir;
+use crate::{jobfile, shardplan, Args};
+
+/// Seconds a worker waits before asking again when every unit is leased.
+pub const WAIT_SECONDS: u64 = 10;
+
+/// How long the server keeps telling workers the search is over before it
+/// exits, so those waiting for a unit hear it too.
+const LINGER: Duration = Duration::from_secs(2 * WAIT_SECONDS);
+
+/// Largest request body the server reads.
+const MAX_BODY: usize = 1 << 20;
+
+/// Job file keys each worker sets for itself, left out of the job it's sent.
+const PER_RUN_KEYS: [&str; 9] =
+    ["start-index", "end-index", "state-dir", "resume", "output", "stats-file", "daemon", "dashboard", "interactive"];
+
+/// File in the server's state directory holding the global progress.
+const STATE_FILE: &str = "server.json";
+
+/// `POST /claim`: a worker asking for work.
+#[derive(Serialize, Deserialize)]
+pub struct Hello {
+    pub worker: String,
+    #[serde(default)]
+    pub capabilities: Capabilities,
+}
+
+/// What a worker brings, as it tells the server with each claim.
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Capabilities {
+    /// CPU threads its units run on
+    pub threads: usize,
+    /// Whether its units run with `--gpu`
+    pub gpu: bool,
+    /// Candidates per second over its last finished unit, once it has one
+    pub rate: Option<f64>,
+}
+
+/// The server's answer to a claim.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Claim {
+    /// Search this unit
+    Unit(Unit),
+    /// Every unit is leased; ask again after this many seconds
+    Wait(u64),
+    /// The search is over: `found` or `finished`
+    Stop(String),
+}
+
+/// Ranks `[start, end)` of the job, given as a job file for `--config`.
+#[derive(Serialize, Deserialize)]
+pub struct Unit {
+    pub job: String,
+    pub start: u64,
+    pub end: u64,
+}
+
+/// `POST /progress`, `/match`, `/done`, and `/release`: news about a unit.
+#[derive(Serialize, Deserialize)]
+pub struct Report {
+    pub worker: String,
+    pub start: u64,
+    pub end: u64,
+    /// Candidates of the unit searched so far
+    #[serde(default)]
+    pub processed: u64,
+    /// A match, as the unit's `--output` file records it
+    #[serde(default)]
+    pub record: Option<serde_json::Value>,
+}
+
+/// Whether the worker should keep going: keep searching its unit after
+/// progress or a match, or claim another after finishing one.
+#[derive(Serialize, Deserialize)]
+pub struct Reply {
+    #[serde(rename = "continue")]
+    pub proceed: bool,
+}
+
+/// A match and who found it.
+#[derive(Clone, Serialize, Deserialize)]
+struct Found {
+    worker: String,
+    start: u64,
+    end: u64,
+    record: serde_json::Value,
+}
+
+/// What survives a restart of the server. Units are numbered from the start
+/// of the range; those handed out but not done when it stopped are handed
+/// out again.
+#[derive(Serialize, Deserialize)]
+struct Saved {
+    config: String,
+    start: u64,
+    end: u64,
+    unit_size: u64,
+    /// Units below this have been handed out
+    next: u64,
+    done: BTreeSet<u64>,
+    matches: Vec<Found>,
+}
+
+struct Lease {
+    worker: String,
+    renewed: Instant,
+    processed: u64,
+}
+
+#[derive(Serialize)]
+struct LeaseStatus {
+    worker: String,
+    start: u64,
+    end: u64,
+    processed: u64,
+    seconds_since_report: u64,
+}
+
+/// `GET /status`.
+#[derive(Serialize)]
+struct Status {
+    state: &'static str,
+    start: u64,
+    end: u64,
+    units: u64,
+    units_done: u64,
+    candidates_done: u64,
+    percent: f64,
+    leases: Vec<LeaseStatus>,
+    /// Every worker that claimed a unit since the server started
+    workers: BTreeMap<String, Capabilities>,
+    matches: usize,
+}
+
+struct State {
+    saved: Saved,
+    /// Units handed out before whose leases lapsed or were given back
+    pending: BTreeSet<u64>,
+    leases: HashMap<u64, Lease>,
+    workers: BTreeMap<String, Capabilities>,
+    /// When the search was first seen to be over
+    over_since: Option<Instant>,
+}
+
+/// The work server: hands out units of the range, keeps the global progress
+/// in the state directory, and tells every worker to stop once there's a
+/// match (unless the job has `find-all`).
+struct Server {
+    job: String,
+    find_all: bool,
+    token: Option<String>,
+    lease: Duration,
+    path: String,
+    state: Mutex<State>,
+}
+
+impl Saved {
+    fn units(&self) -> u64 {
+        (self.end - self.start).div_ceil(self.unit_size)
+    }
+
+    fn range(&self, unit: u64) -> (u64, u64) {
+        let start = self.start + unit * self.unit_size;
+        (start, (start + self.unit_size).min(self.end))
+    }
+
+    /// The unit `[start, end)` is, if it is one.
+    fn unit(&self, start: u64, end: u64) -> Option<u64> {
+        let unit = start.checked_sub(self.start)? / self.unit_size;
+        (unit < self.units() && self.range(unit) == (start, end)).then_some(unit)
+    }
+
+    fn candidates_done(&self) -> u64 {
+        self.done.iter().map(|&unit| self.range(unit)).map(|(start, end)| end - start).sum()
+    }
+}
+
+impl Server {
+    fn save(&self, state: &State) -> Result<()> {
+        let json = serde_json::to_string_pretty(&state.saved)?;
+        let tmp = format!("{}.tmp", self.path);
+        fs::write(&tmp, json).map_err(|e| anyhow::anyhow!("Failed to write {}: {}", tmp, e))?;
+        fs::rename(&tmp, &self.path).map_err(|e| anyhow::anyhow!("Failed to replace {}: {}", self.path, e))?;
+        Ok(())
+    }
+
+    /// Why the search is over, if it is.
+    fn over(&self, state: &State) -> Option<&'static str> {
+        if !state.saved.matches.is_empty() && !self.find_all {
+            Some("found")
+        } else if state.saved.done.len() as u64 == state.saved.units() {
+            Some("finished")
+        } else {
+            None
+        }
+    }
+
+    /// Hands the units of lapsed leases back out and notes when the search
+    /// ended. True once it has and every worker has heard.
+    fn tick(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let lapsed: Vec<u64> =
+            state.leases.iter().filter(|(_, lease)| lease.renewed.elapsed() > self.lease).map(|(&unit, _)| unit).collect();
+        for unit in lapsed {
+            let lease = state.leases.remove(&unit).expect("lapsed lease");
+            let (start, end) = state.saved.range(unit);
+            println!("{} stopped reporting; unit {}..{} goes back in the queue", lease.worker, start, end);
+            state.pending.insert(unit);
+        }
+        if self.over(&state).is_none() {
+            return false;
+        }
+        let since = *state.over_since.get_or_insert_with(Instant::now);
+        state.leases.is_empty() && since.elapsed() >= LINGER
+    }
+
+    fn claim(&self, hello: Hello) -> Result<Claim> {
+        let mut state = self.state.lock().unwrap();
+        let known = state.workers.insert(hello.worker.clone(), hello.capabilities.clone());
+        if known.is_none() {
+            println!("{} joined: {}", hello.worker, describe(&hello.capabilities));
+        }
+        if let Some(reason) = self.over(&state) {
+            return Ok(Claim::Stop(reason.to_string()));
+        }
+        let unit = match state.pending.pop_first() {
+            Some(unit) => unit,
+            None if state.saved.next < state.saved.units() => {
+                state.saved.next += 1;
+                self.save(&state)?;
+                state.saved.next - 1
+            }
+            None => return Ok(Claim::Wait(WAIT_SECONDS)),
+        };
+        let (start, end) = state.saved.range(unit);
+        println!("{} claimed unit {}..{}", hello.worker, start, end);
+        state.leases.insert(unit, Lease { worker: hello.worker, renewed: Instant::now(), processed: 0 });
+        Ok(Claim::Unit(Unit { job: self.job.clone(), start, end }))
+    }
+
+    /// Renews the worker's lease on the unit it reports on, taking the unit
+    /// back up after a restart of the server. False when the unit is done or
+    /// leased to another worker.
+    fn renew(&self, state: &mut State, unit: u64, report: &Report) -> bool {
+        if state.saved.done.contains(&unit) {
+            return false;
+        }
+        match state.leases.get_mut(&unit) {
+            Some(lease) if lease.worker != report.worker => false,
+            Some(lease) => {
+                lease.renewed = Instant::now();
+                lease.processed = report.processed;
+                true
+            }
+            None => {
+                state.pending.remove(&unit);
+                let lease = Lease { worker: report.worker.clone(), renewed: Instant::now(), processed: report.processed };
+                state.leases.insert(unit, lease);
+                true
+            }
+        }
+    }
+
+    fn report(&self, kind: &str, report: Report) -> Result<Reply> {
+        let mut state = self.state.lock().unwrap();
+        let unit = state
+            .saved
+            .unit(report.start, report.end)
+            .ok_or_else(|| anyhow::anyhow!("{}..{} is not a unit of this job", report.start, report.end))?;
+        let proceed = match kind {
+            "progress" => self.renew(&mut state, unit, &report) && self.over(&state).is_none(),
+            "match" => {
+                let record = report.record.clone().ok_or_else(|| anyhow::anyhow!("Match report without a record"))?;
+                println!("{} found a match in unit {}..{}:", report.worker, report.start, report.end);
+                println!("{}", serde_json::to_string_pretty(&record)?);
+                state.saved.matches.push(Found { worker: report.worker.clone(), start: report.start, end: report.end, record });
+                self.save(&state)?;
+                self.renew(&mut state, unit, &report) && self.over(&state).is_none()
+            }
+            "done" => {
+                state.leases.remove(&unit);
+                state.pending.remove(&unit);
+                state.saved.done.insert(unit);
+                self.save(&state)?;
+                let (done, units) = (state.saved.done.len(), state.saved.units());
+                println!(
+                    "{} finished unit {}..{}: {} of {} units done ({:.1}%)",
+                    report.worker,
+                    report.start,
+                    report.end,
+                    done,
+                    units,
+                    done as f64 * 100.0 / units as f64
+                );
+                return Ok(Reply { proceed: self.over(&state).is_none() });
+            }
+            "release" => {
+                if state.leases.get(&unit).is_some_and(|lease| lease.worker == report.worker) {
+                    state.leases.remove(&unit);
+                    state.pending.insert(unit);
+                    println!("{} gave back unit {}..{}", report.worker, report.start, report.end);
+                }
+                false
+            }
+            _ => return Err(anyhow::anyhow!("Unknown report {}", kind)),
+        };
+        // A worker told to stop drops its unit
+        if !proceed && state.leases.get(&unit).is_some_and(|lease| lease.worker == report.worker) {
+            state.leases.remove(&unit);
+        }
+        Ok(Reply { proceed })
+    }
+
+    fn status(&self) -> Status {
+        let state = self.state.lock().unwrap();
+        let units = state.saved.units();
+        let candidates_done = state.saved.candidates_done();
+        let total = state.saved.end - state.saved.start;
+        let mut leases: Vec<LeaseStatus> = state
+            .leases
+            .iter()
+            .map(|(&unit, lease)| {
+                let (start, end) = state.saved.range(unit);
+                LeaseStatus {
+                    worker: lease.worker.clone(),
+                    start,
+                    end,
+                    processed: lease.processed,
+                    seconds_since_report: lease.renewed.elapsed().as_secs(),
+                }
+            })
+            .collect();
+        leases.sort_by_key(|lease| lease.start);
+        Status {
+            state: self.over(&state).unwrap_or("running"),
+            start: state.saved.start,
+            end: state.saved.end,
+            units,
+            units_done: state.saved.done.len() as u64,
+            candidates_done,
+            percent: if total > 0 { candidates_done as f64 * 100.0 / total as f64 } else { 100.0 },
+            leases,
+            workers: state.workers.clone(),
+            matches: state.saved.matches.len(),
+        }
+    }
+
+    fn handle(&self, mut stream: TcpStream) -> Result<()> {
+        stream.set_read_timeout(Some(Duration::from_secs(30)))?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+        let mut length = 0;
+        let mut authorization = None;
+        loop {
+            let mut header = String::new();
+            if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                match name.trim().to_ascii_lowercase().as_str() {
+                    "content-length" => length = value.trim().parse()?,
+                    "authorization" => authorization = Some(value.trim().to_string()),
+                    _ => {}
+                }
+            }
+        }
+        if length > MAX_BODY {
+            return respond(&mut stream, "413 Payload Too Large", "request too large");
+        }
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body)?;
+
+        let mut parts = request_line.split_whitespace();
+        let (method, path) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+        if let Some(token) = &self.token {
+            if authorization.as_deref().and_then(|value| value.strip_prefix("Bearer ")) != Some(token.as_str()) {
+                return respond(&mut stream, "401 Unauthorized", "missing or wrong token");
+            }
+        }
+        let reply = match (method, path) {
+            ("GET", "/status") => serde_json::to_string(&self.status()).map_err(Into::into),
+            ("POST", "/claim") => {
+                serde_json::from_slice(&body).map_err(Into::into).and_then(|hello| self.claim(hello)).and_then(|claim| {
+                    serde_json::to_string(&claim).map_err(Into::into)
+                })
+            }
+            ("POST", "/progress" | "/match" | "/done" | "/release") => serde_json::from_slice(&body)
+                .map_err(Into::into)
+                .and_then(|report| self.report(&path[1..], report))
+                .and_then(|reply| serde_json::to_string(&reply).map_err(Into::into)),
+            _ => return respond(&mut stream, "404 Not Found", "not found"),
+        };
+        match reply {
+            Ok(json) => respond(&mut stream, "200 OK", &json),
+            Err(e) => {
+                eprintln!("Work server request {} {} failed: {}", method, path, e);
+                respond(&mut stream, "400 Bad Request", &e.to_string())
+            }
+        }
+    }
+}
+
+fn respond(stream: &mut TcpStream, status: &str, body: &str) -> Result<()> {
+    let content_type = if status.starts_with("200") { "application/json" } else { "text/plain" };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )?;
+    stream.flush()?;
+    Ok(())
+}
+
+/// A worker's capabilities as the server prints them when it joins.
+fn describe(capabilities: &Capabilities) -> String {
+    let mut description =
+        format!("{} thread{}", capabilities.threads, if capabilities.threads == 1 { "" } else { "s" });
+    if capabilities.gpu {
+        description.push_str(", GPU");
+    }
+    if let Some(rate) = capabilities.rate {
+        description.push_str(&format!(", {:.0}/s", rate));
+    }
+    description
+}
+
+/// How `serve` runs.
+pub struct ServeOptions {
+    pub listen: String,
+    pub unit_size: u64,
+    pub lease_seconds: u64,
+    pub token: Option<String>,
+    pub state_dir: Option<String>,
+    /// UDP port to answer workers looking for a server on, if any
+    pub announce: Option<u16>,
+}
+
+/// `serve`: splits the search given after `--` into units of
+/// `unit_size` ranks and hands them to workers over HTTP until the range is
+/// done or a match ends the search. Progress is kept in `server.json` in the
+/// state directory, so a restarted server picks up where it left off.
+pub fn run(options: ServeOptions, search: &[String]) -> Result<()> {
+    let (args, matches) = crate::parse_search(search)?;
+    let space = shardplan::space(&args)?;
+    let end = args.end_index.unwrap_or(space).min(space);
+    let start = args.start_index.min(end);
+    if options.unit_size == 0 {
+        return Err(anyhow::anyhow!("--unit-size must be at least 1"));
+    }
+    let config = crate::config_hash(&args)?;
+    // Workers get the effective options, so they need none of the job
+    // files, only the input files they name
+    let mut job: toml::Table = jobfile::render(&Args::command(), &matches).parse()?;
+    for key in PER_RUN_KEYS {
+        job.remove(key);
+    }
+    let job = toml::to_string(&job)?;
+
+    let listener = TcpListener::bind(&options.listen)
+        .map_err(|e| anyhow::anyhow!("Failed to bind work server to {}: {}", options.listen, e))?;
+    if let Some(port) = options.announce {
+        beacon::announce(port, listener.local_addr()?, options.token.is_some())?;
+    }
+
+    let state_dir = StateDir::open(options.state_dir.as_deref())?;
+    let path = state_dir.resolve(STATE_FILE);
+    let saved = match fs::read_to_string(&path) {
+        Ok(json) => {
+            let saved: Saved =
+                serde_json::from_str(&json).map_err(|e| anyhow::anyhow!("Invalid server state {}: {}", path, e))?;
+            if (saved.config.as_str(), saved.start, saved.end, saved.unit_size) != (config.as_str(), start, end, options.unit_size) {
+                return Err(anyhow::anyhow!(
+                    "{} is the state of another job or unit size; serve this one from another --state-dir",
+                    path
+                ));
+            }
+            saved
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Saved {
+            config,
+            start,
+            end,
+            unit_size: options.unit_size,
+            next: 0,
+            done: BTreeSet::new(),
+            matches: Vec::new(),
+        },
+        Err(e) => return Err(anyhow::anyhow!("Failed to read {}: {}", path, e)),
+    };
+    // Units handed out before a restart are handed out again
+    let pending = (0..saved.next).filter(|unit| !saved.done.contains(unit)).collect();
+    let server = Arc::new(Server {
+        job,
+        find_all: args.find_all,
+        token: options.token,
+        lease: Duration::from_secs(options.lease_seconds.max(1)),
+        path,
+        state: Mutex::new(State { saved, pending, leases: HashMap::new(), workers: BTreeMap::new(), over_since: None }),
+    });
+
+    let status = server.status();
+    println!(
+        "Serving {}..{} ({} candidates of {}) as {} units of {} on http://{}; {} units done",
+        start,
+        end,
+        end - start,
+        space,
+        status.units,
+        options.unit_size,
+        options.listen,
+        status.units_done
+    );
+    if let Some(port) = options.announce {
+        println!("Answering workers looking for a server on UDP port {}", port);
+    }
+    if server.token.is_none() {
+        println!("Any host that reaches this port can take part and read matches; consider --token and --encrypt-result");
+    }
+    let handler = Arc::clone(&server);
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    if let Err(e) = handler.handle(stream) {
+                        eprintln!("Work server request failed: {}", e);
+                    }
+                }
+                Err(e) => eprintln!("Work server accept failed: {}", e),
+            }
+        }
+    });
+
+    // Progress is saved as it comes in, so an interrupted server only has
+    // to let go of its state directory
+    let interrupted = Arc::new(AtomicBool::new(false));
+    let flag = Arc::clone(&interrupted);
+    signals::install_interrupt_handler(move || flag.store(true, Ordering::SeqCst))?;
+    while !server.tick() {
+        if interrupted.load(Ordering::SeqCst) {
+            let status = server.status();
+            println!(
+                "Server interrupted with {} of {} units done; progress is kept in {}",
+                status.units_done, status.units, server.path
+            );
+            state_dir.release();
+            return Ok(());
+        }
+        std::thread::sleep(Duration::from_secs(1));
+    }
+    let state = server.state.lock().unwrap();
+    match server.over(&state) {
+        Some("found") => println!("Search stopped: {} match(es) recorded in {}", state.saved.matches.len(), server.path),
+        _ => println!(
+            "Search finished: all {} units done, {} match(es) recorded in {}",
+            state.saved.units(),
+            state.saved.matches.len(),
+            server.path
+        ),
+    }
+    state_dir.release();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A server for ranks 100..350 in units of 100, the last one short.
+    fn server(name: &str, find_all: bool, lease: Duration) -> Server {
+        let path = std::env::temp_dir().join(format!("bip39_recovery-server-{}-{}.json", name, std::process::id()));
+        let saved = Saved {
+            config: "config".to_string(),
+            start: 100,
+            end: 350,
+            unit_size: 100,
+            next: 0,
+            done: BTreeSet::new(),
+            matches: Vec::new(),
+        };
+        Server {
+            job: "job".to_string(),
+            find_all,
+            token: None,
+            lease,
+            path: path.to_string_lossy().into_owned(),
+            state: Mutex::new(State {
+                saved,
+                pending: BTreeSet::new(),
+                leases: HashMap::new(),
+                workers: BTreeMap::new(),
+                over_since: None,
+            }),
+        }
+    }
+
+    fn claim(server: &Server, worker: &str) -> Claim {
+        server.claim(Hello { worker: worker.to_string(), capabilities: Capabilities::default() }).unwrap()
+    }
+
+    fn claim_unit(server: &Server, worker: &str) -> (u64, u64) {
+        match claim(server, worker) {
+            Claim::Unit(unit) => (unit.start, unit.end),
+            _ => panic!("{} got no unit", worker),
+        }
+    }
+
+    fn report(server: &Server, kind: &str, worker: &str, (start, end): (u64, u64)) -> bool {
+        let record = (kind == "match").then(|| serde_json::json!({ "mnemonic": "legal winner" }));
+        let report = Report { worker: worker.to_string(), start, end, processed: 10, record };
+        server.report(kind, report).unwrap().proceed
+    }
+
+    fn saved(server: &Server) -> Saved {
+        serde_json::from_str(&fs::read_to_string(&server.path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn units_cover_the_range() {
+        let server = server("units", false, Duration::from_secs(60));
+        let state = server.state.lock().unwrap();
+        assert_eq!(state.saved.units(), 3);
+        assert_eq!(state.saved.range(2), (300, 350));
+        assert_eq!(state.saved.unit(200, 300), Some(1));
+        assert_eq!(state.saved.unit(300, 350), Some(2));
+        assert_eq!(state.saved.unit(300, 400), None);
+        assert_eq!(state.saved.unit(150, 250), None);
+        assert_eq!(state.saved.unit(0, 100), None);
+    }
+
+    #[test]
+    fn hands_out_each_unit_then_finishes() {
+        let server = server("finish", false, Duration::from_secs(60));
+        assert_eq!(claim_unit(&server, "a"), (100, 200));
+        assert_eq!(claim_unit(&server, "b"), (200, 300));
+        assert_eq!(claim_unit(&server, "a"), (300, 350));
+        assert!(matches!(claim(&server, "c"), Claim::Wait(WAIT_SECONDS)));
+        assert_eq!(saved(&server).next, 3);
+
+        assert!(report(&server, "progress", "a", (100, 200)));
+        assert!(report(&server, "done", "a", (100, 200)));
+        assert!(report(&server, "done", "b", (200, 300)));
+        // Progress on a finished unit tells the worker to move on
+        assert!(!report(&server, "progress", "b", (200, 300)));
+        assert!(!report(&server, "done", "a", (300, 350)));
+        assert!(matches!(claim(&server, "c"), Claim::Stop(reason) if reason == "finished"));
+        assert_eq!(saved(&server).done, BTreeSet::from([0, 1, 2]));
+        let stray = Report { worker: "a".to_string(), start: 120, end: 220, processed: 0, record: None };
+        assert!(server.report("progress", stray).is_err());
+        let _ = fs::remove_file(&server.path);
+    }
+
+    /// A unit whose worker stops reporting goes to the next worker to ask,
+    /// and the first worker is told to drop it.
+    #[test]
+    fn lapsed_leases_are_requeued() {
+        let server = server("lapsed", false, Duration::from_millis(200));
+        let first = claim_unit(&server, "a");
+        assert_eq!(claim_unit(&server, "b"), (200, 300));
+        std::thread::sleep(Duration::from_millis(120));
+        assert!(report(&server, "progress", "b", (200, 300)));
+        std::thread::sleep(Duration::from_millis(120));
+        assert!(!server.tick());
+
+        // Only a's lease lapsed, so a's unit comes back before the last one
+        assert_eq!(claim_unit(&server, "c"), first);
+        assert_eq!(claim_unit(&server, "c"), (300, 350));
+        assert!(!report(&server, "progress", "a", first));
+        assert!(report(&server, "progress", "c", first));
+        assert!(report(&server, "progress", "b", (200, 300)));
+        let _ = fs::remove_file(&server.path);
+    }
+
+    #[test]
+    fn released_units_are_requeued() {
+        let server = server("released", false, Duration::from_secs(60));
+        let unit = claim_unit(&server, "a");
+        // Only the leaseholder can give a unit back
+        assert!(!report(&server, "release", "b", unit));
+        assert!(matches!(claim(&server, "b"), Claim::Unit(unit) if unit.start == 200));
+        assert!(!report(&server, "release", "a", unit));
+        assert_eq!(claim_unit(&server, "b"), unit);
+        let _ = fs::remove_file(&server.path);
+    }
+
+    /// After a restart, units handed out before are pending, and a worker
+    /// still on one takes its lease back by reporting.
+    #[test]
+    fn a_restarted_server_takes_units_back_up() {
+        let server = server("restart", false, Duration::from_secs(60));
+        {
+            let mut state = server.state.lock().unwrap();
+            state.saved.next = 2;
+            state.pending = BTreeSet::from([0, 1]);
+        }
+        assert!(report(&server, "progress", "a", (100, 200)));
+        assert_eq!(claim_unit(&server, "b"), (200, 300));
+        assert_eq!(claim_unit(&server, "b"), (300, 350));
+        assert!(matches!(claim(&server, "c"), Claim::Wait(_)));
+        let _ = fs::remove_file(&server.path);
+    }
+
+    #[test]
+    fn a_match_stops_every_worker() {
+        let found = server("match", false, Duration::from_secs(60));
+        let (first, second) = (claim_unit(&found, "a"), claim_unit(&found, "b"));
+        assert!(!report(&found, "match", "a", first));
+        assert_eq!(saved(&found).matches.len(), 1);
+        assert!(!report(&found, "progress", "b", second));
+        assert!(matches!(claim(&found, "c"), Claim::Stop(reason) if reason == "found"));
+        assert!(found.state.lock().unwrap().leases.is_empty());
+        let _ = fs::remove_file(&found.path);
+
+        let find_all = server("find-all", true, Duration::from_secs(60));
+        let unit = claim_unit(&find_all, "a");
+        assert!(report(&find_all, "match", "a", unit));
+        assert!(report(&find_all, "match", "a", unit));
+        assert_eq!(saved(&find_all).matches.len(), 2);
+        assert_eq!(claim_unit(&find_all, "b"), (200, 300));
+        let _ = fs::remove_file(&find_all.path);
+    }
+}
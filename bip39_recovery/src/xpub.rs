@@ -0,0 +1,89 @@
+use std::str::FromStr;
+
+use anyhow::Result;
+use bitcoin::base58;
+use bitcoin::bip32::{ChildNumber, DerivationPath, Fingerprint, Xpriv, Xpub};
+use secp256k1::Secp256k1;
+
+/// Matrix encoding whose "address" is the extended public key at the path,
+/// serialized with the mainnet `xpub` version.
+pub const ACCOUNT_XPUB: &str = "xpub";
+
+/// Matrix encoding whose "address" is the fingerprint of the key at the
+/// path, which at `m` is the wallet's master fingerprint.
+pub const MASTER_FINGERPRINT: &str = "fingerprint";
+
+/// Bits a fingerprint pins down, for estimating accidental matches.
+pub const FINGERPRINT_BITS: i32 = 32;
+
+/// SLIP-132 version bytes, the BIP-43 purpose of the accounts they belong
+/// to, and whether they are for test networks.
+const VERSIONS: [([u8; 4], u32, bool); 6] = [
+    ([0x04, 0x88, 0xb2, 0x1e], 44, false), // xpub
+    ([0x04, 0x9d, 0x7c, 0xb2], 49, false), // ypub
+    ([0x04, 0xb2, 0x47, 0x46], 84, false), // zpub
+    ([0x04, 0x35, 0x87, 0xcf], 44, true),  // tpub
+    ([0x04, 0x4a, 0x52, 0x62], 49, true),  // upub
+    ([0x04, 0x5f, 0x1c, 0xf6], 84, true),  // vpub
+];
+
+/// Account depth of BIP-44 style paths, `m/<purpose>'/<coin>'/<account>'`.
+const ACCOUNT_DEPTH: u8 = 3;
+
+/// A target extended public key.
+#[derive(Clone, Debug)]
+pub struct AccountKey {
+    /// The key re-serialized as an `xpub`, as [`encode`] produces it
+    pub key: String,
+    /// The account path its version and child number imply, when it is an
+    /// account-level key
+    pub path: Option<DerivationPath>,
+}
+
+/// Parses an `xpub`, `ypub`, `zpub`, or their testnet forms. The version
+/// only says which script type the wallet used, so it is normalized away
+/// for matching and kept as the purpose of the implied path.
+pub fn parse(key: &str) -> Result<AccountKey> {
+    let mut data = base58::decode_check(key.trim())
+        .map_err(|e| anyhow::anyhow!("Invalid extended public key {}: {}", key, e))?;
+    if data.len() != 78 {
+        return Err(anyhow::anyhow!("{} is not a serialized extended key", key));
+    }
+    let (purpose, testnet) = VERSIONS
+        .iter()
+        .find(|(version, _, _)| data[..4] == *version)
+        .map(|(_, purpose, testnet)| (*purpose, *testnet))
+        .ok_or_else(|| anyhow::anyhow!("{} is not an xpub, ypub, zpub, tpub, upub, or vpub", key))?;
+    data[..4].copy_from_slice(&VERSIONS[0].0);
+    let xpub = Xpub::decode(&data).map_err(|e| anyhow::anyhow!("Invalid extended public key {}: {}", key, e))?;
+
+    let path = match xpub.child_number {
+        ChildNumber::Hardened { index } if xpub.depth == ACCOUNT_DEPTH => Some(DerivationPath::from(vec![
+            ChildNumber::Hardened { index: purpose },
+            ChildNumber::Hardened { index: u32::from(testnet) },
+            ChildNumber::Hardened { index },
+        ])),
+        _ => None,
+    };
+    Ok(AccountKey { key: xpub.to_string(), path })
+}
+
+/// The extended public key of `xprv`, as compared against [`AccountKey::key`].
+pub fn encode(xprv: &Xpriv, secp: &Secp256k1<secp256k1::All>) -> String {
+    Xpub::from_priv(secp, xprv).to_string()
+}
+
+/// Normalizes a fingerprint given as 8 hex digits (as in descriptors and
+/// PSBTs, e.g. `73c5da0a`) for matching.
+pub fn parse_fingerprint(fingerprint: &str) -> Result<String> {
+    let digits = fingerprint.trim();
+    let digits = digits.strip_prefix("0x").unwrap_or(digits);
+    let parsed = Fingerprint::from_str(digits)
+        .map_err(|e| anyhow::anyhow!("Invalid fingerprint {} (expected 8 hex digits): {}", fingerprint, e))?;
+    Ok(parsed.to_string())
+}
+
+/// The fingerprint of `xprv`, as compared against [`parse_fingerprint`].
+pub fn fingerprint(xprv: &Xpriv, secp: &Secp256k1<secp256k1::All>) -> String {
+    xprv.fingerprint(secp).to_string()
+}
@@ -57,7 +57,7 @@ if command -v nvcc &> /dev/null; then
     echo "✓ CUDA compiler found: $(nvcc --version | head -n 1)"
     echo "Note: CUDA support in bip39_recovery must be enabled during build time"
 else
-    echo "ℹ CUDA compiler not found (CUDA support will not be available)"
+    echo "ℹ CUDA compiler not found (only needed to build with --features cuda; a binary built elsewhere runs without it)"
 fi
 
 echo ""